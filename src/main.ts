@@ -2,6 +2,7 @@ import GUI from 'lil-gui';
 import {
     AmbientLight,
     BoxGeometry,
+    BufferAttribute,
     BufferGeometry,
     Color,
     CylinderGeometry,
@@ -20,10 +21,9 @@ import {
     WebGLRenderer
 } from 'three';
 import Stats from 'three/addons/libs/stats.module.js';
-import { STLLoader } from 'three/addons/loaders/STLLoader.js';
 import { BufferGeometryUtils, TrackballControls } from 'three/examples/jsm/Addons.js';
 
-import { default as initRustModule } from '../wasm/build/wasm_main_module';
+import { default as initRustModule, load_model } from '../wasm/build/wasm_main_module';
 import { splitDisjointGeometry } from './split-geometry';
 import { stupidMicroBenchmarkArrays, stupidMicroBenchmarkSimple } from './stupid-microbenchmark';
 import { computeTriangleNormals } from './triangle-normals';
@@ -310,14 +310,32 @@ async function onLoadFile(filename: string, contents: ArrayBuffer) {
     setTitle(filename);
 
     disposeModel(curModel);
-    const stlLoader = new STLLoader();
     let startTime = performance.now();
-    const stlGeo = stlLoader.parse(contents);
+    let model;
+    try {
+        model = load_model(new Uint8Array(contents), filename);
+    } catch (e) {
+        // Errors from the wasm module have a stable `code`, e.g. TRUNCATED_FILE or UNSUPPORTED_FORMAT.
+        const error = e as Error & { code?: string };
+        console.log(`Failed to load file ${filename}: ${error.code} ${error.message}`);
+        curModel = createModelFromGeo(new BufferGeometry().setFromPoints([]));
+        return;
+    }
     let deltaTime = performance.now() - startTime;
     console.log(`Loading ${filename} took ${deltaTime}ms`);
+    const positions = model.positions;
+    for (let i = 0; i < model.metadata_count; i++) {
+        console.log(`${model.metadata_key(i)}: ${model.metadata_value(i)}`);
+    }
+    for (let i = 0; i < model.warning_count; i++) {
+        console.log(`Warning at line ${model.warning_line(i)}: ${model.warning_message(i)}`);
+    }
+    model.free();
+    const geo = new BufferGeometry();
+    geo.setAttribute('position', new BufferAttribute(positions, 3));
 
     startTime = performance.now();
-    curModel = createModelFromGeo(stlGeo);
+    curModel = createModelFromGeo(geo);
     deltaTime = performance.now() - startTime;
     console.log(`Creating model from geo for ${filename} took ${deltaTime}ms`);
 
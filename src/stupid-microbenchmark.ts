@@ -1,5 +1,7 @@
 import type { InitOutput as RustModule } from "../wasm/build/wasm_main_module";
-import { Float32Vec, get_settings, not_atan2, triple_array, triple_array_with_vec } from "../wasm/build/wasm_main_module";
+import {
+    Float32Vec, alloc, dealloc, get_settings, not_atan2, triple_array, triple_array_raw, triple_array_with_vec
+} from "../wasm/build/wasm_main_module";
 import { notAtan2 } from "./not-atan";
 
 // This is the stupidest micro-benchmark, but still useful for getting an idea on how much the function call costs.
@@ -214,8 +216,6 @@ function tripleRustArrayVecNoCopy(module: RustModule, n: number) {
     inputVec.free();
 }
 
-let resultSpanPtr: number;
-
 // Similar to HEAPF32 in Emscripten.
 let heapF32: Float32Array = new Float32Array();
 function checkHeapF32(module: RustModule) {
@@ -224,39 +224,24 @@ function checkHeapF32(module: RustModule) {
     }
 }
 
-// Similar to HEAPU32 in Emscripten.
-let heapU32: Uint32Array = new Uint32Array();
-function checkHeapU32(module: RustModule) {
-    if (heapU32.buffer.byteLength === 0) {
-        heapU32 = new Uint32Array(module.memory.buffer);
-    }
-}
-
 function tripleRustArrayRaw(module: RustModule, n: number) {
-    if (resultSpanPtr === undefined) {
-        resultSpanPtr = module.alloc(8);
-    }
-
     const base = 4567.89;
-    const inputDataPtr = module.alloc(n * 4);
+    const inputDataPtr = alloc(n * 4);
     checkHeapF32(module);
     for (let i = 0; i < n; i++) {
         heapF32[inputDataPtr / 4 + i] = base + i;
     }
 
-    module.triple_array_raw(resultSpanPtr, inputDataPtr, n);
-    checkHeapU32(module);
-    const resultDataPtr = heapU32[resultSpanPtr / 4];
-    const resultLen = heapU32[resultSpanPtr / 4 + 1];
+    const resultVec = triple_array_raw(inputDataPtr, n);
     checkHeapF32(module);
-    const lastResult = heapF32[resultDataPtr / 4 + resultLen - 1];
+    const lastResult = heapF32[resultVec.data_ptr / 4 + resultVec.len - 1];
     const resultDiff = lastResult - 3 * (base + n - 1);
     // Account for difference between float and double.
     if (Math.abs(resultDiff) > 1e-5 * n) {
         throw new Error(`Expected ${3 * (base + n - 1)}, got ${lastResult}, diff = ${resultDiff.toExponential()}`);
     }
-    module.dealloc(resultDataPtr, resultLen * 4);
-    module.dealloc(inputDataPtr, n * 4);
+    resultVec.free();
+    dealloc(inputDataPtr, n * 4);
 }
 
 // This is the micro-benchmark measures the costs of passing Float32Array.
@@ -16,8 +16,20 @@ cd `dirname $0`
 BUILD_DIR="./build"
 WASM_TARGET="wasm32-unknown-unknown"
 
+# THREADS=1 ./run-build.sh builds the variant with the threads feature. It needs the nightly toolchain: std must be
+# rebuilt with atomics to use shared memory. The module must then be used from a web worker, see parallel.rs.
+TOOLCHAIN=""
+CARGO_ARGS=""
+if [ "$THREADS" = "1" ]; then
+    echo "Building with threads"
+    # RUSTFLAGS replaces the flags from .cargo/config.toml, so simd128 is repeated here.
+    export RUSTFLAGS="-C target-feature=+atomics,+bulk-memory,+simd128 -C link-arg=--max-memory=4294967296"
+    TOOLCHAIN="+nightly"
+    CARGO_ARGS="-Z build-std=panic_abort,std --features threads"
+fi
+
 echo "Building WebAssembly module..."
-cargo build --target $WASM_TARGET --profile $BUILD_PROFILE
+cargo $TOOLCHAIN build --target $WASM_TARGET --profile $BUILD_PROFILE $CARGO_ARGS
 
 WASM_INPUT="./target/$WASM_TARGET/$BUILD_PROFILE/wasm_main_module.wasm"
 if [ ! -f "$WASM_INPUT" ]; then
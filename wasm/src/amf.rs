@@ -0,0 +1,380 @@
+use std::collections::HashMap;
+
+use wasm_bindgen::prelude::*;
+
+use crate::error::Error;
+use crate::model::{INVALID_VERTEX, ImportedModel, Model, ModelPart, uses_invalid_vertex};
+use crate::vector3::Vector3;
+use crate::xml::{XmlEvent, XmlReader, local_name};
+use crate::zip::ZipArchive;
+
+fn unit_scale(unit: &str) -> Option<f32> {
+    Some(match unit {
+        "millimeter" => 1.0,
+        "inch" => 25.4,
+        "feet" => 304.8,
+        "meter" => 1000.0,
+        "micron" | "micrometer" => 0.001,
+        _ => return None,
+    })
+}
+
+#[derive(Clone, Debug, Default)]
+struct Material {
+    name: Option<String>,
+    color: Option<[u8; 4]>,
+}
+
+#[derive(Clone, Debug, Default)]
+struct Object {
+    id: String,
+    name: Option<String>,
+    color: Option<[u8; 4]>,
+    vertices: Vec<Vector3>,
+    triangles: Vec<[u32; 3]>,
+    // Material id and color of each volume.
+    volumes: Vec<(Option<String>, Option<[u8; 4]>)>,
+}
+
+// The element a <color> or <metadata> belongs to.
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Owner {
+    Material,
+    Object,
+    Volume,
+    Other,
+}
+
+fn owner(parent: Option<&str>) -> Owner {
+    match parent {
+        Some("material") => Owner::Material,
+        Some("object") => Owner::Object,
+        Some("volume") => Owner::Volume,
+        _ => Owner::Other,
+    }
+}
+
+// Converts the color channels (0..1 floats) into RGBA. AMF allows formulas in the channels, which are not supported.
+fn to_color(channels: [Option<f32>; 4]) -> Option<[u8; 4]> {
+    let [r, g, b, a] = channels;
+    let rgba = [r?, g?, b?, a.unwrap_or(1.0)];
+    Some(rgba.map(|c| (c.clamp(0.0, 1.0) * 255.0).round() as u8))
+}
+
+fn parse_amf_xml(text: &str) -> Result<Model, String> {
+    let mut model = Model::default();
+    let mut reader = XmlReader::new(text);
+    let mut stack: Vec<&str> = vec![];
+    let mut text_buf = String::new();
+    let mut scale = 1.0;
+    let mut materials: HashMap<String, Material> = HashMap::new();
+    let mut objects: Vec<Object> = vec![];
+    let mut material: Option<(String, Material)> = None;
+    let mut object: Option<Object> = None;
+    let mut coords = [None; 3];
+    let mut tri = [None; 3];
+    let mut color = [None; 4];
+    let mut metadata_type: Option<String> = None;
+    let mut has_constellations = false;
+    let mut has_root = false;
+
+    while let Some(event) = reader.next_event()? {
+        match event {
+            XmlEvent::Start { name, attributes, self_closing } => {
+                let name = local_name(name);
+                if stack.is_empty() {
+                    if name != "amf" || has_root {
+                        return Err(format!("unexpected root element <{}>, not an AMF file", name));
+                    }
+                    has_root = true;
+                }
+                text_buf.clear();
+                match name {
+                    "amf" => {
+                        if let Some(unit) = attributes.get("unit") {
+                            scale = unit_scale(unit.trim()).unwrap_or_else(|| {
+                                model.warn(0, format!("unknown unit '{}', assuming millimeters", unit));
+                                1.0
+                            });
+                        }
+                    }
+                    "material" => {
+                        let id = attributes.get("id").unwrap_or_default().into_owned();
+                        material = Some((id, Material::default()));
+                    }
+                    "object" => {
+                        let id = attributes.get("id").unwrap_or_default().into_owned();
+                        object = Some(Object { id, ..Default::default() });
+                    }
+                    "volume" => {
+                        if let Some(object) = &mut object {
+                            let material_id = attributes.get("materialid").map(|m| m.into_owned());
+                            object.volumes.push((material_id, None));
+                        }
+                    }
+                    "coordinates" => coords = [None; 3],
+                    "triangle" => tri = [None; 3],
+                    "color" => color = [None; 4],
+                    "metadata" => metadata_type = attributes.get("type").map(|t| t.into_owned()),
+                    "constellation" => has_constellations = true,
+                    _ => {}
+                }
+                if !self_closing {
+                    stack.push(name);
+                }
+            }
+            XmlEvent::Text(text) => text_buf.push_str(&text),
+            XmlEvent::End { .. } => {
+                let name = stack.pop().unwrap();
+                let parent = stack.last().copied();
+                let value = text_buf.trim();
+                let number = || value.parse::<f32>().ok().filter(|v| v.is_finite());
+                match (name, parent) {
+                    ("x" | "y" | "z", Some("coordinates")) => {
+                        coords[(name.as_bytes()[0] - b'x') as usize] = number();
+                    }
+                    ("v1" | "v2" | "v3", Some("triangle")) => {
+                        tri[(name.as_bytes()[1] - b'1') as usize] = value.parse::<u32>().ok();
+                    }
+                    ("r" | "g" | "b" | "a", Some("color")) => {
+                        color["rgba".find(name).unwrap()] = number();
+                    }
+                    ("coordinates", _) => {
+                        if let Some(object) = &mut object {
+                            let [Some(x), Some(y), Some(z)] = coords else {
+                                model.warn(0, format!("invalid vertex in object {}", object.id));
+                                object.vertices.push(INVALID_VERTEX);
+                                continue;
+                            };
+                            object.vertices.push(Vector3::new(x, y, z) * scale);
+                        }
+                    }
+                    ("triangle", _) => {
+                        if let Some(object) = &mut object {
+                            match tri {
+                                [Some(a), Some(b), Some(c)] => object.triangles.push([a, b, c]),
+                                _ => model.warn(0, format!("invalid triangle in object {}", object.id)),
+                            }
+                        }
+                    }
+                    ("color", parent) => {
+                        let rgba = to_color(color);
+                        if rgba.is_none() {
+                            model.warn(0, "unsupported color".to_string());
+                        }
+                        match owner(parent) {
+                            Owner::Material => {
+                                if let Some((_, material)) = &mut material {
+                                    material.color = rgba;
+                                }
+                            }
+                            Owner::Object => {
+                                if let Some(object) = &mut object {
+                                    object.color = rgba;
+                                }
+                            }
+                            Owner::Volume => {
+                                if let Some(volume) = object.as_mut().and_then(|o| o.volumes.last_mut()) {
+                                    volume.1 = rgba;
+                                }
+                            }
+                            Owner::Other => {}
+                        }
+                    }
+                    ("metadata", parent) if metadata_type.as_deref() == Some("name") => {
+                        let value = Some(value.to_string());
+                        match owner(parent) {
+                            Owner::Material => {
+                                if let Some((_, material)) = &mut material {
+                                    material.name = value;
+                                }
+                            }
+                            Owner::Object => {
+                                if let Some(object) = &mut object {
+                                    object.name = value;
+                                }
+                            }
+                            _ => {}
+                        }
+                    }
+                    ("material", _) => {
+                        if let Some((id, material)) = material.take() {
+                            materials.insert(id, material);
+                        }
+                    }
+                    ("object", _) => objects.extend(object.take()),
+                    _ => {}
+                }
+            }
+        }
+    }
+
+    if !has_root {
+        return Err("not an AMF file".to_string());
+    }
+    if has_constellations {
+        model.warn(0, "constellations are not supported, objects are kept at their own positions".to_string());
+    }
+    for object in objects {
+        let mut positions = Vec::with_capacity(object.triangles.len() * 9);
+        let mut invalid = 0;
+        for tri in &object.triangles {
+            if tri.iter().any(|&v| v as usize >= object.vertices.len()) || uses_invalid_vertex(&object.vertices, tri) {
+                invalid += 1;
+                continue;
+            }
+            for &v in tri {
+                let p = object.vertices[v as usize];
+                positions.extend_from_slice(&[p.x, p.y, p.z]);
+            }
+        }
+        if invalid > 0 {
+            model.warn(0, format!("{} triangles in object {} have invalid vertices", invalid, object.id));
+        }
+        // The first volume defines the material of the part.
+        let (material_id, volume_color) = object.volumes.first().cloned().unwrap_or_default();
+        let material = material_id.as_ref().and_then(|id| materials.get(id));
+        let color = object
+            .color
+            .or(volume_color)
+            .or_else(|| material.and_then(|m| m.color));
+        // Materials without a name are identified by their id.
+        let material_name = material.and_then(|m| m.name.clone()).or(material_id);
+        model.parts.push(ModelPart {
+            name: object
+                .name
+                .unwrap_or_else(|| format!("Object {}", object.id)),
+            material: material_name,
+            color,
+            positions,
+        });
+    }
+    Ok(model)
+}
+
+/// Parses an AMF file, either plain XML or zip-compressed. Each object becomes a part named after its name metadata,
+/// with all its volumes merged. The material is the material of the first volume, the color is taken from the object,
+/// the first volume or its material, in this order. Coordinates are converted to millimeters. Constellations, curved
+/// triangles and textures are not supported.
+pub fn parse_amf(data: &[u8]) -> Result<Model, Error> {
+    if data.starts_with(b"PK") {
+        let archive = ZipArchive::new(data)?;
+        // The archive usually contains a single file named after the original one.
+        let entry = archive
+            .entries
+            .iter()
+            .find(|e| e.name.to_ascii_lowercase().ends_with(".amf"))
+            .or(archive.entries.first())
+            .ok_or("empty AMF archive")?;
+        let contents = archive.read(entry)?;
+        return parse_amf(&contents);
+    }
+    let text = std::str::from_utf8(data).map_err(|_| "AMF file is not valid UTF-8".to_string())?;
+    Ok(parse_amf_xml(text)?)
+}
+
+/// See parse_amf.
+#[wasm_bindgen]
+pub fn load_amf(data: &[u8]) -> Result<ImportedModel, Error> {
+    parse_amf(data).map(ImportedModel::new)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::zip::tests::create_zip;
+
+    const AMF: &str = r#"<?xml version="1.0" encoding="utf-8"?>
+<amf unit="inch" version="1.1">
+  <metadata type="name">Plate</metadata>
+  <material id="2">
+    <metadata type="name">PLA</metadata>
+    <color><r>0</r><g>0</g><b>1</b></color>
+  </material>
+  <object id="1">
+    <metadata type="name">Tri &amp; more</metadata>
+    <mesh>
+      <vertices>
+        <vertex><coordinates><x>0</x><y>0</y><z>0</z></coordinates></vertex>
+        <vertex><coordinates><x>1</x><y>0</y><z>0</z></coordinates></vertex>
+        <vertex><coordinates><x>0</x><y>1</y><z>0</z></coordinates></vertex>
+        <vertex><coordinates><x>0</x><y>0</y><z>1</z></coordinates></vertex>
+      </vertices>
+      <volume materialid="2">
+        <triangle><v1>0</v1><v2>2</v2><v3>1</v3></triangle>
+      </volume>
+      <volume>
+        <color><r>1</r><g>0</g><b>0</b><a>0.5</a></color>
+        <triangle><v1>0</v1><v2>1</v2><v3>3</v3></triangle>
+        <triangle><v1>0</v1><v2>1</v2><v3>9</v3></triangle>
+      </volume>
+    </mesh>
+  </object>
+  <object id="5">
+    <color><r>0</r><g>1</g><b>0</b></color>
+    <mesh>
+      <vertices>
+        <vertex><coordinates><x>0</x><y>0</y><z>0</z></coordinates></vertex>
+        <vertex><coordinates><x>1</x><y>0</y><z>0</z></coordinates></vertex>
+        <vertex><coordinates><x>0</x><y>1</y></coordinates></vertex>
+      </vertices>
+      <volume materialid="7"><triangle><v1>0</v1><v2>1</v2><v3>2</v3></triangle></volume>
+    </mesh>
+  </object>
+  <constellation id="10"><instance objectid="1"><deltax>5</deltax></instance></constellation>
+</amf>
+"#;
+
+    #[test]
+    fn test_parse() {
+        let model = parse_amf(AMF.as_bytes()).unwrap();
+        assert_eq!(model.parts.len(), 2);
+        let part = &model.parts[0];
+        assert_eq!(part.name, "Tri & more");
+        assert_eq!(part.material.as_deref(), Some("PLA"));
+        assert_eq!(part.color, Some([0, 0, 255, 255]));
+        assert_eq!(part.positions.len(), 18);
+        assert_eq!(part.positions[..9], [0.0, 0.0, 0.0, 0.0, 25.4, 0.0, 25.4, 0.0, 0.0]);
+
+        let part = &model.parts[1];
+        assert_eq!(part.name, "Object 5");
+        assert_eq!(part.material.as_deref(), Some("7"));
+        assert_eq!(part.color, Some([0, 255, 0, 255]));
+        assert!(part.positions.is_empty());
+
+        let messages: Vec<&str> = model.warnings.iter().map(|w| w.message.as_str()).collect();
+        assert_eq!(
+            messages,
+            vec![
+                "invalid vertex in object 5",
+                "constellations are not supported, objects are kept at their own positions",
+                "1 triangles in object 1 have invalid vertices",
+                "1 triangles in object 5 have invalid vertices",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_zipped() {
+        let data = create_zip(&[("plate.amf", AMF.as_bytes(), true)]);
+        let model = parse_amf(&data).unwrap();
+        assert_eq!(model, parse_amf(AMF.as_bytes()).unwrap());
+        assert!(parse_amf(&create_zip(&[])).is_err());
+    }
+
+    #[test]
+    fn test_errors() {
+        assert!(parse_amf(b"<amf><object id=\"1\"></amf>").is_err());
+        assert!(parse_amf(&[0xff, 0xfe]).is_err());
+        let model = parse_amf(b"<amf unit=\"parsec\"/>").unwrap();
+        assert!(model.parts.is_empty());
+        assert_eq!(model.warnings.len(), 1);
+    }
+
+    #[test]
+    fn test_wasm_wrapper() {
+        let model = load_amf(AMF.as_bytes()).unwrap();
+        assert_eq!(model.part_count(), 2);
+        assert!(load_amf(b"").is_err());
+    }
+}
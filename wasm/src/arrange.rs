@@ -0,0 +1,425 @@
+use wasm_bindgen::prelude::*;
+
+use crate::error::{Error, checked_item, split_by_tri_counts};
+use crate::polygon::{convex_hull_2d, signed_area};
+use crate::vector2::Vector2;
+
+/// Which rotations around Z may be applied to parts.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum RotationMode {
+    None,
+    RightAngles,
+    /// Rotations by multiples of `step_degrees`.
+    Free {
+        step_degrees: f32,
+    },
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct ArrangeOptions {
+    /// The bed is the rectangle [0, bed_width] x [0, bed_depth] in XY plane.
+    pub bed_width: f32,
+    pub bed_depth: f32,
+    /// Minimum distance between footprints of parts. Parts may touch the bed borders.
+    pub spacing: f32,
+    pub rotation: RotationMode,
+}
+
+/// The part must be rotated around Z axis by `rotation` radians (around the origin) and then translated by
+/// `translation`.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct PartPlacement {
+    pub rotation: f32,
+    pub translation: Vector2,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Arrangement {
+    /// One entry per input part, None if the part did not fit.
+    pub placements: Vec<Option<PartPlacement>>,
+    pub unplaced: Vec<usize>,
+}
+
+// A footprint placed on the bed.
+struct Placed {
+    hull: Vec<Vector2>,
+    min: Vector2,
+    max: Vector2,
+}
+
+fn bounds(poly: &[Vector2]) -> (Vector2, Vector2) {
+    let mut min = Vector2::new(f32::INFINITY, f32::INFINITY);
+    let mut max = Vector2::new(f32::NEG_INFINITY, f32::NEG_INFINITY);
+    for p in poly {
+        min = Vector2::new(min.x.min(p.x), min.y.min(p.y));
+        max = Vector2::new(max.x.max(p.x), max.y.max(p.y));
+    }
+    (min, max)
+}
+
+fn rotate(p: Vector2, angle: f32) -> Vector2 {
+    let (sin, cos) = angle.sin_cos();
+    Vector2::new(p.x * cos - p.y * sin, p.x * sin + p.y * cos)
+}
+
+// Returns true if the convex polygons are separated by at least `margin` along one of the edge normals (separating
+// axis theorem). This is conservative: for some configurations the actual distance may be larger than the margin even
+// if the function returns false.
+fn separated(a: &[Vector2], b: &[Vector2], margin: f32) -> bool {
+    if a.len() < 2 || b.len() < 2 {
+        // Points and segments have no edge normals which work as separating axes, fall back to bounding boxes.
+        let (a_min, a_max) = bounds(a);
+        let (b_min, b_max) = bounds(b);
+        return a_min.x - b_max.x >= margin
+            || b_min.x - a_max.x >= margin
+            || a_min.y - b_max.y >= margin
+            || b_min.y - a_max.y >= margin;
+    }
+    for poly in [a, b] {
+        for i in 0..poly.len() {
+            let edge = poly[(i + 1) % poly.len()] - poly[i];
+            let len = edge.length();
+            if len == 0.0 {
+                continue;
+            }
+            let axis = Vector2::new(edge.y / len, -edge.x / len);
+            let project = |poly: &[Vector2]| {
+                poly.iter()
+                    .map(|p| p.dot(axis))
+                    .fold((f32::INFINITY, f32::NEG_INFINITY), |(lo, hi), d| (lo.min(d), hi.max(d)))
+            };
+            let (a_lo, a_hi) = project(a);
+            let (b_lo, b_hi) = project(b);
+            if b_lo - a_hi >= margin || a_lo - b_hi >= margin {
+                return true;
+            }
+        }
+    }
+    false
+}
+
+fn rotation_angles(mode: RotationMode) -> Vec<f32> {
+    match mode {
+        RotationMode::None => vec![0.0],
+        RotationMode::RightAngles => (0..4)
+            .map(|i| i as f32 * std::f32::consts::FRAC_PI_2)
+            .collect(),
+        RotationMode::Free { step_degrees } => {
+            if step_degrees.is_nan() || step_degrees <= 0.0 {
+                return vec![0.0];
+            }
+            let count = (360.0 / step_degrees).ceil().min(360.0) as usize;
+            (0..count)
+                .map(|i| (i as f32 * step_degrees).to_radians())
+                .collect()
+        }
+    }
+}
+
+/// Returns the footprint of the part: the convex hull of its vertices projected on XY plane. `pos` has the same layout
+/// as in split_disjoint_geometry.
+pub fn part_footprint(pos: &[f32]) -> Vec<Vector2> {
+    let points: Vec<Vector2> = pos
+        .chunks_exact(3)
+        .map(|p| Vector2::new(p[0], p[1]))
+        .collect();
+    convex_hull_2d(&points)
+}
+
+/// Lays out parts on a rectangular bed using bottom-left placement of their footprints. Parts are placed from the
+/// largest footprint to the smallest. For each part and each allowed rotation the candidate positions are the bed
+/// corner and the positions next to the bounding boxes of already placed parts; the lowest and then leftmost
+/// non-overlapping one wins.
+pub fn arrange_parts(parts: &[Vec<f32>], options: &ArrangeOptions) -> Arrangement {
+    let footprints: Vec<Vec<Vector2>> = parts.iter().map(|p| part_footprint(p)).collect();
+    let mut order: Vec<usize> = (0..parts.len()).collect();
+    order.sort_by(|&a, &b| signed_area(&footprints[b]).total_cmp(&signed_area(&footprints[a])));
+    let angles = rotation_angles(options.rotation);
+    let bed = Vector2::new(options.bed_width, options.bed_depth);
+    // Tolerance for fitting into the bed, so that a part with exactly the bed size fits.
+    let bed_epsilon = 1e-5 * (bed.x.abs() + bed.y.abs());
+
+    let mut placed: Vec<Placed> = vec![];
+    let mut placements = vec![None; parts.len()];
+    let mut unplaced = vec![];
+    for part_idx in order {
+        if footprints[part_idx].is_empty() {
+            unplaced.push(part_idx);
+            continue;
+        }
+
+        // (y, x, angle, translation, hull) of the best position found so far.
+        let mut best: Option<(f32, f32, f32, Vector2, Vec<Vector2>)> = None;
+        for &angle in &angles {
+            let rotated: Vec<Vector2> = footprints[part_idx]
+                .iter()
+                .map(|&p| rotate(p, angle))
+                .collect();
+            let (min, max) = bounds(&rotated);
+            let size = max - min;
+            if size.x > bed.x + bed_epsilon || size.y > bed.y + bed_epsilon {
+                continue;
+            }
+
+            let mut xs = vec![0.0];
+            let mut ys = vec![0.0];
+            for other in &placed {
+                xs.push(other.max.x + options.spacing);
+                xs.push(other.min.x);
+                ys.push(other.max.y + options.spacing);
+                ys.push(other.min.y);
+            }
+            for &y in &ys {
+                for &x in &xs {
+                    if y + size.y > bed.y + bed_epsilon || x + size.x > bed.x + bed_epsilon {
+                        continue;
+                    }
+                    if let Some((best_y, best_x, ..)) = best
+                        && (y, x) >= (best_y, best_x)
+                    {
+                        continue;
+                    }
+                    let translation = Vector2::new(x, y) - min;
+                    let hull: Vec<Vector2> = rotated.iter().map(|&p| p + translation).collect();
+                    let fits = placed.iter().all(|other| {
+                        let (hull_min, hull_max) = (Vector2::new(x, y), Vector2::new(x, y) + size);
+                        // Cheap bounding box check before the exact one.
+                        hull_min.x - other.max.x >= options.spacing
+                            || other.min.x - hull_max.x >= options.spacing
+                            || hull_min.y - other.max.y >= options.spacing
+                            || other.min.y - hull_max.y >= options.spacing
+                            || separated(&hull, &other.hull, options.spacing)
+                    });
+                    if fits {
+                        best = Some((y, x, angle, translation, hull));
+                    }
+                }
+            }
+        }
+
+        match best {
+            Some((_, _, rotation, translation, hull)) => {
+                let (min, max) = bounds(&hull);
+                placed.push(Placed { hull, min, max });
+                placements[part_idx] = Some(PartPlacement { rotation, translation });
+            }
+            None => unplaced.push(part_idx),
+        }
+    }
+    unplaced.sort_unstable();
+    Arrangement { placements, unplaced }
+}
+
+/// Result of arrange_on_bed for JS, indexed by the input part.
+#[wasm_bindgen]
+pub struct BedArrangement {
+    arrangement: Arrangement,
+}
+
+impl BedArrangement {
+    fn placement(&self, part: usize) -> Result<Option<PartPlacement>, Error> {
+        checked_item(&self.arrangement.placements, part, "part").copied()
+    }
+}
+
+#[wasm_bindgen]
+impl BedArrangement {
+    pub fn is_placed(&self, part: usize) -> Result<bool, Error> {
+        Ok(self.placement(part)?.is_some())
+    }
+
+    /// Rotation around Z axis in radians, applied before the translation.
+    pub fn rotation(&self, part: usize) -> Result<f32, Error> {
+        Ok(self.placement(part)?.map_or(0.0, |p| p.rotation))
+    }
+
+    pub fn translation_x(&self, part: usize) -> Result<f32, Error> {
+        Ok(self.placement(part)?.map_or(0.0, |p| p.translation.x))
+    }
+
+    pub fn translation_y(&self, part: usize) -> Result<f32, Error> {
+        Ok(self.placement(part)?.map_or(0.0, |p| p.translation.y))
+    }
+
+    /// Indices of parts which did not fit on the bed.
+    #[wasm_bindgen(getter)]
+    pub fn unplaced(&self) -> Box<[u32]> {
+        self.arrangement
+            .unplaced
+            .iter()
+            .map(|&i| i as u32)
+            .collect()
+    }
+}
+
+/// Arranges parts on the bed, see arrange_parts. `pos` contains the triangles of all parts one after another,
+/// `part_tri_counts` contains the number of triangles in each part. `rotation_step_degrees` is 0 to disable rotation,
+/// 90 for right angles or any other positive step for free rotation. The bed sizes must be positive, the spacing and
+/// the rotation step must not be negative.
+#[wasm_bindgen]
+pub fn arrange_on_bed(
+    pos: &[f32],
+    part_tri_counts: &[u32],
+    bed_width: f32,
+    bed_depth: f32,
+    spacing: f32,
+    rotation_step_degrees: f32,
+) -> Result<BedArrangement, Error> {
+    if !bed_width.is_finite() || !bed_depth.is_finite() || bed_width <= 0.0 || bed_depth <= 0.0 {
+        return Err(Error::InvalidInput(format!("invalid bed size {} x {}", bed_width, bed_depth)));
+    }
+    if !spacing.is_finite() || spacing < 0.0 {
+        return Err(Error::InvalidInput(format!("invalid part spacing {}", spacing)));
+    }
+    if !rotation_step_degrees.is_finite() || rotation_step_degrees < 0.0 {
+        return Err(Error::InvalidInput(format!("invalid rotation step {}", rotation_step_degrees)));
+    }
+    let parts: Vec<Vec<f32>> = split_by_tri_counts(pos, part_tri_counts)?
+        .into_iter()
+        .map(|part| part.to_vec())
+        .collect();
+    let rotation = if rotation_step_degrees == 0.0 {
+        RotationMode::None
+    } else if rotation_step_degrees == 90.0 {
+        RotationMode::RightAngles
+    } else {
+        RotationMode::Free { step_degrees: rotation_step_degrees }
+    };
+    let options = ArrangeOptions { bed_width, bed_depth, spacing, rotation };
+    Ok(BedArrangement { arrangement: arrange_parts(&parts, &options) })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::plane_section::tests::create_box_geometry;
+    use crate::vector3::Vector3;
+
+    fn placed_hull(part: &[f32], placement: &PartPlacement) -> Vec<Vector2> {
+        part_footprint(part)
+            .iter()
+            .map(|&p| rotate(p, placement.rotation) + placement.translation)
+            .collect()
+    }
+
+    fn check_arrangement(parts: &[Vec<f32>], options: &ArrangeOptions, arrangement: &Arrangement) {
+        let hulls: Vec<Option<Vec<Vector2>>> = parts
+            .iter()
+            .zip(&arrangement.placements)
+            .map(|(part, p)| p.as_ref().map(|p| placed_hull(part, p)))
+            .collect();
+        for (i, hull) in hulls.iter().enumerate() {
+            let Some(hull) = hull else {
+                assert!(arrangement.unplaced.contains(&i));
+                continue;
+            };
+            let (min, max) = bounds(hull);
+            assert!(min.x >= -1e-4 && min.y >= -1e-4, "part {} is outside of the bed: {:?}", i, min);
+            assert!(max.x <= options.bed_width + 1e-4 && max.y <= options.bed_depth + 1e-4, "part {} is outside", i);
+            for other in hulls[i + 1..].iter().flatten() {
+                assert!(separated(hull, other, options.spacing - 1e-4), "part {} overlaps", i);
+            }
+        }
+    }
+
+    fn box_part(w: f32, d: f32) -> Vec<f32> {
+        create_box_geometry(Vector3::new(-w / 2.0, -d / 2.0, 0.0), Vector3::new(w / 2.0, d / 2.0, 1.0))
+    }
+
+    #[test]
+    fn test_separated() {
+        let a = vec![Vector2::new(0.0, 0.0), Vector2::new(1.0, 0.0), Vector2::new(1.0, 1.0), Vector2::new(0.0, 1.0)];
+        let b: Vec<Vector2> = a.iter().map(|&p| p + Vector2::new(1.5, 0.0)).collect();
+        assert!(separated(&a, &b, 0.5));
+        assert!(!separated(&a, &b, 0.6));
+        let c: Vec<Vector2> = a.iter().map(|&p| p + Vector2::new(0.5, 0.5)).collect();
+        assert!(!separated(&a, &c, 0.0));
+    }
+
+    #[test]
+    fn test_grid_of_boxes() {
+        // Nine 10x10 boxes with 2mm spacing fit exactly into a 34x34 bed.
+        let parts: Vec<Vec<f32>> = (0..9).map(|_| box_part(10.0, 10.0)).collect();
+        let options = ArrangeOptions { bed_width: 34.0, bed_depth: 34.0, spacing: 2.0, rotation: RotationMode::None };
+        let arrangement = arrange_parts(&parts, &options);
+        assert!(arrangement.unplaced.is_empty());
+        check_arrangement(&parts, &options, &arrangement);
+
+        // The tenth box does not fit.
+        let parts: Vec<Vec<f32>> = (0..10).map(|_| box_part(10.0, 10.0)).collect();
+        let arrangement = arrange_parts(&parts, &options);
+        assert_eq!(arrangement.unplaced.len(), 1);
+        check_arrangement(&parts, &options, &arrangement);
+    }
+
+    #[test]
+    fn test_rotation() {
+        // A long part fits into a narrow bed only when rotated.
+        let parts = vec![box_part(30.0, 5.0)];
+        let mut options =
+            ArrangeOptions { bed_width: 10.0, bed_depth: 40.0, spacing: 1.0, rotation: RotationMode::None };
+        assert_eq!(arrange_parts(&parts, &options).unplaced, vec![0]);
+
+        options.rotation = RotationMode::RightAngles;
+        let arrangement = arrange_parts(&parts, &options);
+        assert!(arrangement.unplaced.is_empty());
+        let rotation = arrangement.placements[0].unwrap().rotation;
+        assert!((rotation - std::f32::consts::FRAC_PI_2).abs() < 1e-6);
+        check_arrangement(&parts, &options, &arrangement);
+
+        // A part which fits only diagonally.
+        let parts = vec![box_part(13.0, 1.0)];
+        options =
+            ArrangeOptions { bed_width: 10.0, bed_depth: 10.0, spacing: 1.0, rotation: RotationMode::RightAngles };
+        assert_eq!(arrange_parts(&parts, &options).unplaced, vec![0]);
+        options.rotation = RotationMode::Free { step_degrees: 15.0 };
+        let arrangement = arrange_parts(&parts, &options);
+        assert!(arrangement.unplaced.is_empty());
+        check_arrangement(&parts, &options, &arrangement);
+    }
+
+    #[test]
+    fn test_mixed_parts() {
+        let mut parts = vec![];
+        for i in 0..20 {
+            let size = 3.0 + (i * 7 % 11) as f32;
+            parts.push(box_part(size, 20.0 - size));
+        }
+        let options =
+            ArrangeOptions { bed_width: 100.0, bed_depth: 80.0, spacing: 1.5, rotation: RotationMode::RightAngles };
+        let arrangement = arrange_parts(&parts, &options);
+        assert!(arrangement.unplaced.is_empty());
+        check_arrangement(&parts, &options, &arrangement);
+    }
+
+    #[test]
+    fn test_wasm_wrapper() {
+        let mut pos = box_part(10.0, 10.0);
+        pos.extend(box_part(50.0, 50.0));
+        let arrangement = arrange_on_bed(&pos, &[12, 12], 30.0, 30.0, 1.0, 90.0).unwrap();
+        assert!(arrangement.is_placed(0).unwrap());
+        assert!(!arrangement.is_placed(1).unwrap());
+        assert_eq!(arrangement.unplaced().to_vec(), vec![1]);
+        assert_eq!(arrangement.translation_x(0).unwrap(), 5.0);
+        assert_eq!(arrangement.translation_y(0).unwrap(), 5.0);
+        assert_eq!(arrangement.rotation(0).unwrap(), 0.0);
+        assert_eq!(arrangement.is_placed(2).unwrap_err().code(), "INVALID_INPUT");
+        assert!(arrangement.translation_x(2).is_err());
+
+        for (width, depth, spacing) in
+            [(0.0, 30.0, 1.0), (30.0, -1.0, 1.0), (f32::NAN, 30.0, 1.0), (30.0, f32::INFINITY, 1.0), (30.0, 30.0, -1.0)]
+        {
+            let err = arrange_on_bed(&pos, &[12, 12], width, depth, spacing, 90.0)
+                .err()
+                .unwrap();
+            assert_eq!(err.code(), "INVALID_INPUT");
+        }
+        assert!(arrange_on_bed(&pos, &[12, 12], 30.0, 30.0, f32::NAN, 90.0).is_err());
+        assert!(arrange_on_bed(&pos, &[12, 12], 30.0, 30.0, 0.0, 90.0).is_ok());
+        for step in [-45.0, f32::NAN, f32::INFINITY] {
+            assert!(arrange_on_bed(&pos, &[12, 12], 30.0, 30.0, 1.0, step).is_err());
+        }
+        assert!(arrange_on_bed(&pos, &[12, 12], 30.0, 30.0, 1.0, 0.0).is_ok());
+        assert!(arrange_on_bed(&pos, &[12, 12], 30.0, 30.0, 1.0, 15.0).is_ok());
+    }
+}
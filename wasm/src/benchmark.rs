@@ -1,54 +1,35 @@
-use wasm_bindgen::prelude::*;
+use std::alloc::Layout;
 
-#[wasm_bindgen]
-pub struct Float32Vec {
-    data: Vec<f32>,
-}
-
-#[wasm_bindgen]
-impl Float32Vec {
-    #[wasm_bindgen(constructor)]
-    pub fn new(len: usize) -> Float32Vec {
-        let mut data = Vec::new();
-        data.resize(len, 0.0);
-        Float32Vec {
-            data,
-        }
-    }
+use wasm_bindgen::prelude::*;
 
-    #[wasm_bindgen(getter)]
-    pub fn data_ptr(&mut self) -> *mut f32 {
-        self.data.as_mut_ptr()
-    }
+use crate::error::Error;
+use crate::typed_vec::{Float32Vec, try_zeroed_vec};
 
-    #[wasm_bindgen(getter)]
-    pub fn len(&self) -> usize {
-        self.data.len()
-    }
-
-    // This method is a bit slower than creating a Float32Array in JS code using data_ptr + len.
-    #[wasm_bindgen(getter)]
-    pub fn array(&self) -> js_sys::Float32Array {
-        unsafe { js_sys::Float32Array::view(&self.data) }
+fn raw_layout(n: usize) -> Result<Layout, Error> {
+    if n == 0 {
+        return Err(Error::InvalidInput("can not allocate 0 bytes".to_string()));
     }
+    Layout::from_size_align(n, 16).map_err(|_| Error::InvalidInput(format!("invalid allocation size {}", n)))
 }
 
 #[wasm_bindgen]
-pub fn alloc(n: usize) -> *mut u8 {
-    unsafe { std::alloc::alloc(std::alloc::Layout::from_size_align_unchecked(n, 16)) }
+pub fn alloc(n: usize) -> Result<*mut u8, Error> {
+    let ptr = unsafe { std::alloc::alloc(raw_layout(n)?) };
+    if ptr.is_null() {
+        return Err(Error::OutOfMemory(format!("failed to allocate {} bytes", n)));
+    }
+    Ok(ptr)
 }
 
+/// Frees memory returned by alloc, `n` must be the size passed to alloc.
 #[wasm_bindgen]
-pub fn dealloc(ptr: *mut u8, n: usize) {
-    unsafe {
-        std::alloc::dealloc(ptr, std::alloc::Layout::from_size_align_unchecked(n, 16))
+pub fn dealloc(ptr: *mut u8, n: usize) -> Result<(), Error> {
+    let layout = raw_layout(n)?;
+    if ptr.is_null() {
+        return Err(Error::InvalidInput("can not free a null pointer".to_string()));
     }
-}
-
-#[repr(C)]
-pub struct SpanPair {
-    ptr: *mut u8,
-    len: usize,
+    unsafe { std::alloc::dealloc(ptr, layout) }
+    Ok(())
 }
 
 #[wasm_bindgen]
@@ -62,25 +43,20 @@ pub fn triple_array(input: &[f32]) -> Box<[f32]> {
 
 #[wasm_bindgen]
 pub fn triple_array_with_vec(input: &Float32Vec) -> Float32Vec {
-    let mut result = vec![0.0; input.data.len()];
-    for (src, dst) in input.data.iter().zip(&mut result) {
+    let mut result = vec![0.0; input.len()];
+    for (src, dst) in input.as_slice().iter().zip(&mut result) {
         *dst = *src * 3.0;
     }
-    Float32Vec {
-        data: result,
-    }
+    Float32Vec::from(result)
 }
 
+/// Reads `n` floats from memory allocated with alloc, the result is owned by JS and must be freed there.
 #[wasm_bindgen]
-pub fn triple_array_raw(output_ptr: *mut SpanPair, input_ptr: *mut f32, n: usize) {
-    let result_ptr = alloc(n * 4) as *mut f32;
-    unsafe {
-        let input = std::slice::from_raw_parts(input_ptr, n);
-        let output = std::slice::from_raw_parts_mut(result_ptr, n);
-        for (src, dst) in input.iter().zip(output) {
-            *dst = *src * 3.0;
-        }
-        (*output_ptr).ptr = result_ptr as *mut u8;
-        (*output_ptr).len = n;
+pub fn triple_array_raw(input_ptr: *const f32, n: usize) -> Result<Float32Vec, Error> {
+    let mut result = try_zeroed_vec(n)?;
+    let input = unsafe { std::slice::from_raw_parts(input_ptr, n) };
+    for (src, dst) in input.iter().zip(&mut result) {
+        *dst = *src * 3.0;
     }
+    Ok(Float32Vec::from(result))
 }
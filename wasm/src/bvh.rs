@@ -0,0 +1,285 @@
+use crate::error::Error;
+use crate::indexed_mesh::IndexedMesh;
+use crate::parallel;
+use crate::progress::Progress;
+use crate::vector3::Vector3;
+
+// Leaves with more triangles are split. Small leaves make the tree deeper, large ones make the leaf tests slower.
+const MAX_LEAF_SIZE: usize = 4;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+struct BvhNode {
+    bbox_min: Vector3,
+    bbox_max: Vector3,
+    /// For leaves, the first index in Bvh::tri_indices. For inner nodes, the index of the left child, the right child
+    /// follows it.
+    first: u32,
+    /// Number of triangles in a leaf, 0 for inner nodes.
+    count: u32,
+}
+
+/// Ray intersection with a mesh triangle.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct RayHit {
+    pub tri_idx: usize,
+    /// Ray parameter of the hit, i.e. the hit point is origin + dir * distance. It is the distance if dir is normalized.
+    pub distance: f32,
+}
+
+/// Bounding volume hierarchy over the triangles of an IndexedMesh. The tree is built by splitting the triangles at the
+/// median of their centroids along the longest axis, which keeps its depth logarithmic.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Bvh {
+    nodes: Vec<BvhNode>,
+    /// Triangle indices ordered so that each leaf references a contiguous range.
+    tri_indices: Vec<u32>,
+}
+
+fn component(v: Vector3, axis: usize) -> f32 {
+    match axis {
+        0 => v.x,
+        1 => v.y,
+        _ => v.z,
+    }
+}
+
+fn min(a: Vector3, b: Vector3) -> Vector3 {
+    Vector3::new(a.x.min(b.x), a.y.min(b.y), a.z.min(b.z))
+}
+
+fn max(a: Vector3, b: Vector3) -> Vector3 {
+    Vector3::new(a.x.max(b.x), a.y.max(b.y), a.z.max(b.z))
+}
+
+const EMPTY_MIN: Vector3 = Vector3 { x: f32::INFINITY, y: f32::INFINITY, z: f32::INFINITY };
+const EMPTY_MAX: Vector3 = Vector3 { x: f32::NEG_INFINITY, y: f32::NEG_INFINITY, z: f32::NEG_INFINITY };
+
+// Returns the ray parameter where the ray enters the box, or None if it misses the box or enters it after max_t.
+fn intersect_box(origin: Vector3, inv_dir: Vector3, bbox_min: Vector3, bbox_max: Vector3, max_t: f32) -> Option<f32> {
+    let mut t_enter = 0.0f32;
+    let mut t_exit = max_t;
+    for axis in 0..3 {
+        let o = component(origin, axis);
+        let inv = component(inv_dir, axis);
+        let t1 = (component(bbox_min, axis) - o) * inv;
+        let t2 = (component(bbox_max, axis) - o) * inv;
+        // NaN appears when the ray lies in the slab plane, min/max ignore it.
+        t_enter = t_enter.max(t1.min(t2));
+        t_exit = t_exit.min(t1.max(t2));
+    }
+    (t_enter <= t_exit).then_some(t_enter)
+}
+
+// Moller-Trumbore intersection, both sides of the triangle are hit.
+pub(crate) fn intersect_triangle(origin: Vector3, dir: Vector3, [a, b, c]: [Vector3; 3]) -> Option<f32> {
+    let edge1 = b - a;
+    let edge2 = c - a;
+    let p = dir.cross(edge2);
+    let det = edge1.dot(p);
+    if det == 0.0 || !det.is_finite() {
+        return None;
+    }
+    let inv_det = 1.0 / det;
+    let s = origin - a;
+    let u = s.dot(p) * inv_det;
+    if !(0.0..=1.0).contains(&u) {
+        return None;
+    }
+    let q = s.cross(edge1);
+    let v = dir.dot(q) * inv_det;
+    if v < 0.0 || u + v > 1.0 {
+        return None;
+    }
+    let t = edge2.dot(q) * inv_det;
+    (t >= 0.0).then_some(t)
+}
+
+impl Bvh {
+    /// Builds the tree, reports the progress and stops if the operation is cancelled. The progress is the fraction of
+    /// triangles placed into the leaves.
+    pub fn build(mesh: &IndexedMesh, progress: Progress) -> Result<Self, Error> {
+        let tri_count = mesh.triangles.len();
+        if tri_count == 0 {
+            progress.update(0, 0)?;
+            return Ok(Self::default());
+        }
+        let bounds: Vec<(Vector3, Vector3)> = parallel::map_range(tri_count, |t| {
+            let [a, b, c] = mesh.triangle(t);
+            (min(min(a, b), c), max(max(a, b), c))
+        });
+        let centroid = |t: u32| {
+            let (lo, hi) = bounds[t as usize];
+            (lo + hi) * 0.5
+        };
+
+        let mut tri_indices: Vec<u32> = (0..tri_count as u32).collect();
+        let mut nodes = vec![BvhNode { bbox_min: EMPTY_MIN, bbox_max: EMPTY_MAX, first: 0, count: tri_count as u32 }];
+        let mut stack = vec![0];
+        let mut leaf_tri_count = 0;
+        while let Some(node_idx) = stack.pop() {
+            progress.update(leaf_tri_count, tri_count)?;
+            let first = nodes[node_idx].first as usize;
+            let count = nodes[node_idx].count as usize;
+            let tris = &mut tri_indices[first..first + count];
+            // min and max do not depend on the order, so the bounds are the same for any number of threads.
+            let [bbox_min, bbox_max, centroid_min, centroid_max] = parallel::map_reduce(
+                tris,
+                [EMPTY_MIN, EMPTY_MAX, EMPTY_MIN, EMPTY_MAX],
+                |&t| {
+                    let (lo, hi) = bounds[t as usize];
+                    [lo, hi, centroid(t), centroid(t)]
+                },
+                |a, b| [min(a[0], b[0]), max(a[1], b[1]), min(a[2], b[2]), max(a[3], b[3])],
+            );
+            nodes[node_idx].bbox_min = bbox_min;
+            nodes[node_idx].bbox_max = bbox_max;
+
+            let extent = centroid_max - centroid_min;
+            let axis = if extent.x >= extent.y && extent.x >= extent.z {
+                0
+            } else if extent.y >= extent.z {
+                1
+            } else {
+                2
+            };
+            // Triangles with the same centroid can not be separated, keep them in a larger leaf.
+            let spread = component(extent, axis);
+            if count <= MAX_LEAF_SIZE || spread.is_nan() || spread <= 0.0 {
+                leaf_tri_count += count;
+                continue;
+            }
+            let half = count / 2;
+            tris.select_nth_unstable_by(half, |&a, &b| {
+                component(centroid(a), axis).total_cmp(&component(centroid(b), axis))
+            });
+            let left = nodes.len();
+            for (first, count) in [(first, half), (first + half, count - half)] {
+                nodes.push(BvhNode {
+                    bbox_min: EMPTY_MIN,
+                    bbox_max: EMPTY_MAX,
+                    first: first as u32,
+                    count: count as u32,
+                });
+            }
+            nodes[node_idx].first = left as u32;
+            nodes[node_idx].count = 0;
+            stack.push(left);
+            stack.push(left + 1);
+        }
+        progress.update(tri_count, tri_count)?;
+        Ok(Self { nodes, tri_indices })
+    }
+
+    /// Finds the nearest triangle hit by the ray starting at `origin` in the direction `dir`. `mesh` must be the mesh
+    /// the tree was built for.
+    pub fn raycast(&self, mesh: &IndexedMesh, origin: Vector3, dir: Vector3) -> Option<RayHit> {
+        if self.nodes.is_empty() {
+            return None;
+        }
+        let inv_dir = Vector3::new(1.0 / dir.x, 1.0 / dir.y, 1.0 / dir.z);
+        let mut best: Option<RayHit> = None;
+        let mut stack = vec![0usize];
+        while let Some(node_idx) = stack.pop() {
+            let node = self.nodes[node_idx];
+            let max_t = best.map_or(f32::INFINITY, |hit| hit.distance);
+            if intersect_box(origin, inv_dir, node.bbox_min, node.bbox_max, max_t).is_none() {
+                continue;
+            }
+            if node.count == 0 {
+                stack.push(node.first as usize);
+                stack.push(node.first as usize + 1);
+                continue;
+            }
+            let first = node.first as usize;
+            for &t in &self.tri_indices[first..first + node.count as usize] {
+                if let Some(distance) = intersect_triangle(origin, dir, mesh.triangle(t as usize))
+                    && distance < best.map_or(f32::INFINITY, |hit| hit.distance)
+                {
+                    best = Some(RayHit { tri_idx: t as usize, distance });
+                }
+            }
+        }
+        best
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parallel::tests::with_threads;
+    use crate::plane_section::tests::create_box_geometry;
+    use crate::progress::tests::{assert_complete, run_with_progress};
+    use crate::slicer::tests::create_sphere_geometry;
+
+    fn brute_force_raycast(mesh: &IndexedMesh, origin: Vector3, dir: Vector3) -> Option<f32> {
+        (0..mesh.triangles.len())
+            .filter_map(|t| intersect_triangle(origin, dir, mesh.triangle(t)))
+            .min_by(f32::total_cmp)
+    }
+
+    #[test]
+    fn test_empty() {
+        let bvh = Bvh::build(&IndexedMesh::default(), Progress::none()).unwrap();
+        let hit = bvh.raycast(&IndexedMesh::default(), Vector3::ZERO, Vector3::new(0.0, 0.0, 1.0));
+        assert_eq!(hit, None);
+    }
+
+    #[test]
+    fn test_box() {
+        let mesh = IndexedMesh::from_triangle_soup(&create_box_geometry(
+            Vector3::new(0.0, 0.0, 0.0),
+            Vector3::new(1.0, 2.0, 3.0),
+        ));
+        let bvh = Bvh::build(&mesh, Progress::none()).unwrap();
+        let hit = bvh
+            .raycast(&mesh, Vector3::new(0.5, 0.5, 10.0), Vector3::new(0.0, 0.0, -1.0))
+            .unwrap();
+        assert_eq!(hit.distance, 7.0);
+        let [a, b, c] = mesh.triangle(hit.tri_idx);
+        assert!(a.z == 3.0 && b.z == 3.0 && c.z == 3.0);
+        // From the inside the ray hits the far side.
+        let hit = bvh
+            .raycast(&mesh, Vector3::new(0.5, 0.5, 1.0), Vector3::new(0.0, 2.0, 0.0))
+            .unwrap();
+        assert_eq!(hit.distance, 0.75);
+        assert_eq!(bvh.raycast(&mesh, Vector3::new(0.5, 0.5, 10.0), Vector3::new(0.0, 0.0, 1.0)), None);
+        assert_eq!(bvh.raycast(&mesh, Vector3::new(5.0, 0.5, 10.0), Vector3::new(0.0, 0.0, -1.0)), None);
+    }
+
+    #[test]
+    fn test_matches_brute_force() {
+        let mut pos = create_sphere_geometry(1.0, 32, 16);
+        pos.extend(create_box_geometry(Vector3::new(2.0, -1.0, -1.0), Vector3::new(3.0, 1.0, 1.0)));
+        let mesh = IndexedMesh::from_triangle_soup(&pos);
+        let bvh = Bvh::build(&mesh, Progress::none()).unwrap();
+        for i in 0..200 {
+            let angle = i as f32 * 0.37;
+            let origin = Vector3::new(5.0 * angle.cos(), 5.0 * angle.sin(), (i % 7) as f32 * 0.3 - 1.0);
+            let target = Vector3::new((i % 5) as f32 * 0.6 - 0.5, (i % 3) as f32 * 0.4 - 0.4, 0.1);
+            let dir = target - origin;
+            let expected = brute_force_raycast(&mesh, origin, dir);
+            let hit = bvh.raycast(&mesh, origin, dir);
+            assert_eq!(hit.map(|h| h.distance), expected, "ray {}", i);
+            if let Some(hit) = hit {
+                assert_eq!(intersect_triangle(origin, dir, mesh.triangle(hit.tri_idx)), expected);
+            }
+        }
+    }
+
+    #[test]
+    fn test_progress() {
+        let mesh = IndexedMesh::from_triangle_soup(&create_sphere_geometry(1.0, 32, 16));
+        let (result, fractions) = run_with_progress(2.0, |progress| Bvh::build(&mesh, progress));
+        assert_eq!(result, Bvh::build(&mesh, Progress::none()));
+        assert_complete(&fractions);
+        let (result, _) = run_with_progress(0.5, |progress| Bvh::build(&mesh, progress));
+        assert_eq!(result, Err(Error::Cancelled));
+    }
+
+    #[test]
+    fn test_threads() {
+        let mesh = IndexedMesh::from_triangle_soup(&create_sphere_geometry(1.0, 128, 64));
+        let expected = with_threads(1, || Bvh::build(&mesh, Progress::none()).unwrap());
+        assert_eq!(with_threads(4, || Bvh::build(&mesh, Progress::none()).unwrap()), expected);
+    }
+}
@@ -0,0 +1,321 @@
+use std::collections::HashMap;
+
+use crate::vector3::Vector3;
+
+/// A convex hull as a list of triangles with outward-facing normals, indices refer to the input points.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct ConvexHull {
+    pub triangles: Vec<[u32; 3]>,
+}
+
+// Hull computations are done in f64: quickhull is sensitive to rounding errors and the points are few compared to
+// the mesh size.
+#[derive(Clone, Copy, Debug)]
+struct Point {
+    x: f64,
+    y: f64,
+    z: f64,
+}
+
+impl Point {
+    fn sub(self, o: Self) -> Self {
+        Self { x: self.x - o.x, y: self.y - o.y, z: self.z - o.z }
+    }
+
+    fn cross(self, o: Self) -> Self {
+        Self { x: self.y * o.z - self.z * o.y, y: self.z * o.x - self.x * o.z, z: self.x * o.y - self.y * o.x }
+    }
+
+    fn dot(self, o: Self) -> f64 {
+        self.x * o.x + self.y * o.y + self.z * o.z
+    }
+
+    fn length(self) -> f64 {
+        self.dot(self).sqrt()
+    }
+}
+
+struct Face {
+    v: [u32; 3],
+    normal: Point,
+    offset: f64,
+    outside: Vec<u32>,
+    alive: bool,
+}
+
+impl Face {
+    fn new(points: &[Point], v: [u32; 3]) -> Self {
+        let [a, b, c] = v.map(|i| points[i as usize]);
+        let n = b.sub(a).cross(c.sub(a));
+        let len = n.length();
+        let normal = if len > 0.0 { Point { x: n.x / len, y: n.y / len, z: n.z / len } } else { n };
+        Self { v, normal, offset: normal.dot(a), outside: vec![], alive: true }
+    }
+
+    fn distance(&self, p: Point) -> f64 {
+        self.normal.dot(p) - self.offset
+    }
+}
+
+/// Computes the convex hull of the points using quickhull. Returns an empty hull if the points are (nearly) coplanar.
+pub fn convex_hull(input: &[Vector3]) -> ConvexHull {
+    let points: Vec<Point> = input
+        .iter()
+        .map(|p| Point { x: p.x as f64, y: p.y as f64, z: p.z as f64 })
+        .collect();
+    if points.len() < 4 {
+        return ConvexHull::default();
+    }
+
+    // The initial tetrahedron: two extreme points along an axis, the farthest point from their line and the farthest
+    // point from their plane.
+    let coord = |i: usize, axis: usize| [points[i].x, points[i].y, points[i].z][axis];
+    let (mut i0, mut i1, mut max_dist) = (0, 0, -1.0);
+    for axis in 0..3 {
+        let min = (0..points.len())
+            .min_by(|&a, &b| coord(a, axis).total_cmp(&coord(b, axis)))
+            .unwrap();
+        let max = (0..points.len())
+            .max_by(|&a, &b| coord(a, axis).total_cmp(&coord(b, axis)))
+            .unwrap();
+        let d = points[max].sub(points[min]).length();
+        if d > max_dist {
+            (i0, i1, max_dist) = (min, max, d);
+        }
+    }
+    if max_dist <= 0.0 {
+        return ConvexHull::default();
+    }
+    // Points closer than epsilon to a face are considered to be on it.
+    let epsilon = max_dist * 1e-10;
+    let line = points[i1].sub(points[i0]);
+    let i2 = (0..points.len())
+        .max_by(|&a, &b| {
+            let da = line.cross(points[a].sub(points[i0])).length();
+            let db = line.cross(points[b].sub(points[i0])).length();
+            da.total_cmp(&db)
+        })
+        .unwrap();
+    if line.cross(points[i2].sub(points[i0])).length() / max_dist <= epsilon {
+        return ConvexHull::default();
+    }
+    let base = Face::new(&points, [i0 as u32, i1 as u32, i2 as u32]);
+    let i3 = (0..points.len())
+        .max_by(|&a, &b| {
+            base.distance(points[a])
+                .abs()
+                .total_cmp(&base.distance(points[b]).abs())
+        })
+        .unwrap();
+    if base.distance(points[i3]).abs() <= epsilon {
+        return ConvexHull::default();
+    }
+
+    let [i0, i1, i2, i3] = [i0 as u32, i1 as u32, i2 as u32, i3 as u32];
+    let tetra = if base.distance(points[i3 as usize]) < 0.0 {
+        [[i0, i1, i2], [i0, i3, i1], [i1, i3, i2], [i2, i3, i0]]
+    } else {
+        [[i0, i2, i1], [i0, i1, i3], [i1, i2, i3], [i2, i0, i3]]
+    };
+    let mut faces: Vec<Face> = tetra.iter().map(|&v| Face::new(&points, v)).collect();
+    // Maps directed edge -> face containing it.
+    let mut edge_faces: HashMap<(u32, u32), usize> = HashMap::new();
+    for (f, face) in faces.iter().enumerate() {
+        for i in 0..3 {
+            edge_faces.insert((face.v[i], face.v[(i + 1) % 3]), f);
+        }
+    }
+    for p in 0..points.len() as u32 {
+        if [i0, i1, i2, i3].contains(&p) {
+            continue;
+        }
+        if let Some(face) = faces
+            .iter_mut()
+            .find(|face| face.distance(points[p as usize]) > epsilon)
+        {
+            face.outside.push(p);
+        }
+    }
+
+    let mut pending: Vec<usize> = (0..faces.len()).collect();
+    while let Some(f) = pending.pop() {
+        if !faces[f].alive || faces[f].outside.is_empty() {
+            continue;
+        }
+        let eye = *faces[f]
+            .outside
+            .iter()
+            .max_by(|&&a, &&b| {
+                faces[f]
+                    .distance(points[a as usize])
+                    .total_cmp(&faces[f].distance(points[b as usize]))
+            })
+            .unwrap();
+        let eye_point = points[eye as usize];
+
+        // Find all faces visible from the eye point with a DFS over face adjacency.
+        let mut visible = vec![f];
+        let mut stack = vec![f];
+        faces[f].alive = false;
+        while let Some(cur) = stack.pop() {
+            for i in 0..3 {
+                let (a, b) = (faces[cur].v[i], faces[cur].v[(i + 1) % 3]);
+                let Some(&neighbor) = edge_faces.get(&(b, a)) else {
+                    continue;
+                };
+                if faces[neighbor].alive && faces[neighbor].distance(eye_point) > epsilon {
+                    faces[neighbor].alive = false;
+                    visible.push(neighbor);
+                    stack.push(neighbor);
+                }
+            }
+        }
+
+        // The horizon consists of edges of visible faces whose neighbors are not visible.
+        let mut horizon = vec![];
+        for &vf in &visible {
+            for i in 0..3 {
+                let (a, b) = (faces[vf].v[i], faces[vf].v[(i + 1) % 3]);
+                if let Some(&neighbor) = edge_faces.get(&(b, a))
+                    && faces[neighbor].alive
+                {
+                    horizon.push((a, b));
+                }
+            }
+        }
+        for &vf in &visible {
+            for i in 0..3 {
+                edge_faces.remove(&(faces[vf].v[i], faces[vf].v[(i + 1) % 3]));
+            }
+        }
+
+        let first_new = faces.len();
+        for &(a, b) in &horizon {
+            let face = Face::new(&points, [a, b, eye]);
+            for i in 0..3 {
+                edge_faces.insert((face.v[i], face.v[(i + 1) % 3]), faces.len());
+            }
+            faces.push(face);
+        }
+
+        let orphans: Vec<u32> = visible
+            .iter()
+            .flat_map(|&vf| std::mem::take(&mut faces[vf].outside))
+            .collect();
+        for p in orphans {
+            if p == eye {
+                continue;
+            }
+            let point = points[p as usize];
+            if let Some(face) = faces[first_new..]
+                .iter_mut()
+                .find(|face| face.distance(point) > epsilon)
+            {
+                face.outside.push(p);
+            }
+        }
+        pending.extend(first_new..faces.len());
+    }
+
+    ConvexHull { triangles: faces.iter().filter(|f| f.alive).map(|f| f.v).collect() }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn hull_volume(points: &[Vector3], hull: &ConvexHull) -> f32 {
+        hull.triangles
+            .iter()
+            .map(|t| {
+                let [a, b, c] = t.map(|i| points[i as usize]);
+                a.dot(b.cross(c)) / 6.0
+            })
+            .sum()
+    }
+
+    fn assert_closed(hull: &ConvexHull) {
+        let mut edges = HashMap::new();
+        for t in &hull.triangles {
+            for i in 0..3 {
+                *edges.entry((t[i], t[(i + 1) % 3])).or_insert(0) += 1;
+            }
+        }
+        for (&(a, b), &count) in &edges {
+            assert_eq!(count, 1);
+            assert_eq!(edges.get(&(b, a)), Some(&1), "edge {}-{} has no twin", a, b);
+        }
+    }
+
+    #[test]
+    fn test_degenerate() {
+        assert!(convex_hull(&[]).triangles.is_empty());
+        let flat = [
+            Vector3::new(0.0, 0.0, 0.0),
+            Vector3::new(1.0, 0.0, 0.0),
+            Vector3::new(0.0, 1.0, 0.0),
+            Vector3::new(1.0, 1.0, 0.0),
+        ];
+        assert!(convex_hull(&flat).triangles.is_empty());
+        let line =
+            [Vector3::ZERO, Vector3::new(1.0, 1.0, 1.0), Vector3::new(2.0, 2.0, 2.0), Vector3::new(3.0, 3.0, 3.0)];
+        assert!(convex_hull(&line).triangles.is_empty());
+    }
+
+    #[test]
+    fn test_tetrahedron() {
+        let points = [
+            Vector3::new(0.0, 0.0, 0.0),
+            Vector3::new(1.0, 0.0, 0.0),
+            Vector3::new(0.0, 1.0, 0.0),
+            Vector3::new(0.0, 0.0, 1.0),
+            // Inside point
+            Vector3::new(0.1, 0.1, 0.1),
+        ];
+        let hull = convex_hull(&points);
+        assert_eq!(hull.triangles.len(), 4);
+        assert_closed(&hull);
+        assert!((hull_volume(&points, &hull) - 1.0 / 6.0).abs() < 1e-6);
+        assert!(hull.triangles.iter().all(|t| !t.contains(&4)));
+    }
+
+    #[test]
+    fn test_cube_with_inner_points() {
+        let mut points = vec![];
+        for i in 0..1000 {
+            // Deterministic pseudo-random points inside the cube.
+            let f = |k: u32| ((i * 7919 + k * 104729) % 1000) as f32 / 1000.0 * 1.8 - 0.9;
+            points.push(Vector3::new(f(1), f(2), f(3)));
+        }
+        for corner in 0..8 {
+            let c = |bit: u32| if corner & (1 << bit) != 0 { 1.0 } else { -1.0 };
+            points.push(Vector3::new(c(0), c(1), c(2)));
+        }
+        let hull = convex_hull(&points);
+        assert_closed(&hull);
+        assert!((hull_volume(&points, &hull) - 8.0).abs() < 1e-4);
+        for t in &hull.triangles {
+            assert!(t.iter().all(|&i| i >= 1000), "only corners must be hull vertices");
+        }
+    }
+
+    #[test]
+    fn test_sphere_points() {
+        let mut points = vec![];
+        for i in 0..20 {
+            for j in 0..40 {
+                let theta = std::f32::consts::PI * (i as f32 + 0.5) / 20.0;
+                let phi = 2.0 * std::f32::consts::PI * j as f32 / 40.0;
+                points.push(Vector3::new(theta.sin() * phi.cos(), theta.sin() * phi.sin(), theta.cos()));
+            }
+        }
+        let hull = convex_hull(&points);
+        assert_closed(&hull);
+        let volume = hull_volume(&points, &hull);
+        assert!(volume > 3.9 && volume < 4.0 / 3.0 * std::f32::consts::PI, "{}", volume);
+        // The hull is a closed triangulated sphere, so it has 2 * V - 4 triangles.
+        let vertices: std::collections::HashSet<u32> = hull.triangles.iter().flatten().copied().collect();
+        assert_eq!(hull.triangles.len(), 2 * vertices.len() - 4);
+        assert!(vertices.len() > points.len() / 2);
+    }
+}
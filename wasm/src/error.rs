@@ -0,0 +1,203 @@
+use std::fmt;
+
+use wasm_bindgen::prelude::*;
+
+/// Error returned by the exported functions. In JS it becomes an `Error` with the message and a `code` property, see
+/// Error::code.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Error {
+    /// The arguments or the file contents are malformed.
+    InvalidInput(String),
+    /// The file ends before the data it declares.
+    TruncatedFile(String),
+    /// The file format (or a feature of the format) is not supported.
+    UnsupportedFormat(String),
+    /// The allocation failed or the requested size is too large.
+    OutOfMemory(String),
+    /// The operation was cancelled with a ProgressToken.
+    Cancelled,
+}
+
+impl Error {
+    /// Stable code which JS can rely on, unlike the message.
+    pub fn code(&self) -> &'static str {
+        match self {
+            Error::InvalidInput(_) => "INVALID_INPUT",
+            Error::TruncatedFile(_) => "TRUNCATED_FILE",
+            Error::UnsupportedFormat(_) => "UNSUPPORTED_FORMAT",
+            Error::OutOfMemory(_) => "OUT_OF_MEMORY",
+            Error::Cancelled => "CANCELLED",
+        }
+    }
+
+    /// Prepends the context (e.g. the file name) to the message, keeping the kind of the error.
+    pub fn context(self, context: &str) -> Self {
+        let with_context = |message: String| format!("{}: {}", context, message);
+        match self {
+            Error::InvalidInput(message) => Error::InvalidInput(with_context(message)),
+            Error::TruncatedFile(message) => Error::TruncatedFile(with_context(message)),
+            Error::UnsupportedFormat(message) => Error::UnsupportedFormat(with_context(message)),
+            Error::OutOfMemory(message) => Error::OutOfMemory(with_context(message)),
+            Error::Cancelled => Error::Cancelled,
+        }
+    }
+
+    pub fn message(&self) -> &str {
+        match self {
+            Error::InvalidInput(message)
+            | Error::TruncatedFile(message)
+            | Error::UnsupportedFormat(message)
+            | Error::OutOfMemory(message) => message,
+            Error::Cancelled => "the operation was cancelled",
+        }
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.message())
+    }
+}
+
+impl std::error::Error for Error {}
+
+// Most parsers report malformed data with plain messages.
+impl From<String> for Error {
+    fn from(message: String) -> Self {
+        Error::InvalidInput(message)
+    }
+}
+
+impl From<&str> for Error {
+    fn from(message: &str) -> Self {
+        Error::InvalidInput(message.to_string())
+    }
+}
+
+impl From<Error> for JsValue {
+    fn from(error: Error) -> Self {
+        let js_error = js_sys::Error::new(error.message());
+        // Setting a property on a fresh object can not fail.
+        let _ = js_sys::Reflect::set(&js_error, &JsValue::from_str("code"), &JsValue::from_str(error.code()));
+        js_error.into()
+    }
+}
+
+/// Checks that `pos` is a triangle soup: interleaved x, y, z coordinates, 9 floats per triangle.
+pub fn check_triangle_soup(pos: &[f32]) -> Result<(), Error> {
+    if !pos.len().is_multiple_of(9) {
+        return Err(Error::InvalidInput(format!("triangle soup length {} is not a multiple of 9", pos.len())));
+    }
+    Ok(())
+}
+
+/// Returns the item with index `idx`, e.g. passed from JS to a getter, an index out of range is an InvalidInput error.
+pub fn checked_item<'a, T>(items: &'a [T], idx: usize, kind: &str) -> Result<&'a T, Error> {
+    items.get(idx).ok_or_else(|| {
+        Error::InvalidInput(format!("{} index {} is out of range, the count is {}", kind, idx, items.len()))
+    })
+}
+
+/// Splits a triangle soup into parts with `part_tri_counts` triangles each, the counts must add up to the soup length.
+pub fn split_by_tri_counts<'a>(pos: &'a [f32], part_tri_counts: &[u32]) -> Result<Vec<&'a [f32]>, Error> {
+    check_triangle_soup(pos)?;
+    let total: u64 = part_tri_counts.iter().map(|&c| c as u64).sum();
+    if total * 9 != pos.len() as u64 {
+        return Err(Error::InvalidInput(format!(
+            "part triangle counts add up to {}, the triangle soup has {} triangles",
+            total,
+            pos.len() / 9
+        )));
+    }
+    let mut offset = 0;
+    Ok(part_tri_counts
+        .iter()
+        .map(|&count| {
+            let part = &pos[offset..offset + count as usize * 9];
+            offset += count as usize * 9;
+            part
+        })
+        .collect())
+}
+
+/// Part of a model exported from JS, see split_export_parts.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ExportPart<'a> {
+    pub name: String,
+    pub positions: &'a [f32],
+    /// sRGB color, None for the parts past the end of the colors.
+    pub color: Option<[u8; 3]>,
+}
+
+/// Splits a triangle soup into parts like split_by_tri_counts, `colors` contains an RGB color for each part and
+/// `names` a name for each part. Parts without a name get "part N".
+pub fn split_export_parts<'a>(
+    pos: &'a [f32],
+    part_tri_counts: &[u32],
+    colors: &[u8],
+    names: Vec<String>,
+) -> Result<Vec<ExportPart<'a>>, Error> {
+    let mut names = names;
+    names.resize(part_tri_counts.len(), String::new());
+    Ok(split_by_tri_counts(pos, part_tri_counts)?
+        .into_iter()
+        .zip(names)
+        .enumerate()
+        .map(|(i, (positions, name))| ExportPart {
+            name: if name.is_empty() { format!("part {}", i + 1) } else { name },
+            positions,
+            color: colors.get(i * 3..i * 3 + 3).map(|c| [c[0], c[1], c[2]]),
+        })
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_codes() {
+        let error = Error::from("bad".to_string());
+        assert_eq!(error, Error::InvalidInput("bad".to_string()));
+        assert_eq!(error.code(), "INVALID_INPUT");
+        assert_eq!(error.to_string(), "bad");
+        let error = Error::TruncatedFile("end".to_string()).context("a.stl");
+        assert_eq!(error, Error::TruncatedFile("a.stl: end".to_string()));
+        assert_eq!(error.code(), "TRUNCATED_FILE");
+        assert_eq!(Error::UnsupportedFormat(String::new()).code(), "UNSUPPORTED_FORMAT");
+        assert_eq!(Error::OutOfMemory(String::new()).code(), "OUT_OF_MEMORY");
+    }
+
+    #[test]
+    fn test_split_by_tri_counts() {
+        let pos: Vec<f32> = (0..27).map(|i| i as f32).collect();
+        let parts = split_by_tri_counts(&pos, &[1, 0, 2]).unwrap();
+        assert_eq!(parts, vec![&pos[..9], &[][..], &pos[9..]]);
+        assert!(split_by_tri_counts(&pos, &[1, 1]).is_err());
+        assert!(split_by_tri_counts(&pos, &[u32::MAX, 4]).is_err());
+        assert!(split_by_tri_counts(&pos[..10], &[1]).is_err());
+        assert!(check_triangle_soup(&[]).is_ok());
+    }
+
+    #[test]
+    fn test_split_export_parts() {
+        let pos: Vec<f32> = (0..27).map(|i| i as f32).collect();
+        let parts =
+            split_export_parts(&pos, &[1, 0, 2], &[1, 2, 3, 4, 5], vec!["a".to_string(), String::new()]).unwrap();
+        let names: Vec<&str> = parts.iter().map(|p| p.name.as_str()).collect();
+        assert_eq!(names, ["a", "part 2", "part 3"]);
+        assert_eq!(parts[0].color, Some([1, 2, 3]));
+        assert_eq!(parts[1].color, None);
+        assert_eq!(parts[2].positions, &pos[9..]);
+        assert!(split_export_parts(&pos, &[1], &[], vec![]).is_err());
+    }
+
+    #[test]
+    fn test_checked_item() {
+        assert_eq!(checked_item(&[1, 2], 1, "part"), Ok(&2));
+        let error = checked_item(&[1, 2], 2, "part").unwrap_err();
+        assert_eq!(error.code(), "INVALID_INPUT");
+        assert_eq!(error.to_string(), "part index 2 is out of range, the count is 2");
+        assert!(checked_item::<u8>(&[], 0, "part").is_err());
+    }
+}
@@ -0,0 +1,529 @@
+use wasm_bindgen::prelude::*;
+
+use crate::amf::parse_amf;
+use crate::error::Error;
+use crate::glb::{is_glb, parse_glb};
+use crate::model::{DEFAULT_PART_NAME, ImportedModel, Model};
+use crate::obj::parse_obj;
+use crate::off::parse_off;
+use crate::ply::parse_ply_model;
+use crate::stl::{is_ascii_stl, is_binary_stl, parse_stl};
+use crate::three_mf::parse_3mf;
+use crate::vrml::{parse_vrml, parse_x3d};
+use crate::xml::{XmlEvent, XmlReader, local_name};
+use crate::zip::{ZipArchive, gunzip, is_gzip};
+
+// Only the beginning of the file is inspected for text formats.
+const SNIFF_LEN: usize = 4096;
+// Statements which may appear in an OBJ file, including the ones for free-form geometry.
+const OBJ_KEYWORDS: &[&str] = &[
+    "v", "vt", "vn", "vp", "f", "l", "p", "o", "g", "s", "mg", "usemtl", "mtllib", "maplib", "usemap", "cstype", "deg",
+    "bmat", "step", "curv", "curv2", "surf", "parm", "trim", "hole", "scrv", "sp", "end",
+];
+
+/// Model file format detected from the contents.
+#[wasm_bindgen]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ModelFormat {
+    Unknown = 0,
+    Off = 1,
+    Amf = 2,
+    /// VRML97 or X3D in the classic VRML encoding.
+    Vrml = 3,
+    /// X3D in the XML encoding.
+    X3d = 4,
+    BinaryStl = 5,
+    AsciiStl = 6,
+    Obj = 7,
+    Ply = 8,
+    ThreeMf = 9,
+    Glb = 10,
+    /// Gzip-compressed file of any other format.
+    Gzip = 11,
+    /// Zip archive with one or more model files (other than 3MF and AMF).
+    Zip = 12,
+}
+
+impl ModelFormat {
+    pub fn name(self) -> &'static str {
+        match self {
+            ModelFormat::Unknown => "unknown",
+            ModelFormat::Off => "OFF",
+            ModelFormat::Amf => "AMF",
+            ModelFormat::Vrml => "VRML",
+            ModelFormat::X3d => "X3D",
+            ModelFormat::BinaryStl => "binary STL",
+            ModelFormat::AsciiStl => "ASCII STL",
+            ModelFormat::Obj => "OBJ",
+            ModelFormat::Ply => "PLY",
+            ModelFormat::ThreeMf => "3MF",
+            ModelFormat::Glb => "GLB",
+            ModelFormat::Gzip => "gzip",
+            ModelFormat::Zip => "zip",
+        }
+    }
+}
+
+// Returns the beginning of the data as text, cutting an incomplete UTF-8 sequence at the end.
+fn text_prefix(data: &[u8]) -> Option<&str> {
+    let prefix = &data[..data.len().min(SNIFF_LEN)];
+    match std::str::from_utf8(prefix) {
+        Ok(text) => Some(text),
+        Err(e) if e.error_len().is_none() => Some(std::str::from_utf8(&prefix[..e.valid_up_to()]).unwrap()),
+        Err(_) => None,
+    }
+}
+
+// Returns the name of the root element of an XML document, skipping the prolog.
+fn xml_root(text: &str) -> Option<&str> {
+    let mut reader = XmlReader::new(text);
+    loop {
+        match reader.next_event() {
+            Ok(Some(XmlEvent::Start { name, .. })) => return Some(local_name(name)),
+            Ok(Some(XmlEvent::Text(_))) => continue,
+            _ => return None,
+        }
+    }
+}
+
+// Returns true if every statement is an OBJ statement and there is at least one vertex. If the text is cut by the
+// sniffing limit, the last line is ignored.
+fn looks_like_obj(text: &str, is_cut: bool) -> bool {
+    let text = match text.rfind('\n') {
+        Some(end) if is_cut => &text[..end],
+        _ => text,
+    };
+    let mut has_vertex = false;
+    for line in text.lines() {
+        let Some(keyword) = line.split_whitespace().next() else {
+            continue;
+        };
+        if keyword.starts_with('#') {
+            continue;
+        }
+        if !OBJ_KEYWORDS.contains(&keyword) {
+            return false;
+        }
+        has_vertex |= keyword == "v";
+    }
+    has_vertex
+}
+
+/// Detects the format of the model from its contents, see format_from_file_name for the fallback. Zip archives are
+/// detected by their entries.
+pub fn detect_format(data: &[u8]) -> ModelFormat {
+    if is_gzip(data) {
+        return ModelFormat::Gzip;
+    }
+    if data.starts_with(b"PK\x03\x04") {
+        // Broken archives are reported by the zip importer.
+        let Ok(archive) = ZipArchive::new(data) else {
+            return ModelFormat::Zip;
+        };
+        let has_extension = |ext: &str| {
+            archive
+                .entries
+                .iter()
+                .any(|e| e.name.to_ascii_lowercase().ends_with(ext))
+        };
+        return if has_extension(".model") {
+            ModelFormat::ThreeMf
+        } else if has_extension(".amf") {
+            ModelFormat::Amf
+        } else {
+            ModelFormat::Zip
+        };
+    }
+    if is_glb(data) {
+        return ModelFormat::Glb;
+    }
+    // Binary STL headers often start with "solid", so the size check goes before the text checks.
+    if is_binary_stl(data) {
+        return ModelFormat::BinaryStl;
+    }
+    // The PLY header is text even for binary PLY files, but the body may follow in the sniffed prefix.
+    if data.starts_with(b"ply\n") || data.starts_with(b"ply\r") {
+        return ModelFormat::Ply;
+    }
+    if is_ascii_stl(data) {
+        return ModelFormat::AsciiStl;
+    }
+    let Some(text) = text_prefix(data) else {
+        return ModelFormat::Unknown;
+    };
+    let text = text.trim_start_matches('\u{feff}').trim_start();
+    if text.starts_with("#VRML") || text.starts_with("#X3D") {
+        return ModelFormat::Vrml;
+    }
+    let first_word = text.split_whitespace().next().unwrap_or_default();
+    // OFF, COFF, NOFF, STCNOFF, 4OFF etc.
+    if let Some(prefix) = first_word.strip_suffix("OFF")
+        && prefix.chars().all(|c| "STCN4n".contains(c))
+    {
+        return ModelFormat::Off;
+    }
+    if text.starts_with('<') {
+        return match xml_root(text) {
+            Some("amf") => ModelFormat::Amf,
+            Some("X3D") => ModelFormat::X3d,
+            _ => ModelFormat::Unknown,
+        };
+    }
+    if looks_like_obj(text, data.len() > SNIFF_LEN) {
+        return ModelFormat::Obj;
+    }
+    ModelFormat::Unknown
+}
+
+/// See detect_format.
+#[wasm_bindgen]
+pub fn detect_model_format(data: &[u8]) -> ModelFormat {
+    detect_format(data)
+}
+
+// Returns the file name without the directories.
+fn base_name(file_name: &str) -> &str {
+    file_name.rsplit(['/', '\\']).next().unwrap_or_default()
+}
+
+// Returns the file name without the directories and the extension.
+fn file_stem(file_name: &str) -> &str {
+    let name = base_name(file_name);
+    match name.rfind('.') {
+        Some(dot) if dot > 0 => &name[..dot],
+        _ => name,
+    }
+}
+
+/// Guesses the format from the file extension. It is used only if the contents are not recognized, e.g. for truncated
+/// binary STLs, which fail the size check, or OBJ files with unknown statements.
+pub fn format_from_file_name(file_name: &str) -> ModelFormat {
+    let name = base_name(file_name);
+    let extension = name.rfind('.').map_or("", |dot| &name[dot + 1..]);
+    match extension.to_ascii_lowercase().as_str() {
+        "stl" => ModelFormat::BinaryStl,
+        "obj" => ModelFormat::Obj,
+        "ply" => ModelFormat::Ply,
+        "3mf" => ModelFormat::ThreeMf,
+        "glb" => ModelFormat::Glb,
+        "off" => ModelFormat::Off,
+        "amf" => ModelFormat::Amf,
+        "wrl" | "vrml" | "x3dv" => ModelFormat::Vrml,
+        "x3d" => ModelFormat::X3d,
+        "gz" => ModelFormat::Gzip,
+        "zip" => ModelFormat::Zip,
+        _ => ModelFormat::Unknown,
+    }
+}
+
+fn utf8(data: &[u8]) -> Result<&str, Error> {
+    std::str::from_utf8(data).map_err(|_| Error::InvalidInput("the file is not valid UTF-8".to_string()))
+}
+
+// Imports all model files from the archive, skipping other files. Models with a single part are named after the file,
+// parts of other models get the file name as a prefix.
+fn parse_zip(data: &[u8]) -> Result<Model, Error> {
+    let archive = ZipArchive::new(data)?;
+    let mut model = Model::default();
+    let mut model_count = 0;
+    let mut first_error = None;
+    for entry in &archive.entries {
+        let name = entry.name.as_str();
+        let base = base_name(name);
+        if base.is_empty() || base.starts_with('.') || name.starts_with("__MACOSX/") {
+            continue;
+        }
+        let entry_model = archive.read(entry).and_then(|contents| {
+            if detect_format(&contents) == ModelFormat::Unknown && format_from_file_name(name) == ModelFormat::Unknown {
+                return Ok(None);
+            }
+            parse_model(&contents, name, true).map(Some)
+        });
+        let entry_model = match entry_model {
+            Ok(Some(entry_model)) => entry_model,
+            Ok(None) => continue,
+            Err(e) => {
+                model.warn(0, format!("skipped {}: {}", name, e));
+                first_error.get_or_insert_with(|| e.context(&format!("failed to import {}", name)));
+                continue;
+            }
+        };
+        model_count += 1;
+        let stem = file_stem(name);
+        let single_part = entry_model.parts.len() == 1;
+        for mut part in entry_model.parts {
+            part.name = if single_part { stem.to_string() } else { format!("{}/{}", stem, part.name) };
+            model.parts.push(part);
+        }
+        for (key, value) in entry_model.metadata {
+            model.metadata.push((format!("{}: {}", name, key), value));
+        }
+        for warning in entry_model.warnings {
+            model.warn(warning.line, format!("{}: {}", name, warning.message));
+        }
+    }
+    if model_count == 0 {
+        return Err(
+            first_error.unwrap_or_else(|| Error::UnsupportedFormat("zip archive has no supported models".to_string()))
+        );
+    }
+    Ok(model)
+}
+
+fn parse_model(data: &[u8], file_name: &str, nested: bool) -> Result<Model, Error> {
+    let mut format = detect_format(data);
+    if format == ModelFormat::Unknown {
+        format = format_from_file_name(file_name);
+    }
+    let mut model = match format {
+        ModelFormat::Gzip | ModelFormat::Zip if nested => {
+            return Err(Error::UnsupportedFormat("nested compressed files are not supported".to_string()));
+        }
+        ModelFormat::Zip => parse_zip(data)?,
+        ModelFormat::Gzip => {
+            let (stored_name, contents) = gunzip(data)?;
+            // The name stored in the header is the original name, otherwise strip the .gz from the given one.
+            let inner_name = stored_name.unwrap_or_else(|| file_stem(file_name).to_string());
+            let mut model = parse_model(&contents, &inner_name, true)?;
+            model
+                .metadata
+                .push(("compression".to_string(), "gzip".to_string()));
+            return Ok(model);
+        }
+        ModelFormat::BinaryStl | ModelFormat::AsciiStl => parse_stl(data)?,
+        ModelFormat::Obj => parse_obj(&String::from_utf8_lossy(data)),
+        ModelFormat::Ply => parse_ply_model(data)?,
+        ModelFormat::ThreeMf => parse_3mf(data)?,
+        ModelFormat::Glb => parse_glb(data)?,
+        ModelFormat::Off => parse_off(utf8(data)?)?,
+        ModelFormat::Amf => parse_amf(data)?,
+        ModelFormat::Vrml => parse_vrml(utf8(data)?)?,
+        ModelFormat::X3d => parse_x3d(utf8(data)?)?,
+        ModelFormat::Unknown => return Err(Error::UnsupportedFormat("unknown model format".to_string())),
+    };
+    model
+        .metadata
+        .insert(0, ("format".to_string(), format.name().to_string()));
+    // Formats without part names get the name of the file.
+    let stem = file_stem(file_name);
+    if !stem.is_empty() {
+        for part in &mut model.parts {
+            if part.name.is_empty() || part.name == DEFAULT_PART_NAME {
+                part.name = stem.to_string();
+            }
+        }
+    }
+    Ok(model)
+}
+
+/// Imports a model of any supported format, see detect_format. Gzip files and zip archives (with one or more models)
+/// are decompressed first, nested archives are not supported. The file name is optional (may be empty): it is used as
+/// a fallback for the format detection and as the name of the parts if the format has no names. The detected format is
+/// stored in the "format" metadata.
+pub fn import_model(data: &[u8], file_name: &str) -> Result<Model, Error> {
+    parse_model(data, file_name, false)
+}
+
+/// See import_model.
+#[wasm_bindgen]
+pub fn load_model(data: &[u8], file_name: &str) -> Result<ImportedModel, Error> {
+    import_model(data, file_name).map(ImportedModel::new)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::glb_writer::write_glb;
+    use crate::stl_writer::{FacetColors, write_ascii_stl, write_binary_stl};
+    use crate::three_mf_writer::write_3mf;
+    use crate::zip::tests::{create_gzip, create_zip};
+
+    #[test]
+    fn test_detect() {
+        assert_eq!(detect_format(b"OFF\n3 1 0\n"), ModelFormat::Off);
+        assert_eq!(detect_format(b"\n  STCNOFF 3 1 0\n"), ModelFormat::Off);
+        assert_eq!(detect_format(b"OFFSET 1"), ModelFormat::Unknown);
+        assert_eq!(detect_format(b"XOFF\n"), ModelFormat::Unknown);
+        assert_eq!(detect_format(b"#VRML V2.0 utf8\n"), ModelFormat::Vrml);
+        assert_eq!(detect_format(b"\xef\xbb\xbf#X3D V3.0 utf8\n"), ModelFormat::Vrml);
+        assert_eq!(
+            detect_format(b"<?xml version=\"1.0\"?>\n<!-- c -->\n<!DOCTYPE X3D>\n<X3D version=\"3.3\">"),
+            ModelFormat::X3d
+        );
+        assert_eq!(detect_format(b"<?xml version=\"1.0\"?><amf unit=\"inch\">"), ModelFormat::Amf);
+        assert_eq!(detect_format(b"<html></html>"), ModelFormat::Unknown);
+        assert_eq!(detect_format(&create_zip(&[("a.AMF", b"<amf/>", false)])), ModelFormat::Amf);
+        assert_eq!(detect_format(&create_zip(&[("a.txt", b"", false)])), ModelFormat::Zip);
+        assert_eq!(detect_format(b"PK\x03\x04 truncated"), ModelFormat::Zip);
+        assert_eq!(detect_format(b""), ModelFormat::Unknown);
+        assert_eq!(detect_format(&[0xff, 0x00, 0x12]), ModelFormat::Unknown);
+    }
+
+    #[test]
+    fn test_truncated_utf8() {
+        // A multi-byte character cut at the sniffing limit does not prevent the detection.
+        let mut data = b"OFF\n".to_vec();
+        data.resize(SNIFF_LEN - 1, b' ');
+        data.extend_from_slice("é".as_bytes());
+        assert_eq!(detect_format(&data), ModelFormat::Off);
+    }
+
+    #[test]
+    fn test_detect_mesh_formats() {
+        let tris = [0.0, 0.0, 0.0, 1.0, 0.0, 0.0, 0.0, 1.0, 0.0];
+        let mut binary = write_binary_stl(&tris, FacetColors::None);
+        binary[..5].copy_from_slice(b"solid");
+        assert_eq!(detect_format(&binary), ModelFormat::BinaryStl);
+        assert_eq!(detect_format(write_ascii_stl(&tris, "x").as_bytes()), ModelFormat::AsciiStl);
+        assert_eq!(
+            detect_format(b"# comment\nmtllib a.mtl\no a\nv 0 0 0\nv 1 0 0\nv 0 1 0\nf 1 2 3\n"),
+            ModelFormat::Obj
+        );
+        assert_eq!(detect_format(b"o a\nf 1 2 3\n"), ModelFormat::Unknown);
+        assert_eq!(detect_format(b"v 0 0 0\nhello\n"), ModelFormat::Unknown);
+        assert_eq!(detect_format(b"ply\nformat binary_little_endian 1.0\n\xff\xfe"), ModelFormat::Ply);
+        assert_eq!(detect_format(&write_glb(&[])), ModelFormat::Glb);
+        assert_eq!(detect_format(&write_3mf(&[])), ModelFormat::ThreeMf);
+        assert_eq!(detect_format(&create_gzip(None, b"OFF")), ModelFormat::Gzip);
+    }
+
+    #[test]
+    fn test_detect_long_obj() {
+        // The line cut by the sniffing limit is ignored.
+        let mut data = "v 0.125 0.25 0.5\n".repeat(SNIFF_LEN / 10).into_bytes();
+        data.truncate(SNIFF_LEN + 3);
+        assert_eq!(detect_format(&data), ModelFormat::Obj);
+    }
+
+    #[test]
+    fn test_file_names() {
+        assert_eq!(format_from_file_name("C:\\models\\a.b.STL"), ModelFormat::BinaryStl);
+        assert_eq!(format_from_file_name("dir.3mf/model"), ModelFormat::Unknown);
+        assert_eq!(format_from_file_name("model.stl.gz"), ModelFormat::Gzip);
+        assert_eq!(format_from_file_name(""), ModelFormat::Unknown);
+        assert_eq!(file_stem("/tmp/part.v2.stl"), "part.v2");
+        assert_eq!(file_stem(".hidden"), ".hidden");
+    }
+
+    fn metadata(model: &ImportedModel) -> Vec<(String, String)> {
+        (0..model.metadata_count())
+            .map(|i| (model.metadata_key(i).unwrap(), model.metadata_value(i).unwrap()))
+            .collect()
+    }
+
+    #[test]
+    fn test_load() {
+        let model = load_model(b"OFF\n3 1 0\n0 0 0\n1 0 0\n0 1 0\n3 0 1 2\n", "dir/triangle.off").unwrap();
+        assert_eq!(model.part_count(), 1);
+        assert_eq!(model.part_name(0).unwrap(), "triangle");
+        assert_eq!(metadata(&model), vec![("format".to_string(), "OFF".to_string())]);
+        let model = load_model(b"<amf><object id=\"1\"><mesh/></object></amf>", "").unwrap();
+        assert_eq!(model.part_count(), 1);
+        // The ASCII STL parser is lenient with empty solids.
+        let model = load_model(b"solid x\nendsolid x\n", "").unwrap();
+        assert_eq!(model.part_count(), 1);
+        assert!(load_model(b"#VRML V2.0 utf8\n\xff", "a.wrl").is_err());
+        assert_eq!(
+            load_model(b"hello", "a.txt").err(),
+            Some(Error::UnsupportedFormat("unknown model format".to_string()))
+        );
+    }
+
+    #[test]
+    fn test_load_fallback_and_gzip() {
+        let tris = [0.0, 0.0, 0.0, 1.0, 0.0, 0.0, 0.0, 1.0, 0.0];
+        let binary = write_binary_stl(&tris, FacetColors::None);
+        let model = load_model(&binary, "").unwrap();
+        assert_eq!(model.part_name(0).unwrap(), DEFAULT_PART_NAME);
+        assert_eq!(*model.positions(), tris);
+
+        // Padded binary STLs are detected by the extension only.
+        let mut padded = binary.clone();
+        padded.extend_from_slice(&[0; 10]);
+        assert_eq!(detect_format(&padded), ModelFormat::Unknown);
+        let model = load_model(&padded, "part.stl").unwrap();
+        assert_eq!(model.part_name(0).unwrap(), "part");
+        assert_eq!(model.warning_count(), 1);
+        assert!(load_model(&padded, "part.bin").is_err());
+
+        let model = load_model(&create_gzip(Some("inner.stl"), &binary), "outer.stl.gz").unwrap();
+        assert_eq!(model.part_name(0).unwrap(), "inner");
+        assert_eq!(
+            metadata(&model),
+            vec![
+                ("format".to_string(), "binary STL".to_string()),
+                ("header".to_string(), "Binary STL written by stl-web-viewer".to_string()),
+                ("compression".to_string(), "gzip".to_string())
+            ]
+        );
+        let model = load_model(&create_gzip(None, &padded), "outer.stl.gz").unwrap();
+        assert_eq!(model.part_name(0).unwrap(), "outer");
+        let nested = create_gzip(None, &create_gzip(None, &binary));
+        assert!(load_model(&nested, "").is_err());
+    }
+
+    #[test]
+    fn test_load_zip() {
+        let tris = [0.0, 0.0, 0.0, 1.0, 0.0, 0.0, 0.0, 1.0, 0.0];
+        let binary = write_binary_stl(&tris, FacetColors::None);
+        let two_solids = format!("{}{}", write_ascii_stl(&tris, "a"), write_ascii_stl(&tris, "b"));
+        let data = create_zip(&[
+            ("readme.txt", b"not a model", false),
+            ("models/", b"", false),
+            ("models/left.stl", &binary, true),
+            ("__MACOSX/models/._left.stl", b"resource fork", false),
+            ("models/right.STL", two_solids.as_bytes(), true),
+            ("broken.off", b"OFF\n1 1 0\n", false),
+        ]);
+        let model = load_model(&data, "models.zip").unwrap();
+        assert_eq!(model.part_count(), 3);
+        assert_eq!(model.part_name(0).unwrap(), "left");
+        assert_eq!(model.part_name(1).unwrap(), "right/a");
+        assert_eq!(model.part_name(2).unwrap(), "right/b");
+        assert_eq!(metadata(&model)[0], ("format".to_string(), "zip".to_string()));
+        assert_eq!(metadata(&model)[1], ("models/left.stl: format".to_string(), "binary STL".to_string()));
+        assert_eq!(model.warning_count(), 1);
+        assert!(
+            model
+                .warning_message(0)
+                .unwrap()
+                .starts_with("skipped broken.off: ")
+        );
+
+        assert_eq!(
+            load_model(&create_zip(&[("a.txt", b"", false)]), "")
+                .err()
+                .unwrap()
+                .to_string(),
+            "zip archive has no supported models"
+        );
+        let broken = create_zip(&[("broken.off", b"OFF\n1 1 0\n", false)]);
+        assert!(
+            load_model(&broken, "")
+                .err()
+                .unwrap()
+                .to_string()
+                .starts_with("failed to import broken.off: ")
+        );
+        let nested = create_zip(&[("a.stl.gz", &create_gzip(None, &binary), false)]);
+        assert!(load_model(&nested, "").is_err());
+    }
+
+    #[test]
+    fn test_load_truncated() {
+        let tris = [0.0, 0.0, 0.0, 1.0, 0.0, 0.0, 0.0, 1.0, 0.0];
+        let binary = write_binary_stl(&tris, FacetColors::None);
+        let zip = create_zip(&[("a.stl", &binary, true)]);
+        let gzip = create_gzip(Some("a.stl"), &binary);
+        for data in [zip, gzip] {
+            for len in 0..data.len() {
+                assert!(load_model(&data[..len], "a.gz").is_err(), "{}", len);
+            }
+            assert!(load_model(&data, "").is_ok());
+        }
+        assert_eq!(
+            load_model(b"PK\x03\x04", "").err(),
+            Some(Error::TruncatedFile("zip archive is truncated".to_string()))
+        );
+    }
+}
@@ -0,0 +1,657 @@
+use wasm_bindgen::prelude::*;
+
+use crate::error::{Error, checked_item};
+
+/// Line segments of one kind of moves, ready to be rendered as LineSegments.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct SegmentBuffer {
+    /// Interleaved x, y, z coordinates, two points (6 floats) per segment.
+    pub positions: Vec<f32>,
+    /// Feedrate of each segment in mm/s.
+    pub feedrates: Vec<f32>,
+}
+
+impl SegmentBuffer {
+    fn push(&mut self, from: [f32; 3], to: [f32; 3], feedrate: f32) {
+        self.positions.extend_from_slice(&from);
+        self.positions.extend_from_slice(&to);
+        self.feedrates.push(feedrate);
+    }
+
+    fn append(&mut self, other: &mut Self) {
+        self.positions.append(&mut other.positions);
+        self.feedrates.append(&mut other.feedrates);
+    }
+
+    pub fn segment_count(&self) -> usize {
+        self.feedrates.len()
+    }
+}
+
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct GcodeLayer {
+    /// Z of the extrusions of the layer.
+    pub z: f32,
+    pub extrusions: SegmentBuffer,
+    pub travels: SegmentBuffer,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct GcodeWarning {
+    /// 1-based line number.
+    pub line: usize,
+    pub message: String,
+}
+
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Toolpaths {
+    pub layers: Vec<GcodeLayer>,
+    pub warnings: Vec<GcodeWarning>,
+}
+
+// Maximum angle of a single segment when tessellating arcs.
+const ARC_SEGMENT_ANGLE: f32 = std::f32::consts::PI / 36.0;
+// Layers with z closer than this are merged.
+const LAYER_EPSILON: f32 = 1e-4;
+
+struct ParserState {
+    pos: [f32; 3],
+    e: f32,
+    absolute_xyz: bool,
+    absolute_e: bool,
+    // Millimeters per unit, 25.4 after G20.
+    scale: f32,
+    // In mm/s.
+    feedrate: f32,
+}
+
+// Parameters of a command, already converted to millimeters.
+#[derive(Default)]
+struct Params {
+    values: [Option<f32>; 26],
+}
+
+impl Params {
+    fn get(&self, letter: u8) -> Option<f32> {
+        self.values[(letter - b'A') as usize]
+    }
+}
+
+struct ToolpathBuilder {
+    toolpaths: Toolpaths,
+    // Travels after the last extrusion, they are added to the layer of the next extrusion.
+    pending_travels: SegmentBuffer,
+}
+
+impl ToolpathBuilder {
+    fn add_move(&mut self, from: [f32; 3], to: [f32; 3], extrude: bool, feedrate: f32) {
+        if from == to {
+            return;
+        }
+        if !extrude {
+            self.pending_travels.push(from, to, feedrate);
+            return;
+        }
+        let layers = &mut self.toolpaths.layers;
+        if layers
+            .last()
+            .is_none_or(|layer| (layer.z - to[2]).abs() > LAYER_EPSILON)
+        {
+            layers.push(GcodeLayer { z: to[2], ..Default::default() });
+        }
+        let layer = layers.last_mut().unwrap();
+        layer.travels.append(&mut self.pending_travels);
+        layer.extrusions.push(from, to, feedrate);
+    }
+
+    fn finish(mut self) -> Toolpaths {
+        if self.pending_travels.segment_count() > 0 {
+            if self.toolpaths.layers.is_empty() {
+                let z = self.pending_travels.positions[5];
+                self.toolpaths
+                    .layers
+                    .push(GcodeLayer { z, ..Default::default() });
+            }
+            let layer = self.toolpaths.layers.last_mut().unwrap();
+            layer.travels.append(&mut self.pending_travels);
+        }
+        self.toolpaths
+    }
+}
+
+// Strips the comments and the checksum from the line.
+fn strip_line(line: &str) -> String {
+    let line = line.split(';').next().unwrap();
+    let line = line.split('*').next().unwrap();
+    let mut result = String::with_capacity(line.len());
+    let mut in_comment = false;
+    for c in line.chars() {
+        match c {
+            '(' => in_comment = true,
+            ')' if in_comment => in_comment = false,
+            _ if !in_comment => result.push(c),
+            _ => {}
+        }
+    }
+    result
+}
+
+// Splits the line into (letter, number) words. Both "G1 X10" and "G1X10" forms are supported.
+fn parse_words(line: &str) -> Result<Vec<(u8, f32)>, String> {
+    let bytes = line.as_bytes();
+    let mut words = vec![];
+    let mut i = 0;
+    while i < bytes.len() {
+        if bytes[i].is_ascii_whitespace() {
+            i += 1;
+            continue;
+        }
+        let letter = bytes[i].to_ascii_uppercase();
+        if !letter.is_ascii_alphabetic() {
+            return Err(format!("unexpected character '{}'", line[i..].chars().next().unwrap()));
+        }
+        i += 1;
+        let start = i;
+        while i < bytes.len() && (bytes[i].is_ascii_digit() || matches!(bytes[i], b'.' | b'-' | b'+')) {
+            i += 1;
+        }
+        let value = if start == i {
+            // Parameters without values like "G28 X" are allowed.
+            0.0
+        } else {
+            line[start..i]
+                .parse::<f32>()
+                .map_err(|_| format!("invalid number '{}' for {}", &line[start..i], letter as char))?
+        };
+        if !value.is_finite() {
+            return Err(format!("invalid number for {}", letter as char));
+        }
+        words.push((letter, value));
+    }
+    Ok(words)
+}
+
+/// Parses G-code and extracts the toolpaths. Supports linear moves (G0/G1), arcs in XY plane (G2/G3, either I/J or R
+/// form), absolute and relative positioning (G90/G91, M82/M83), units (G20/G21), homing (G28, a travel to 0 on the
+/// given axes or on all of them) and setting the position (G92). Moves increasing the extruder position are extrusions, all other moves are travels. A new layer starts with the first
+/// extrusion at a new height, travels are assigned to the layer of the following extrusion. Malformed lines are skipped
+/// with a warning.
+pub fn parse_gcode_toolpaths(text: &str) -> Toolpaths {
+    let mut state =
+        ParserState { pos: [0.0; 3], e: 0.0, absolute_xyz: true, absolute_e: true, scale: 1.0, feedrate: 0.0 };
+    let mut builder = ToolpathBuilder { toolpaths: Toolpaths::default(), pending_travels: SegmentBuffer::default() };
+
+    for (line_idx, line) in text.lines().enumerate() {
+        let line_number = line_idx + 1;
+        let mut warn = |message: String| {
+            builder
+                .toolpaths
+                .warnings
+                .push(GcodeWarning { line: line_number, message });
+        };
+        let words = match parse_words(&strip_line(line)) {
+            Ok(words) => words,
+            Err(message) => {
+                warn(message);
+                continue;
+            }
+        };
+        // Skip the line number.
+        let words = match words.first() {
+            Some((b'N', _)) => &words[1..],
+            _ => &words[..],
+        };
+        let Some(&(letter, code)) = words.first() else {
+            continue;
+        };
+        let mut params = Params::default();
+        for &(param, value) in &words[1..] {
+            params.values[(param - b'A') as usize] = Some(value);
+        }
+
+        match (letter, code) {
+            (b'G', 0.0 | 1.0 | 2.0 | 3.0) => {
+                if let Err(message) = execute_move(code as u32, &params, &mut state, &mut builder) {
+                    builder
+                        .toolpaths
+                        .warnings
+                        .push(GcodeWarning { line: line_number, message });
+                }
+            }
+            (b'G', 20.0) => state.scale = 25.4,
+            (b'G', 21.0) => state.scale = 1.0,
+            (b'G', 90.0) => {
+                state.absolute_xyz = true;
+                state.absolute_e = true;
+            }
+            (b'G', 91.0) => {
+                state.absolute_xyz = false;
+                state.absolute_e = false;
+            }
+            (b'M', 82.0) => state.absolute_e = true,
+            (b'M', 83.0) => state.absolute_e = false,
+            (b'G', 28.0) => {
+                let all_axes = [b'X', b'Y', b'Z'].iter().all(|&a| params.get(a).is_none());
+                let mut target = state.pos;
+                for (i, axis) in [b'X', b'Y', b'Z'].into_iter().enumerate() {
+                    if all_axes || params.get(axis).is_some() {
+                        target[i] = 0.0;
+                    }
+                }
+                builder.add_move(state.pos, target, false, state.feedrate);
+                state.pos = target;
+            }
+            (b'G', 92.0) => {
+                let no_axes = [b'X', b'Y', b'Z', b'E']
+                    .iter()
+                    .all(|&a| params.get(a).is_none());
+                for (i, axis) in [b'X', b'Y', b'Z'].into_iter().enumerate() {
+                    if let Some(v) = params.get(axis) {
+                        state.pos[i] = v * state.scale;
+                    } else if no_axes {
+                        state.pos[i] = 0.0;
+                    }
+                }
+                if let Some(v) = params.get(b'E') {
+                    state.e = v * state.scale;
+                } else if no_axes {
+                    state.e = 0.0;
+                }
+            }
+            (b'G' | b'M' | b'T', _) => {
+                // Other commands do not affect the toolpaths.
+            }
+            _ => warn(format!("unknown command {}{}", letter as char, code)),
+        }
+    }
+    builder.finish()
+}
+
+fn execute_move(
+    code: u32,
+    params: &Params,
+    state: &mut ParserState,
+    builder: &mut ToolpathBuilder,
+) -> Result<(), String> {
+    let scale = state.scale;
+    if let Some(f) = params.get(b'F') {
+        if f <= 0.0 {
+            return Err(format!("invalid feedrate {}", f));
+        }
+        // G-code feedrates are in units per minute.
+        state.feedrate = f * scale / 60.0;
+    }
+    let mut target = state.pos;
+    for (i, axis) in [b'X', b'Y', b'Z'].into_iter().enumerate() {
+        if let Some(v) = params.get(axis) {
+            target[i] = if state.absolute_xyz { v * scale } else { state.pos[i] + v * scale };
+        }
+    }
+    let target_e = match params.get(b'E') {
+        Some(v) if state.absolute_e => v * scale,
+        Some(v) => state.e + v * scale,
+        None => state.e,
+    };
+    let extrude = target_e > state.e;
+
+    if code <= 1 {
+        builder.add_move(state.pos, target, extrude, state.feedrate);
+    } else {
+        let points = arc_points(code == 2, state.pos, target, params, scale)?;
+        let mut from = state.pos;
+        for to in points {
+            builder.add_move(from, to, extrude, state.feedrate);
+            from = to;
+        }
+    }
+    state.pos = target;
+    state.e = target_e;
+    Ok(())
+}
+
+// Tessellates an arc from `from` to `to`, returns the points after `from`. Z changes linearly (helical moves).
+fn arc_points(
+    clockwise: bool,
+    from: [f32; 3],
+    to: [f32; 3],
+    params: &Params,
+    scale: f32,
+) -> Result<Vec<[f32; 3]>, String> {
+    let (dx, dy) = (to[0] - from[0], to[1] - from[1]);
+    let (cx, cy) = if let Some(r) = params.get(b'R') {
+        // Negative radius selects the arc longer than 180 degrees.
+        let r = r * scale;
+        let chord = (dx * dx + dy * dy).sqrt();
+        if chord == 0.0 || chord > 2.0 * r.abs() * (1.0 + 1e-4) {
+            return Err(format!("arc radius {} does not fit the endpoints", r / scale));
+        }
+        let h = (r * r - chord * chord / 4.0).max(0.0).sqrt();
+        // The center is to the right of the chord for clockwise arcs shorter than 180 degrees.
+        let sign = if clockwise == (r > 0.0) { -1.0 } else { 1.0 };
+        (from[0] + dx / 2.0 - sign * h * dy / chord, from[1] + dy / 2.0 + sign * h * dx / chord)
+    } else {
+        let (i, j) = (params.get(b'I'), params.get(b'J'));
+        if i.is_none() && j.is_none() {
+            return Err("arc without I, J or R".to_string());
+        }
+        (from[0] + i.unwrap_or(0.0) * scale, from[1] + j.unwrap_or(0.0) * scale)
+    };
+
+    let radius = ((from[0] - cx).powi(2) + (from[1] - cy).powi(2)).sqrt();
+    let start_angle = (from[1] - cy).atan2(from[0] - cx);
+    let end_angle = (to[1] - cy).atan2(to[0] - cx);
+    let mut sweep = end_angle - start_angle;
+    // Coinciding endpoints give a full circle.
+    let full_turn = 2.0 * std::f32::consts::PI;
+    if clockwise {
+        if sweep >= 0.0 {
+            sweep -= full_turn;
+        }
+    } else if sweep <= 0.0 {
+        sweep += full_turn;
+    }
+    if radius == 0.0 {
+        return Ok(vec![to]);
+    }
+
+    let count = ((sweep.abs() / ARC_SEGMENT_ANGLE).ceil() as usize).max(1);
+    let mut points = Vec::with_capacity(count);
+    for k in 1..count {
+        let t = k as f32 / count as f32;
+        let angle = start_angle + sweep * t;
+        points.push([cx + radius * angle.cos(), cy + radius * angle.sin(), from[2] + (to[2] - from[2]) * t]);
+    }
+    points.push(to);
+    Ok(points)
+}
+
+/// Result of parse_gcode for JS.
+#[wasm_bindgen]
+pub struct GcodeToolpaths {
+    toolpaths: Toolpaths,
+}
+
+impl GcodeToolpaths {
+    fn layer(&self, layer: usize) -> Result<&GcodeLayer, Error> {
+        checked_item(&self.toolpaths.layers, layer, "layer")
+    }
+}
+
+#[wasm_bindgen]
+impl GcodeToolpaths {
+    #[wasm_bindgen(getter)]
+    pub fn layer_count(&self) -> usize {
+        self.toolpaths.layers.len()
+    }
+
+    pub fn layer_z(&self, layer: usize) -> Result<f32, Error> {
+        Ok(self.layer(layer)?.z)
+    }
+
+    /// Returns interleaved x, y, z coordinates, two points per segment.
+    pub fn extrusion_positions(&self, layer: usize) -> Result<Box<[f32]>, Error> {
+        Ok(self
+            .layer(layer)?
+            .extrusions
+            .positions
+            .clone()
+            .into_boxed_slice())
+    }
+
+    /// Returns the feedrate of each extrusion segment in mm/s.
+    pub fn extrusion_feedrates(&self, layer: usize) -> Result<Box<[f32]>, Error> {
+        Ok(self
+            .layer(layer)?
+            .extrusions
+            .feedrates
+            .clone()
+            .into_boxed_slice())
+    }
+
+    pub fn travel_positions(&self, layer: usize) -> Result<Box<[f32]>, Error> {
+        Ok(self
+            .layer(layer)?
+            .travels
+            .positions
+            .clone()
+            .into_boxed_slice())
+    }
+
+    pub fn travel_feedrates(&self, layer: usize) -> Result<Box<[f32]>, Error> {
+        Ok(self
+            .layer(layer)?
+            .travels
+            .feedrates
+            .clone()
+            .into_boxed_slice())
+    }
+
+    #[wasm_bindgen(getter)]
+    pub fn warning_count(&self) -> usize {
+        self.toolpaths.warnings.len()
+    }
+
+    pub fn warning_line(&self, idx: usize) -> Result<usize, Error> {
+        Ok(checked_item(&self.toolpaths.warnings, idx, "warning")?.line)
+    }
+
+    pub fn warning_message(&self, idx: usize) -> Result<String, Error> {
+        Ok(checked_item(&self.toolpaths.warnings, idx, "warning")?
+            .message
+            .clone())
+    }
+}
+
+/// See parse_gcode_toolpaths.
+#[wasm_bindgen]
+pub fn parse_gcode(text: &str) -> GcodeToolpaths {
+    GcodeToolpaths { toolpaths: parse_gcode_toolpaths(text) }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn segments(buffer: &SegmentBuffer) -> Vec<[f32; 6]> {
+        buffer
+            .positions
+            .chunks_exact(6)
+            .map(|s| s.try_into().unwrap())
+            .collect()
+    }
+
+    #[test]
+    fn test_empty() {
+        let toolpaths = parse_gcode_toolpaths("");
+        assert!(toolpaths.layers.is_empty());
+        assert!(toolpaths.warnings.is_empty());
+        let toolpaths = parse_gcode_toolpaths("; only a comment\n\nM104 S200\n");
+        assert!(toolpaths.layers.is_empty());
+        assert!(toolpaths.warnings.is_empty());
+    }
+
+    #[test]
+    fn test_layers() {
+        let gcode = "\
+G21 ; millimeters
+G90
+M82
+G1 Z0.2 F3000
+G1 X10 Y0 E1 F1200
+G1 X10 Y10 E2
+G0 X0 Y0
+G1 Z0.4
+G1 X10 Y0 E3
+";
+        let toolpaths = parse_gcode_toolpaths(gcode);
+        assert!(toolpaths.warnings.is_empty(), "{:?}", toolpaths.warnings);
+        assert_eq!(toolpaths.layers.len(), 2);
+
+        let layer0 = &toolpaths.layers[0];
+        assert_eq!(layer0.z, 0.2);
+        assert_eq!(
+            segments(&layer0.extrusions),
+            vec![[0.0, 0.0, 0.2, 10.0, 0.0, 0.2], [10.0, 0.0, 0.2, 10.0, 10.0, 0.2]]
+        );
+        assert_eq!(layer0.extrusions.feedrates, vec![20.0, 20.0]);
+        // The initial Z move.
+        assert_eq!(segments(&layer0.travels), vec![[0.0, 0.0, 0.0, 0.0, 0.0, 0.2]]);
+        assert_eq!(layer0.travels.feedrates, vec![50.0]);
+
+        let layer1 = &toolpaths.layers[1];
+        assert_eq!(layer1.z, 0.4);
+        assert_eq!(segments(&layer1.travels), vec![[10.0, 10.0, 0.2, 0.0, 0.0, 0.2], [0.0, 0.0, 0.2, 0.0, 0.0, 0.4]]);
+        assert_eq!(layer1.extrusions.segment_count(), 1);
+    }
+
+    #[test]
+    fn test_relative_modes() {
+        let gcode = "\
+G91
+G1 X1 Y1 E0.5 F600
+G1 X1 E0.5
+M82
+G1 X1 E1.5
+G1 X1 E1.0 ; retraction in absolute E mode, travel
+G90
+M83
+G1 X10 E0.2
+G92 E0
+G1 X11 E0.1
+";
+        let toolpaths = parse_gcode_toolpaths(gcode);
+        assert!(toolpaths.warnings.is_empty(), "{:?}", toolpaths.warnings);
+        assert_eq!(toolpaths.layers.len(), 1);
+        let layer = &toolpaths.layers[0];
+        assert_eq!(
+            segments(&layer.extrusions),
+            vec![
+                [0.0, 0.0, 0.0, 1.0, 1.0, 0.0],
+                [1.0, 1.0, 0.0, 2.0, 1.0, 0.0],
+                [2.0, 1.0, 0.0, 3.0, 1.0, 0.0],
+                [4.0, 1.0, 0.0, 10.0, 1.0, 0.0],
+                [10.0, 1.0, 0.0, 11.0, 1.0, 0.0],
+            ]
+        );
+        assert_eq!(segments(&layer.travels), vec![[3.0, 1.0, 0.0, 4.0, 1.0, 0.0]]);
+    }
+
+    #[test]
+    fn test_homing() {
+        let gcode = "\
+G1 X5 Y5 Z1 F600
+G28 X
+G91
+G1 X1 Y1 E1
+G90
+G28
+G1 X2 E2
+";
+        let toolpaths = parse_gcode_toolpaths(gcode);
+        assert!(toolpaths.warnings.is_empty(), "{:?}", toolpaths.warnings);
+        assert_eq!(toolpaths.layers.len(), 2);
+        assert_eq!(segments(&toolpaths.layers[0].extrusions), vec![[0.0, 5.0, 1.0, 1.0, 6.0, 1.0]]);
+        assert_eq!(
+            segments(&toolpaths.layers[0].travels),
+            vec![[0.0, 0.0, 0.0, 5.0, 5.0, 1.0], [5.0, 5.0, 1.0, 0.0, 5.0, 1.0]]
+        );
+        assert_eq!(segments(&toolpaths.layers[1].travels), vec![[1.0, 6.0, 1.0, 0.0, 0.0, 0.0]]);
+        assert_eq!(segments(&toolpaths.layers[1].extrusions), vec![[0.0, 0.0, 0.0, 2.0, 0.0, 0.0]]);
+    }
+
+    #[test]
+    fn test_inches() {
+        let toolpaths = parse_gcode_toolpaths("G20\nG1 X1 Y2 Z0.01 E0.1 F60\n");
+        let layer = &toolpaths.layers[0];
+        assert!((layer.z - 0.254).abs() < 1e-6);
+        let expected = [0.0, 0.0, 0.0, 25.4, 50.8, 0.254];
+        let actual = segments(&layer.extrusions);
+        assert_eq!(actual.len(), 1);
+        assert!(
+            actual[0]
+                .iter()
+                .zip(expected)
+                .all(|(a, e)| (a - e).abs() < 1e-5),
+            "{:?}",
+            actual
+        );
+        assert!((layer.extrusions.feedrates[0] - 25.4).abs() < 1e-5);
+    }
+
+    #[test]
+    fn test_arcs() {
+        // A counterclockwise half circle from (10, 0) to (-10, 0) around the origin.
+        let toolpaths = parse_gcode_toolpaths("G0 X10 Y0\nG3 X-10 Y0 I-10 J0 E1\n");
+        assert!(toolpaths.warnings.is_empty(), "{:?}", toolpaths.warnings);
+        let arc = segments(&toolpaths.layers[0].extrusions);
+        assert_eq!(arc.len(), 36);
+        for s in &arc {
+            for p in [&s[0..3], &s[3..6]] {
+                assert!(((p[0] * p[0] + p[1] * p[1]).sqrt() - 10.0).abs() < 1e-4);
+                // Counterclockwise from (10, 0) passes through the upper half.
+                assert!(p[1] >= -1e-4);
+            }
+        }
+        assert_eq!(arc.last().unwrap()[3..6], [-10.0, 0.0, 0.0]);
+
+        // The same half circle clockwise in R form passes through the lower half.
+        let toolpaths = parse_gcode_toolpaths("G0 X10 Y0\nG2 X-10 Y0 R10 E1\n");
+        assert!(toolpaths.warnings.is_empty(), "{:?}", toolpaths.warnings);
+        let arc = segments(&toolpaths.layers[0].extrusions);
+        assert_eq!(arc.len(), 36);
+        assert!(arc.iter().all(|s| s[1] <= 1e-4 && s[4] <= 1e-4));
+
+        // A clockwise quarter circle with R form: from (0, 10) to (10, 0) around the origin.
+        let toolpaths = parse_gcode_toolpaths("G0 X0 Y10\nG2 X10 Y0 R10 E1\n");
+        let arc = segments(&toolpaths.layers[0].extrusions);
+        assert_eq!(arc.len(), 18);
+        assert!(
+            arc.iter()
+                .all(|s| ((s[3] * s[3] + s[4] * s[4]).sqrt() - 10.0).abs() < 1e-4)
+        );
+
+        // A full circle.
+        let toolpaths = parse_gcode_toolpaths("G0 X10 Y0\nG2 X10 Y0 I-10 J0 E1\n");
+        assert_eq!(toolpaths.layers[0].extrusions.segment_count(), 72);
+    }
+
+    #[test]
+    fn test_warnings() {
+        let gcode = "\
+G1 X10 E1
+G1 X1.2.3
+G1 X20 Y#
+N10 G1 X30 E2 *45
+G2 X0 Y0 E3
+Q5
+G1 X40 E4 F0
+G1 X50 (comment) E5
+";
+        let toolpaths = parse_gcode_toolpaths(gcode);
+        let warning_lines: Vec<usize> = toolpaths.warnings.iter().map(|w| w.line).collect();
+        assert_eq!(warning_lines, vec![2, 3, 5, 6, 7], "{:?}", toolpaths.warnings);
+        // Valid lines are still parsed.
+        let ends: Vec<f32> = segments(&toolpaths.layers[0].extrusions)
+            .iter()
+            .map(|s| s[3])
+            .collect();
+        assert_eq!(ends, vec![10.0, 30.0, 50.0]);
+    }
+
+    #[test]
+    fn test_wasm_wrapper() {
+        let result = parse_gcode("G1 Z0.3 F600\nG1 X5 E1\nG1 X5 Y5 E2 F1200\nG1 X1.2.3\n");
+        assert_eq!(result.layer_count(), 1);
+        assert_eq!(result.layer_z(0).unwrap(), 0.3);
+        assert_eq!(result.extrusion_positions(0).unwrap().len(), 12);
+        assert_eq!(result.extrusion_feedrates(0).unwrap().to_vec(), vec![10.0, 20.0]);
+        assert_eq!(result.travel_positions(0).unwrap().len(), 6);
+        assert_eq!(result.travel_feedrates(0).unwrap().to_vec(), vec![10.0]);
+        assert_eq!(result.warning_count(), 1);
+        assert_eq!(result.warning_line(0).unwrap(), 4);
+        assert!(result.warning_message(0).unwrap().contains("1.2.3"));
+        assert_eq!(result.layer_z(1).unwrap_err().code(), "INVALID_INPUT");
+        assert!(result.travel_positions(1).is_err());
+        assert!(result.warning_line(1).is_err());
+    }
+}
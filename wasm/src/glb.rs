@@ -0,0 +1,642 @@
+use wasm_bindgen::prelude::*;
+
+use crate::error::Error;
+use crate::json::{JsonValue, parse_json};
+use crate::model::{ImportedModel, Model, ModelPart};
+use crate::three_mf::{Transform, apply_transform, compose};
+use crate::vector3::Vector3;
+
+const GLB_MAGIC: &[u8] = b"glTF";
+const CHUNK_JSON: u32 = 0x4e4f534a;
+const CHUNK_BIN: u32 = 0x004e4942;
+// Limits the recursion for deep node hierarchies, cycles and shared nodes are rejected by node_parents.
+const MAX_NODE_DEPTH: usize = 64;
+// The largest byteStride allowed by the glTF spec.
+const MAX_BYTE_STRIDE: usize = 252;
+// Accessors without a buffer view are zero-filled from their count alone, which limits the memory they can request.
+const MAX_ZERO_FILLED_VALUES: usize = 1 << 24;
+// Converts the Y-up glTF coordinates into Z-up: (x, y, z) -> (x, -z, y).
+const Y_UP_TO_Z_UP: Transform = [1.0, 0.0, 0.0, 0.0, 0.0, 1.0, 0.0, -1.0, 0.0, 0.0, 0.0, 0.0];
+
+/// Returns true if the data starts with the GLB header.
+pub fn is_glb(data: &[u8]) -> bool {
+    data.starts_with(GLB_MAGIC)
+}
+
+fn read_u32(data: &[u8], offset: usize) -> Result<u32, Error> {
+    data.get(offset..offset + 4)
+        .map(|b| u32::from_le_bytes(b.try_into().unwrap()))
+        .ok_or_else(|| Error::TruncatedFile("GLB file is truncated".to_string()))
+}
+
+fn linear_to_srgb(c: f64) -> u8 {
+    let c = c.clamp(0.0, 1.0);
+    let srgb = if c <= 0.0031308 { c * 12.92 } else { 1.055 * c.powf(1.0 / 2.4) - 0.055 };
+    (srgb * 255.0).round() as u8
+}
+
+fn determinant(t: &Transform) -> f32 {
+    t[0] * (t[4] * t[8] - t[5] * t[7]) - t[1] * (t[3] * t[8] - t[5] * t[6]) + t[2] * (t[3] * t[7] - t[4] * t[6])
+}
+
+fn floats(value: Option<&JsonValue>, len: usize) -> Option<Vec<f32>> {
+    let values: Vec<f32> = value?
+        .elements()
+        .iter()
+        .map(|v| v.as_f64().map(|v| v as f32))
+        .collect::<Option<_>>()?;
+    (values.len() == len).then_some(values)
+}
+
+// Returns the local transform of the node, see three_mf::Transform for the layout.
+fn node_transform(node: &JsonValue) -> Transform {
+    if let Some(m) = floats(node.get("matrix"), 16) {
+        // Column-major 4x4 matrix: the columns become the rows of the row-vector transform.
+        return [m[0], m[1], m[2], m[4], m[5], m[6], m[8], m[9], m[10], m[12], m[13], m[14]];
+    }
+    let s = floats(node.get("scale"), 3).unwrap_or(vec![1.0; 3]);
+    let scale = [s[0], 0.0, 0.0, 0.0, s[1], 0.0, 0.0, 0.0, s[2], 0.0, 0.0, 0.0];
+    let q = floats(node.get("rotation"), 4).unwrap_or(vec![0.0, 0.0, 0.0, 1.0]);
+    let (x, y, z, w) = (q[0], q[1], q[2], q[3]);
+    let rotation = [
+        1.0 - 2.0 * (y * y + z * z),
+        2.0 * (x * y + z * w),
+        2.0 * (x * z - y * w),
+        2.0 * (x * y - z * w),
+        1.0 - 2.0 * (x * x + z * z),
+        2.0 * (y * z + x * w),
+        2.0 * (x * z + y * w),
+        2.0 * (y * z - x * w),
+        1.0 - 2.0 * (x * x + y * y),
+        0.0,
+        0.0,
+        0.0,
+    ];
+    let t = floats(node.get("translation"), 3).unwrap_or(vec![0.0; 3]);
+    let translation = [1.0, 0.0, 0.0, 0.0, 1.0, 0.0, 0.0, 0.0, 1.0, t[0], t[1], t[2]];
+    compose(&compose(&scale, &rotation), &translation)
+}
+
+// Returns the parent of each node. The glTF spec allows at most one parent per node, so the hierarchy is a forest and
+// reading it visits each node once. Nodes with more parents, children out of range and cycles (which are not reachable
+// from any root) are rejected: nodes shared between parents expand exponentially with the depth.
+fn node_parents(nodes: &[JsonValue]) -> Result<Vec<Option<usize>>, String> {
+    let mut parents = vec![None; nodes.len()];
+    for (idx, node) in nodes.iter().enumerate() {
+        for child in node
+            .get("children")
+            .map_or(&[][..], |c| c.elements())
+            .iter()
+            .filter_map(|c| c.as_usize())
+        {
+            let parent = parents
+                .get_mut(child)
+                .ok_or_else(|| format!("node {} has invalid child {}", idx, child))?;
+            if parent.replace(idx).is_some() {
+                return Err(format!("node {} has more than one parent", child));
+            }
+        }
+    }
+    // Follows the parents of each node up to a root, stopping at the nodes already known to reach one.
+    let mut reaches_root = vec![false; nodes.len()];
+    let mut path = vec![];
+    for start in 0..nodes.len() {
+        let mut node = start;
+        while !reaches_root[node] {
+            if path.len() > nodes.len() {
+                return Err(format!("node {} is its own ancestor", start));
+            }
+            path.push(node);
+            match parents[node] {
+                Some(parent) => node = parent,
+                None => break,
+            }
+        }
+        for node in path.drain(..) {
+            reaches_root[node] = true;
+        }
+    }
+    Ok(parents)
+}
+
+struct GltfReader<'a> {
+    doc: &'a JsonValue,
+    bin: &'a [u8],
+    model: Model,
+}
+
+impl<'a> GltfReader<'a> {
+    fn item(&self, kind: &str, idx: usize) -> Result<&'a JsonValue, String> {
+        self.doc
+            .get(kind)
+            .and_then(|items| items.elements().get(idx))
+            .ok_or_else(|| format!("invalid {} index {}", kind, idx))
+    }
+
+    // Returns the accessor values as f64 (which represents all component types exactly), `components` per element.
+    fn accessor(&self, idx: usize, components: usize) -> Result<Vec<f64>, String> {
+        let accessor = self.item("accessors", idx)?;
+        let count = accessor
+            .get("count")
+            .and_then(|c| c.as_usize())
+            .ok_or("accessor without count")?;
+        let expected_type = if components == 1 { "SCALAR" } else { "VEC3" };
+        if accessor.get("type").and_then(|t| t.as_str()) != Some(expected_type) {
+            return Err(format!("accessor {} must have type {}", idx, expected_type));
+        }
+        if accessor.get("sparse").is_some() {
+            return Err("sparse accessors are not supported".to_string());
+        }
+        let component_type = accessor.get("componentType").and_then(|c| c.as_usize());
+        let component_size = match component_type {
+            Some(5120 | 5121) => 1,
+            Some(5122 | 5123) => 2,
+            Some(5125 | 5126) => 4,
+            _ => return Err(format!("accessor {} has invalid component type", idx)),
+        };
+        let element_size = component_size * components;
+        let value_count = count
+            .checked_mul(components)
+            .ok_or_else(|| format!("accessor {} is too large", idx))?;
+        let Some(view_idx) = accessor.get("bufferView").and_then(|v| v.as_usize()) else {
+            // Accessors without a buffer view are filled with zeros.
+            if value_count > MAX_ZERO_FILLED_VALUES {
+                return Err(format!("accessor {} without buffer view is too large", idx));
+            }
+            return Ok(vec![0.0; value_count]);
+        };
+        let view = self.item("bufferViews", view_idx)?;
+        if view.get("buffer").and_then(|b| b.as_usize()) != Some(0) || self.item("buffers", 0)?.get("uri").is_some() {
+            return Err("external buffers are not supported".to_string());
+        }
+        let view_offset = view
+            .get("byteOffset")
+            .and_then(|o| o.as_usize())
+            .unwrap_or(0);
+        let view_len = view
+            .get("byteLength")
+            .and_then(|l| l.as_usize())
+            .ok_or("buffer view without length")?;
+        let view_data = view_offset
+            .checked_add(view_len)
+            .and_then(|view_end| self.bin.get(view_offset..view_end))
+            .ok_or("buffer view is out of the binary chunk")?;
+        let offset = accessor
+            .get("byteOffset")
+            .and_then(|o| o.as_usize())
+            .unwrap_or(0);
+        let stride = match view.get("byteStride") {
+            Some(stride) => stride
+                .as_usize()
+                .filter(|&s| s >= element_size && s % 4 == 0 && s <= MAX_BYTE_STRIDE)
+                .ok_or_else(|| format!("buffer view {} has invalid byte stride", view_idx))?,
+            None => element_size,
+        };
+        // The end of the last element, None on overflow.
+        let end = match count {
+            0 => Some(0),
+            _ => stride
+                .checked_mul(count - 1)
+                .and_then(|e| e.checked_add(offset))
+                .and_then(|e| e.checked_add(element_size)),
+        };
+        if end.is_none_or(|end| end > view_data.len()) {
+            return Err(format!("accessor {} is out of its buffer view", idx));
+        }
+
+        let mut values = Vec::with_capacity(value_count);
+        for i in 0..count {
+            for c in 0..components {
+                let start = offset + i * stride + c * component_size;
+                let b = &view_data[start..start + component_size];
+                values.push(match component_type.unwrap() {
+                    5120 => b[0] as i8 as f64,
+                    5121 => b[0] as f64,
+                    5122 => i16::from_le_bytes([b[0], b[1]]) as f64,
+                    5123 => u16::from_le_bytes([b[0], b[1]]) as f64,
+                    5125 => u32::from_le_bytes([b[0], b[1], b[2], b[3]]) as f64,
+                    _ => f32::from_le_bytes([b[0], b[1], b[2], b[3]]) as f64,
+                });
+            }
+        }
+        Ok(values)
+    }
+
+    // Appends the triangles of the primitive to `positions`.
+    fn read_primitive(
+        &self,
+        primitive: &JsonValue,
+        transform: &Transform,
+        positions: &mut Vec<f32>,
+    ) -> Result<(), String> {
+        let mode = primitive
+            .get("mode")
+            .and_then(|m| m.as_usize())
+            .unwrap_or(4);
+        let position_idx = primitive
+            .get("attributes")
+            .and_then(|a| a.get("POSITION"))
+            .and_then(|p| p.as_usize())
+            .ok_or("primitive without positions")?;
+        let vertices: Vec<Vector3> = self
+            .accessor(position_idx, 3)?
+            .chunks_exact(3)
+            .map(|p| apply_transform(transform, Vector3::new(p[0] as f32, p[1] as f32, p[2] as f32)))
+            .collect();
+        let indices: Vec<usize> = match primitive.get("indices").and_then(|i| i.as_usize()) {
+            Some(idx) => self
+                .accessor(idx, 1)?
+                .into_iter()
+                .map(|i| i as usize)
+                .collect(),
+            None => (0..vertices.len()).collect(),
+        };
+        if indices.iter().any(|&i| i >= vertices.len()) {
+            return Err("vertex index is out of range".to_string());
+        }
+        let triangles: Vec<[usize; 3]> = match mode {
+            4 => indices
+                .chunks_exact(3)
+                .map(|t| [t[0], t[1], t[2]])
+                .collect(),
+            // Every other triangle of a strip has the reversed order.
+            5 => (2..indices.len())
+                .map(|i| {
+                    if i % 2 == 0 {
+                        [indices[i - 2], indices[i - 1], indices[i]]
+                    } else {
+                        [indices[i - 1], indices[i - 2], indices[i]]
+                    }
+                })
+                .collect(),
+            6 => (2..indices.len())
+                .map(|i| [indices[0], indices[i - 1], indices[i]])
+                .collect(),
+            _ => return Err(format!("primitive mode {} is not supported", mode)),
+        };
+        // Mirroring transforms flip the orientation of the faces.
+        let flip = determinant(transform) < 0.0;
+        for mut tri in triangles {
+            if flip {
+                tri.swap(1, 2);
+            }
+            for i in tri {
+                let p = vertices[i];
+                positions.extend_from_slice(&[p.x, p.y, p.z]);
+            }
+        }
+        Ok(())
+    }
+
+    fn read_node(&mut self, idx: usize, parent: &Transform, depth: usize) -> Result<(), String> {
+        if depth > MAX_NODE_DEPTH {
+            return Err("nodes are nested too deep".to_string());
+        }
+        let node = self.item("nodes", idx)?;
+        let transform = compose(&node_transform(node), parent);
+        if let Some(mesh_idx) = node.get("mesh").and_then(|m| m.as_usize()) {
+            let mesh = self.item("meshes", mesh_idx)?;
+            let mut part = ModelPart {
+                name: node
+                    .get("name")
+                    .or(mesh.get("name"))
+                    .and_then(|n| n.as_str())
+                    .map_or_else(|| format!("Mesh {}", mesh_idx), |n| n.to_string()),
+                ..Default::default()
+            };
+            for (i, primitive) in mesh
+                .get("primitives")
+                .map_or(&[][..], |p| p.elements())
+                .iter()
+                .enumerate()
+            {
+                if let Err(e) = self.read_primitive(primitive, &transform, &mut part.positions) {
+                    self.model
+                        .warn(0, format!("skipped primitive {} of {}: {}", i, part.name, e));
+                    continue;
+                }
+                // The first primitive with a material defines the material of the part.
+                if part.material.is_none()
+                    && part.color.is_none()
+                    && let Some(material_idx) = primitive.get("material").and_then(|m| m.as_usize())
+                {
+                    let material = self.item("materials", material_idx)?;
+                    part.material = material
+                        .get("name")
+                        .and_then(|n| n.as_str())
+                        .map(|n| n.to_string());
+                    let factor = material
+                        .get("pbrMetallicRoughness")
+                        .and_then(|p| p.get("baseColorFactor"));
+                    if let Some(c) = floats(factor, 4) {
+                        let alpha = (c[3].clamp(0.0, 1.0) * 255.0).round() as u8;
+                        part.color = Some([
+                            linear_to_srgb(c[0] as f64),
+                            linear_to_srgb(c[1] as f64),
+                            linear_to_srgb(c[2] as f64),
+                            alpha,
+                        ]);
+                    }
+                }
+            }
+            self.model.parts.push(part);
+        }
+        for child in node
+            .get("children")
+            .map_or(&[][..], |c| c.elements())
+            .iter()
+            .filter_map(|c| c.as_usize())
+        {
+            self.read_node(child, &transform, depth + 1)?;
+        }
+        Ok(())
+    }
+}
+
+/// Parses a binary glTF 2.0 file. Each node with a mesh becomes a part named after the node (or the mesh), with the node
+/// transforms applied and the coordinates converted from Y-up to Z-up. The material and color are taken from the first
+/// primitive with a material. Only the default scene is read; external buffers, sparse accessors and compression
+/// extensions are not supported.
+pub fn parse_glb(data: &[u8]) -> Result<Model, Error> {
+    if !is_glb(data) {
+        return Err(Error::InvalidInput("not a GLB file".to_string()));
+    }
+    if read_u32(data, 4)? != 2 {
+        return Err(Error::UnsupportedFormat("only glTF 2.0 is supported".to_string()));
+    }
+    let total_len = (read_u32(data, 8)? as usize).min(data.len());
+    let mut json = None;
+    let mut bin: &[u8] = &[];
+    let mut offset = 12;
+    while offset + 8 <= total_len {
+        let chunk_len = read_u32(data, offset)? as usize;
+        let chunk_type = read_u32(data, offset + 4)?;
+        let chunk = data
+            .get(offset + 8..offset + 8 + chunk_len)
+            .ok_or_else(|| Error::TruncatedFile("GLB file is truncated".to_string()))?;
+        match chunk_type {
+            CHUNK_JSON if json.is_none() => json = Some(chunk),
+            CHUNK_BIN if bin.is_empty() => bin = chunk,
+            _ => {}
+        }
+        offset += 8 + chunk_len;
+    }
+    let json = json.ok_or("GLB file has no JSON chunk")?;
+    let json = std::str::from_utf8(json).map_err(|_| "GLB JSON is not valid UTF-8".to_string())?;
+    let doc = parse_json(json)?;
+    if let Some(extension) = doc
+        .get("extensionsRequired")
+        .and_then(|e| e.elements().first())
+    {
+        return Err(Error::UnsupportedFormat(format!(
+            "required extension {} is not supported",
+            extension.as_str().unwrap_or("?")
+        )));
+    }
+
+    let mut model = Model::default();
+    if let Some(asset) = doc.get("asset") {
+        for key in ["generator", "copyright"] {
+            if let Some(value) = asset.get(key).and_then(|v| v.as_str()) {
+                model.metadata.push((key.to_string(), value.to_string()));
+            }
+        }
+    }
+    let parents = node_parents(doc.get("nodes").map_or(&[][..], |n| n.elements()))?;
+    let scene_idx = doc.get("scene").and_then(|s| s.as_usize()).unwrap_or(0);
+    let roots: Vec<usize> = match doc.get("scenes").and_then(|s| s.elements().get(scene_idx)) {
+        Some(scene) => {
+            let mut roots: Vec<usize> = scene
+                .get("nodes")
+                .map_or(&[][..], |n| n.elements())
+                .iter()
+                .filter_map(|n| n.as_usize())
+                .collect();
+            if let Some(&child) = roots
+                .iter()
+                .find(|&&n| parents.get(n).is_some_and(|p| p.is_some()))
+            {
+                return Err(Error::InvalidInput(format!("scene node {} is not a root node", child)));
+            }
+            // Repeated roots would be read more than once.
+            roots.sort_unstable();
+            roots.dedup();
+            roots
+        }
+        // Without scenes, all nodes which are not children of other nodes are shown.
+        None => (0..parents.len())
+            .filter(|&i| parents[i].is_none())
+            .collect(),
+    };
+    let mut reader = GltfReader { doc: &doc, bin, model };
+    for root in roots {
+        reader.read_node(root, &Y_UP_TO_Z_UP, 0)?;
+    }
+    Ok(reader.model)
+}
+
+/// See parse_glb.
+#[wasm_bindgen]
+pub fn load_glb(data: &[u8]) -> Result<ImportedModel, Error> {
+    parse_glb(data).map(ImportedModel::new)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::glb_writer::{GlbPart, write_glb};
+    use crate::plane_section::tests::create_box_geometry;
+
+    // Builds a GLB file from the JSON and the binary chunk.
+    fn build_glb(json: &str, bin: &[u8]) -> Vec<u8> {
+        let mut json = json.as_bytes().to_vec();
+        json.resize(json.len().next_multiple_of(4), b' ');
+        let mut bin = bin.to_vec();
+        bin.resize(bin.len().next_multiple_of(4), 0);
+        let mut data = b"glTF".to_vec();
+        data.extend_from_slice(&2u32.to_le_bytes());
+        data.extend_from_slice(&((12 + 8 + json.len() + 8 + bin.len()) as u32).to_le_bytes());
+        data.extend_from_slice(&(json.len() as u32).to_le_bytes());
+        data.extend_from_slice(&CHUNK_JSON.to_le_bytes());
+        data.extend_from_slice(&json);
+        data.extend_from_slice(&(bin.len() as u32).to_le_bytes());
+        data.extend_from_slice(&CHUNK_BIN.to_le_bytes());
+        data.extend_from_slice(&bin);
+        data
+    }
+
+    fn sorted_triangles(positions: &[f32]) -> Vec<[i32; 9]> {
+        let mut tris: Vec<[i32; 9]> = positions
+            .chunks_exact(9)
+            .map(|t| {
+                // Rotate the vertices so that the smallest one goes first, keeping the orientation.
+                let vs: Vec<[i32; 3]> = t
+                    .chunks(3)
+                    .map(|v| [0, 1, 2].map(|i| (v[i] * 1000.0).round() as i32))
+                    .collect();
+                let first = (0..3).min_by_key(|&i| vs[i]).unwrap();
+                let mut result = [0; 9];
+                for k in 0..3 {
+                    result[k * 3..k * 3 + 3].copy_from_slice(&vs[(first + k) % 3]);
+                }
+                result
+            })
+            .collect();
+        tris.sort();
+        tris
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let cube = create_box_geometry(Vector3::new(0.0, 0.0, 0.0), Vector3::new(1.0, 2.0, 3.0));
+        let parts = [
+            GlbPart { name: "red", positions: &cube, color: [255, 0, 0] },
+            GlbPart { name: "gray", positions: &cube, color: [128, 128, 128] },
+        ];
+        let model = parse_glb(&write_glb(&parts)).unwrap();
+        assert!(model.warnings.is_empty());
+        assert_eq!(model.metadata, vec![("generator".to_string(), "stl-web-viewer".to_string())]);
+        assert_eq!(model.parts.len(), 2);
+        assert_eq!(model.parts[0].name, "red");
+        assert_eq!(model.parts[0].material.as_deref(), Some("red"));
+        assert_eq!(model.parts[0].color, Some([255, 0, 0, 255]));
+        assert_eq!(model.parts[1].color, Some([128, 128, 128, 255]));
+        // The writer rotates the model into Y-up, the reader rotates it back.
+        assert_eq!(sorted_triangles(&model.parts[0].positions), sorted_triangles(&cube));
+    }
+
+    #[test]
+    fn test_transforms_and_modes() {
+        let mut bin = vec![];
+        for v in [0.0f32, 0.0, 0.0, 1.0, 0.0, 0.0, 0.0, 1.0, 0.0, 1.0, 1.0, 0.0] {
+            bin.extend_from_slice(&v.to_le_bytes());
+        }
+        for i in [0u16, 1, 2, 3] {
+            bin.extend_from_slice(&i.to_le_bytes());
+        }
+        let json = r#"{
+            "asset": {"version": "2.0"},
+            "nodes": [
+                {"name": "strip", "mesh": 0, "children": [1], "translation": [0, 0, 5]},
+                {"mesh": 1, "matrix": [-1,0,0,0, 0,1,0,0, 0,0,1,0, 0,0,0,1]}
+            ],
+            "meshes": [
+                {"primitives": [{"attributes": {"POSITION": 0}, "indices": 1, "mode": 5}]},
+                {"name": "fan", "primitives": [{"attributes": {"POSITION": 0}, "mode": 1}, {"attributes": {"POSITION": 0}}]}
+            ],
+            "accessors": [
+                {"bufferView": 0, "componentType": 5126, "count": 4, "type": "VEC3"},
+                {"bufferView": 1, "componentType": 5123, "count": 4, "type": "SCALAR"}
+            ],
+            "bufferViews": [{"buffer": 0, "byteLength": 48}, {"buffer": 0, "byteOffset": 48, "byteLength": 8}],
+            "buffers": [{"byteLength": 56}]
+        }"#;
+        let model = parse_glb(&build_glb(json, &bin)).unwrap();
+        assert_eq!(model.parts.len(), 2);
+        let strip = &model.parts[0];
+        assert_eq!(strip.name, "strip");
+        // Glft z = 5 becomes y = -5, glTF y becomes z.
+        assert_eq!(
+            strip.positions,
+            [
+                0.0, -5.0, 0.0, 1.0, -5.0, 0.0, 0.0, -5.0, 1.0, //
+                0.0, -5.0, 1.0, 1.0, -5.0, 0.0, 1.0, -5.0, 1.0
+            ]
+        );
+        // The mirrored child is flipped, the line primitive is skipped and the remaining 4 vertices form one triangle.
+        let fan = &model.parts[1];
+        assert_eq!(fan.name, "fan");
+        assert_eq!(fan.positions, [0.0, -5.0, 0.0, 0.0, -5.0, 1.0, -1.0, -5.0, 0.0]);
+        assert_eq!(model.warnings.len(), 1);
+        assert!(model.warnings[0].message.contains("mode 1"));
+    }
+
+    #[test]
+    fn test_errors() {
+        assert!(parse_glb(b"").is_err());
+        assert!(parse_glb(b"glTF\x01\0\0\0").is_err());
+        let data = write_glb(&[]);
+        assert!(parse_glb(&data[..data.len() - 4]).is_err());
+        let required =
+            build_glb(r#"{"asset":{"version":"2.0"},"extensionsRequired":["KHR_draco_mesh_compression"]}"#, &[]);
+        assert!(
+            parse_glb(&required)
+                .unwrap_err()
+                .to_string()
+                .contains("KHR_draco")
+        );
+        let cyclic = build_glb(r#"{"nodes":[{"children":[1]},{"children":[0]}],"scenes":[{"nodes":[0]}]}"#, &[]);
+        assert!(parse_glb(&cyclic).is_err());
+        // The cycle is not reachable from the root, it is still rejected.
+        let unreachable =
+            build_glb(r#"{"nodes":[{},{"children":[2]},{"children":[1]}],"scenes":[{"nodes":[0]}]}"#, &[]);
+        assert!(parse_glb(&unreachable).is_err());
+        let own_child = build_glb(r#"{"nodes":[{"children":[0]}]}"#, &[]);
+        assert!(parse_glb(&own_child).is_err());
+        // Node n has two children n + 1 in a chain, it would be read 2^64 times.
+        let shared: Vec<String> = (1..64)
+            .map(|n| format!(r#"{{"children":[{},{}]}}"#, n, n))
+            .chain([r#"{"mesh":0}"#.to_string()])
+            .collect();
+        let shared = build_glb(&format!(r#"{{"nodes":[{}],"scenes":[{{"nodes":[0]}}]}}"#, shared.join(",")), &[]);
+        assert_eq!(parse_glb(&shared).unwrap_err().code(), "INVALID_INPUT");
+        let not_root = build_glb(r#"{"nodes":[{"children":[1]},{}],"scenes":[{"nodes":[1]}]}"#, &[]);
+        assert!(parse_glb(&not_root).is_err());
+        let bad_child = build_glb(r#"{"nodes":[{"children":[5]}]}"#, &[]);
+        assert!(parse_glb(&bad_child).is_err());
+        let bad_accessor = build_glb(
+            r#"{"nodes":[{"mesh":0}],"meshes":[{"primitives":[{"attributes":{"POSITION":0}}]}],
+                "accessors":[{"bufferView":0,"componentType":5126,"count":100,"type":"VEC3"}],
+                "bufferViews":[{"buffer":0,"byteLength":12}],"buffers":[{"byteLength":12}]}"#,
+            &[0; 12],
+        );
+        let model = parse_glb(&bad_accessor).unwrap();
+        assert!(model.parts[0].positions.is_empty());
+        assert_eq!(model.warnings.len(), 1);
+
+        // Strides smaller than the element or not a multiple of 4 are invalid, huge counts and offsets must not allocate
+        // or overflow on wasm32.
+        for (accessor, view) in [
+            (r#""byteOffset":0,"count":4000000000"#, r#""byteStride":0"#),
+            (r#""byteOffset":0,"count":1"#, r#""byteStride":6"#),
+            (r#""byteOffset":0,"count":4294967295"#, r#""byteStride":12"#),
+            (r#""byteOffset":4294967295,"count":1"#, r#""byteStride":12"#),
+        ] {
+            let json = format!(
+                r#"{{"nodes":[{{"mesh":0}}],"meshes":[{{"primitives":[{{"attributes":{{"POSITION":0}}}}]}}],
+                    "accessors":[{{"bufferView":0,"componentType":5126,"type":"VEC3",{}}}],
+                    "bufferViews":[{{"buffer":0,"byteLength":12,{}}}],"buffers":[{{"byteLength":12}}]}}"#,
+                accessor, view
+            );
+            let model = parse_glb(&build_glb(&json, &[0; 12])).unwrap();
+            assert!(model.parts[0].positions.is_empty(), "{}", json);
+            assert_eq!(model.warnings.len(), 1, "{}", json);
+        }
+        let zero_filled = build_glb(
+            r#"{"nodes":[{"mesh":0}],"meshes":[{"primitives":[{"attributes":{"POSITION":0}}]}],
+                "accessors":[{"componentType":5126,"count":4000000000,"type":"VEC3"}]}"#,
+            &[],
+        );
+        let model = parse_glb(&zero_filled).unwrap();
+        assert!(model.parts[0].positions.is_empty());
+        let out_of_chunk = build_glb(
+            r#"{"nodes":[{"mesh":0}],"meshes":[{"primitives":[{"attributes":{"POSITION":0}}]}],
+                "accessors":[{"bufferView":0,"componentType":5126,"count":1,"type":"VEC3"}],
+                "bufferViews":[{"buffer":0,"byteOffset":4294967295,"byteLength":12}],
+                "buffers":[{"byteLength":12}]}"#,
+            &[0; 12],
+        );
+        assert!(
+            parse_glb(&out_of_chunk).unwrap().parts[0]
+                .positions
+                .is_empty()
+        );
+    }
+
+    #[test]
+    fn test_colors() {
+        assert_eq!(linear_to_srgb(0.0), 0);
+        assert_eq!(linear_to_srgb(1.0), 255);
+        assert_eq!(linear_to_srgb(0.21586053), 128);
+    }
+}
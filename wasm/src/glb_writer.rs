@@ -0,0 +1,391 @@
+use std::collections::HashMap;
+use std::fmt::Write;
+
+use wasm_bindgen::prelude::*;
+
+use crate::error::{Error, split_export_parts};
+use crate::indexed_mesh::IndexedMesh;
+use crate::vector3::Vector3;
+
+const GLB_MAGIC: u32 = 0x46546c67;
+const CHUNK_JSON: u32 = 0x4e4f534a;
+const CHUNK_BIN: u32 = 0x004e4942;
+const COMPONENT_FLOAT: u32 = 5126;
+const COMPONENT_UNSIGNED_INT: u32 = 5125;
+const TARGET_ARRAY_BUFFER: u32 = 34962;
+const TARGET_ELEMENT_ARRAY_BUFFER: u32 = 34963;
+// Adjacent faces with normals differing by more than this angle get separate vertices, so that the sharp edges of
+// mechanical parts are not smoothed out.
+const CREASE_ANGLE_DEGREES: f32 = 30.0;
+// The color used by the viewer for parts without colors.
+const DEFAULT_COLOR: [u8; 3] = [0x80, 0x80, 0x80];
+
+/// A part of the exported model: a named mesh with a single material.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct GlbPart<'a> {
+    pub name: &'a str,
+    /// Interleaved x, y, z coordinates, 9 floats per triangle.
+    pub positions: &'a [f32],
+    /// sRGB color.
+    pub color: [u8; 3],
+}
+
+/// Vertex data of a single glTF primitive.
+#[derive(Clone, Debug, Default, PartialEq)]
+struct Primitive {
+    positions: Vec<Vector3>,
+    normals: Vec<Vector3>,
+    indices: Vec<u32>,
+}
+
+// Welds the vertices and computes vertex normals as area-weighted sums of the adjacent face normals, splitting the
+// vertices at creases.
+fn build_primitive(pos: &[f32]) -> Primitive {
+    let mesh = IndexedMesh::from_triangle_soup(pos);
+    let face_normals: Vec<Vector3> = (0..mesh.triangles.len())
+        .map(|t| {
+            let [a, b, c] = mesh.triangle(t);
+            (b - a).cross(c - a)
+        })
+        .collect();
+    let unit_normals: Vec<Vector3> = face_normals.iter().map(|n| n.normalize()).collect();
+    let mut vertex_tris = vec![vec![]; mesh.positions.len()];
+    for (t, tri) in mesh.triangles.iter().enumerate() {
+        for &v in tri {
+            vertex_tris[v as usize].push(t);
+        }
+    }
+
+    let min_cos = CREASE_ANGLE_DEGREES.to_radians().cos();
+    let mut result = Primitive::default();
+    let mut vertex_ids: HashMap<(u32, Vector3), u32> = HashMap::new();
+    for (t, tri) in mesh.triangles.iter().enumerate() {
+        let own = unit_normals[t];
+        for &v in tri {
+            let mut sum = Vector3::ZERO;
+            for &other in &vertex_tris[v as usize] {
+                // Degenerate triangles take the normal of all neighbors.
+                if own == Vector3::ZERO || unit_normals[other].dot(own) >= min_cos {
+                    sum = sum + face_normals[other];
+                }
+            }
+            let mut normal = sum.normalize();
+            if normal == Vector3::ZERO {
+                // glTF requires unit normals, pick an arbitrary one for isolated degenerate triangles.
+                normal = if own == Vector3::ZERO { Vector3::new(0.0, 0.0, 1.0) } else { own };
+            }
+            let id = *vertex_ids.entry((v, normal)).or_insert_with(|| {
+                result.positions.push(mesh.positions[v as usize]);
+                result.normals.push(normal);
+                (result.positions.len() - 1) as u32
+            });
+            result.indices.push(id);
+        }
+    }
+    result
+}
+
+fn srgb_to_linear(c: u8) -> f32 {
+    let c = c as f32 / 255.0;
+    if c <= 0.04045 { c / 12.92 } else { ((c + 0.055) / 1.055).powf(2.4) }
+}
+
+fn json_string(s: &str) -> String {
+    let mut result = String::with_capacity(s.len() + 2);
+    result.push('"');
+    for c in s.chars() {
+        match c {
+            '"' => result.push_str("\\\""),
+            '\\' => result.push_str("\\\\"),
+            '\n' => result.push_str("\\n"),
+            '\r' => result.push_str("\\r"),
+            '\t' => result.push_str("\\t"),
+            c if (c as u32) < 0x20 => write!(result, "\\u{:04x}", c as u32).unwrap(),
+            c => result.push(c),
+        }
+    }
+    result.push('"');
+    result
+}
+
+// JSON has no representation for infinities and NaNs.
+fn json_number(v: f32) -> String {
+    if v.is_finite() { v.to_string() } else { "0".to_string() }
+}
+
+fn json_vector(v: Vector3) -> String {
+    format!("[{},{},{}]", json_number(v.x), json_number(v.y), json_number(v.z))
+}
+
+/// Writes the parts as a binary glTF 2.0 file. Each part with at least one triangle becomes a node with a mesh of
+/// a single indexed primitive with positions, vertex normals and its own material. The model is assumed to be Z-up,
+/// as 3D printing formats are; the root node rotates it into the Y-up glTF coordinate system.
+pub fn write_glb(parts: &[GlbPart]) -> Vec<u8> {
+    let mut bin: Vec<u8> = vec![];
+    let mut buffer_views = vec![];
+    let mut accessors = vec![];
+    let mut meshes = vec![];
+    let mut materials = vec![];
+    let mut nodes = vec![];
+    for part in parts {
+        let primitive = build_primitive(part.positions);
+        if primitive.indices.is_empty() {
+            continue;
+        }
+        let mut add_view = |bytes: &[u8], target: u32| {
+            buffer_views.push(format!(
+                "{{\"buffer\":0,\"byteOffset\":{},\"byteLength\":{},\"target\":{}}}",
+                bin.len(),
+                bytes.len(),
+                target
+            ));
+            // All components are 4 bytes, so the views stay aligned.
+            bin.extend_from_slice(bytes);
+            buffer_views.len() - 1
+        };
+        let vec3_bytes = |vs: &[Vector3]| -> Vec<u8> {
+            vs.iter()
+                .flat_map(|v| [v.x, v.y, v.z])
+                .flat_map(f32::to_le_bytes)
+                .collect()
+        };
+        let index_bytes: Vec<u8> = primitive
+            .indices
+            .iter()
+            .flat_map(|i| i.to_le_bytes())
+            .collect();
+        let positions_view = add_view(&vec3_bytes(&primitive.positions), TARGET_ARRAY_BUFFER);
+        let normals_view = add_view(&vec3_bytes(&primitive.normals), TARGET_ARRAY_BUFFER);
+        let indices_view = add_view(&index_bytes, TARGET_ELEMENT_ARRAY_BUFFER);
+
+        let (min, max) = primitive.positions.iter().fold(
+            (
+                Vector3::new(f32::INFINITY, f32::INFINITY, f32::INFINITY),
+                Vector3::new(f32::NEG_INFINITY, f32::NEG_INFINITY, f32::NEG_INFINITY),
+            ),
+            |(min, max), p| {
+                (
+                    Vector3::new(min.x.min(p.x), min.y.min(p.y), min.z.min(p.z)),
+                    Vector3::new(max.x.max(p.x), max.y.max(p.y), max.z.max(p.z)),
+                )
+            },
+        );
+        let vertex_count = primitive.positions.len();
+        accessors.push(format!(
+            "{{\"bufferView\":{},\"componentType\":{},\"count\":{},\"type\":\"VEC3\",\"min\":{},\"max\":{}}}",
+            positions_view,
+            COMPONENT_FLOAT,
+            vertex_count,
+            json_vector(min),
+            json_vector(max)
+        ));
+        accessors.push(format!(
+            "{{\"bufferView\":{},\"componentType\":{},\"count\":{},\"type\":\"VEC3\"}}",
+            normals_view, COMPONENT_FLOAT, vertex_count
+        ));
+        accessors.push(format!(
+            "{{\"bufferView\":{},\"componentType\":{},\"count\":{},\"type\":\"SCALAR\"}}",
+            indices_view,
+            COMPONENT_UNSIGNED_INT,
+            primitive.indices.len()
+        ));
+
+        let [r, g, b] = part.color.map(srgb_to_linear);
+        let name = json_string(part.name);
+        materials.push(format!(
+            "{{\"name\":{},\"pbrMetallicRoughness\":{{\"baseColorFactor\":[{},{},{},1],\"metallicFactor\":0,\
+             \"roughnessFactor\":0.8}}}}",
+            name, r, g, b
+        ));
+        let first_accessor = accessors.len() - 3;
+        meshes.push(format!(
+            "{{\"name\":{},\"primitives\":[{{\"attributes\":{{\"POSITION\":{},\"NORMAL\":{}}},\"indices\":{},\
+             \"material\":{},\"mode\":4}}]}}",
+            name,
+            first_accessor,
+            first_accessor + 1,
+            first_accessor + 2,
+            materials.len() - 1
+        ));
+        nodes.push(format!("{{\"name\":{},\"mesh\":{}}}", name, meshes.len() - 1));
+    }
+
+    let mut json = String::from("{\"asset\":{\"version\":\"2.0\",\"generator\":\"stl-web-viewer\"},\"scene\":0,");
+    if nodes.is_empty() {
+        // Empty arrays are not allowed by the schema, so an empty model has a scene without nodes.
+        json.push_str("\"scenes\":[{}]}");
+    } else {
+        let children: Vec<String> = (1..=nodes.len()).map(|i| i.to_string()).collect();
+        // Rotation by -90 degrees around X converts Z-up to Y-up.
+        let half = std::f32::consts::FRAC_1_SQRT_2;
+        let root = format!(
+            "{{\"name\":\"model\",\"rotation\":[{},0,0,{}],\"children\":[{}]}}",
+            -half,
+            half,
+            children.join(",")
+        );
+        write!(
+            json,
+            "\"scenes\":[{{\"nodes\":[0]}}],\"nodes\":[{},{}],\"meshes\":[{}],\"materials\":[{}],\"accessors\":[{}],\
+             \"bufferViews\":[{}],\"buffers\":[{{\"byteLength\":{}}}]}}",
+            root,
+            nodes.join(","),
+            meshes.join(","),
+            materials.join(","),
+            accessors.join(","),
+            buffer_views.join(","),
+            bin.len()
+        )
+        .unwrap();
+    }
+
+    // Chunks must be 4-byte aligned: JSON is padded with spaces, binary data with zeros.
+    let mut json = json.into_bytes();
+    json.resize(json.len().next_multiple_of(4), b' ');
+    bin.resize(bin.len().next_multiple_of(4), 0);
+    let mut total_len = 12 + 8 + json.len();
+    if !bin.is_empty() {
+        total_len += 8 + bin.len();
+    }
+    let mut result = Vec::with_capacity(total_len);
+    result.extend_from_slice(&GLB_MAGIC.to_le_bytes());
+    result.extend_from_slice(&2u32.to_le_bytes());
+    result.extend_from_slice(&(total_len as u32).to_le_bytes());
+    result.extend_from_slice(&(json.len() as u32).to_le_bytes());
+    result.extend_from_slice(&CHUNK_JSON.to_le_bytes());
+    result.extend_from_slice(&json);
+    if !bin.is_empty() {
+        result.extend_from_slice(&(bin.len() as u32).to_le_bytes());
+        result.extend_from_slice(&CHUNK_BIN.to_le_bytes());
+        result.extend_from_slice(&bin);
+    }
+    result
+}
+
+/// Writes a GLB file, see write_glb. `pos` contains the triangles of all parts one after another, `part_tri_counts`
+/// contains the number of triangles in each part. `colors` contains an RGB color for each part (e.g. the viewer palette
+/// colors or the original STL colors), parts without a color are gray. Parts without a name get "part N".
+#[wasm_bindgen]
+pub fn export_glb(pos: &[f32], part_tri_counts: &[u32], colors: &[u8], names: Vec<String>) -> Result<Box<[u8]>, Error> {
+    let parts = split_export_parts(pos, part_tri_counts, colors, names)?;
+    let parts: Vec<GlbPart> = parts
+        .iter()
+        .map(|p| GlbPart { name: &p.name, positions: p.positions, color: p.color.unwrap_or(DEFAULT_COLOR) })
+        .collect();
+    Ok(write_glb(&parts).into_boxed_slice())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::plane_section::tests::create_box_geometry;
+
+    fn read_u32(data: &[u8], offset: usize) -> u32 {
+        u32::from_le_bytes(data[offset..offset + 4].try_into().unwrap())
+    }
+
+    // Returns the JSON text and the binary chunk, checking the container structure.
+    fn parse_glb(data: &[u8]) -> (String, Vec<u8>) {
+        assert_eq!(read_u32(data, 0), GLB_MAGIC);
+        assert_eq!(read_u32(data, 4), 2);
+        assert_eq!(read_u32(data, 8) as usize, data.len());
+        let json_len = read_u32(data, 12) as usize;
+        assert!(json_len.is_multiple_of(4));
+        assert_eq!(read_u32(data, 16), CHUNK_JSON);
+        let json = String::from_utf8(data[20..20 + json_len].to_vec()).unwrap();
+        let rest = &data[20 + json_len..];
+        if rest.is_empty() {
+            return (json, vec![]);
+        }
+        let bin_len = read_u32(rest, 0) as usize;
+        assert!(bin_len.is_multiple_of(4));
+        assert_eq!(read_u32(rest, 4), CHUNK_BIN);
+        assert_eq!(rest.len(), 8 + bin_len);
+        (json, rest[8..].to_vec())
+    }
+
+    #[test]
+    fn test_box_normals() {
+        let cube = create_box_geometry(Vector3::new(0.0, 0.0, 0.0), Vector3::new(1.0, 1.0, 1.0));
+        let primitive = build_primitive(&cube);
+        // Every corner of the box has three vertices, one per face.
+        assert_eq!(primitive.positions.len(), 24);
+        assert_eq!(primitive.indices.len(), 36);
+        for (p, n) in primitive.positions.iter().zip(&primitive.normals) {
+            assert!((n.length() - 1.0).abs() < 1e-6);
+            // Normals point outwards.
+            let center = Vector3::new(0.5, 0.5, 0.5);
+            assert!((*p - center).dot(*n) > 0.0);
+        }
+    }
+
+    #[test]
+    fn test_smooth_normals() {
+        // Two triangles at a shallow angle share the vertices and get averaged normals.
+        let pos = [0.0, 0.0, 0.0, 1.0, 0.0, 0.0, 0.0, 1.0, 0.0, 1.0, 0.0, 0.0, 1.0, 1.0, 0.1, 0.0, 1.0, 0.0];
+        let primitive = build_primitive(&pos);
+        assert_eq!(primitive.positions.len(), 4);
+        assert!(primitive.normals.iter().all(|n| n.z > 0.99));
+        // Degenerate triangles still get unit normals.
+        let degenerate = build_primitive(&[0.0, 0.0, 0.0, 1.0, 1.0, 1.0, 2.0, 2.0, 2.0]);
+        assert_eq!(degenerate.normals, vec![Vector3::new(0.0, 0.0, 1.0); 3]);
+    }
+
+    #[test]
+    fn test_glb() {
+        let cube = create_box_geometry(Vector3::new(0.0, 0.0, 0.0), Vector3::new(1.0, 2.0, 3.0));
+        let parts = [
+            GlbPart { name: "cube \"A\"", positions: &cube, color: [255, 0, 0] },
+            GlbPart { name: "empty", positions: &[], color: [0, 0, 0] },
+            GlbPart { name: "cube B", positions: &cube, color: [0, 0, 255] },
+        ];
+        let (json, bin) = parse_glb(&write_glb(&parts));
+        // Two parts, each with 24 positions, 24 normals and 36 indices.
+        assert_eq!(bin.len(), 2 * (24 * 12 * 2 + 36 * 4));
+        assert!(
+            json.contains(
+                "\"nodes\":[{\"name\":\"model\",\"rotation\":[-0.70710677,0,0,0.70710677],\"children\":[1,2]}"
+            )
+        );
+        assert!(json.contains("{\"name\":\"cube \\\"A\\\"\",\"mesh\":0}"));
+        assert!(json.contains("{\"name\":\"cube B\",\"mesh\":1}"));
+        assert!(!json.contains("empty"));
+        assert!(json.contains("\"min\":[0,0,0],\"max\":[1,2,3]"));
+        assert!(json.contains("\"baseColorFactor\":[1,0,0,1]"));
+        assert!(json.contains("\"baseColorFactor\":[0,0,1,1]"));
+        assert!(json.contains(&format!("\"buffers\":[{{\"byteLength\":{}}}]", bin.len())));
+        assert!(json.contains("\"byteOffset\":576,\"byteLength\":144,\"target\":34963"));
+        // Indices of the first part are within the vertex range.
+        let indices_offset = 24 * 12 * 2;
+        for i in 0..36 {
+            assert!(read_u32(&bin, indices_offset + i * 4) < 24);
+        }
+    }
+
+    #[test]
+    fn test_empty() {
+        let (json, bin) = parse_glb(&write_glb(&[]));
+        assert!(bin.is_empty());
+        assert!(json.trim_end().ends_with("\"scenes\":[{}]}"));
+    }
+
+    #[test]
+    fn test_helpers() {
+        assert_eq!(json_string("a\"b\\c\n\u{1}"), "\"a\\\"b\\\\c\\n\\u0001\"");
+        assert_eq!(json_number(f32::NAN), "0");
+        assert_eq!(srgb_to_linear(0), 0.0);
+        assert_eq!(srgb_to_linear(255), 1.0);
+        assert!((srgb_to_linear(128) - 0.2158605).abs() < 1e-6);
+    }
+
+    #[test]
+    fn test_wasm_wrapper() {
+        let cube = create_box_geometry(Vector3::new(0.0, 0.0, 0.0), Vector3::new(1.0, 1.0, 1.0));
+        let pos = cube.repeat(2);
+        let (json, _) = parse_glb(&export_glb(&pos, &[12, 12], &[255, 255, 255], vec!["first".to_string()]).unwrap());
+        assert!(json.contains("{\"name\":\"first\",\"mesh\":0}"));
+        assert!(json.contains("{\"name\":\"part 2\",\"mesh\":1}"));
+        assert!(json.contains("\"baseColorFactor\":[1,1,1,1]"));
+        assert!(json.contains("\"baseColorFactor\":[0.21586053,0.21586053,0.21586053,1]"));
+    }
+}
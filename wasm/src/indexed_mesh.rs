@@ -0,0 +1,147 @@
+use std::collections::HashMap;
+
+use crate::error::Error;
+use crate::parallel::{self, Prehashed, PrehashedMap};
+use crate::progress::Progress;
+use crate::vector3::Vector3;
+
+/// A triangle mesh with shared vertices. Vertices are welded only if their coordinates are exactly equal, which is
+/// what STL files and the other parts of the crate assume.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct IndexedMesh {
+    pub positions: Vec<Vector3>,
+    pub triangles: Vec<[u32; 3]>,
+}
+
+/// Unique undirected edges of an IndexedMesh. `triangle_edges[t][i]` is the index of the edge between vertices i and
+/// (i + 1) % 3 of triangle t.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct MeshEdges {
+    /// Vertex indices of each edge, the smaller index goes first.
+    pub vertices: Vec<[u32; 2]>,
+    pub triangle_edges: Vec<[u32; 3]>,
+}
+
+impl IndexedMesh {
+    /// Builds the indexed mesh from the interleaved x, y, z coordinates, 3 vertices (9 floats) per triangle. Trailing
+    /// floats which do not form a complete triangle are ignored.
+    pub fn from_triangle_soup(pos: &[f32]) -> Self {
+        Self::from_triangle_soup_with_progress(pos, Progress::none()).expect("welding without a token can not fail")
+    }
+
+    /// Same as from_triangle_soup, reports the progress and stops if the operation is cancelled.
+    pub fn from_triangle_soup_with_progress(pos: &[f32], progress: Progress) -> Result<Self, Error> {
+        let tri_count = pos.len() / 9;
+        // Hashing is the expensive part of the welding, it is done in parallel. The vertex ids are then assigned in the
+        // order of the first occurrence, which does not depend on the number of threads.
+        let vertices =
+            parallel::map_chunks(&pos[..tri_count * 9], 3, |v| Prehashed::new(Vector3::new(v[0], v[1], v[2])));
+        let mut vertex_ids: PrehashedMap<Vector3, u32> =
+            PrehashedMap::with_capacity_and_hasher(tri_count / 2 + 3, Default::default());
+        let mut positions = vec![];
+        let mut triangles = Vec::with_capacity(tri_count);
+        for (tri_idx, tri) in vertices.chunks_exact(3).enumerate() {
+            progress.update(tri_idx, tri_count)?;
+            let mut ids = [0u32; 3];
+            for (id, &v) in ids.iter_mut().zip(tri) {
+                *id = *vertex_ids.entry(v).or_insert_with(|| {
+                    positions.push(v.key);
+                    (positions.len() - 1) as u32
+                });
+            }
+            triangles.push(ids);
+        }
+        progress.update(tri_count, tri_count)?;
+        Ok(Self { positions, triangles })
+    }
+
+    /// Returns interleaved x, y, z coordinates, 9 floats per triangle.
+    pub fn to_triangle_soup(&self) -> Vec<f32> {
+        let mut result = Vec::with_capacity(self.triangles.len() * 9);
+        for tri in &self.triangles {
+            for &idx in tri {
+                let v = self.positions[idx as usize];
+                result.extend_from_slice(&[v.x, v.y, v.z]);
+            }
+        }
+        result
+    }
+
+    pub fn triangle(&self, tri_idx: usize) -> [Vector3; 3] {
+        let [a, b, c] = self.triangles[tri_idx];
+        [self.positions[a as usize], self.positions[b as usize], self.positions[c as usize]]
+    }
+
+    pub fn build_edges(&self) -> MeshEdges {
+        let mut edge_ids: HashMap<(u32, u32), u32> = HashMap::with_capacity(self.triangles.len() * 3 / 2);
+        let mut vertices = vec![];
+        let mut triangle_edges = Vec::with_capacity(self.triangles.len());
+        for tri in &self.triangles {
+            let mut ids = [0u32; 3];
+            for (i, id) in ids.iter_mut().enumerate() {
+                let a = tri[i];
+                let b = tri[(i + 1) % 3];
+                let key = (a.min(b), a.max(b));
+                *id = *edge_ids.entry(key).or_insert_with(|| {
+                    vertices.push([key.0, key.1]);
+                    (vertices.len() - 1) as u32
+                });
+            }
+            triangle_edges.push(ids);
+        }
+        MeshEdges { vertices, triangle_edges }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parallel::tests::with_threads;
+    use crate::progress::tests::{assert_complete, run_with_progress};
+    use crate::slicer::tests::create_sphere_geometry;
+
+    #[test]
+    fn test_welding() {
+        let pos = [
+            0.0, 0.0, 0.0, 1.0, 0.0, 0.0, 0.0, 1.0, 0.0, // triangle 1
+            1.0, 0.0, 0.0, 1.0, 1.0, 0.0, 0.0, 1.0, 0.0, // triangle 2
+            0.0, 0.0, 0.0, // incomplete triangle
+        ];
+        let mesh = IndexedMesh::from_triangle_soup(&pos);
+        assert_eq!(mesh.positions.len(), 4);
+        assert_eq!(mesh.triangles, vec![[0, 1, 2], [1, 3, 2]]);
+        assert_eq!(mesh.to_triangle_soup(), pos[..18].to_vec());
+        assert_eq!(
+            mesh.triangle(1),
+            [Vector3::new(1.0, 0.0, 0.0), Vector3::new(1.0, 1.0, 0.0), Vector3::new(0.0, 1.0, 0.0)]
+        );
+    }
+
+    #[test]
+    fn test_edges() {
+        let mesh = IndexedMesh { positions: vec![Vector3::ZERO; 4], triangles: vec![[0, 1, 2], [1, 3, 2]] };
+        let edges = mesh.build_edges();
+        assert_eq!(edges.vertices, vec![[0, 1], [1, 2], [0, 2], [1, 3], [2, 3]]);
+        assert_eq!(edges.triangle_edges, vec![[0, 1, 2], [3, 4, 1]]);
+    }
+
+    #[test]
+    fn test_welding_progress() {
+        let pos = create_sphere_geometry(1.0, 16, 8);
+        let (result, fractions) =
+            run_with_progress(2.0, |progress| IndexedMesh::from_triangle_soup_with_progress(&pos, progress));
+        assert_eq!(result, Ok(IndexedMesh::from_triangle_soup(&pos)));
+        assert_complete(&fractions);
+        let (result, _) =
+            run_with_progress(0.5, |progress| IndexedMesh::from_triangle_soup_with_progress(&pos, progress));
+        assert_eq!(result, Err(Error::Cancelled));
+    }
+
+    #[test]
+    fn test_threads() {
+        let pos = create_sphere_geometry(1.0, 128, 64);
+        let expected = with_threads(1, || IndexedMesh::from_triangle_soup(&pos));
+        assert_eq!(expected.positions.len(), 128 * 63 + 2);
+        assert_eq!(with_threads(4, || IndexedMesh::from_triangle_soup(&pos)), expected);
+    }
+}
@@ -0,0 +1,241 @@
+// Protects from stack overflows on deeply nested documents.
+const MAX_DEPTH: usize = 128;
+
+/// A parsed JSON value. Object members keep the document order.
+#[derive(Clone, Debug, PartialEq)]
+pub enum JsonValue {
+    Null,
+    Bool(bool),
+    Number(f64),
+    String(String),
+    Array(Vec<JsonValue>),
+    Object(Vec<(String, JsonValue)>),
+}
+
+impl JsonValue {
+    /// Returns the member of an object, or None for missing members and non-objects.
+    pub fn get(&self, key: &str) -> Option<&JsonValue> {
+        match self {
+            JsonValue::Object(members) => members.iter().find(|(k, _)| k == key).map(|(_, v)| v),
+            _ => None,
+        }
+    }
+
+    pub fn as_f64(&self) -> Option<f64> {
+        match self {
+            JsonValue::Number(n) => Some(*n),
+            _ => None,
+        }
+    }
+
+    /// Returns the value if it is a non-negative integer.
+    pub fn as_usize(&self) -> Option<usize> {
+        self.as_f64()
+            .filter(|n| n.fract() == 0.0 && *n >= 0.0 && *n <= u32::MAX as f64)
+            .map(|n| n as usize)
+    }
+
+    pub fn as_str(&self) -> Option<&str> {
+        match self {
+            JsonValue::String(s) => Some(s),
+            _ => None,
+        }
+    }
+
+    /// Returns the elements of an array, or an empty slice for non-arrays.
+    pub fn elements(&self) -> &[JsonValue] {
+        match self {
+            JsonValue::Array(elements) => elements,
+            _ => &[],
+        }
+    }
+}
+
+struct JsonParser<'a> {
+    text: &'a str,
+    pos: usize,
+}
+
+impl JsonParser<'_> {
+    fn error(&self, message: &str) -> String {
+        format!("invalid JSON at offset {}: {}", self.pos, message)
+    }
+
+    fn skip_whitespace(&mut self) {
+        let rest = &self.text[self.pos..];
+        self.pos += rest.len() - rest.trim_start_matches([' ', '\t', '\n', '\r']).len();
+    }
+
+    fn peek(&self) -> Option<u8> {
+        self.text.as_bytes().get(self.pos).copied()
+    }
+
+    fn consume(&mut self, literal: &str) -> bool {
+        if self.text[self.pos..].starts_with(literal) {
+            self.pos += literal.len();
+            true
+        } else {
+            false
+        }
+    }
+
+    fn parse_value(&mut self, depth: usize) -> Result<JsonValue, String> {
+        if depth > MAX_DEPTH {
+            return Err(self.error("nested too deep"));
+        }
+        self.skip_whitespace();
+        match self.peek() {
+            Some(b'{') => {
+                self.pos += 1;
+                let mut members = vec![];
+                self.skip_whitespace();
+                if self.consume("}") {
+                    return Ok(JsonValue::Object(members));
+                }
+                loop {
+                    self.skip_whitespace();
+                    let key = self.parse_string()?;
+                    self.skip_whitespace();
+                    if !self.consume(":") {
+                        return Err(self.error("expected ':'"));
+                    }
+                    members.push((key, self.parse_value(depth + 1)?));
+                    self.skip_whitespace();
+                    if self.consume("}") {
+                        return Ok(JsonValue::Object(members));
+                    }
+                    if !self.consume(",") {
+                        return Err(self.error("expected ',' or '}'"));
+                    }
+                }
+            }
+            Some(b'[') => {
+                self.pos += 1;
+                let mut elements = vec![];
+                self.skip_whitespace();
+                if self.consume("]") {
+                    return Ok(JsonValue::Array(elements));
+                }
+                loop {
+                    elements.push(self.parse_value(depth + 1)?);
+                    self.skip_whitespace();
+                    if self.consume("]") {
+                        return Ok(JsonValue::Array(elements));
+                    }
+                    if !self.consume(",") {
+                        return Err(self.error("expected ',' or ']'"));
+                    }
+                }
+            }
+            Some(b'"') => Ok(JsonValue::String(self.parse_string()?)),
+            _ if self.consume("null") => Ok(JsonValue::Null),
+            _ if self.consume("true") => Ok(JsonValue::Bool(true)),
+            _ if self.consume("false") => Ok(JsonValue::Bool(false)),
+            _ => {
+                let rest = &self.text[self.pos..];
+                let len = rest
+                    .find(|c: char| !(c.is_ascii_digit() || "+-.eE".contains(c)))
+                    .unwrap_or(rest.len());
+                let number = rest[..len]
+                    .parse::<f64>()
+                    .map_err(|_| self.error("unexpected character"))?;
+                self.pos += len;
+                Ok(JsonValue::Number(number))
+            }
+        }
+    }
+
+    fn parse_string(&mut self) -> Result<String, String> {
+        if !self.consume("\"") {
+            return Err(self.error("expected a string"));
+        }
+        let mut result = String::new();
+        loop {
+            let rest = &self.text[self.pos..];
+            let special = rest
+                .find(['"', '\\'])
+                .ok_or_else(|| self.error("unterminated string"))?;
+            result.push_str(&rest[..special]);
+            self.pos += special + 1;
+            if rest.as_bytes()[special] == b'"' {
+                return Ok(result);
+            }
+            let escape = self
+                .peek()
+                .ok_or_else(|| self.error("unterminated string"))?;
+            self.pos += 1;
+            match escape {
+                b'"' => result.push('"'),
+                b'\\' => result.push('\\'),
+                b'/' => result.push('/'),
+                b'b' => result.push('\u{8}'),
+                b'f' => result.push('\u{c}'),
+                b'n' => result.push('\n'),
+                b'r' => result.push('\r'),
+                b't' => result.push('\t'),
+                b'u' => {
+                    let mut code = self.parse_hex4()?;
+                    // Surrogate pairs encode the characters outside of the BMP.
+                    if (0xd800..0xdc00).contains(&code) && self.consume("\\u") {
+                        let low = self.parse_hex4()?;
+                        code = 0x10000 + ((code - 0xd800) << 10) + (low.wrapping_sub(0xdc00) & 0x3ff);
+                    }
+                    result.push(char::from_u32(code).unwrap_or(char::REPLACEMENT_CHARACTER));
+                }
+                _ => return Err(self.error("invalid escape")),
+            }
+        }
+    }
+
+    fn parse_hex4(&mut self) -> Result<u32, String> {
+        let hex = self
+            .text
+            .get(self.pos..self.pos + 4)
+            .and_then(|h| u32::from_str_radix(h, 16).ok())
+            .ok_or_else(|| self.error("invalid unicode escape"))?;
+        self.pos += 4;
+        Ok(hex)
+    }
+}
+
+/// Parses a JSON document.
+pub fn parse_json(text: &str) -> Result<JsonValue, String> {
+    let mut parser = JsonParser { text, pos: 0 };
+    let value = parser.parse_value(0)?;
+    parser.skip_whitespace();
+    if parser.pos != text.len() {
+        return Err(parser.error("trailing characters"));
+    }
+    Ok(value)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse() {
+        let value =
+            parse_json(r#" {"a": [1, -2.5e1, true, false, null], "b": {"c": "x\"\n\u00e9\ud83d\ude00"}, "d": {}} "#)
+                .unwrap();
+        let a = value.get("a").unwrap().elements();
+        assert_eq!(a.len(), 5);
+        assert_eq!(a[0].as_usize(), Some(1));
+        assert_eq!(a[1].as_f64(), Some(-25.0));
+        assert_eq!(a[1].as_usize(), None);
+        assert_eq!(a[2], JsonValue::Bool(true));
+        assert_eq!(a[4], JsonValue::Null);
+        assert_eq!(value.get("b").unwrap().get("c").unwrap().as_str(), Some("x\"\né😀"));
+        assert_eq!(value.get("d"), Some(&JsonValue::Object(vec![])));
+        assert_eq!(value.get("e"), None);
+        assert!(a[0].elements().is_empty());
+    }
+
+    #[test]
+    fn test_errors() {
+        for text in ["", "{", "[1,]", "{\"a\" 1}", "\"abc", "tru", "1 2", "\"\\x\"", "\"\\u12\"", "{1: 2}"] {
+            assert!(parse_json(text).is_err(), "{}", text);
+        }
+        assert!(parse_json(&"[".repeat(1000)).is_err());
+    }
+}
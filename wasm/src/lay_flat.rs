@@ -0,0 +1,324 @@
+use wasm_bindgen::prelude::*;
+
+use crate::convex_hull::convex_hull;
+use crate::error::{Error, check_triangle_soup, checked_item};
+use crate::indexed_mesh::IndexedMesh;
+use crate::mesh_stats::compute_mesh_stats;
+use crate::overhang::{analyze_overhangs, check_overhang_threshold};
+use crate::plane_section::Plane;
+use crate::polygon::point_in_polygon;
+use crate::vector3::Vector3;
+
+// Hull faces with normals closer than ~1 degree are considered coplanar.
+const COPLANAR_COS: f32 = 0.99985;
+
+/// A candidate print orientation with the base face lying on the build plate.
+#[derive(Clone, Debug, PartialEq)]
+pub struct LayFlatOrientation {
+    /// Outward normal of the base face in model coordinates.
+    pub normal: Vector3,
+    /// Column-major 3x3 matrix rotating the model so that the base face normal points to -Z.
+    pub rotation: [f32; 9],
+    /// Area of model triangles touching the build plate.
+    pub contact_area: f32,
+    /// True if the projection of the center of mass lies inside the base face.
+    pub stable: bool,
+    pub overhang_area: f32,
+}
+
+/// Returns the column-major rotation matrix which rotates unit vector `from` to unit vector `to`.
+pub fn rotation_between(from: Vector3, to: Vector3) -> [f32; 9] {
+    let c = from.dot(to);
+    if c < -0.999999 {
+        // Rotate by PI around any axis perpendicular to from: R = 2 * u * u^T - I.
+        let axis = if from.x.abs() < 0.9 { Vector3::new(1.0, 0.0, 0.0) } else { Vector3::new(0.0, 1.0, 0.0) };
+        let u = from.cross(axis).normalize();
+        let u = [u.x, u.y, u.z];
+        let mut m = [0.0; 9];
+        for col in 0..3 {
+            for row in 0..3 {
+                m[col * 3 + row] = 2.0 * u[row] * u[col] - if row == col { 1.0 } else { 0.0 };
+            }
+        }
+        return m;
+    }
+    // Rodrigues formula: R = I + [v]x + [v]x^2 / (1 + c), v = from x to.
+    let v = from.cross(to);
+    let k = 1.0 / (1.0 + c);
+    #[rustfmt::skip]
+    let m = [
+        1.0 - k * (v.y * v.y + v.z * v.z), v.z + k * v.x * v.y, -v.y + k * v.x * v.z,
+        -v.z + k * v.x * v.y, 1.0 - k * (v.x * v.x + v.z * v.z), v.x + k * v.y * v.z,
+        v.y + k * v.x * v.z, -v.x + k * v.y * v.z, 1.0 - k * (v.x * v.x + v.y * v.y),
+    ];
+    m
+}
+
+struct Candidate {
+    normal: Vector3,
+    hull_area: f32,
+    hull_faces: Vec<[Vector3; 3]>,
+}
+
+/// Proposes stable print orientations. Faces of the convex hull are clustered by normal into candidate base faces
+/// (on a convex hull coplanar faces are always adjacent), then the `max_candidates` largest ones are ranked: stable
+/// orientations go first, then orientations with larger contact area minus overhang area (see analyze_overhangs for
+/// `overhang_threshold_degrees`). `pos` has the same layout as in split_disjoint_geometry.
+pub fn suggest_orientations(
+    pos: &[f32],
+    max_candidates: usize,
+    overhang_threshold_degrees: f32,
+) -> Result<Vec<LayFlatOrientation>, Error> {
+    check_overhang_threshold(overhang_threshold_degrees)?;
+    let mesh = IndexedMesh::from_triangle_soup(pos);
+    let hull = convex_hull(&mesh.positions);
+    if hull.triangles.is_empty() || max_candidates == 0 {
+        return Ok(vec![]);
+    }
+
+    let mut hull_faces: Vec<([Vector3; 3], Vector3, f32)> = hull
+        .triangles
+        .iter()
+        .map(|t| {
+            let v = t.map(|i| mesh.positions[i as usize]);
+            let cross = (v[1] - v[0]).cross(v[2] - v[0]);
+            (v, cross.normalize(), 0.5 * cross.length())
+        })
+        .filter(|&(_, _, area)| area > 0.0)
+        .collect();
+    hull_faces.sort_by(|a, b| b.2.total_cmp(&a.2));
+
+    let mut candidates: Vec<Candidate> = vec![];
+    for (v, normal, area) in hull_faces {
+        match candidates
+            .iter_mut()
+            .find(|c| c.normal.dot(normal) > COPLANAR_COS)
+        {
+            Some(c) => {
+                c.hull_area += area;
+                c.hull_faces.push(v);
+            }
+            None => candidates.push(Candidate { normal, hull_area: area, hull_faces: vec![v] }),
+        }
+    }
+    candidates.sort_by(|a, b| b.hull_area.total_cmp(&a.hull_area));
+    candidates.truncate(max_candidates);
+
+    let stats = compute_mesh_stats(pos);
+    let epsilon = 1e-5 * (stats.bbox_max - stats.bbox_min).length();
+    let mut result = vec![];
+    for candidate in candidates {
+        let normal = candidate.normal;
+        let support = candidate.hull_faces[0][0].dot(normal);
+
+        let mut contact_area = 0.0;
+        for t in 0..mesh.triangles.len() {
+            let [a, b, c] = mesh.triangle(t);
+            let cross = (b - a).cross(c - a);
+            let on_plane = [a, b, c].iter().all(|p| support - p.dot(normal) <= epsilon);
+            if on_plane && cross.normalize().dot(normal) > COPLANAR_COS {
+                contact_area += 0.5 * cross.length();
+            }
+        }
+
+        let plane = Plane::new(candidate.hull_faces[0][0], normal).unwrap();
+        let basis = plane.basis();
+        let centroid = plane.project(stats.centroid, basis);
+        let stable = candidate.hull_faces.iter().any(|tri| {
+            let projected = tri.map(|p| plane.project(p, basis));
+            point_in_polygon(centroid, &projected)
+        });
+
+        let overhang_area = analyze_overhangs(pos, normal * -1.0, overhang_threshold_degrees)?
+            .regions
+            .iter()
+            .map(|r| r.area)
+            .sum();
+
+        result.push(LayFlatOrientation {
+            normal,
+            rotation: rotation_between(normal, Vector3::new(0.0, 0.0, -1.0)),
+            contact_area,
+            stable,
+            overhang_area,
+        });
+    }
+
+    result.sort_by(|a, b| {
+        b.stable
+            .cmp(&a.stable)
+            .then((b.contact_area - b.overhang_area).total_cmp(&(a.contact_area - a.overhang_area)))
+    });
+    Ok(result)
+}
+
+/// Result of suggest_lay_flat for JS, orientations are sorted from best to worst.
+#[wasm_bindgen]
+pub struct LayFlatSuggestions {
+    orientations: Vec<LayFlatOrientation>,
+}
+
+impl LayFlatSuggestions {
+    fn orientation(&self, idx: usize) -> Result<&LayFlatOrientation, Error> {
+        checked_item(&self.orientations, idx, "orientation")
+    }
+}
+
+#[wasm_bindgen]
+impl LayFlatSuggestions {
+    #[wasm_bindgen(getter)]
+    pub fn count(&self) -> usize {
+        self.orientations.len()
+    }
+
+    /// Column-major 3x3 rotation matrix, can be passed to Matrix3.fromArray().
+    pub fn rotation(&self, idx: usize) -> Result<Box<[f32]>, Error> {
+        Ok(Box::new(self.orientation(idx)?.rotation))
+    }
+
+    pub fn contact_area(&self, idx: usize) -> Result<f32, Error> {
+        Ok(self.orientation(idx)?.contact_area)
+    }
+
+    pub fn overhang_area(&self, idx: usize) -> Result<f32, Error> {
+        Ok(self.orientation(idx)?.overhang_area)
+    }
+
+    pub fn is_stable(&self, idx: usize) -> Result<bool, Error> {
+        Ok(self.orientation(idx)?.stable)
+    }
+}
+
+/// See suggest_orientations.
+#[wasm_bindgen]
+pub fn suggest_lay_flat(
+    pos: &[f32],
+    max_candidates: usize,
+    overhang_threshold_degrees: f32,
+) -> Result<LayFlatSuggestions, Error> {
+    check_triangle_soup(pos)?;
+    Ok(LayFlatSuggestions { orientations: suggest_orientations(pos, max_candidates, overhang_threshold_degrees)? })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::plane_section::tests::create_box_geometry;
+    use crate::slicer::tests::create_sphere_geometry;
+
+    // Multiplies the column-major 3x3 matrix by the vector.
+    fn apply_rotation(m: &[f32; 9], v: Vector3) -> Vector3 {
+        Vector3::new(
+            m[0] * v.x + m[3] * v.y + m[6] * v.z,
+            m[1] * v.x + m[4] * v.y + m[7] * v.z,
+            m[2] * v.x + m[5] * v.y + m[8] * v.z,
+        )
+    }
+
+    fn assert_near(a: Vector3, b: Vector3) {
+        assert!((a - b).length() < 1e-5, "{:?} != {:?}", a, b);
+    }
+
+    #[test]
+    fn test_rotation_between() {
+        let down = Vector3::new(0.0, 0.0, -1.0);
+        for from in [
+            Vector3::new(1.0, 0.0, 0.0),
+            Vector3::new(0.0, 0.0, 1.0),
+            Vector3::new(0.0, 0.0, -1.0),
+            Vector3::new(1.0, 2.0, -3.0).normalize(),
+        ] {
+            let m = rotation_between(from, down);
+            assert_near(apply_rotation(&m, from), down);
+            // The matrix must be a rotation: orthonormal columns with positive orientation.
+            let x = apply_rotation(&m, Vector3::new(1.0, 0.0, 0.0));
+            let y = apply_rotation(&m, Vector3::new(0.0, 1.0, 0.0));
+            let z = apply_rotation(&m, Vector3::new(0.0, 0.0, 1.0));
+            assert!((x.length() - 1.0).abs() < 1e-5 && x.dot(y).abs() < 1e-5);
+            assert_near(x.cross(y), z);
+        }
+    }
+
+    #[test]
+    fn test_degenerate() {
+        assert!(suggest_orientations(&[], 5, 45.0).unwrap().is_empty());
+        let flat = [0.0, 0.0, 0.0, 1.0, 0.0, 0.0, 0.0, 1.0, 0.0];
+        assert!(suggest_orientations(&flat, 5, 45.0).unwrap().is_empty());
+        assert!(suggest_orientations(&flat, 5, 120.0).is_err());
+    }
+
+    #[test]
+    fn test_box() {
+        // The largest face of a box goes first, all six faces are stable and have no overhangs.
+        let cube = create_box_geometry(Vector3::new(0.0, 0.0, 0.0), Vector3::new(1.0, 2.0, 4.0));
+        let orientations = suggest_orientations(&cube, 10, 45.0).unwrap();
+        assert_eq!(orientations.len(), 6);
+        assert!(
+            orientations
+                .iter()
+                .all(|o| o.stable && o.overhang_area == 0.0)
+        );
+        assert!((orientations[0].contact_area - 8.0).abs() < 1e-5);
+        assert!((orientations[1].contact_area - 8.0).abs() < 1e-5);
+        assert!((orientations[5].contact_area - 2.0).abs() < 1e-5);
+        assert_eq!(orientations[0].normal.x.abs(), 1.0);
+
+        assert_eq!(suggest_orientations(&cube, 2, 45.0).unwrap().len(), 2);
+    }
+
+    #[test]
+    fn test_t_shape() {
+        // A "T" made of a thin tall stem and a wide top bar. Lying on the side is the best (no overhangs and the largest
+        // contact). Standing on the stem is stable but the bar overhangs, so lying on the bar top is preferred.
+        let stem = create_box_geometry(Vector3::new(-0.5, -0.5, 0.0), Vector3::new(0.5, 0.5, 5.0));
+        let bar = create_box_geometry(Vector3::new(-3.0, -0.5, 5.0), Vector3::new(3.0, 0.5, 6.0));
+        let mut t = stem.clone();
+        t.extend_from_slice(&bar);
+        let orientations = suggest_orientations(&t, 10, 45.0).unwrap();
+        assert_eq!(orientations[0].normal.y.abs(), 1.0);
+        assert!((orientations[0].contact_area - 11.0).abs() < 1e-5);
+        assert_eq!(orientations[0].overhang_area, 0.0);
+
+        let find = |normal: Vector3| {
+            orientations
+                .iter()
+                .position(|o| (o.normal - normal).length() < 1e-5)
+                .unwrap()
+        };
+        let on_bar = find(Vector3::new(0.0, 0.0, 1.0));
+        let on_stem = find(Vector3::new(0.0, 0.0, -1.0));
+        assert!(on_bar < on_stem);
+        assert!(orientations[on_stem].stable);
+        assert!((orientations[on_stem].contact_area - 1.0).abs() < 1e-5);
+        // The stem and the bar are separate boxes, so the whole bottom of the bar is an overhang.
+        assert!((orientations[on_stem].overhang_area - 6.0).abs() < 1e-5);
+        assert!((orientations[on_bar].contact_area - 6.0).abs() < 1e-5);
+    }
+
+    #[test]
+    fn test_unstable() {
+        // A sphere on top of a thin leg: the centroid projects outside of the tiny leg base.
+        let leg = create_box_geometry(Vector3::new(3.0, -0.1, -10.0), Vector3::new(3.2, 0.1, 0.0));
+        let mut model = create_sphere_geometry(3.0, 32, 16);
+        model.extend_from_slice(&leg);
+        let orientations = suggest_orientations(&model, 1000, 45.0).unwrap();
+        let on_leg = orientations
+            .iter()
+            .find(|o| (o.normal - Vector3::new(0.0, 0.0, -1.0)).length() < 1e-5)
+            .unwrap();
+        assert!(!on_leg.stable);
+        assert!(orientations[0].stable);
+    }
+
+    #[test]
+    fn test_wasm_wrapper() {
+        let cube = create_box_geometry(Vector3::new(0.0, 0.0, 0.0), Vector3::new(1.0, 2.0, 4.0));
+        let suggestions = suggest_lay_flat(&cube, 3, 45.0).unwrap();
+        assert_eq!(suggestions.count(), 3);
+        assert_eq!(suggestions.rotation(0).unwrap().len(), 9);
+        assert!(suggestions.is_stable(0).unwrap());
+        assert!((suggestions.contact_area(0).unwrap() - 8.0).abs() < 1e-5);
+        assert_eq!(suggestions.overhang_area(0).unwrap(), 0.0);
+        assert_eq!(suggestions.rotation(3).unwrap_err().code(), "INVALID_INPUT");
+    }
+}
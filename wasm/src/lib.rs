@@ -1,5 +1,42 @@
+mod amf;
+mod arrange;
 mod benchmark;
+mod bvh;
+mod convex_hull;
+mod error;
+mod format;
+mod gcode;
+mod glb;
+mod glb_writer;
+mod indexed_mesh;
+mod json;
+mod lay_flat;
+mod mesh_stats;
+mod model;
+mod model_handle;
 mod not_atan;
+mod obj;
+mod off;
+mod overhang;
+mod parallel;
+mod part_export;
+mod plane_section;
+mod ply;
+mod polygon;
+mod print_estimate;
+mod progress;
+mod section_caps;
+mod slicer;
 mod split_geometry;
+mod stl;
+mod stl_stream;
+mod stl_writer;
+mod three_mf;
+mod three_mf_writer;
+mod typed_vec;
 mod util;
+mod vector2;
 mod vector3;
+mod vrml;
+mod xml;
+mod zip;
@@ -0,0 +1,108 @@
+use crate::vector3::Vector3;
+
+/// Basic geometric properties of a triangle soup.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct MeshStats {
+    pub triangle_count: usize,
+    pub surface_area: f32,
+    /// Signed volume, positive for closed meshes with outward-facing normals. Meaningless for open meshes.
+    pub volume: f32,
+    /// Center of mass of the solid if the volume is non-zero, otherwise the area-weighted center of the surface.
+    pub centroid: Vector3,
+    pub bbox_min: Vector3,
+    pub bbox_max: Vector3,
+}
+
+/// Computes mesh statistics. `pos` has the same layout as in split_disjoint_geometry. The sums are accumulated in f64 so
+/// that the results do not depend much on the triangle order.
+pub fn compute_mesh_stats(pos: &[f32]) -> MeshStats {
+    let mut area = 0.0f64;
+    let mut volume = 0.0f64;
+    let mut volume_centroid = [0.0f64; 3];
+    let mut area_centroid = [0.0f64; 3];
+    let mut bbox_min = Vector3::new(f32::INFINITY, f32::INFINITY, f32::INFINITY);
+    let mut bbox_max = Vector3::new(f32::NEG_INFINITY, f32::NEG_INFINITY, f32::NEG_INFINITY);
+
+    for tri in pos.chunks_exact(9) {
+        let a = Vector3::new(tri[0], tri[1], tri[2]);
+        let b = Vector3::new(tri[3], tri[4], tri[5]);
+        let c = Vector3::new(tri[6], tri[7], tri[8]);
+        for v in [a, b, c] {
+            bbox_min = Vector3::new(bbox_min.x.min(v.x), bbox_min.y.min(v.y), bbox_min.z.min(v.z));
+            bbox_max = Vector3::new(bbox_max.x.max(v.x), bbox_max.y.max(v.y), bbox_max.z.max(v.z));
+        }
+
+        let tri_area = 0.5 * (b - a).cross(c - a).length() as f64;
+        // Signed volume of the tetrahedron formed by the triangle and the origin.
+        let tet_volume = a.dot(b.cross(c)) as f64 / 6.0;
+        area += tri_area;
+        volume += tet_volume;
+        let sum = a + b + c;
+        for (i, s) in [sum.x, sum.y, sum.z].into_iter().enumerate() {
+            // The centroid of the tetrahedron is (a + b + c + origin) / 4, of the triangle (a + b + c) / 3.
+            volume_centroid[i] += tet_volume * s as f64 / 4.0;
+            area_centroid[i] += tri_area * s as f64 / 3.0;
+        }
+    }
+
+    let triangle_count = pos.len() / 9;
+    if triangle_count == 0 {
+        bbox_min = Vector3::ZERO;
+        bbox_max = Vector3::ZERO;
+    }
+    let size = (bbox_max - bbox_min).length() as f64;
+    let centroid = if volume.abs() > 1e-9 * size * size * size {
+        Vector3::new(
+            (volume_centroid[0] / volume) as f32,
+            (volume_centroid[1] / volume) as f32,
+            (volume_centroid[2] / volume) as f32,
+        )
+    } else if area > 0.0 {
+        Vector3::new(
+            (area_centroid[0] / area) as f32,
+            (area_centroid[1] / area) as f32,
+            (area_centroid[2] / area) as f32,
+        )
+    } else {
+        (bbox_min + bbox_max) * 0.5
+    };
+
+    MeshStats { triangle_count, surface_area: area as f32, volume: volume as f32, centroid, bbox_min, bbox_max }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::plane_section::tests::create_box_geometry;
+
+    #[test]
+    fn test_empty() {
+        let stats = compute_mesh_stats(&[]);
+        assert_eq!(stats.triangle_count, 0);
+        assert_eq!(stats.surface_area, 0.0);
+        assert_eq!(stats.volume, 0.0);
+        assert_eq!(stats.centroid, Vector3::ZERO);
+    }
+
+    #[test]
+    fn test_box() {
+        let cube = create_box_geometry(Vector3::new(1.0, 2.0, 3.0), Vector3::new(3.0, 5.0, 7.0));
+        let stats = compute_mesh_stats(&cube);
+        assert_eq!(stats.triangle_count, 12);
+        assert!((stats.surface_area - 2.0 * (6.0 + 8.0 + 12.0)).abs() < 1e-4);
+        assert!((stats.volume - 24.0).abs() < 1e-4);
+        assert!((stats.centroid - Vector3::new(2.0, 3.5, 5.0)).length() < 1e-4);
+        assert_eq!(stats.bbox_min, Vector3::new(1.0, 2.0, 3.0));
+        assert_eq!(stats.bbox_max, Vector3::new(3.0, 5.0, 7.0));
+    }
+
+    #[test]
+    fn test_open_surface() {
+        // A single flat triangle has no volume, its centroid is the area centroid.
+        let pos = [0.0, 0.0, 0.0, 3.0, 0.0, 0.0, 0.0, 3.0, 0.0];
+        let stats = compute_mesh_stats(&pos);
+        assert_eq!(stats.volume, 0.0);
+        assert!((stats.surface_area - 4.5).abs() < 1e-6);
+        assert!((stats.centroid - Vector3::new(1.0, 1.0, 0.0)).length() < 1e-6);
+    }
+}
@@ -0,0 +1,225 @@
+use wasm_bindgen::prelude::*;
+
+use crate::error::{Error, checked_item};
+use crate::plane_section::Plane;
+use crate::polygon::triangulate;
+use crate::vector3::Vector3;
+
+/// A named part of an imported model.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct ModelPart {
+    pub name: String,
+    pub material: Option<String>,
+    /// RGBA color of the part, if the format defines it.
+    pub color: Option<[u8; 4]>,
+    /// Triangle soup with the same layout as in split_disjoint_geometry.
+    pub positions: Vec<f32>,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct ModelWarning {
+    /// 1-based line number, 0 if the warning is not related to a specific line.
+    pub line: usize,
+    pub message: String,
+}
+
+/// Placeholder for a vertex which failed to parse, it keeps the indices of the following vertices valid. Parsed
+/// coordinates are finite, so the importers tell it apart by NaN and skip the faces using it.
+pub const INVALID_VERTEX: Vector3 = Vector3 { x: f32::NAN, y: f32::NAN, z: f32::NAN };
+
+/// Returns true if the face uses an INVALID_VERTEX, the indices must be in range.
+pub fn uses_invalid_vertex(vertices: &[Vector3], face: &[u32]) -> bool {
+    face.iter().any(|&v| vertices[v as usize].x.is_nan())
+}
+
+/// Name of the parts in formats without part names.
+pub const DEFAULT_PART_NAME: &str = "Mesh";
+
+/// Result of importing a model file. Recoverable problems (e.g. invalid faces) are reported as warnings.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Model {
+    pub parts: Vec<ModelPart>,
+    /// Key-value metadata from the file (e.g. title or generator), in the file order.
+    pub metadata: Vec<(String, String)>,
+    pub warnings: Vec<ModelWarning>,
+}
+
+impl Model {
+    pub fn warn(&mut self, line: usize, message: String) {
+        self.warnings.push(ModelWarning { line, message });
+    }
+}
+
+/// Triangulates a planar polygon face given as indices into `vertices`. The triangles keep the orientation of the face.
+/// Non-planar faces are triangulated in the projection on their average plane, degenerate faces are triangulated as a
+/// fan.
+pub fn triangulate_face(vertices: &[Vector3], face: &[u32]) -> Vec<[u32; 3]> {
+    if face.len() < 3 {
+        return vec![];
+    }
+    if face.len() == 3 {
+        return vec![[face[0], face[1], face[2]]];
+    }
+    let fan = || {
+        (1..face.len() - 1)
+            .map(|i| [face[0], face[i], face[i + 1]])
+            .collect()
+    };
+
+    // Newell's method gives a robust normal for non-convex and slightly non-planar polygons.
+    let mut normal = Vector3::ZERO;
+    for i in 0..face.len() {
+        let a = vertices[face[i] as usize];
+        let b = vertices[face[(i + 1) % face.len()] as usize];
+        normal = normal + Vector3::new((a.y - b.y) * (a.z + b.z), (a.z - b.z) * (a.x + b.x), (a.x - b.x) * (a.y + b.y));
+    }
+    let Some(plane) = Plane::new(vertices[face[0] as usize], normal) else {
+        return fan();
+    };
+    let basis = plane.basis();
+    let points: Vec<_> = face
+        .iter()
+        .map(|&v| plane.project(vertices[v as usize], basis))
+        .collect();
+    let ring: Vec<usize> = (0..face.len()).collect();
+    let triangles = triangulate(&points, &ring, &[]);
+    if triangles.len() != face.len() - 2 {
+        return fan();
+    }
+    triangles.iter().map(|t| t.map(|i| face[i])).collect()
+}
+
+/// Imported model for JS.
+#[wasm_bindgen]
+pub struct ImportedModel {
+    model: Model,
+}
+
+impl ImportedModel {
+    pub fn new(model: Model) -> Self {
+        Self { model }
+    }
+
+    pub fn into_model(self) -> Model {
+        self.model
+    }
+}
+
+impl ImportedModel {
+    fn part(&self, part: usize) -> Result<&ModelPart, Error> {
+        checked_item(&self.model.parts, part, "part")
+    }
+}
+
+#[wasm_bindgen]
+impl ImportedModel {
+    #[wasm_bindgen(getter)]
+    pub fn part_count(&self) -> usize {
+        self.model.parts.len()
+    }
+
+    pub fn part_name(&self, part: usize) -> Result<String, Error> {
+        Ok(self.part(part)?.name.clone())
+    }
+
+    pub fn part_material(&self, part: usize) -> Result<Option<String>, Error> {
+        Ok(self.part(part)?.material.clone())
+    }
+
+    /// RGBA color of the part, 4 bytes.
+    pub fn part_color(&self, part: usize) -> Result<Option<Box<[u8]>>, Error> {
+        Ok(self.part(part)?.color.map(|c| Box::from(c.as_slice())))
+    }
+
+    /// Triangle soup of the part, 9 floats per triangle.
+    pub fn part_positions(&self, part: usize) -> Result<Box<[f32]>, Error> {
+        Ok(self.part(part)?.positions.clone().into_boxed_slice())
+    }
+
+    /// All parts merged into a single triangle soup.
+    #[wasm_bindgen(getter)]
+    pub fn positions(&self) -> Box<[f32]> {
+        self.model
+            .parts
+            .iter()
+            .flat_map(|p| p.positions.iter().copied())
+            .collect()
+    }
+
+    #[wasm_bindgen(getter)]
+    pub fn warning_count(&self) -> usize {
+        self.model.warnings.len()
+    }
+
+    pub fn warning_line(&self, idx: usize) -> Result<usize, Error> {
+        Ok(checked_item(&self.model.warnings, idx, "warning")?.line)
+    }
+
+    pub fn warning_message(&self, idx: usize) -> Result<String, Error> {
+        Ok(checked_item(&self.model.warnings, idx, "warning")?
+            .message
+            .clone())
+    }
+
+    /// Number of key-value metadata entries, e.g. the detected format or the file header.
+    #[wasm_bindgen(getter)]
+    pub fn metadata_count(&self) -> usize {
+        self.model.metadata.len()
+    }
+
+    pub fn metadata_key(&self, idx: usize) -> Result<String, Error> {
+        Ok(checked_item(&self.model.metadata, idx, "metadata")?
+            .0
+            .clone())
+    }
+
+    pub fn metadata_value(&self, idx: usize) -> Result<String, Error> {
+        Ok(checked_item(&self.model.metadata, idx, "metadata")?
+            .1
+            .clone())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn face_normal(vertices: &[Vector3], t: [u32; 3]) -> Vector3 {
+        let [a, b, c] = t.map(|i| vertices[i as usize]);
+        (b - a).cross(c - a)
+    }
+
+    #[test]
+    fn test_triangulate_face() {
+        // A concave L-shaped hexagon in the XZ plane, oriented with the normal pointing to -Y.
+        let vertices = vec![
+            Vector3::new(0.0, 0.0, 0.0),
+            Vector3::new(2.0, 0.0, 0.0),
+            Vector3::new(2.0, 0.0, 1.0),
+            Vector3::new(1.0, 0.0, 1.0),
+            Vector3::new(1.0, 0.0, 2.0),
+            Vector3::new(0.0, 0.0, 2.0),
+        ];
+        let face = [0, 1, 2, 3, 4, 5];
+        let triangles = triangulate_face(&vertices, &face);
+        assert_eq!(triangles.len(), 4);
+        let mut area = 0.0;
+        for &t in &triangles {
+            let n = face_normal(&vertices, t);
+            assert!(n.y <= 0.0, "{:?}", t);
+            area += 0.5 * n.length();
+        }
+        assert!((area - 3.0).abs() < 1e-6);
+
+        // Reversed face gives reversed triangles.
+        let reversed = [5, 4, 3, 2, 1, 0];
+        for t in triangulate_face(&vertices, &reversed) {
+            assert!(face_normal(&vertices, t).y >= 0.0);
+        }
+
+        assert!(triangulate_face(&vertices, &[0, 1]).is_empty());
+        assert_eq!(triangulate_face(&vertices, &[0, 1, 2]), vec![[0, 1, 2]]);
+        // Degenerate (collinear) face falls back to a fan.
+        assert_eq!(triangulate_face(&vertices, &[0, 0, 1, 1]).len(), 2);
+    }
+}
@@ -0,0 +1,401 @@
+use wasm_bindgen::prelude::*;
+
+use crate::bvh::Bvh;
+use crate::error::{Error, check_triangle_soup};
+use crate::indexed_mesh::{IndexedMesh, MeshEdges};
+use crate::mesh_stats::{MeshStats, compute_mesh_stats};
+use crate::model::ImportedModel;
+use crate::parallel;
+use crate::progress::{Progress, ProgressToken, with_js_progress};
+use crate::split_geometry::split_disjoint_triangles_union_find;
+use crate::typed_vec::{Float32Vec, Uint32Vec};
+use crate::vector3::Vector3;
+
+/// Result of ModelHandle::pick.
+#[wasm_bindgen]
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct PickHit {
+    /// Index of the triangle in the original triangle soup.
+    #[wasm_bindgen(readonly)]
+    pub triangle: usize,
+    #[wasm_bindgen(readonly)]
+    pub part: usize,
+    /// Ray parameter of the hit, the distance if the ray direction is normalized.
+    #[wasm_bindgen(readonly)]
+    pub distance: f32,
+}
+
+/// A model kept in wasm memory together with the structures derived from it: the welded mesh, its edges, the BVH and
+/// the disjoint parts. They are built once, so repeated queries do not pass the geometry between JS and wasm. The
+/// handle must be freed with free().
+#[wasm_bindgen]
+pub struct ModelHandle {
+    mesh: IndexedMesh,
+    edges: MeshEdges,
+    bvh: Bvh,
+    /// Triangle soup with the triangles of each part stored contiguously, in the order of parts.
+    part_positions: Vec<f32>,
+    /// First triangle of each part in part_positions, plus the total triangle count.
+    part_offsets: Vec<usize>,
+    /// Part of each triangle of the original soup.
+    triangle_parts: Vec<u32>,
+    part_stats: Vec<MeshStats>,
+    /// Stats of the whole model.
+    stats: MeshStats,
+}
+
+impl ModelHandle {
+    /// Builds the handle from a triangle soup with the same layout as in split_disjoint_geometry. The soup is reordered
+    /// in place into the part positions. If the operation is cancelled, everything built so far is dropped.
+    pub fn from_triangle_soup(mut pos: Vec<f32>, progress: Progress) -> Result<Self, Error> {
+        let parts = split_disjoint_triangles_union_find(&pos, progress.stage(0.0, 0.5))?;
+        let mesh = IndexedMesh::from_triangle_soup_with_progress(&pos, progress.stage(0.5, 0.7))?;
+        let edges = mesh.build_edges();
+        let bvh = Bvh::build(&mesh, progress.stage(0.7, 0.9))?;
+
+        let mut part_offsets = vec![0];
+        let mut triangle_parts = vec![0; pos.len() / 9];
+        // New index of each triangle, the triangles of each part go contiguously.
+        let mut order = vec![0u32; pos.len() / 9];
+        for (part_idx, part) in parts.iter().enumerate() {
+            for (i, &tri_idx) in part.iter().enumerate() {
+                order[tri_idx] = (part_offsets[part_idx] + i) as u32;
+                triangle_parts[tri_idx] = part_idx as u32;
+            }
+            part_offsets.push(part_offsets[part_idx] + part.len());
+        }
+        // Applies the permutation by swapping each triangle into its place until the one in place i belongs there.
+        for i in 0..order.len() {
+            while order[i] as usize != i {
+                let j = order[i] as usize;
+                for k in 0..9 {
+                    pos.swap(i * 9 + k, j * 9 + k);
+                }
+                order.swap(i, j);
+            }
+        }
+        let part_positions = pos;
+        let part_stats = parallel::map_tasks(parts.len(), |part| {
+            compute_mesh_stats(&part_positions[part_offsets[part] * 9..part_offsets[part + 1] * 9])
+        });
+        let stats = compute_mesh_stats(&part_positions);
+        progress.update(1, 1)?;
+        Ok(Self { mesh, edges, bvh, part_positions, part_offsets, triangle_parts, part_stats, stats })
+    }
+
+    fn part_stats(&self, part: usize) -> Result<&MeshStats, Error> {
+        self.part_stats.get(part).ok_or_else(|| {
+            Error::InvalidInput(format!(
+                "part index {} is out of range, the model has {} parts",
+                part,
+                self.part_count()
+            ))
+        })
+    }
+
+    fn part_range(&self, part: usize) -> Result<std::ops::Range<usize>, Error> {
+        self.part_stats(part)?;
+        Ok(self.part_offsets[part] * 9..self.part_offsets[part + 1] * 9)
+    }
+
+    // Number of triangles using each edge.
+    fn edge_use_counts(&self) -> Vec<u32> {
+        let mut counts = vec![0u32; self.edges.vertices.len()];
+        for tri_edges in &self.edges.triangle_edges {
+            for &edge in tri_edges {
+                counts[edge as usize] += 1;
+            }
+        }
+        counts
+    }
+}
+
+fn bbox_to_box(stats: &MeshStats) -> Box<[f32]> {
+    let (lo, hi) = (stats.bbox_min, stats.bbox_max);
+    Box::new([lo.x, lo.y, lo.z, hi.x, hi.y, hi.z])
+}
+
+#[wasm_bindgen]
+impl ModelHandle {
+    /// Copies the triangle soup (9 floats per triangle) and builds the model structures.
+    #[wasm_bindgen(constructor)]
+    pub fn new(pos: &[f32]) -> Result<ModelHandle, Error> {
+        check_triangle_soup(pos)?;
+        Self::from_triangle_soup(pos.to_vec(), Progress::none())
+    }
+
+    /// Same as the constructor, reports the progress to the token and to the optional callback (which receives the
+    /// fraction of the work done). The token can cancel the building.
+    pub fn with_progress(
+        pos: &[f32],
+        token: &ProgressToken,
+        on_progress: Option<js_sys::Function>,
+    ) -> Result<ModelHandle, Error> {
+        check_triangle_soup(pos)?;
+        with_js_progress(Some(token), on_progress, |progress| Self::from_triangle_soup(pos.to_vec(), progress))
+    }
+
+    /// Same as the constructor, but takes the ownership of the buffer instead of copying it from JS. The buffer is
+    /// reused for the part positions.
+    pub fn from_positions(positions: Float32Vec) -> Result<ModelHandle, Error> {
+        let pos = positions.into_vec();
+        check_triangle_soup(&pos)?;
+        Self::from_triangle_soup(pos, Progress::none())
+    }
+
+    /// Builds the handle from all parts of an imported model, the model is consumed.
+    pub fn from_model(model: ImportedModel) -> Result<ModelHandle, Error> {
+        let pos: Vec<f32> = model
+            .into_model()
+            .parts
+            .into_iter()
+            .flat_map(|p| p.positions)
+            .collect();
+        check_triangle_soup(&pos)?;
+        Self::from_triangle_soup(pos, Progress::none())
+    }
+
+    #[wasm_bindgen(getter)]
+    pub fn triangle_count(&self) -> usize {
+        self.triangle_parts.len()
+    }
+
+    /// Number of unique vertices after welding the equal ones.
+    #[wasm_bindgen(getter)]
+    pub fn vertex_count(&self) -> usize {
+        self.mesh.positions.len()
+    }
+
+    /// Number of edges used by a single triangle. A closed mesh has none.
+    #[wasm_bindgen(getter)]
+    pub fn open_edge_count(&self) -> usize {
+        self.edge_use_counts().iter().filter(|&&c| c == 1).count()
+    }
+
+    /// Number of edges shared by more than two triangles.
+    #[wasm_bindgen(getter)]
+    pub fn non_manifold_edge_count(&self) -> usize {
+        self.edge_use_counts().iter().filter(|&&c| c > 2).count()
+    }
+
+    /// Number of disjoint parts, see split_disjoint_geometry.
+    #[wasm_bindgen(getter)]
+    pub fn part_count(&self) -> usize {
+        self.part_stats.len()
+    }
+
+    pub fn part_triangle_count(&self, part: usize) -> Result<usize, Error> {
+        Ok(self.part_range(part)?.len() / 9)
+    }
+
+    /// Returns the part of the triangle with the given index in the original triangle soup.
+    pub fn triangle_part(&self, triangle: usize) -> Result<usize, Error> {
+        match self.triangle_parts.get(triangle) {
+            Some(&part) => Ok(part as usize),
+            None => Err(Error::InvalidInput(format!(
+                "triangle index {} is out of range, the model has {} triangles",
+                triangle,
+                self.triangle_count()
+            ))),
+        }
+    }
+
+    /// Returns a view of the triangle soup of the part. The view becomes invalid when the wasm memory grows or the handle
+    /// is freed.
+    pub fn part_positions(&self, part: usize) -> Result<js_sys::Float32Array, Error> {
+        let range = self.part_range(part)?;
+        Ok(unsafe { js_sys::Float32Array::view(&self.part_positions[range]) })
+    }
+
+    /// Returns a copy of the triangle soup of the part which is owned by JS.
+    pub fn copy_part_positions(&self, part: usize) -> Result<Float32Vec, Error> {
+        Float32Vec::from_slice(&self.part_positions[self.part_range(part)?])
+    }
+
+    /// Returns the indices of the triangles of the part in the original triangle soup.
+    pub fn part_triangles(&self, part: usize) -> Result<Uint32Vec, Error> {
+        self.part_stats(part)?;
+        let triangles: Vec<u32> = (0..self.triangle_parts.len() as u32)
+            .filter(|&t| self.triangle_parts[t as usize] == part as u32)
+            .collect();
+        Ok(Uint32Vec::from(triangles))
+    }
+
+    pub fn part_surface_area(&self, part: usize) -> Result<f32, Error> {
+        Ok(self.part_stats(part)?.surface_area)
+    }
+
+    /// Signed volume of the part, see MeshStats::volume.
+    pub fn part_volume(&self, part: usize) -> Result<f32, Error> {
+        Ok(self.part_stats(part)?.volume)
+    }
+
+    /// Returns the x, y, z coordinates of the part centroid.
+    pub fn part_centroid(&self, part: usize) -> Result<Box<[f32]>, Error> {
+        let c = self.part_stats(part)?.centroid;
+        Ok(Box::new([c.x, c.y, c.z]))
+    }
+
+    /// Returns min x, y, z and max x, y, z of the part bounding box.
+    pub fn part_bbox(&self, part: usize) -> Result<Box<[f32]>, Error> {
+        Ok(bbox_to_box(self.part_stats(part)?))
+    }
+
+    /// Returns min x, y, z and max x, y, z of the model bounding box.
+    #[wasm_bindgen(getter)]
+    pub fn bbox(&self) -> Box<[f32]> {
+        bbox_to_box(&self.stats)
+    }
+
+    /// Finds the nearest triangle hit by the ray from (origin_x, origin_y, origin_z) in the direction (dir_x, dir_y,
+    /// dir_z). Both sides of the triangles are hit.
+    pub fn pick(
+        &self,
+        origin_x: f32,
+        origin_y: f32,
+        origin_z: f32,
+        dir_x: f32,
+        dir_y: f32,
+        dir_z: f32,
+    ) -> Option<PickHit> {
+        let origin = Vector3::new(origin_x, origin_y, origin_z);
+        let dir = Vector3::new(dir_x, dir_y, dir_z);
+        self.bvh
+            .raycast(&self.mesh, origin, dir)
+            .map(|hit| PickHit {
+                triangle: hit.tri_idx,
+                part: self.triangle_parts[hit.tri_idx] as usize,
+                distance: hit.distance,
+            })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::model::{Model, ModelPart};
+    use crate::parallel::tests::with_threads;
+    use crate::plane_section::tests::create_box_geometry;
+    use crate::progress::tests::{assert_complete, run_with_progress};
+    use crate::slicer::tests::create_sphere_geometry;
+
+    fn two_boxes() -> Vec<f32> {
+        let mut pos = create_box_geometry(Vector3::new(0.0, 0.0, 0.0), Vector3::new(1.0, 1.0, 1.0));
+        pos.extend(create_box_geometry(Vector3::new(5.0, 0.0, 0.0), Vector3::new(7.0, 1.0, 1.0)));
+        pos
+    }
+
+    #[test]
+    fn test_queries() {
+        let handle = ModelHandle::new(&two_boxes()).unwrap();
+        assert_eq!(handle.triangle_count(), 24);
+        assert_eq!(handle.vertex_count(), 16);
+        assert_eq!(handle.open_edge_count(), 0);
+        assert_eq!(handle.non_manifold_edge_count(), 0);
+        assert_eq!(handle.part_count(), 2);
+        assert_eq!(handle.part_triangle_count(1).unwrap(), 12);
+        assert_eq!(handle.triangle_part(0).unwrap(), 0);
+        assert_eq!(handle.triangle_part(12).unwrap(), 1);
+        assert_eq!(handle.part_triangles(1).unwrap().as_slice(), (12..24).collect::<Vec<u32>>());
+        assert_eq!(compute_mesh_stats(handle.copy_part_positions(1).unwrap().as_slice()).volume, 2.0);
+        assert_eq!(handle.part_volume(1).unwrap(), 2.0);
+        assert_eq!(handle.part_surface_area(0).unwrap(), 6.0);
+        assert_eq!(*handle.part_centroid(1).unwrap(), [6.0, 0.5, 0.5]);
+        assert_eq!(*handle.part_bbox(1).unwrap(), [5.0, 0.0, 0.0, 7.0, 1.0, 1.0]);
+        assert_eq!(*handle.bbox(), [0.0, 0.0, 0.0, 7.0, 1.0, 1.0]);
+
+        for part in [2, usize::MAX] {
+            assert_eq!(handle.part_volume(part).unwrap_err().code(), "INVALID_INPUT");
+            assert!(handle.part_triangle_count(part).is_err());
+            assert!(handle.part_triangles(part).is_err());
+            assert!(handle.copy_part_positions(part).is_err());
+            assert!(handle.part_surface_area(part).is_err());
+            assert!(handle.part_centroid(part).is_err());
+            assert!(handle.part_bbox(part).is_err());
+        }
+        assert!(handle.triangle_part(24).is_err());
+    }
+
+    #[test]
+    fn test_pick() {
+        let handle = ModelHandle::new(&two_boxes()).unwrap();
+        let hit = handle.pick(6.0, 0.5, 10.0, 0.0, 0.0, -1.0).unwrap();
+        assert_eq!(hit.part, 1);
+        assert_eq!(hit.distance, 9.0);
+        assert_eq!(handle.triangle_part(hit.triangle).unwrap(), 1);
+        let hit = handle.pick(-1.0, 0.5, 0.5, 1.0, 0.0, 0.0).unwrap();
+        assert_eq!((hit.part, hit.distance), (0, 1.0));
+        assert_eq!(handle.pick(3.0, 0.5, 10.0, 0.0, 0.0, -1.0), None);
+    }
+
+    #[test]
+    fn test_construction() {
+        let empty = ModelHandle::new(&[]).unwrap();
+        assert_eq!((empty.triangle_count(), empty.part_count()), (0, 0));
+        assert_eq!(empty.pick(0.0, 0.0, 0.0, 1.0, 0.0, 0.0), None);
+        assert!(ModelHandle::new(&[0.0; 10]).is_err());
+
+        let handle = ModelHandle::from_positions(Float32Vec::from(two_boxes())).unwrap();
+        assert_eq!(handle.part_count(), 2);
+        assert!(ModelHandle::from_positions(Float32Vec::from(vec![0.0; 10])).is_err());
+
+        // Interleaved triangles of the two boxes are grouped by part when the buffer is reordered in place.
+        let boxes = two_boxes();
+        let (first, second) = boxes.split_at(12 * 9);
+        let interleaved: Vec<f32> = first
+            .chunks(9)
+            .zip(second.chunks(9))
+            .flat_map(|(a, b)| a.iter().chain(b).copied())
+            .collect();
+        let handle = ModelHandle::from_positions(Float32Vec::from(interleaved)).unwrap();
+        assert_eq!(handle.part_positions, boxes);
+        assert_eq!(handle.part_triangles(1).unwrap().as_slice(), (0..12).map(|t| 2 * t + 1).collect::<Vec<u32>>());
+        let parts = two_boxes()
+            .chunks(12 * 9)
+            .map(|p| ModelPart { positions: p.to_vec(), ..Default::default() })
+            .collect();
+        let model = ImportedModel::new(Model { parts, ..Default::default() });
+        let handle = ModelHandle::from_model(model).unwrap();
+        assert_eq!(handle.triangle_count(), 24);
+    }
+
+    #[test]
+    fn test_progress() {
+        let pos = two_boxes();
+        let (result, fractions) =
+            run_with_progress(2.0, |progress| ModelHandle::from_triangle_soup(pos.clone(), progress));
+        let handle = result.unwrap();
+        assert_complete(&fractions);
+        assert_eq!(handle.part_count(), 2);
+        assert_eq!(handle.part_volume(1).unwrap(), 2.0);
+
+        let (result, fractions) =
+            run_with_progress(0.6, |progress| ModelHandle::from_triangle_soup(pos.clone(), progress));
+        assert_eq!(result.err(), Some(Error::Cancelled));
+        assert!(fractions.last().unwrap() < &0.7);
+
+        let token = ProgressToken::new();
+        token.cancel();
+        assert_eq!(ModelHandle::with_progress(&pos, &token, None).err(), Some(Error::Cancelled));
+        token.reset();
+        let handle = ModelHandle::with_progress(&pos, &token, None).unwrap();
+        assert_eq!(token.progress(), 1.0);
+        assert_eq!(handle.part_count(), 2);
+        assert!(ModelHandle::with_progress(&pos[..10], &token, None).is_err());
+    }
+
+    #[test]
+    fn test_threads() {
+        let mut pos = create_sphere_geometry(1.0, 64, 32);
+        pos.extend(two_boxes());
+        let build = || ModelHandle::new(&pos).unwrap();
+        let (expected, handle) = (with_threads(1, build), with_threads(4, build));
+        assert_eq!(handle.mesh, expected.mesh);
+        assert_eq!(handle.bvh, expected.bvh);
+        assert_eq!(handle.part_positions, expected.part_positions);
+        assert_eq!(handle.triangle_parts, expected.triangle_parts);
+        assert_eq!(handle.part_stats, expected.part_stats);
+        assert_eq!(handle.stats, expected.stats);
+        assert_eq!(handle.part_count(), 3);
+    }
+}
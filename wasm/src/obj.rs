@@ -0,0 +1,239 @@
+use wasm_bindgen::prelude::*;
+
+use crate::model::{INVALID_VERTEX, ImportedModel, Model, ModelPart, triangulate_face, uses_invalid_vertex};
+use crate::vector3::Vector3;
+
+// Resolves a 1-based (or negative, relative to the end) OBJ vertex index into a 0-based one.
+fn resolve_index(token: &str, vertex_count: usize) -> Result<u32, String> {
+    // Only the position index is used from "v/vt/vn".
+    let index_str = token.split('/').next().unwrap();
+    let index: i64 = index_str
+        .parse()
+        .map_err(|_| format!("invalid vertex index '{}'", token))?;
+    let resolved = if index > 0 { index - 1 } else { vertex_count as i64 + index };
+    if index == 0 || resolved < 0 || resolved >= vertex_count as i64 {
+        return Err(format!("vertex index {} is out of range", index));
+    }
+    Ok(resolved as u32)
+}
+
+/// Parses a Wavefront OBJ file. Each `o` or `g` statement starts a new part named after it, `usemtl` sets the material
+/// of the following faces. Faces with the same name and material are collected into a single part, parts are ordered
+/// by their first face. Polygon faces are triangulated, negative (relative) indices are supported. Texture
+/// coordinates, normals, free-form geometry and lines are ignored. Faces using a vertex which failed to parse are
+/// skipped with a warning.
+pub fn parse_obj(text: &str) -> Model {
+    let mut model = Model::default();
+    let mut vertices: Vec<Vector3> = vec![];
+    let mut name = String::new();
+    let mut material: Option<String> = None;
+    // Index of the part for the current name and material.
+    let mut current_part: Option<usize> = None;
+    let mut face = vec![];
+
+    let mut lines = text.lines().enumerate();
+    while let Some((line_idx, line)) = lines.next() {
+        let line_number = line_idx + 1;
+        // Lines ending with a backslash are continued on the next line.
+        let mut line = line.to_string();
+        while line.ends_with('\\') {
+            line.pop();
+            match lines.next() {
+                Some((_, next)) => {
+                    line.push(' ');
+                    line.push_str(next);
+                }
+                None => break,
+            }
+        }
+        let line = line.split('#').next().unwrap();
+        let mut tokens = line.split_whitespace();
+        let Some(keyword) = tokens.next() else {
+            continue;
+        };
+        match keyword {
+            "v" => {
+                let coords: Vec<f32> = tokens.take(3).filter_map(|t| t.parse().ok()).collect();
+                if coords.len() != 3 || coords.iter().any(|c| !c.is_finite()) {
+                    model.warn(line_number, "invalid vertex".to_string());
+                    vertices.push(INVALID_VERTEX);
+                    continue;
+                }
+                vertices.push(Vector3::new(coords[0], coords[1], coords[2]));
+            }
+            "f" => {
+                face.clear();
+                let resolved: Result<(), String> = tokens.try_for_each(|t| {
+                    face.push(resolve_index(t, vertices.len())?);
+                    Ok(())
+                });
+                if let Err(message) = resolved {
+                    model.warn(line_number, message);
+                    continue;
+                }
+                if face.len() < 3 {
+                    model.warn(line_number, format!("face with {} vertices", face.len()));
+                    continue;
+                }
+                if uses_invalid_vertex(&vertices, &face) {
+                    model.warn(line_number, "face uses an invalid vertex".to_string());
+                    continue;
+                }
+                let part_idx = *current_part.get_or_insert_with(|| {
+                    model
+                        .parts
+                        .iter()
+                        .position(|p| p.name == name && p.material == material)
+                        .unwrap_or_else(|| {
+                            model.parts.push(ModelPart {
+                                name: name.clone(),
+                                material: material.clone(),
+                                color: None,
+                                positions: vec![],
+                            });
+                            model.parts.len() - 1
+                        })
+                });
+                let positions = &mut model.parts[part_idx].positions;
+                for t in triangulate_face(&vertices, &face) {
+                    for v in t {
+                        let p = vertices[v as usize];
+                        positions.extend_from_slice(&[p.x, p.y, p.z]);
+                    }
+                }
+            }
+            "o" | "g" => {
+                // Groups may have several names, they are joined into one.
+                name = tokens.collect::<Vec<_>>().join(" ");
+                current_part = None;
+            }
+            "usemtl" => {
+                material = tokens.next().map(|m| m.to_string());
+                current_part = None;
+            }
+            _ => {}
+        }
+    }
+    model
+}
+
+/// See parse_obj.
+#[wasm_bindgen]
+pub fn load_obj(text: &str) -> ImportedModel {
+    ImportedModel::new(parse_obj(text))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_empty() {
+        assert_eq!(parse_obj(""), Model::default());
+        assert_eq!(parse_obj("# comment\nv 1 2 3\n").parts, vec![]);
+    }
+
+    #[test]
+    fn test_polygons() {
+        let obj = "\
+v 0 0 0
+v 1 0 0
+v 1 1 0
+v 0 1 0
+v 0.5 0.5 1
+f 1 2 3 4
+f 1/1 5/2 2/3
+f 2//1 5//1 3//1
+f -2 -1 -3
+";
+        let model = parse_obj(obj);
+        assert!(model.warnings.is_empty(), "{:?}", model.warnings);
+        assert_eq!(model.parts.len(), 1);
+        let part = &model.parts[0];
+        assert_eq!(part.name, "");
+        assert_eq!(part.material, None);
+        assert_eq!(part.positions.len(), 5 * 9);
+        // The negative indices refer to vertices 4, 5 and 3.
+        assert_eq!(&part.positions[36..45], &[0.0, 1.0, 0.0, 0.5, 0.5, 1.0, 1.0, 1.0, 0.0]);
+        // The quad keeps its orientation (normal points to +Z).
+        for t in part.positions[0..18].chunks_exact(9) {
+            let a = Vector3::new(t[0], t[1], t[2]);
+            let b = Vector3::new(t[3], t[4], t[5]);
+            let c = Vector3::new(t[6], t[7], t[8]);
+            assert!((b - a).cross(c - a).z > 0.0);
+        }
+    }
+
+    #[test]
+    fn test_groups_and_materials() {
+        let obj = "\
+mtllib scene.mtl
+v 0 0 0
+v 1 0 0
+v 0 1 0
+o first
+usemtl red
+f 1 2 3
+usemtl blue
+f 1 2 3
+f 1 2 3
+g second part
+f 1 2 3
+o first
+usemtl red
+f 1 2 \\
+3
+o empty
+";
+        let model = parse_obj(obj);
+        assert!(model.warnings.is_empty(), "{:?}", model.warnings);
+        let parts: Vec<(&str, Option<&str>, usize)> = model
+            .parts
+            .iter()
+            .map(|p| (p.name.as_str(), p.material.as_deref(), p.positions.len() / 9))
+            .collect();
+        assert_eq!(
+            parts,
+            vec![("first", Some("red"), 2), ("first", Some("blue"), 2), ("second part", Some("blue"), 1)]
+        );
+    }
+
+    #[test]
+    fn test_warnings() {
+        let obj = "\
+v 0 0 0
+v 1 0 0
+v 0 1 x
+v 0 0 1
+f 1 2 4
+f 1 2 5
+f 1 2 0
+f 1 2
+f 1 a 2
+f 1 2 3
+";
+        let model = parse_obj(obj);
+        let lines: Vec<usize> = model.warnings.iter().map(|w| w.line).collect();
+        assert_eq!(lines, vec![3, 6, 7, 8, 9, 10]);
+        assert_eq!(model.warnings[5].message, "face uses an invalid vertex");
+        // Only the first face is valid, the one using the invalid vertex is skipped.
+        assert_eq!(model.parts[0].positions, [0.0, 0.0, 0.0, 1.0, 0.0, 0.0, 0.0, 0.0, 1.0]);
+    }
+
+    #[test]
+    fn test_wasm_wrapper() {
+        let model = load_obj("v 0 0 0\nv 1 0 0\nv 0 1 0\ng a\nusemtl m\nf 1 2 3\ng b\nf 3 2 1\nf 1 2 4\n");
+        assert_eq!(model.part_count(), 2);
+        assert_eq!(model.part_name(1).unwrap(), "b");
+        assert_eq!(model.part_material(0).unwrap(), Some("m".to_string()));
+        assert_eq!(model.part_positions(1).unwrap().len(), 9);
+        assert_eq!(model.positions().len(), 18);
+        assert_eq!(model.warning_count(), 1);
+        assert_eq!(model.warning_line(0).unwrap(), 9);
+        assert!(model.warning_message(0).unwrap().contains("out of range"));
+        assert_eq!(model.part_name(2).unwrap_err().code(), "INVALID_INPUT");
+        assert!(model.part_positions(usize::MAX).is_err());
+        assert!(model.warning_line(1).is_err());
+        assert!(model.metadata_key(model.metadata_count()).is_err());
+    }
+}
@@ -0,0 +1,211 @@
+use wasm_bindgen::prelude::*;
+
+use crate::error::Error;
+use crate::model::{
+    DEFAULT_PART_NAME, INVALID_VERTEX, ImportedModel, Model, ModelPart, triangulate_face, uses_invalid_vertex,
+};
+use crate::vector3::Vector3;
+
+// Parses the face color: RGB or RGBA, either as integers 0..255 or as floats 0..1. Colormap indices (a single value)
+// are ignored.
+fn parse_face_color(values: &[&str]) -> Option<[u8; 4]> {
+    if values.len() != 3 && values.len() != 4 {
+        return None;
+    }
+    let floats: Vec<f32> = values
+        .iter()
+        .map(|v| v.parse().ok())
+        .collect::<Option<_>>()?;
+    let is_float = values.iter().any(|v| v.contains('.')) && floats.iter().all(|&v| v <= 1.0);
+    let mut color = [255u8; 4];
+    for (c, v) in color.iter_mut().zip(floats) {
+        let v = if is_float { v * 255.0 } else { v };
+        *c = v.round().clamp(0.0, 255.0) as u8;
+    }
+    Some(color)
+}
+
+/// Parses an ASCII OFF file (also COFF, NOFF, STOFF and 4OFF variants; only the positions are read from vertices).
+/// The whole file becomes a single part. Polygon faces are triangulated; the part gets the color of the first face with
+/// a color, per-face colors are not preserved. Binary OFF and nOFF are not supported.
+pub fn parse_off(text: &str) -> Result<Model, Error> {
+    let mut model = Model::default();
+    let mut lines = text
+        .lines()
+        .enumerate()
+        .map(|(i, line)| (i + 1, line.split('#').next().unwrap().trim()))
+        .filter(|(_, line)| !line.is_empty());
+
+    let (_, header) = lines.next().ok_or("empty OFF file")?;
+    let mut header_tokens = header.split_whitespace();
+    let keyword = header_tokens.next().unwrap();
+    let Some(prefix) = keyword.strip_suffix("OFF") else {
+        return Err(Error::InvalidInput("missing OFF header".to_string()));
+    };
+    if prefix.contains('n') {
+        return Err(Error::UnsupportedFormat("nOFF files are not supported".to_string()));
+    }
+    let homogeneous = prefix.contains('4');
+    let mut count_tokens: Vec<&str> = header_tokens.collect();
+    if count_tokens.first() == Some(&"BINARY") {
+        return Err(Error::UnsupportedFormat("binary OFF files are not supported".to_string()));
+    }
+    if count_tokens.is_empty() {
+        let (_, counts) = lines.next().ok_or("missing OFF element counts")?;
+        count_tokens = counts.split_whitespace().collect();
+    }
+    let counts: Vec<usize> = count_tokens
+        .iter()
+        .take(2)
+        .map(|t| t.parse().ok())
+        .collect::<Option<_>>()
+        .filter(|c: &Vec<usize>| c.len() == 2)
+        .ok_or("invalid OFF element counts")?;
+    let (vertex_count, face_count) = (counts[0], counts[1]);
+
+    // The counts come from the file, do not trust them for preallocation.
+    let mut vertices = Vec::with_capacity(vertex_count.min(text.len() / 6));
+    for _ in 0..vertex_count {
+        let (line_number, line) = lines.next().ok_or("unexpected end of OFF vertices")?;
+        let coord_count = if homogeneous { 4 } else { 3 };
+        let coords: Vec<f32> = line
+            .split_whitespace()
+            .take(coord_count)
+            .filter_map(|t| t.parse().ok())
+            .collect();
+        if coords.len() != coord_count || coords.iter().any(|c| !c.is_finite()) {
+            model.warn(line_number, "invalid vertex".to_string());
+            vertices.push(INVALID_VERTEX);
+            continue;
+        }
+        let w = if homogeneous && coords[3] != 0.0 { coords[3] } else { 1.0 };
+        vertices.push(Vector3::new(coords[0] / w, coords[1] / w, coords[2] / w));
+    }
+
+    let mut part = ModelPart { name: DEFAULT_PART_NAME.to_string(), ..Default::default() };
+    let mut mixed_colors = false;
+    for _ in 0..face_count {
+        let Some((line_number, line)) = lines.next() else {
+            model.warn(0, format!("expected {} faces", face_count));
+            break;
+        };
+        let tokens: Vec<&str> = line.split_whitespace().collect();
+        let Some(n) = tokens[0]
+            .parse::<usize>()
+            .ok()
+            .filter(|&n| tokens.len() > n)
+        else {
+            model.warn(line_number, "invalid face".to_string());
+            continue;
+        };
+        let face: Option<Vec<u32>> = tokens[1..=n]
+            .iter()
+            .map(|t| {
+                t.parse::<u32>()
+                    .ok()
+                    .filter(|&i| (i as usize) < vertices.len())
+            })
+            .collect();
+        let Some(face) = face else {
+            model.warn(line_number, "face vertex index is out of range".to_string());
+            continue;
+        };
+        if n < 3 {
+            model.warn(line_number, "face has less than 3 vertices".to_string());
+            continue;
+        }
+        if uses_invalid_vertex(&vertices, &face) {
+            model.warn(line_number, "face uses an invalid vertex".to_string());
+            continue;
+        }
+        if let Some(color) = parse_face_color(&tokens[n + 1..]) {
+            match part.color {
+                None => part.color = Some(color),
+                Some(c) if c != color => mixed_colors = true,
+                _ => {}
+            }
+        }
+        for tri in triangulate_face(&vertices, &face) {
+            for v in tri {
+                let p = vertices[v as usize];
+                part.positions.extend_from_slice(&[p.x, p.y, p.z]);
+            }
+        }
+    }
+    if mixed_colors {
+        model.warn(0, "faces have different colors, only the first color is used".to_string());
+    }
+    model.parts.push(part);
+    Ok(model)
+}
+
+/// See parse_off.
+#[wasm_bindgen]
+pub fn load_off(text: &str) -> Result<ImportedModel, Error> {
+    parse_off(text).map(ImportedModel::new)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse() {
+        let text = "OFF
+# A unit square and a triangle above it.
+5 2 0
+0 0 0
+1 0 0
+1 1 0
+0 1 0
+0 0 1 # comment
+4 0 1 2 3 255 0 0
+3 0 1 4 1.0 0.0 0.0 0.5
+";
+        let model = parse_off(text).unwrap();
+        assert_eq!(model.parts.len(), 1);
+        let part = &model.parts[0];
+        assert_eq!(part.positions.len(), 3 * 9);
+        assert_eq!(part.color, Some([255, 0, 0, 255]));
+        assert_eq!(part.positions[27 - 9..], [0.0, 0.0, 0.0, 1.0, 0.0, 0.0, 0.0, 0.0, 1.0]);
+        assert_eq!(model.warnings.len(), 1);
+        assert_eq!(model.warnings[0].line, 0);
+    }
+
+    #[test]
+    fn test_variants() {
+        // Counts on the header line, vertex colors and normals are ignored.
+        let text = "CNOFF 3 1 0\n0 0 0 0 0 1 1 1 1\n2 0 0 0 0 1 1 1 1\n0 2 0 0 0 1 1 1 1\n3 0 1 2\n";
+        let model = parse_off(text).unwrap();
+        assert_eq!(model.parts[0].positions, [0.0, 0.0, 0.0, 2.0, 0.0, 0.0, 0.0, 2.0, 0.0]);
+        assert_eq!(model.parts[0].color, None);
+        let text = "4OFF\n3 1 0\n0 0 0 2\n2 0 0 2\n0 2 0 2\n3 0 1 2\n";
+        let model = parse_off(text).unwrap();
+        assert_eq!(model.parts[0].positions, [0.0, 0.0, 0.0, 1.0, 0.0, 0.0, 0.0, 1.0, 0.0]);
+    }
+
+    #[test]
+    fn test_errors() {
+        assert!(parse_off("").is_err());
+        assert!(parse_off("PLY\n").is_err());
+        assert!(parse_off("OFF BINARY\n").is_err());
+        assert!(parse_off("OFF\n3\n").is_err());
+        assert!(parse_off("OFF\n3 1 0\n0 0 0\n").is_err());
+
+        let text = "OFF\n4 5 0\n0 0 0\n1 0 0\nx 1 0\n0 1 0\n3 0 1 2\n3 0 1 3\n3 0 1 5\n2 0 1\n";
+        let model = parse_off(text).unwrap();
+        let lines: Vec<usize> = model.warnings.iter().map(|w| w.line).collect();
+        assert_eq!(lines, vec![5, 7, 9, 10, 0]);
+        assert_eq!(model.warnings[1].message, "face uses an invalid vertex");
+        // Only the face which does not use the invalid vertex is kept.
+        assert_eq!(model.parts[0].positions, [0.0, 0.0, 0.0, 1.0, 0.0, 0.0, 0.0, 1.0, 0.0]);
+    }
+
+    #[test]
+    fn test_face_color() {
+        assert_eq!(parse_face_color(&["0", "128", "255"]), Some([0, 128, 255, 255]));
+        assert_eq!(parse_face_color(&["0.0", "0.5", "1", "0.5"]), Some([0, 128, 255, 128]));
+        assert_eq!(parse_face_color(&["3"]), None);
+        assert_eq!(parse_face_color(&[]), None);
+    }
+}
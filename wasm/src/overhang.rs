@@ -0,0 +1,297 @@
+use wasm_bindgen::prelude::*;
+
+use crate::error::{Error, check_triangle_soup, checked_item};
+use crate::indexed_mesh::IndexedMesh;
+use crate::vector3::Vector3;
+
+/// Connected set of overhanging triangles. Triangles are connected if they share an edge.
+#[derive(Clone, Debug, PartialEq)]
+pub struct OverhangRegion {
+    pub triangles: Vec<u32>,
+    pub area: f32,
+}
+
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct OverhangAnalysis {
+    /// 1 for overhanging triangles, 0 otherwise, one byte per input triangle.
+    pub flags: Vec<u8>,
+    pub regions: Vec<OverhangRegion>,
+}
+
+/// Fails unless the overhang threshold is in 0..=90 degrees.
+pub(crate) fn check_overhang_threshold(threshold_degrees: f32) -> Result<(), Error> {
+    if !(0.0..=90.0).contains(&threshold_degrees) {
+        return Err(Error::InvalidInput(format!(
+            "overhang threshold must be between 0 and 90 degrees, got {}",
+            threshold_degrees
+        )));
+    }
+    Ok(())
+}
+
+/// Finds triangles which need support when printing with the given build direction (pointing from the build plate
+/// upwards). The overhang angle of a triangle facing downwards is the angle between the triangle and the vertical
+/// direction, so 90 degrees is a flat ceiling and 0 degrees is a vertical wall. Triangles with the overhang angle
+/// larger than `threshold_degrees` are overhangs, unless they lie on the build plate (the lowest level of the model).
+/// `pos` has the same layout as in split_disjoint_geometry. Fails if the threshold is outside 0..=90 degrees or the
+/// build direction is zero or not finite.
+pub fn analyze_overhangs(pos: &[f32], build_dir: Vector3, threshold_degrees: f32) -> Result<OverhangAnalysis, Error> {
+    check_overhang_threshold(threshold_degrees)?;
+    let length = build_dir.length();
+    if length == 0.0 || !length.is_finite() {
+        return Err(Error::InvalidInput(format!("invalid build direction {:?}", build_dir)));
+    }
+    let build_dir = build_dir.normalize();
+    let mesh = IndexedMesh::from_triangle_soup(pos);
+    let tri_count = mesh.triangles.len();
+    if tri_count == 0 {
+        return Ok(OverhangAnalysis { flags: vec![0; tri_count], regions: vec![] });
+    }
+
+    let heights: Vec<f32> = mesh.positions.iter().map(|&p| p.dot(build_dir)).collect();
+    let min_height = heights.iter().copied().fold(f32::INFINITY, f32::min);
+    let max_height = heights.iter().copied().fold(f32::NEG_INFINITY, f32::max);
+    let plate_epsilon = 1e-5 * (max_height - min_height).max(1.0);
+    // The triangle is an overhang if sin(overhang angle) = -normal * build_dir > sin(threshold).
+    let min_down = threshold_degrees.to_radians().sin();
+
+    let mut flags = vec![0u8; tri_count];
+    let mut areas = vec![0.0f32; tri_count];
+    for (t, tri) in mesh.triangles.iter().enumerate() {
+        let [a, b, c] = mesh.triangle(t);
+        let cross = (b - a).cross(c - a);
+        let area = 0.5 * cross.length();
+        if area == 0.0 {
+            continue;
+        }
+        let on_plate = tri
+            .iter()
+            .all(|&v| heights[v as usize] - min_height <= plate_epsilon);
+        if !on_plate && -cross.dot(build_dir) / (2.0 * area) > min_down {
+            flags[t] = 1;
+            areas[t] = area;
+        }
+    }
+
+    // Group flagged triangles with a DFS over shared edges. Edge -> triangles adjacency is stored in CSR form.
+    let edges = mesh.build_edges();
+    let mut edge_offsets = vec![0usize; edges.vertices.len() + 1];
+    for (t, tri_edges) in edges.triangle_edges.iter().enumerate() {
+        if flags[t] != 0 {
+            for &e in tri_edges {
+                edge_offsets[e as usize + 1] += 1;
+            }
+        }
+    }
+    for i in 0..edges.vertices.len() {
+        edge_offsets[i + 1] += edge_offsets[i];
+    }
+    let mut fill = edge_offsets.clone();
+    let mut edge_tris = vec![0u32; edge_offsets[edges.vertices.len()]];
+    for (t, tri_edges) in edges.triangle_edges.iter().enumerate() {
+        if flags[t] != 0 {
+            for &e in tri_edges {
+                edge_tris[fill[e as usize]] = t as u32;
+                fill[e as usize] += 1;
+            }
+        }
+    }
+
+    let mut visited = vec![false; tri_count];
+    let mut stack = vec![];
+    let mut regions = vec![];
+    for start in 0..tri_count {
+        if flags[start] == 0 || visited[start] {
+            continue;
+        }
+        visited[start] = true;
+        stack.push(start as u32);
+        let mut region = OverhangRegion { triangles: vec![], area: 0.0 };
+        while let Some(t) = stack.pop() {
+            region.triangles.push(t);
+            region.area += areas[t as usize];
+            for &e in &edges.triangle_edges[t as usize] {
+                for &next in &edge_tris[edge_offsets[e as usize]..edge_offsets[e as usize + 1]] {
+                    if !visited[next as usize] {
+                        visited[next as usize] = true;
+                        stack.push(next);
+                    }
+                }
+            }
+        }
+        region.triangles.sort_unstable();
+        regions.push(region);
+    }
+
+    Ok(OverhangAnalysis { flags, regions })
+}
+
+/// Result of find_overhangs for JS.
+#[wasm_bindgen]
+pub struct Overhangs {
+    analysis: OverhangAnalysis,
+}
+
+#[wasm_bindgen]
+impl Overhangs {
+    /// One byte per triangle, 1 for overhangs. Can be used to build a per-vertex color attribute.
+    #[wasm_bindgen(getter)]
+    pub fn flags(&self) -> Box<[u8]> {
+        self.analysis.flags.clone().into_boxed_slice()
+    }
+
+    #[wasm_bindgen(getter)]
+    pub fn region_count(&self) -> usize {
+        self.analysis.regions.len()
+    }
+
+    pub fn region_area(&self, idx: usize) -> Result<f32, Error> {
+        Ok(checked_item(&self.analysis.regions, idx, "region")?.area)
+    }
+
+    /// Indices of triangles of the region.
+    pub fn region_triangles(&self, idx: usize) -> Result<Box<[u32]>, Error> {
+        Ok(checked_item(&self.analysis.regions, idx, "region")?
+            .triangles
+            .clone()
+            .into_boxed_slice())
+    }
+
+    #[wasm_bindgen(getter)]
+    pub fn total_area(&self) -> f32 {
+        self.analysis.regions.iter().map(|r| r.area).sum()
+    }
+}
+
+/// See analyze_overhangs.
+#[wasm_bindgen]
+pub fn find_overhangs(
+    pos: &[f32],
+    dir_x: f32,
+    dir_y: f32,
+    dir_z: f32,
+    threshold_degrees: f32,
+) -> Result<Overhangs, Error> {
+    check_triangle_soup(pos)?;
+    Ok(Overhangs { analysis: analyze_overhangs(pos, Vector3::new(dir_x, dir_y, dir_z), threshold_degrees)? })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::plane_section::tests::create_box_geometry;
+    use crate::slicer::tests::create_sphere_geometry;
+
+    const UP: Vector3 = Vector3 { x: 0.0, y: 0.0, z: 1.0 };
+
+    #[test]
+    fn test_empty() {
+        let result = analyze_overhangs(&[], UP, 45.0).unwrap();
+        assert!(result.flags.is_empty());
+        assert!(result.regions.is_empty());
+    }
+
+    #[test]
+    fn test_box_on_plate() {
+        // The bottom face lies on the plate and does not need support.
+        let cube = create_box_geometry(Vector3::new(0.0, 0.0, 0.0), Vector3::new(1.0, 1.0, 1.0));
+        let result = analyze_overhangs(&cube, UP, 45.0).unwrap();
+        assert_eq!(result.flags, vec![0; 12]);
+        assert!(result.regions.is_empty());
+
+        // Printing upside down does not change anything, the top face becomes the bottom one.
+        let result = analyze_overhangs(&cube, Vector3::new(0.0, 0.0, -2.0), 45.0).unwrap();
+        assert!(result.regions.is_empty());
+    }
+
+    #[test]
+    fn test_bridge() {
+        // A table: a slab resting on two legs. The legs stand on the plate, the bottom of the slab is an overhang.
+        let leg1 = create_box_geometry(Vector3::new(0.0, 0.0, 0.0), Vector3::new(1.0, 1.0, 2.0));
+        let leg2 = create_box_geometry(Vector3::new(4.0, 0.0, 0.0), Vector3::new(5.0, 1.0, 2.0));
+        let slab = create_box_geometry(Vector3::new(-1.0, 0.0, 2.0), Vector3::new(6.0, 1.0, 3.0));
+        let mut table = leg1.clone();
+        table.extend_from_slice(&leg2);
+        table.extend_from_slice(&slab);
+
+        let result = analyze_overhangs(&table, UP, 45.0).unwrap();
+        // Only the two triangles of the slab bottom (the first face of the third box) are flagged.
+        let flagged: Vec<usize> = (0..result.flags.len())
+            .filter(|&t| result.flags[t] != 0)
+            .collect();
+        assert_eq!(flagged, vec![24, 25]);
+        assert_eq!(result.regions.len(), 1);
+        assert_eq!(result.regions[0].triangles, vec![24, 25]);
+        assert!((result.regions[0].area - 7.0).abs() < 1e-5);
+    }
+
+    #[test]
+    fn test_disjoint_regions() {
+        // Two floating boxes give two separate regions.
+        let box1 = create_box_geometry(Vector3::new(0.0, 0.0, 0.0), Vector3::new(1.0, 1.0, 1.0));
+        let box2 = create_box_geometry(Vector3::new(0.0, 0.0, 2.0), Vector3::new(2.0, 1.0, 3.0));
+        let box3 = create_box_geometry(Vector3::new(5.0, 0.0, 2.0), Vector3::new(6.0, 3.0, 3.0));
+        let mut merged = box1.clone();
+        merged.extend_from_slice(&box2);
+        merged.extend_from_slice(&box3);
+        let result = analyze_overhangs(&merged, UP, 45.0).unwrap();
+        assert_eq!(result.regions.len(), 2);
+        assert!((result.regions[0].area - 2.0).abs() < 1e-5);
+        assert!((result.regions[1].area - 3.0).abs() < 1e-5);
+    }
+
+    #[test]
+    fn test_threshold() {
+        // The lower half of a sphere: the steeper the threshold, the smaller the overhang area.
+        let sphere = create_sphere_geometry(10.0, 64, 32);
+        let areas: Vec<f32> = [0.0, 30.0, 60.0, 89.0]
+            .iter()
+            .map(|&threshold| {
+                analyze_overhangs(&sphere, UP, threshold)
+                    .unwrap()
+                    .regions
+                    .iter()
+                    .map(|r| r.area)
+                    .sum()
+            })
+            .collect();
+        for w in areas.windows(2) {
+            assert!(w[0] > w[1], "{:?}", areas);
+        }
+        // With zero threshold the whole lower hemisphere except the triangles touching the plate is an overhang.
+        let hemisphere = 2.0 * std::f32::consts::PI * 100.0;
+        assert!(areas[0] < hemisphere && areas[0] > 0.9 * hemisphere, "{}", areas[0]);
+        assert_eq!(analyze_overhangs(&sphere, UP, 0.0).unwrap().regions.len(), 1);
+    }
+
+    #[test]
+    fn test_wasm_wrapper() {
+        let box1 = create_box_geometry(Vector3::new(0.0, 0.0, 0.0), Vector3::new(1.0, 1.0, 1.0));
+        let box2 = create_box_geometry(Vector3::new(0.0, 0.0, 2.0), Vector3::new(2.0, 1.0, 3.0));
+        let mut merged = box1.clone();
+        merged.extend_from_slice(&box2);
+        let result = find_overhangs(&merged, 0.0, 0.0, 1.0, 45.0).unwrap();
+        assert_eq!(result.flags().len(), 24);
+        assert_eq!(result.region_count(), 1);
+        assert_eq!(result.region_triangles(0).unwrap().to_vec(), vec![12, 13]);
+        assert!((result.region_area(0).unwrap() - 2.0).abs() < 1e-5);
+        assert_eq!(result.total_area(), result.region_area(0).unwrap());
+        assert_eq!(result.region_triangles(1).unwrap_err().code(), "INVALID_INPUT");
+        assert!(find_overhangs(&merged, 0.0, 0.0, 0.0, 45.0).is_err());
+    }
+
+    #[test]
+    fn test_invalid_input() {
+        let cube = create_box_geometry(Vector3::new(0.0, 0.0, 0.0), Vector3::new(1.0, 1.0, 1.0));
+        for threshold in [-1.0, 90.5, f32::NAN, f32::INFINITY] {
+            let err = analyze_overhangs(&cube, UP, threshold).unwrap_err();
+            assert_eq!(err.code(), "INVALID_INPUT");
+        }
+        assert!(analyze_overhangs(&cube, UP, 90.0).is_ok());
+        for dir in [Vector3::ZERO, Vector3::new(f32::NAN, 0.0, 1.0), Vector3::new(0.0, f32::INFINITY, 1.0)] {
+            assert!(analyze_overhangs(&cube, dir, 45.0).is_err());
+        }
+        // Validated even without triangles.
+        assert!(analyze_overhangs(&[], Vector3::ZERO, 45.0).is_err());
+    }
+}
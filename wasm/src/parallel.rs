@@ -0,0 +1,241 @@
+use std::collections::HashMap;
+use std::hash::{BuildHasher, BuildHasherDefault, DefaultHasher, Hash, Hasher};
+
+#[cfg(feature = "threads")]
+use rayon::prelude::*;
+
+// Parallel iterators are not split into tasks smaller than this, the overhead would outweigh the gain.
+#[cfg(feature = "threads")]
+const MIN_TASK_LEN: usize = 4096;
+
+/// Number of threads the parallel helpers use, 1 without the threads feature.
+pub fn thread_count() -> usize {
+    #[cfg(feature = "threads")]
+    return rayon::current_num_threads();
+    #[cfg(not(feature = "threads"))]
+    1
+}
+
+/// Calls `f` for each index in `0..len` and returns the results in the index order. Runs on the thread pool with the
+/// threads feature, sequentially otherwise.
+pub fn map_range<R: Send>(len: usize, f: impl Fn(usize) -> R + Sync + Send) -> Vec<R> {
+    #[cfg(feature = "threads")]
+    return (0..len)
+        .into_par_iter()
+        .with_min_len(MIN_TASK_LEN)
+        .map(f)
+        .collect();
+    #[cfg(not(feature = "threads"))]
+    (0..len).map(f).collect()
+}
+
+/// Same as map_range, for a few large tasks (e.g. one per thread or per part): each index may run on its own thread.
+pub fn map_tasks<R: Send>(len: usize, f: impl Fn(usize) -> R + Sync + Send) -> Vec<R> {
+    #[cfg(feature = "threads")]
+    return (0..len).into_par_iter().with_max_len(1).map(f).collect();
+    #[cfg(not(feature = "threads"))]
+    (0..len).map(f).collect()
+}
+
+/// Calls `f` for each index in `0..len`, in parallel with the threads feature. `f` can only have effects through shared
+/// state, e.g. atomics.
+pub fn for_each_range(len: usize, f: impl Fn(usize) + Sync + Send) {
+    #[cfg(feature = "threads")]
+    (0..len)
+        .into_par_iter()
+        .with_min_len(MIN_TASK_LEN)
+        .for_each(f);
+    #[cfg(not(feature = "threads"))]
+    (0..len).for_each(f);
+}
+
+/// Same as `items.chunks_exact(chunk_len).map(f).collect()`, but runs on the thread pool with the threads feature.
+pub fn map_chunks<T: Sync, R: Send>(items: &[T], chunk_len: usize, f: impl Fn(&[T]) -> R + Sync + Send) -> Vec<R> {
+    #[cfg(feature = "threads")]
+    return items
+        .par_chunks_exact(chunk_len)
+        .with_min_len(MIN_TASK_LEN)
+        .map(f)
+        .collect();
+    #[cfg(not(feature = "threads"))]
+    items.chunks_exact(chunk_len).map(f).collect()
+}
+
+/// Maps the items and combines the results with `reduce`. The result does not depend on the number of threads only if
+/// `reduce` is associative and commutative, e.g. min or max.
+pub fn map_reduce<T: Sync, R: Copy + Send + Sync>(
+    items: &[T],
+    identity: R,
+    map: impl Fn(&T) -> R + Sync + Send,
+    reduce: impl Fn(R, R) -> R + Sync + Send,
+) -> R {
+    #[cfg(feature = "threads")]
+    return items
+        .par_iter()
+        .with_min_len(MIN_TASK_LEN)
+        .map(map)
+        .reduce(|| identity, reduce);
+    #[cfg(not(feature = "threads"))]
+    items.iter().map(map).fold(identity, reduce)
+}
+
+/// Hashes the key with a fixed hasher, so that the hashes computed on different threads (or passed to Prehashed) agree.
+pub fn hash_one<K: Hash>(key: &K) -> u64 {
+    BuildHasherDefault::<DefaultHasher>::default().hash_one(key)
+}
+
+/// Key with the hash computed by hash_one in advance, e.g. in parallel before the (sequential) inserts into a map.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Prehashed<K> {
+    pub hash: u64,
+    pub key: K,
+}
+
+impl<K: Hash> Prehashed<K> {
+    pub fn new(key: K) -> Self {
+        Self { hash: hash_one(&key), key }
+    }
+}
+
+impl<K> Hash for Prehashed<K> {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        state.write_u64(self.hash);
+    }
+}
+
+/// Hasher of Prehashed keys, it returns the precomputed hash as is.
+#[derive(Default)]
+pub struct PrehashedHasher(u64);
+
+impl Hasher for PrehashedHasher {
+    fn finish(&self) -> u64 {
+        self.0
+    }
+
+    fn write(&mut self, _bytes: &[u8]) {
+        unreachable!("PrehashedHasher only supports Prehashed keys");
+    }
+
+    fn write_u64(&mut self, hash: u64) {
+        self.0 = hash;
+    }
+}
+
+pub type PrehashedMap<K, V> = HashMap<Prehashed<K>, V, BuildHasherDefault<PrehashedHasher>>;
+
+// In the browser the pool threads run in web workers sharing the wasm memory. The module must be built with the
+// atomics target feature (see run-build.sh) and used from a worker: the threads block on Atomics.wait, which is not
+// allowed on the main thread.
+#[cfg(all(feature = "threads", target_arch = "wasm32"))]
+mod wasm_thread_pool {
+    use wasm_bindgen::prelude::*;
+
+    use crate::error::Error;
+
+    /// Returns [module, memory] which a pool worker must pass to initSync, so that it shares the memory with this
+    /// instance.
+    #[wasm_bindgen]
+    pub fn thread_pool_init_data() -> js_sys::Array {
+        js_sys::Array::of2(&wasm_bindgen::module(), &wasm_bindgen::memory())
+    }
+
+    /// Builds the global thread pool. `start_thread` is called with the thread index and a pointer, which must be
+    /// passed to run_thread_pool_worker in a worker. The workers must be created and initialized before this call: it
+    /// does not return until all threads are running, and the browser does not start new workers meanwhile.
+    #[wasm_bindgen]
+    pub fn init_thread_pool(num_threads: usize, start_thread: &js_sys::Function) -> Result<(), Error> {
+        rayon::ThreadPoolBuilder::new()
+            .num_threads(num_threads)
+            .spawn_handler(|thread| {
+                let index = thread.index() as u32;
+                let ptr = Box::into_raw(Box::new(thread)) as u32;
+                start_thread
+                    .call2(&JsValue::NULL, &JsValue::from(index), &JsValue::from(ptr))
+                    .map_err(|_| std::io::Error::other("failed to start a pool thread"))?;
+                Ok(())
+            })
+            .build_global()
+            .map_err(|e| Error::InvalidInput(format!("failed to build the thread pool: {}", e)))
+    }
+
+    /// Runs a pool thread in the calling worker, does not return.
+    #[wasm_bindgen]
+    pub fn run_thread_pool_worker(ptr: u32) {
+        let thread = unsafe { Box::from_raw(ptr as *mut rayon::ThreadBuilder) };
+        thread.run();
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    use super::*;
+
+    /// Runs `f` on a pool of `num_threads` threads. Without the threads feature it just runs `f`.
+    pub(crate) fn with_threads<R: Send>(num_threads: usize, f: impl FnOnce() -> R + Send) -> R {
+        #[cfg(feature = "threads")]
+        return rayon::ThreadPoolBuilder::new()
+            .num_threads(num_threads)
+            .build()
+            .unwrap()
+            .install(f);
+        #[cfg(not(feature = "threads"))]
+        {
+            let _ = num_threads;
+            f()
+        }
+    }
+
+    #[test]
+    fn test_helpers() {
+        for num_threads in [1, 4] {
+            with_threads(num_threads, || {
+                let squares = map_range(10000, |i| i * i);
+                assert_eq!(squares, (0..10000).map(|i| i * i).collect::<Vec<_>>());
+                let sum = AtomicUsize::new(0);
+                for_each_range(10000, |i| {
+                    sum.fetch_add(i, Ordering::Relaxed);
+                });
+                assert_eq!(sum.into_inner(), 49995000);
+                let items: Vec<u32> = (0..30001).collect();
+                let sums = map_chunks(&items, 3, |c| c.iter().sum::<u32>());
+                assert_eq!(sums.len(), 10000);
+                assert_eq!(sums[1], 12);
+                assert_eq!(map_reduce(&items, 0, |&i| i, u32::max), 30000);
+                assert_eq!(map_reduce(&items[..0], 7, |&i| i, u32::max), 7);
+            });
+        }
+        #[cfg(feature = "threads")]
+        assert_eq!(with_threads(3, thread_count), 3);
+        assert_eq!(map_tasks(3, |i| i + 1), [1, 2, 3]);
+    }
+
+    #[cfg(feature = "threads")]
+    #[test]
+    fn test_map_tasks_threads() {
+        // Slow tasks get stolen by the idle threads.
+        let threads = with_threads(4, || {
+            map_tasks(8, |_| {
+                std::thread::sleep(std::time::Duration::from_millis(20));
+                rayon::current_thread_index().unwrap()
+            })
+        });
+        let mut distinct = threads.clone();
+        distinct.sort();
+        distinct.dedup();
+        assert!(distinct.len() > 1, "{:?}", threads);
+    }
+
+    #[test]
+    fn test_prehashed_map() {
+        let mut map: PrehashedMap<(i32, i32), usize> = PrehashedMap::default();
+        for i in 0..100 {
+            *map.entry(Prehashed::new((i % 10, 1))).or_default() += 1;
+        }
+        assert_eq!(map.len(), 10);
+        assert_eq!(map.get(&Prehashed::new((3, 1))), Some(&10));
+        assert_eq!(map.get(&Prehashed::new((3, 2))), None);
+        assert_eq!(Prehashed::new("a").hash, hash_one(&"a"));
+    }
+}
@@ -0,0 +1,149 @@
+use std::collections::HashSet;
+
+use wasm_bindgen::prelude::*;
+
+use crate::error::Error;
+use crate::mesh_stats::compute_mesh_stats;
+use crate::split_geometry::split_disjoint_geometry;
+use crate::stl_writer::{FacetColors, write_binary_stl};
+use crate::zip::ZipWriter;
+
+/// How the files of the split parts are named.
+#[wasm_bindgen]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PartNaming {
+    /// part_01.stl, part_02.stl, ... in the order returned by split_disjoint_geometry.
+    Index = 0,
+    /// part_01_1234.5mm3.stl, ..., parts are ordered from the largest volume to the smallest.
+    Volume = 1,
+    /// The original solid name, with the index appended if there is more than one part.
+    SolidName = 2,
+}
+
+// Replaces characters which are not allowed in file names on common systems.
+fn sanitize_file_name(name: &str) -> String {
+    let sanitized: String = name
+        .trim()
+        .chars()
+        .map(|c| if c.is_control() || "/\\:*?\"<>|".contains(c) { '_' } else { c })
+        .collect();
+    // Trailing dots and spaces are stripped by Windows.
+    let sanitized = sanitized.trim_end_matches(['.', ' ']);
+    if sanitized.is_empty() { "part".to_string() } else { sanitized.to_string() }
+}
+
+// Appends " (2)", " (3)" etc. to the stem until the name is unique (case-insensitively).
+fn unique_name(stem: &str, used: &mut HashSet<String>) -> String {
+    let mut name = format!("{}.stl", stem);
+    let mut n = 2;
+    while !used.insert(name.to_lowercase()) {
+        name = format!("{} ({}).stl", stem, n);
+        n += 1;
+    }
+    name
+}
+
+/// Splits the geometry into disjoint parts (see split_disjoint_geometry) and writes each part as a binary STL into
+/// a zip archive. `solid_name` is used as the base of the file names; empty name is replaced with "part".
+pub fn write_split_parts_zip(pos: &[f32], naming: PartNaming, solid_name: &str) -> Result<Vec<u8>, Error> {
+    let mut parts: Vec<(Vec<f32>, f32)> = split_disjoint_geometry(pos)?
+        .into_iter()
+        .map(|part| {
+            let volume = compute_mesh_stats(&part).volume;
+            (part, volume)
+        })
+        .collect();
+    if naming == PartNaming::Volume {
+        // Stable sort keeps the split order for parts with equal volumes.
+        parts.sort_by(|a, b| b.1.abs().total_cmp(&a.1.abs()));
+    }
+
+    let base = match naming {
+        PartNaming::SolidName => sanitize_file_name(solid_name),
+        PartNaming::Index | PartNaming::Volume => "part".to_string(),
+    };
+    let width = parts.len().to_string().len().max(2);
+    let mut used = HashSet::new();
+    let mut writer = ZipWriter::new();
+    for (i, (part, volume)) in parts.iter().enumerate() {
+        let stem = match naming {
+            PartNaming::SolidName if parts.len() == 1 => base.clone(),
+            PartNaming::Index | PartNaming::SolidName => format!("{}_{:0width$}", base, i + 1),
+            PartNaming::Volume => format!("{}_{:0width$}_{:.1}mm3", base, i + 1, volume.abs()),
+        };
+        let name = unique_name(&stem, &mut used);
+        writer.add_file(&name, &write_binary_stl(part, FacetColors::None), true);
+    }
+    Ok(writer.finish())
+}
+
+/// Writes the disjoint parts as separate binary STLs in a zip archive, see write_split_parts_zip.
+#[wasm_bindgen]
+pub fn export_split_parts(pos: &[f32], naming: PartNaming, solid_name: &str) -> Result<Box<[u8]>, Error> {
+    write_split_parts_zip(pos, naming, solid_name).map(Vec::into_boxed_slice)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::plane_section::tests::create_box_geometry;
+    use crate::vector3::Vector3;
+    use crate::zip::ZipArchive;
+
+    fn two_boxes() -> Vec<f32> {
+        let mut pos = create_box_geometry(Vector3::new(0.0, 0.0, 0.0), Vector3::new(1.0, 1.0, 1.0));
+        pos.extend(create_box_geometry(Vector3::new(5.0, 0.0, 0.0), Vector3::new(7.0, 2.0, 2.0)));
+        pos
+    }
+
+    fn entry_names(data: &[u8]) -> Vec<String> {
+        ZipArchive::new(data)
+            .unwrap()
+            .entries
+            .into_iter()
+            .map(|e| e.name)
+            .collect()
+    }
+
+    #[test]
+    fn test_index_naming() {
+        let pos = two_boxes();
+        let data = write_split_parts_zip(&pos, PartNaming::Index, "ignored").unwrap();
+        assert_eq!(entry_names(&data), vec!["part_01.stl", "part_02.stl"]);
+        let archive = ZipArchive::new(&data).unwrap();
+        for entry in &archive.entries {
+            let stl = archive.read(entry).unwrap();
+            assert_eq!(stl.len(), 84 + 12 * 50);
+        }
+    }
+
+    #[test]
+    fn test_volume_naming() {
+        let data = write_split_parts_zip(&two_boxes(), PartNaming::Volume, "").unwrap();
+        assert_eq!(entry_names(&data), vec!["part_01_8.0mm3.stl", "part_02_1.0mm3.stl"]);
+    }
+
+    #[test]
+    fn test_solid_naming() {
+        let data = write_split_parts_zip(&two_boxes(), PartNaming::SolidName, " plate: v2 ").unwrap();
+        assert_eq!(entry_names(&data), vec!["plate_ v2_01.stl", "plate_ v2_02.stl"]);
+        let single = create_box_geometry(Vector3::new(0.0, 0.0, 0.0), Vector3::new(1.0, 1.0, 1.0));
+        let data = write_split_parts_zip(&single, PartNaming::SolidName, "cube").unwrap();
+        assert_eq!(entry_names(&data), vec!["cube.stl"]);
+        let data = write_split_parts_zip(&single, PartNaming::SolidName, "..").unwrap();
+        assert_eq!(entry_names(&data), vec!["part.stl"]);
+    }
+
+    #[test]
+    fn test_unique_names() {
+        let mut used = HashSet::new();
+        assert_eq!(unique_name("a", &mut used), "a.stl");
+        assert_eq!(unique_name("A", &mut used), "A (2).stl");
+        assert_eq!(unique_name("a", &mut used), "a (3).stl");
+    }
+
+    #[test]
+    fn test_empty() {
+        assert!(entry_names(&export_split_parts(&[], PartNaming::Index, "").unwrap()).is_empty());
+    }
+}
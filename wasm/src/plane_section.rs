@@ -0,0 +1,488 @@
+use std::collections::HashMap;
+
+use wasm_bindgen::prelude::*;
+
+use crate::error::{Error, checked_item};
+use crate::split_geometry::split_disjoint_geometry;
+use crate::vector2::Vector2;
+use crate::vector3::Vector3;
+
+/// A plane defined by a point on it and a unit normal.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Plane {
+    pub origin: Vector3,
+    pub normal: Vector3,
+}
+
+impl Plane {
+    /// Creates a plane, normalizing the normal. Returns None if the normal has zero length.
+    pub fn new(origin: Vector3, normal: Vector3) -> Option<Self> {
+        let normal = normal.normalize();
+        if normal == Vector3::ZERO {
+            return None;
+        }
+        Some(Self { origin, normal })
+    }
+
+    pub fn signed_distance(&self, p: Vector3) -> f32 {
+        (p - self.origin).dot(self.normal)
+    }
+
+    /// Returns unit vectors u and v lying in the plane with u x v = normal.
+    pub fn basis(&self) -> (Vector3, Vector3) {
+        let axis = if self.normal.x.abs() < 0.9 { Vector3::new(1.0, 0.0, 0.0) } else { Vector3::new(0.0, 1.0, 0.0) };
+        let u = self.normal.cross(axis).normalize();
+        let v = self.normal.cross(u);
+        (u, v)
+    }
+
+    /// Returns coordinates of the projection of p onto the plane in the basis returned by basis().
+    pub fn project(&self, p: Vector3, basis: (Vector3, Vector3)) -> Vector2 {
+        let d = p - self.origin;
+        Vector2::new(d.dot(basis.0), d.dot(basis.1))
+    }
+}
+
+/// A polyline produced by cutting a mesh with a plane.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Contour {
+    pub points: Vec<Vector3>,
+    /// False if the chain of segments could not be closed, which means that the mesh is not watertight.
+    pub closed: bool,
+    /// Area enclosed by the contour, positive if the contour is counter-clockwise when looking against the plane normal
+    /// (outer boundary of a solid with outward-facing normals) and negative for holes. Zero for open contours.
+    pub area: f32,
+    pub perimeter: f32,
+}
+
+/// Section contours of a single part, as returned by split_disjoint_geometry.
+#[derive(Clone, Debug, PartialEq)]
+pub struct PartSection {
+    pub part_idx: usize,
+    pub contours: Vec<Contour>,
+}
+
+/// A directed segment between two intersection points, identified by the indices of intersected edges.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Segment {
+    pub from: u32,
+    pub to: u32,
+}
+
+// Orders edge endpoints so that both triangles sharing the edge compute exactly the same intersection point.
+fn canonical_edge(a: Vector3, b: Vector3) -> (Vector3, Vector3) {
+    let key = |v: Vector3| (v.x.to_bits(), v.y.to_bits(), v.z.to_bits());
+    if key(a) <= key(b) { (a, b) } else { (b, a) }
+}
+
+/// Intersects the segment a-b with the plane given signed distances of its endpoints (which must have different
+/// signs).
+pub fn intersect_edge(a: Vector3, da: f32, b: Vector3, db: f32) -> Vector3 {
+    let t = da / (da - db);
+    a + (b - a) * t
+}
+
+/// Returns the oriented intersection of triangle v with the plane as a pair of edge indices (0 is v[0]-v[1], 1 is
+/// v[1]-v[2], 2 is v[2]-v[0]) or None if the triangle does not cross the plane. Vertices lying exactly on the plane are
+/// considered to be above it, which guarantees that neighboring triangles produce consistent segments.
+///
+/// The segment goes from the edge crossing the plane downwards to the edge crossing it upwards, so for a triangle with
+/// outward-facing normal n the segment is directed along plane.normal x n. This makes outer contours counter-clockwise
+/// and holes clockwise.
+pub fn triangle_crossing(d: [f32; 3]) -> Option<(usize, usize)> {
+    let above = [d[0] >= 0.0, d[1] >= 0.0, d[2] >= 0.0];
+    let mut down = None;
+    let mut up = None;
+    for i in 0..3 {
+        let j = (i + 1) % 3;
+        if above[i] && !above[j] {
+            down = Some(i);
+        } else if !above[i] && above[j] {
+            up = Some(i);
+        }
+    }
+    Some((down?, up?))
+}
+
+/// Chains directed segments into polylines. `points[id]` is the position of intersection point `id`. Open chains are
+/// started from points without incoming segments so that each of them is reported as a single maximal polyline.
+pub fn chain_segments(points: &[Vector3], segments: &[Segment], normal: Vector3) -> Vec<Contour> {
+    let mut by_start: HashMap<u32, Vec<usize>> = HashMap::with_capacity(segments.len());
+    let mut incoming: HashMap<u32, usize> = HashMap::with_capacity(segments.len());
+    // Push in reverse so that pop() returns segments in their original order.
+    for (idx, seg) in segments.iter().enumerate().rev() {
+        by_start.entry(seg.from).or_default().push(idx);
+        *incoming.entry(seg.to).or_default() += 1;
+    }
+
+    let mut used = vec![false; segments.len()];
+    let mut result = vec![];
+
+    let mut walk = |start_seg: usize, used: &mut [bool], result: &mut Vec<Contour>| {
+        let first_id = segments[start_seg].from;
+        let mut ids = vec![first_id];
+        let mut cur = start_seg;
+        loop {
+            used[cur] = true;
+            let to = segments[cur].to;
+            if to == first_id {
+                result.push(make_contour(points, &ids, true, normal));
+                return;
+            }
+            ids.push(to);
+            let next = by_start.get_mut(&to).and_then(|candidates| {
+                while let Some(idx) = candidates.pop() {
+                    if !used[idx] {
+                        return Some(idx);
+                    }
+                }
+                None
+            });
+            match next {
+                Some(next) => cur = next,
+                None => {
+                    result.push(make_contour(points, &ids, false, normal));
+                    return;
+                }
+            }
+        }
+    };
+
+    for (idx, seg) in segments.iter().enumerate() {
+        if !used[idx] && !incoming.contains_key(&seg.from) {
+            walk(idx, &mut used, &mut result);
+        }
+    }
+    for idx in 0..segments.len() {
+        if !used[idx] {
+            walk(idx, &mut used, &mut result);
+        }
+    }
+
+    result
+}
+
+fn make_contour(points: &[Vector3], ids: &[u32], closed: bool, normal: Vector3) -> Contour {
+    let points: Vec<Vector3> = ids.iter().map(|&id| points[id as usize]).collect();
+    let mut perimeter = 0.0;
+    for w in points.windows(2) {
+        perimeter += (w[1] - w[0]).length();
+    }
+    let mut area = 0.0;
+    if closed && points.len() > 1 {
+        perimeter += (points[0] - points[points.len() - 1]).length();
+        // Shoelace formula in 3D: twice the area vector is the sum of cross products of consecutive points. Points
+        // are taken relative to the first one to reduce the precision loss.
+        let base = points[0];
+        let mut area_vec = Vector3::ZERO;
+        for w in points[1..].windows(2) {
+            area_vec = area_vec + (w[0] - base).cross(w[1] - base);
+        }
+        area = 0.5 * area_vec.dot(normal);
+    }
+    Contour { points, closed, area, perimeter }
+}
+
+/// Intersects a triangle soup with the plane and chains the resulting segments into contours. `pos` has the same layout
+/// as in split_disjoint_geometry. All triangles are treated as a single body.
+pub fn section_triangles(pos: &[f32], plane: &Plane) -> Vec<Contour> {
+    if !pos.len().is_multiple_of(9) {
+        return vec![];
+    }
+
+    let mut point_ids: HashMap<(Vector3, Vector3), u32> = HashMap::new();
+    let mut points = vec![];
+    let mut segments = vec![];
+
+    for tri in pos.chunks_exact(9) {
+        let v = [
+            Vector3::new(tri[0], tri[1], tri[2]),
+            Vector3::new(tri[3], tri[4], tri[5]),
+            Vector3::new(tri[6], tri[7], tri[8]),
+        ];
+        let d = [plane.signed_distance(v[0]), plane.signed_distance(v[1]), plane.signed_distance(v[2])];
+        let Some((down, up)) = triangle_crossing(d) else {
+            continue;
+        };
+
+        let mut edge_point = |i: usize| {
+            let (a, b) = canonical_edge(v[i], v[(i + 1) % 3]);
+            *point_ids.entry((a, b)).or_insert_with(|| {
+                points.push(intersect_edge(a, plane.signed_distance(a), b, plane.signed_distance(b)));
+                (points.len() - 1) as u32
+            })
+        };
+        let from = edge_point(down);
+        let to = edge_point(up);
+        segments.push(Segment { from, to });
+    }
+
+    chain_segments(&points, &segments, plane.normal)
+}
+
+/// Splits the mesh into parts and computes the section of each part by the plane. Parts not intersecting the plane are
+/// omitted, part_idx refers to the order of parts returned by split_disjoint_geometry.
+pub fn compute_plane_section(pos: &[f32], plane: &Plane) -> Result<Vec<PartSection>, Error> {
+    let mut result = vec![];
+    for (part_idx, part) in split_disjoint_geometry(pos)?.iter().enumerate() {
+        let contours = section_triangles(part, plane);
+        if !contours.is_empty() {
+            result.push(PartSection { part_idx, contours });
+        }
+    }
+    Ok(result)
+}
+
+/// Result of plane_section, flattened into a list of contours for JS.
+#[wasm_bindgen]
+pub struct PlaneSection {
+    contours: Vec<(usize, Contour)>,
+}
+
+impl PlaneSection {
+    fn contour(&self, idx: usize) -> Result<&(usize, Contour), Error> {
+        checked_item(&self.contours, idx, "contour")
+    }
+}
+
+#[wasm_bindgen]
+impl PlaneSection {
+    #[wasm_bindgen(getter)]
+    pub fn contour_count(&self) -> usize {
+        self.contours.len()
+    }
+
+    #[wasm_bindgen(getter)]
+    pub fn open_contour_count(&self) -> usize {
+        self.contours.iter().filter(|(_, c)| !c.closed).count()
+    }
+
+    pub fn contour_part(&self, idx: usize) -> Result<usize, Error> {
+        Ok(self.contour(idx)?.0)
+    }
+
+    /// Returns interleaved x, y, z coordinates of contour points.
+    pub fn contour_points(&self, idx: usize) -> Result<Box<[f32]>, Error> {
+        Ok(self
+            .contour(idx)?
+            .1
+            .points
+            .iter()
+            .flat_map(|p| [p.x, p.y, p.z])
+            .collect())
+    }
+
+    pub fn contour_closed(&self, idx: usize) -> Result<bool, Error> {
+        Ok(self.contour(idx)?.1.closed)
+    }
+
+    pub fn contour_area(&self, idx: usize) -> Result<f32, Error> {
+        Ok(self.contour(idx)?.1.area)
+    }
+
+    pub fn contour_perimeter(&self, idx: usize) -> Result<f32, Error> {
+        Ok(self.contour(idx)?.1.perimeter)
+    }
+}
+
+/// Cuts the mesh with the plane passing through (origin_x, origin_y, origin_z) with the normal (normal_x, normal_y,
+/// normal_z). See compute_plane_section.
+#[wasm_bindgen]
+pub fn plane_section(
+    pos: &[f32],
+    origin_x: f32,
+    origin_y: f32,
+    origin_z: f32,
+    normal_x: f32,
+    normal_y: f32,
+    normal_z: f32,
+) -> Result<PlaneSection, Error> {
+    let origin = Vector3::new(origin_x, origin_y, origin_z);
+    let normal = Vector3::new(normal_x, normal_y, normal_z);
+    let plane = Plane::new(origin, normal).ok_or_else(|| Error::InvalidInput("plane normal is zero".to_string()))?;
+    let mut contours = vec![];
+    for part in compute_plane_section(pos, &plane)? {
+        contours.extend(part.contours.into_iter().map(|c| (part.part_idx, c)));
+    }
+    Ok(PlaneSection { contours })
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+
+    // A box with outward-facing normals, same layout as split_geometry tests but with arbitrary bounds.
+    pub(crate) fn create_box_geometry(min: Vector3, max: Vector3) -> Vec<f32> {
+        let vertices = [
+            [min.x, min.y, min.z],
+            [max.x, min.y, min.z],
+            [max.x, max.y, min.z],
+            [min.x, max.y, min.z],
+            [min.x, min.y, max.z],
+            [max.x, min.y, max.z],
+            [max.x, max.y, max.z],
+            [min.x, max.y, max.z],
+        ];
+        #[rustfmt::skip]
+        let faces = [
+            [0, 2, 1], [0, 3, 2],
+            [4, 5, 6], [4, 6, 7],
+            [1, 2, 6], [1, 6, 5],
+            [0, 7, 3], [0, 4, 7],
+            [3, 6, 2], [3, 7, 6],
+            [0, 1, 5], [0, 5, 4],
+        ];
+        let mut result = Vec::with_capacity(12 * 9);
+        for face in faces {
+            for &vi in &face {
+                result.extend_from_slice(&vertices[vi]);
+            }
+        }
+        result
+    }
+
+    #[test]
+    fn test_plane_basis() {
+        for normal in [Vector3::new(0.0, 0.0, 1.0), Vector3::new(1.0, 0.0, 0.0), Vector3::new(1.0, -2.0, 3.0)] {
+            let plane = Plane::new(Vector3::new(1.0, 2.0, 3.0), normal).unwrap();
+            let (u, v) = plane.basis();
+            assert!((u.length() - 1.0).abs() < 1e-6);
+            assert!((v.length() - 1.0).abs() < 1e-6);
+            assert!((u.cross(v) - plane.normal).length() < 1e-6);
+            let p = plane.origin + u * 2.0 + v * 3.0 + plane.normal * 5.0;
+            let projected = plane.project(p, (u, v));
+            assert!((projected - Vector2::new(2.0, 3.0)).length() < 1e-5);
+        }
+    }
+
+    pub(crate) fn z_plane(z: f32) -> Plane {
+        Plane::new(Vector3::new(0.0, 0.0, z), Vector3::new(0.0, 0.0, 1.0)).unwrap()
+    }
+
+    #[test]
+    fn test_no_intersection() {
+        let cube = create_box_geometry(Vector3::new(0.0, 0.0, 0.0), Vector3::new(1.0, 1.0, 1.0));
+        assert!(section_triangles(&cube, &z_plane(2.0)).is_empty());
+        assert!(section_triangles(&[], &z_plane(0.0)).is_empty());
+        assert!(section_triangles(&[1.0, 2.0], &z_plane(0.0)).is_empty());
+    }
+
+    #[test]
+    fn test_cube_section() {
+        let cube = create_box_geometry(Vector3::new(0.0, 0.0, 0.0), Vector3::new(2.0, 3.0, 1.0));
+        let contours = section_triangles(&cube, &z_plane(0.5));
+        assert_eq!(contours.len(), 1);
+        let c = &contours[0];
+        assert!(c.closed);
+        assert!((c.area - 6.0).abs() < 1e-5, "area {}", c.area);
+        assert!((c.perimeter - 10.0).abs() < 1e-5, "perimeter {}", c.perimeter);
+        for p in &c.points {
+            assert_eq!(p.z, 0.5);
+        }
+
+        // Flipping the plane flips the orientation, but the contour is still counter-clockwise relative to the new
+        // normal.
+        let flipped = Plane::new(Vector3::new(0.0, 0.0, 0.5), Vector3::new(0.0, 0.0, -1.0)).unwrap();
+        let contours = section_triangles(&cube, &flipped);
+        assert_eq!(contours.len(), 1);
+        assert!((contours[0].area - 6.0).abs() < 1e-5);
+    }
+
+    #[test]
+    fn test_plane_through_vertices() {
+        // Vertices on the plane count as lying above it, so the plane through the top face produces a single closed
+        // contour and the plane through the bottom face produces nothing (instead of duplicate or open contours).
+        let cube = create_box_geometry(Vector3::new(0.0, 0.0, 0.0), Vector3::new(1.0, 1.0, 1.0));
+        let contours = section_triangles(&cube, &z_plane(1.0));
+        assert_eq!(contours.len(), 1);
+        assert!(contours[0].closed);
+        assert!((contours[0].area - 1.0).abs() < 1e-5);
+
+        let contours = section_triangles(&cube, &z_plane(0.0));
+        assert!(contours.is_empty());
+    }
+
+    #[test]
+    fn test_oblique_plane() {
+        let cube = create_box_geometry(Vector3::new(-1.0, -1.0, -1.0), Vector3::new(1.0, 1.0, 1.0));
+        // The plane x + y + z = 0 cuts the cube in a regular hexagon with side sqrt(2).
+        let plane = Plane::new(Vector3::ZERO, Vector3::new(1.0, 1.0, 1.0)).unwrap();
+        let contours = section_triangles(&cube, &plane);
+        assert_eq!(contours.len(), 1);
+        let c = &contours[0];
+        assert!(c.closed);
+        let side = 2.0f32.sqrt();
+        let expected_area = 1.5 * 3.0f32.sqrt() * side * side;
+        assert!((c.area - expected_area).abs() < 1e-4, "area {} vs {}", c.area, expected_area);
+        assert!((c.perimeter - 6.0 * side).abs() < 1e-4);
+    }
+
+    #[test]
+    fn test_hole() {
+        // A box with an inner box with inverted normals (a cavity) gives one outer and one inner contour.
+        let outer = create_box_geometry(Vector3::new(0.0, 0.0, 0.0), Vector3::new(4.0, 4.0, 4.0));
+        let inner = create_box_geometry(Vector3::new(1.0, 1.0, 1.0), Vector3::new(3.0, 3.0, 3.0));
+        let mut merged = outer.clone();
+        for tri in inner.chunks_exact(9) {
+            merged.extend_from_slice(&tri[0..3]);
+            merged.extend_from_slice(&tri[6..9]);
+            merged.extend_from_slice(&tri[3..6]);
+        }
+        let mut areas: Vec<f32> = section_triangles(&merged, &z_plane(2.0))
+            .iter()
+            .map(|c| c.area)
+            .collect();
+        areas.sort_by(f32::total_cmp);
+        assert_eq!(areas.len(), 2);
+        assert!((areas[0] + 4.0).abs() < 1e-5);
+        assert!((areas[1] - 16.0).abs() < 1e-5);
+    }
+
+    #[test]
+    fn test_open_chain() {
+        // Remove the two triangles of the +X face: the section becomes a single open chain.
+        let cube = create_box_geometry(Vector3::new(0.0, 0.0, 0.0), Vector3::new(1.0, 1.0, 1.0));
+        let mut open = cube[..36].to_vec();
+        open.extend_from_slice(&cube[54..]);
+        let contours = section_triangles(&open, &z_plane(0.5));
+        assert_eq!(contours.len(), 1);
+        assert!(!contours[0].closed);
+        assert_eq!(contours[0].area, 0.0);
+        // Each of the three remaining side faces is cut through both of its triangles.
+        assert_eq!(contours[0].points.len(), 7);
+        assert!((contours[0].perimeter - 3.0).abs() < 1e-5);
+    }
+
+    #[test]
+    fn test_parts() {
+        let cube1 = create_box_geometry(Vector3::new(0.0, 0.0, 0.0), Vector3::new(1.0, 1.0, 1.0));
+        let cube2 = create_box_geometry(Vector3::new(5.0, 0.0, 0.0), Vector3::new(7.0, 1.0, 3.0));
+        let mut merged = cube1.clone();
+        merged.extend_from_slice(&cube2);
+
+        let parts = compute_plane_section(&merged, &z_plane(0.5)).unwrap();
+        assert_eq!(parts.len(), 2);
+        assert_eq!(parts[0].part_idx, 0);
+        assert!((parts[0].contours[0].area - 1.0).abs() < 1e-5);
+        assert_eq!(parts[1].part_idx, 1);
+        assert!((parts[1].contours[0].area - 2.0).abs() < 1e-5);
+
+        let parts = compute_plane_section(&merged, &z_plane(2.0)).unwrap();
+        assert_eq!(parts.len(), 1);
+        assert_eq!(parts[0].part_idx, 1);
+    }
+
+    #[test]
+    fn test_wasm_wrapper() {
+        let cube = create_box_geometry(Vector3::new(0.0, 0.0, 0.0), Vector3::new(1.0, 1.0, 1.0));
+        let section = plane_section(&cube, 0.0, 0.0, 0.5, 0.0, 0.0, 2.0).unwrap();
+        assert_eq!(section.contour_count(), 1);
+        assert_eq!(section.open_contour_count(), 0);
+        assert_eq!(section.contour_part(0).unwrap(), 0);
+        assert_eq!(section.contour_points(0).unwrap().len(), 8 * 3);
+        assert_eq!(section.contour_area(1).unwrap_err().code(), "INVALID_INPUT");
+
+        assert!(plane_section(&cube, 0.0, 0.0, 0.5, 0.0, 0.0, 0.0).is_err());
+        assert!(plane_section(&cube[..10], 0.0, 0.0, 0.5, 0.0, 0.0, 1.0).is_err());
+    }
+}
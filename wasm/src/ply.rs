@@ -0,0 +1,544 @@
+use wasm_bindgen::prelude::*;
+
+use crate::error::Error;
+use crate::indexed_mesh::IndexedMesh;
+use crate::model::{DEFAULT_PART_NAME, Model, ModelPart, triangulate_face};
+use crate::vector3::Vector3;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Format {
+    Ascii,
+    BinaryLittleEndian,
+    BinaryBigEndian,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum ScalarType {
+    Int8,
+    Uint8,
+    Int16,
+    Uint16,
+    Int32,
+    Uint32,
+    Float32,
+    Float64,
+}
+
+impl ScalarType {
+    fn parse(name: &str) -> Result<Self, String> {
+        Ok(match name {
+            "char" | "int8" => Self::Int8,
+            "uchar" | "uint8" => Self::Uint8,
+            "short" | "int16" => Self::Int16,
+            "ushort" | "uint16" => Self::Uint16,
+            "int" | "int32" => Self::Int32,
+            "uint" | "uint32" => Self::Uint32,
+            "float" | "float32" => Self::Float32,
+            "double" | "float64" => Self::Float64,
+            _ => return Err(format!("unknown property type '{}'", name)),
+        })
+    }
+
+    fn size(self) -> usize {
+        match self {
+            Self::Int8 | Self::Uint8 => 1,
+            Self::Int16 | Self::Uint16 => 2,
+            Self::Int32 | Self::Uint32 | Self::Float32 => 4,
+            Self::Float64 => 8,
+        }
+    }
+
+    fn is_float(self) -> bool {
+        matches!(self, Self::Float32 | Self::Float64)
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+enum PropertyKind {
+    Scalar(ScalarType),
+    List { count: ScalarType, item: ScalarType },
+}
+
+#[derive(Clone, Debug, PartialEq)]
+struct Property {
+    name: String,
+    kind: PropertyKind,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+struct Element {
+    name: String,
+    count: usize,
+    properties: Vec<Property>,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+struct Header {
+    format: Format,
+    elements: Vec<Element>,
+    // Offset of the body in the input.
+    body_offset: usize,
+}
+
+fn parse_header(data: &[u8]) -> Result<Header, Error> {
+    const END_HEADER: &[u8] = b"end_header";
+    let end = data
+        .windows(END_HEADER.len())
+        .position(|w| w == END_HEADER)
+        .ok_or_else(|| Error::TruncatedFile("no end_header in PLY header".to_string()))?;
+    // The body starts after the line break following end_header.
+    let mut body_offset = end + END_HEADER.len();
+    while body_offset < data.len() && data[body_offset] != b'\n' {
+        body_offset += 1;
+    }
+    body_offset = (body_offset + 1).min(data.len());
+
+    let header = std::str::from_utf8(&data[..end]).map_err(|_| "PLY header is not valid text")?;
+    let mut lines = header.lines();
+    if lines.next().map(str::trim) != Some("ply") {
+        return Err(Error::InvalidInput("not a PLY file".to_string()));
+    }
+    let mut format = None;
+    let mut elements: Vec<Element> = vec![];
+    for line in lines {
+        let tokens: Vec<&str> = line.split_whitespace().collect();
+        match tokens.as_slice() {
+            [] | ["comment", ..] | ["obj_info", ..] => {}
+            ["format", name, _version] => {
+                format = Some(match *name {
+                    "ascii" => Format::Ascii,
+                    "binary_little_endian" => Format::BinaryLittleEndian,
+                    "binary_big_endian" => Format::BinaryBigEndian,
+                    _ => return Err(Error::UnsupportedFormat(format!("unknown PLY format '{}'", name))),
+                });
+            }
+            ["element", name, count] => {
+                let count = count
+                    .parse()
+                    .map_err(|_| format!("invalid element count '{}'", count))?;
+                elements.push(Element { name: name.to_string(), count, properties: vec![] });
+            }
+            ["property", "list", count, item, name] => {
+                let element = elements.last_mut().ok_or("property before element")?;
+                let kind = PropertyKind::List { count: ScalarType::parse(count)?, item: ScalarType::parse(item)? };
+                element
+                    .properties
+                    .push(Property { name: name.to_string(), kind });
+            }
+            ["property", ty, name] => {
+                let element = elements.last_mut().ok_or("property before element")?;
+                element
+                    .properties
+                    .push(Property { name: name.to_string(), kind: PropertyKind::Scalar(ScalarType::parse(ty)?) });
+            }
+            _ => return Err(Error::InvalidInput(format!("invalid PLY header line '{}'", line.trim()))),
+        }
+    }
+    let format = format.ok_or("no format in PLY header")?;
+    Ok(Header { format, elements, body_offset })
+}
+
+// Reads scalar values from the body in any of the encodings.
+struct BodyReader<'a> {
+    format: Format,
+    data: &'a [u8],
+    offset: usize,
+}
+
+impl BodyReader<'_> {
+    fn read(&mut self, ty: ScalarType) -> Result<f64, Error> {
+        if self.format == Format::Ascii {
+            return self.read_ascii(ty);
+        }
+        let size = ty.size();
+        let bytes = self
+            .data
+            .get(self.offset..self.offset + size)
+            .ok_or_else(|| Error::TruncatedFile("unexpected end of PLY data".to_string()))?;
+        self.offset += size;
+        let mut buf = [0u8; 8];
+        buf[..size].copy_from_slice(bytes);
+        if self.format == Format::BinaryBigEndian {
+            buf[..size].reverse();
+        }
+        Ok(match ty {
+            ScalarType::Int8 => buf[0] as i8 as f64,
+            ScalarType::Uint8 => buf[0] as f64,
+            ScalarType::Int16 => i16::from_le_bytes([buf[0], buf[1]]) as f64,
+            ScalarType::Uint16 => u16::from_le_bytes([buf[0], buf[1]]) as f64,
+            ScalarType::Int32 => i32::from_le_bytes([buf[0], buf[1], buf[2], buf[3]]) as f64,
+            ScalarType::Uint32 => u32::from_le_bytes([buf[0], buf[1], buf[2], buf[3]]) as f64,
+            ScalarType::Float32 => f32::from_le_bytes([buf[0], buf[1], buf[2], buf[3]]) as f64,
+            ScalarType::Float64 => f64::from_le_bytes(buf),
+        })
+    }
+
+    fn read_ascii(&mut self, ty: ScalarType) -> Result<f64, Error> {
+        while self.offset < self.data.len() && self.data[self.offset].is_ascii_whitespace() {
+            self.offset += 1;
+        }
+        let start = self.offset;
+        while self.offset < self.data.len() && !self.data[self.offset].is_ascii_whitespace() {
+            self.offset += 1;
+        }
+        if start == self.offset {
+            return Err(Error::TruncatedFile("unexpected end of PLY data".to_string()));
+        }
+        // The token consists of non-whitespace ASCII characters if it parses as a number.
+        let token = String::from_utf8_lossy(&self.data[start..self.offset]);
+        let value: f64 = token
+            .parse()
+            .map_err(|_| format!("invalid PLY value '{}'", token))?;
+        if !ty.is_float() && value.fract() != 0.0 {
+            return Err(Error::InvalidInput(format!("invalid integer PLY value '{}'", token)));
+        }
+        Ok(value)
+    }
+}
+
+/// Mesh read from a PLY file.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct PlyMesh {
+    pub mesh: IndexedMesh,
+    /// RGB colors, 3 bytes per vertex, if the vertices have red, green and blue properties.
+    pub colors: Option<Vec<u8>>,
+    /// Number of faces which were skipped because of invalid vertex indices.
+    pub skipped_faces: usize,
+}
+
+/// Parses a PLY file in ASCII or binary (little or big endian) encoding. Vertices must have x, y and z properties, any
+/// other properties are skipped. Colors are read from red, green and blue (or diffuse_red etc.) properties, float colors
+/// are expected to be in [0, 1] range. Faces are read from vertex_indices (or vertex_index) list property and
+/// triangulated. Elements other than vertex and face are skipped.
+pub fn parse_ply(data: &[u8]) -> Result<PlyMesh, Error> {
+    let header = parse_header(data)?;
+    let mut reader = BodyReader { format: header.format, data, offset: header.body_offset };
+    let mut result = PlyMesh::default();
+    // Indices stay signed until the range check, so that negative ones are skipped rather than wrapped to valid ones.
+    let mut faces: Vec<Vec<i64>> = vec![];
+
+    for element in &header.elements {
+        let find = |names: &[&str]| {
+            element
+                .properties
+                .iter()
+                .position(|p| names.contains(&p.name.as_str()))
+        };
+        let (xyz, rgb, indices) = match element.name.as_str() {
+            "vertex" => {
+                let xyz = [find(&["x"]), find(&["y"]), find(&["z"])];
+                if xyz.iter().any(Option::is_none) {
+                    return Err(Error::InvalidInput("PLY vertex element must have x, y and z properties".to_string()));
+                }
+                let rgb =
+                    [find(&["red", "diffuse_red"]), find(&["green", "diffuse_green"]), find(&["blue", "diffuse_blue"])];
+                (
+                    Some(xyz.map(Option::unwrap)),
+                    rgb.iter()
+                        .all(Option::is_some)
+                        .then(|| rgb.map(Option::unwrap)),
+                    None,
+                )
+            }
+            "face" => (None, None, find(&["vertex_indices", "vertex_index"])),
+            _ => (None, None, None),
+        };
+        // Rows without properties take no bytes, a huge count of them would only spin.
+        if element.properties.is_empty() {
+            continue;
+        }
+        if rgb.is_some() {
+            result.colors = Some(Vec::with_capacity(element.count.min(data.len()) * 3));
+        }
+
+        let mut values = vec![0.0f64; element.properties.len()];
+        let mut list = vec![];
+        for _ in 0..element.count {
+            for (i, property) in element.properties.iter().enumerate() {
+                match property.kind {
+                    PropertyKind::Scalar(ty) => values[i] = reader.read(ty)?,
+                    PropertyKind::List { count, item } => {
+                        let n = reader.read(count)?;
+                        if n < 0.0 {
+                            return Err(Error::InvalidInput(format!("negative PLY list size {}", n)));
+                        }
+                        let is_indices = indices == Some(i);
+                        if is_indices {
+                            list.clear();
+                        }
+                        for _ in 0..n as usize {
+                            let value = reader.read(item)?;
+                            if is_indices {
+                                list.push(value);
+                            }
+                        }
+                    }
+                }
+            }
+            if let Some([x, y, z]) = xyz {
+                result
+                    .mesh
+                    .positions
+                    .push(Vector3::new(values[x] as f32, values[y] as f32, values[z] as f32));
+            }
+            if let (Some(rgb), Some(colors)) = (rgb, result.colors.as_mut()) {
+                for c in rgb {
+                    let value = match &element.properties[c].kind {
+                        PropertyKind::Scalar(ty) if ty.is_float() => values[c] * 255.0,
+                        _ => values[c],
+                    };
+                    colors.push(value.round().clamp(0.0, 255.0) as u8);
+                }
+            }
+            if indices.is_some() {
+                faces.push(list.iter().map(|&v| v as i64).collect());
+            }
+        }
+    }
+
+    let vertex_count = result.mesh.positions.len();
+    for face in &faces {
+        if face.len() < 3 || face.iter().any(|&v| v < 0 || v as usize >= vertex_count) {
+            result.skipped_faces += 1;
+            continue;
+        }
+        let face: Vec<u32> = face.iter().map(|&v| v as u32).collect();
+        result
+            .mesh
+            .triangles
+            .extend(triangulate_face(&result.mesh.positions, &face));
+    }
+    Ok(result)
+}
+
+/// Parses a PLY file into a single-part model, see parse_ply. The vertex colors become the part color if all vertices
+/// have the same color, otherwise they are dropped with a warning.
+pub fn parse_ply_model(data: &[u8]) -> Result<Model, Error> {
+    let ply = parse_ply(data)?;
+    let mut model = Model::default();
+    if ply.skipped_faces > 0 {
+        model.warn(0, format!("skipped {} faces with invalid vertex indices", ply.skipped_faces));
+    }
+    let mut color = None;
+    if let Some(colors) = &ply.colors
+        && let Some(first) = colors.get(..3)
+    {
+        if colors.chunks_exact(3).all(|c| c == first) {
+            color = Some([first[0], first[1], first[2], 255]);
+        } else {
+            model.warn(0, "per-vertex colors are not supported".to_string());
+        }
+    }
+    model.parts.push(ModelPart {
+        name: DEFAULT_PART_NAME.to_string(),
+        color,
+        positions: ply.mesh.to_triangle_soup(),
+        ..Default::default()
+    });
+    Ok(model)
+}
+
+/// Result of load_ply for JS.
+#[wasm_bindgen]
+pub struct PlyModel {
+    ply: PlyMesh,
+}
+
+#[wasm_bindgen]
+impl PlyModel {
+    /// Interleaved x, y, z coordinates of vertices.
+    #[wasm_bindgen(getter)]
+    pub fn vertices(&self) -> Box<[f32]> {
+        self.ply
+            .mesh
+            .positions
+            .iter()
+            .flat_map(|p| [p.x, p.y, p.z])
+            .collect()
+    }
+
+    /// Vertex indices, 3 per triangle.
+    #[wasm_bindgen(getter)]
+    pub fn indices(&self) -> Box<[u32]> {
+        self.ply.mesh.triangles.iter().flatten().copied().collect()
+    }
+
+    /// RGB colors, 3 bytes per vertex.
+    #[wasm_bindgen(getter)]
+    pub fn colors(&self) -> Option<Box<[u8]>> {
+        self.ply.colors.clone().map(Vec::into_boxed_slice)
+    }
+
+    /// Triangle soup with the same layout as in split_disjoint_geometry.
+    #[wasm_bindgen(getter)]
+    pub fn positions(&self) -> Box<[f32]> {
+        self.ply.mesh.to_triangle_soup().into_boxed_slice()
+    }
+
+    #[wasm_bindgen(getter)]
+    pub fn skipped_faces(&self) -> usize {
+        self.ply.skipped_faces
+    }
+}
+
+/// See parse_ply.
+#[wasm_bindgen]
+pub fn load_ply(data: &[u8]) -> Result<PlyModel, Error> {
+    parse_ply(data).map(|ply| PlyModel { ply })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const ASCII_PLY: &str = "\
+ply
+format ascii 1.0
+comment a quad and a triangle
+element vertex 5
+property float x
+property float y
+property float z
+property uchar red
+property uchar green
+property uchar blue
+element face 2
+property list uchar int vertex_indices
+end_header
+0 0 0 255 0 0
+1 0 0 0 255 0
+1 1 0 0 0 255
+0 1 0 10 20 30
+0.5 0.5 1 1 2 3
+4 0 1 2 3
+3 0 1 4
+";
+
+    // Encodes the test model from ASCII_PLY in binary format with extra properties and elements.
+    fn binary_ply(big_endian: bool) -> Vec<u8> {
+        let format = if big_endian { "binary_big_endian" } else { "binary_little_endian" };
+        let mut data = format!(
+            "ply\nformat {} 1.0\nelement vertex 5\nproperty double x\nproperty float nx\nproperty float y\n\
+             property float z\nproperty float red\nproperty float green\nproperty float blue\n\
+             element face 2\nproperty uchar flags\nproperty list uchar uint vertex_indices\n\
+             element edge 1\nproperty list ushort short vertices\nend_header\n",
+            format
+        )
+        .into_bytes();
+        let mut push = |bytes: &[u8]| {
+            let mut bytes = bytes.to_vec();
+            if big_endian {
+                bytes.reverse();
+            }
+            data.extend_from_slice(&bytes);
+        };
+        let vertices: [[f64; 3]; 5] =
+            [[0.0, 0.0, 0.0], [1.0, 0.0, 0.0], [1.0, 1.0, 0.0], [0.0, 1.0, 0.0], [0.5, 0.5, 1.0]];
+        for (i, v) in vertices.iter().enumerate() {
+            push(&v[0].to_le_bytes());
+            push(&1.0f32.to_le_bytes());
+            push(&(v[1] as f32).to_le_bytes());
+            push(&(v[2] as f32).to_le_bytes());
+            for c in 0..3 {
+                push(&(if c == i % 3 { 1.0f32 } else { 0.0f32 }).to_le_bytes());
+            }
+        }
+        for face in [vec![0u32, 1, 2, 3], vec![0, 1, 4]] {
+            push(&[7]);
+            push(&[face.len() as u8]);
+            for v in face {
+                push(&v.to_le_bytes());
+            }
+        }
+        push(&2u16.to_le_bytes());
+        push(&0i16.to_le_bytes());
+        push(&1i16.to_le_bytes());
+        data
+    }
+
+    #[test]
+    fn test_ascii() {
+        let ply = parse_ply(ASCII_PLY.as_bytes()).unwrap();
+        assert_eq!(ply.mesh.positions.len(), 5);
+        assert_eq!(ply.mesh.positions[4], Vector3::new(0.5, 0.5, 1.0));
+        assert_eq!(ply.mesh.triangles.len(), 3);
+        assert_eq!(ply.mesh.triangles[2], [0, 1, 4]);
+        assert_eq!(ply.colors.as_ref().unwrap()[9..15], [10, 20, 30, 1, 2, 3]);
+        assert_eq!(ply.skipped_faces, 0);
+    }
+
+    #[test]
+    fn test_binary() {
+        let ascii = parse_ply(ASCII_PLY.as_bytes()).unwrap();
+        for big_endian in [false, true] {
+            let ply = parse_ply(&binary_ply(big_endian)).unwrap();
+            assert_eq!(ply.mesh, ascii.mesh);
+            assert_eq!(ply.colors.as_ref().unwrap()[..9], [255, 0, 0, 0, 255, 0, 0, 0, 255]);
+        }
+    }
+
+    #[test]
+    fn test_no_colors_and_bad_faces() {
+        let data = "ply\r\nformat ascii 1.0\r\nelement vertex 3\r\nproperty float x\r\nproperty float y\r\n\
+                    property float z\r\nelement face 3\r\nproperty list uchar int vertex_index\r\nend_header\r\n\
+                    0 0 0\r\n1 0 0\r\n0 1 0\r\n3 0 1 2\r\n3 0 1 3\r\n2 0 1\r\n";
+        let ply = parse_ply(data.as_bytes()).unwrap();
+        assert_eq!(ply.colors, None);
+        assert_eq!(ply.mesh.triangles, vec![[0, 1, 2]]);
+        assert_eq!(ply.skipped_faces, 2);
+        let ply = parse_ply(data.replace("3 0 1 3", "3 -1 1 2").as_bytes()).unwrap();
+        assert_eq!(ply.mesh.triangles, vec![[0, 1, 2]]);
+        assert_eq!(ply.skipped_faces, 2);
+        // Elements without properties are skipped whatever their count.
+        let junk = data.replace("element face", "element junk 18000000000000000000\r\nelement face");
+        let ply = parse_ply(junk.as_bytes()).unwrap();
+        assert_eq!(ply.mesh.triangles, vec![[0, 1, 2]]);
+    }
+
+    #[test]
+    fn test_model() {
+        let model = parse_ply_model(ASCII_PLY.as_bytes()).unwrap();
+        assert_eq!(model.parts.len(), 1);
+        assert_eq!(model.parts[0].positions.len(), 27);
+        assert_eq!(model.parts[0].color, None);
+        assert_eq!(model.warnings.len(), 1);
+        let gray = "ply\nformat ascii 1.0\nelement vertex 3\nproperty float x\nproperty float y\nproperty float z\n\
+                    property uchar red\nproperty uchar green\nproperty uchar blue\nelement face 1\n\
+                    property list uchar int vertex_indices\nend_header\n\
+                    0 0 0 9 9 9\n1 0 0 9 9 9\n0 1 0 9 9 9\n3 0 1 2\n";
+        let model = parse_ply_model(gray.as_bytes()).unwrap();
+        assert_eq!(model.parts[0].color, Some([9, 9, 9, 255]));
+        assert!(model.warnings.is_empty());
+    }
+
+    #[test]
+    fn test_errors() {
+        assert!(parse_ply(b"").is_err());
+        assert!(parse_ply(b"solid stl\nend_header\n").is_err());
+        assert!(parse_ply(b"ply\nelement vertex 1\nproperty float x\nend_header\n").is_err());
+        let no_z = "ply\nformat ascii 1.0\nelement vertex 1\nproperty float x\nproperty float y\nend_header\n0 0\n";
+        assert!(parse_ply(no_z.as_bytes()).is_err());
+        let bad_type = "ply\nformat ascii 1.0\nelement vertex 1\nproperty float128 x\nend_header\n";
+        assert!(parse_ply(bad_type.as_bytes()).is_err());
+        // Truncated data.
+        let truncated = &ASCII_PLY[..ASCII_PLY.len() - 8];
+        assert!(parse_ply(truncated.as_bytes()).is_err());
+        let binary = binary_ply(false);
+        assert!(parse_ply(&binary[..binary.len() - 1]).is_err());
+        // Fractional index.
+        let fractional = ASCII_PLY.replace("3 0 1 4", "3 0 1.5 4");
+        assert!(parse_ply(fractional.as_bytes()).is_err());
+    }
+
+    #[test]
+    fn test_wasm_wrapper() {
+        let model = load_ply(ASCII_PLY.as_bytes()).unwrap();
+        assert_eq!(model.vertices().len(), 15);
+        assert_eq!(model.indices().len(), 9);
+        assert_eq!(model.colors().unwrap().len(), 15);
+        assert_eq!(model.positions().len(), 27);
+        assert_eq!(model.skipped_faces(), 0);
+
+        assert!(load_ply(b"not a ply").is_err());
+    }
+}
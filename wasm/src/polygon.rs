@@ -0,0 +1,431 @@
+use crate::vector2::Vector2;
+
+/// Twice the signed area of the polygon is the sum of cross products, the result is positive for counter-clockwise
+/// polygons.
+pub fn signed_area(poly: &[Vector2]) -> f32 {
+    if poly.len() < 3 {
+        return 0.0;
+    }
+    let base = poly[0];
+    let mut area = 0.0;
+    for w in poly[1..].windows(2) {
+        area += (w[0] - base).cross(w[1] - base);
+    }
+    0.5 * area
+}
+
+/// Even-odd point in polygon test. Points exactly on the boundary may be classified either way.
+pub fn point_in_polygon(p: Vector2, poly: &[Vector2]) -> bool {
+    let mut inside = false;
+    let mut j = poly.len().wrapping_sub(1);
+    for i in 0..poly.len() {
+        let a = poly[i];
+        let b = poly[j];
+        if (a.y > p.y) != (b.y > p.y) && p.x < a.x + (p.y - a.y) * (b.x - a.x) / (b.y - a.y) {
+            inside = !inside;
+        }
+        j = i;
+    }
+    inside
+}
+
+/// Computes the convex hull of the points using the monotone chain algorithm. Returns the hull in counter-clockwise
+/// order without collinear points.
+pub fn convex_hull_2d(points: &[Vector2]) -> Vec<Vector2> {
+    let mut sorted = points.to_vec();
+    sorted.sort_by(|a, b| a.x.total_cmp(&b.x).then(a.y.total_cmp(&b.y)));
+    sorted.dedup();
+    if sorted.len() < 3 {
+        return sorted;
+    }
+
+    let mut hull: Vec<Vector2> = Vec::with_capacity(sorted.len() + 1);
+    // Lower hull, then upper hull.
+    for pass in 0..2 {
+        let start = hull.len();
+        for &p in &sorted {
+            while hull.len() >= start + 2 {
+                let a = hull[hull.len() - 2];
+                let b = hull[hull.len() - 1];
+                if (b - a).cross(p - b) > 0.0 {
+                    break;
+                }
+                hull.pop();
+            }
+            hull.push(p);
+        }
+        // The last point is the first point of the other chain.
+        hull.pop();
+        if pass == 0 {
+            sorted.reverse();
+        }
+    }
+    hull
+}
+
+/// An outer polygon with its holes, given as indices into the list of polygons passed to nest_polygons.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct NestedPolygon {
+    pub outer: usize,
+    pub holes: Vec<usize>,
+}
+
+/// Returns the nesting depth of each polygon: the number of other polygons containing it. Polygons with even depth are
+/// outer boundaries, polygons with odd depth are holes (even-odd rule). The polygons are assumed not to intersect each
+/// other, so testing a single vertex is enough.
+pub fn nesting_depths(polys: &[Vec<Vector2>]) -> Vec<usize> {
+    let mut depths = vec![0; polys.len()];
+    for (i, poly) in polys.iter().enumerate() {
+        let Some(&p) = poly.first() else {
+            continue;
+        };
+        depths[i] = polys
+            .iter()
+            .enumerate()
+            .filter(|&(j, other)| j != i && point_in_polygon(p, other))
+            .count();
+    }
+    depths
+}
+
+/// Groups polygons into outer boundaries with holes using the even-odd rule. Each hole is assigned to the smallest
+/// polygon directly containing it. Orientation of the input polygons is ignored.
+pub fn nest_polygons(polys: &[Vec<Vector2>]) -> Vec<NestedPolygon> {
+    let depths = nesting_depths(polys);
+    let areas: Vec<f32> = polys.iter().map(|p| signed_area(p).abs()).collect();
+
+    let mut result = vec![];
+    let mut outer_to_result = vec![usize::MAX; polys.len()];
+    for (i, &depth) in depths.iter().enumerate() {
+        if depth % 2 == 0 {
+            outer_to_result[i] = result.len();
+            result.push(NestedPolygon { outer: i, holes: vec![] });
+        }
+    }
+    for (i, &depth) in depths.iter().enumerate() {
+        if depth % 2 == 0 {
+            continue;
+        }
+        let p = polys[i][0];
+        let parent = (0..polys.len())
+            .filter(|&j| depths[j] + 1 == depth && point_in_polygon(p, &polys[j]))
+            .min_by(|&a, &b| areas[a].total_cmp(&areas[b]));
+        if let Some(parent) = parent {
+            result[outer_to_result[parent]].holes.push(i);
+        }
+    }
+    result
+}
+
+// Returns true if b is inside triangle p0-p1-p2 or on its boundary. The triangle must be counter-clockwise.
+fn point_in_triangle(p0: Vector2, p1: Vector2, p2: Vector2, b: Vector2) -> bool {
+    (p1 - p0).cross(b - p0) >= 0.0 && (p2 - p1).cross(b - p1) >= 0.0 && (p0 - p2).cross(b - p2) >= 0.0
+}
+
+// Returns true if the direction from ring[i] to m lies inside the polygon interior near ring[i]. The ring must be
+// counter-clockwise.
+fn locally_inside(points: &[Vector2], ring: &[usize], i: usize, m: Vector2) -> bool {
+    let n = ring.len();
+    let prev = points[ring[(i + n - 1) % n]];
+    let cur = points[ring[i]];
+    let next = points[ring[(i + 1) % n]];
+    if (cur - prev).cross(next - cur) >= 0.0 {
+        // Convex vertex: the interior is the wedge from next to prev.
+        (next - cur).cross(m - cur) >= 0.0 && (m - cur).cross(prev - cur) >= 0.0
+    } else {
+        // Reflex vertex: the interior is everything except the wedge from prev to next.
+        !((prev - cur).cross(m - cur) > 0.0 && (m - cur).cross(next - cur) > 0.0)
+    }
+}
+
+// Connects the hole to the ring with a pair of coincident edges, see "Triangulation by Ear Clipping" by David Eberly.
+// The ring must be counter-clockwise and the hole clockwise. Returns false if the hole is not inside the ring.
+fn bridge_hole(points: &[Vector2], ring: &mut Vec<usize>, hole: &[usize]) -> bool {
+    let Some((m_idx, &m_id)) = hole
+        .iter()
+        .enumerate()
+        .max_by(|(_, a), (_, b)| points[**a].x.total_cmp(&points[**b].x))
+    else {
+        return false;
+    };
+    let m = points[m_id];
+
+    // Cast the ray from m in +X direction and find the nearest edge.
+    let n = ring.len();
+    let mut best_x = f32::INFINITY;
+    let mut best_vertex = usize::MAX;
+    for i in 0..n {
+        let a = points[ring[i]];
+        let b = points[ring[(i + 1) % n]];
+        if a.y == b.y || (a.y - m.y) * (b.y - m.y) > 0.0 {
+            continue;
+        }
+        let x = a.x + (m.y - a.y) * (b.x - a.x) / (b.y - a.y);
+        if x >= m.x && x < best_x {
+            best_x = x;
+            // Take the endpoint with the larger x, it is the visible vertex unless some other vertex is inside the
+            // triangle m-i-p.
+            best_vertex = if a.x > b.x { i } else { (i + 1) % n };
+        }
+    }
+    if best_vertex == usize::MAX {
+        return false;
+    }
+
+    let hit = Vector2::new(best_x, m.y);
+    let p = points[ring[best_vertex]];
+    let (t0, t1, t2) = if p.y < m.y { (m, p, hit) } else { (m, hit, p) };
+    let mut best_tan = f32::INFINITY;
+    let mut best_dist = f32::INFINITY;
+    if p != hit {
+        for i in 0..n {
+            let r = points[ring[i]];
+            if r == p || r.x < m.x || !point_in_triangle(t0, t1, t2, r) || !locally_inside(points, ring, i, m) {
+                continue;
+            }
+            let tan = (r.y - m.y).abs() / (r.x - m.x);
+            let dist = (r - m).length();
+            if tan < best_tan || (tan == best_tan && dist < best_dist) {
+                best_tan = tan;
+                best_dist = dist;
+                best_vertex = i;
+            }
+        }
+    }
+
+    // ring[..=best] + hole[m..] + hole[..=m] + ring[best..]
+    let mut merged = Vec::with_capacity(ring.len() + hole.len() + 2);
+    merged.extend_from_slice(&ring[..=best_vertex]);
+    merged.extend_from_slice(&hole[m_idx..]);
+    merged.extend_from_slice(&hole[..=m_idx]);
+    merged.extend_from_slice(&ring[best_vertex..]);
+    *ring = merged;
+    true
+}
+
+fn is_ear(points: &[Vector2], ring: &[usize], prev: &[usize], next: &[usize], i: usize) -> bool {
+    let a = points[ring[prev[i]]];
+    let b = points[ring[i]];
+    let c = points[ring[next[i]]];
+    if (b - a).cross(c - b) <= 0.0 {
+        return false;
+    }
+    let mut j = next[next[i]];
+    while j != prev[i] {
+        let p = points[ring[j]];
+        if p != a && p != b && p != c && point_in_triangle(a, b, c, p) {
+            return false;
+        }
+        j = next[j];
+    }
+    true
+}
+
+/// Triangulates a polygon with holes using ear clipping. `outer` and `holes` are rings of indices into `points`. The
+/// orientation of the rings is normalized internally. Returns triangles as triples of indices into `points`, oriented
+/// counter-clockwise. The complexity is quadratic in the total number of vertices.
+pub fn triangulate(points: &[Vector2], outer: &[usize], holes: &[Vec<usize>]) -> Vec<[usize; 3]> {
+    let ring_area = |ring: &[usize]| signed_area(&ring.iter().map(|&i| points[i]).collect::<Vec<_>>());
+
+    let mut ring = outer.to_vec();
+    if ring_area(&ring) < 0.0 {
+        ring.reverse();
+    }
+    // Bridging holes from right to left guarantees that the bridges do not cross holes which are not yet merged.
+    let mut sorted_holes: Vec<Vec<usize>> = holes.iter().filter(|h| h.len() >= 3).cloned().collect();
+    for hole in &mut sorted_holes {
+        if ring_area(hole) > 0.0 {
+            hole.reverse();
+        }
+    }
+    let max_x = |hole: &Vec<usize>| {
+        hole.iter()
+            .map(|&i| points[i].x)
+            .fold(f32::NEG_INFINITY, f32::max)
+    };
+    sorted_holes.sort_by(|a, b| max_x(b).total_cmp(&max_x(a)));
+    for hole in &sorted_holes {
+        bridge_hole(points, &mut ring, hole);
+    }
+
+    let n = ring.len();
+    let mut result = Vec::with_capacity(n.saturating_sub(2));
+    if n < 3 {
+        return result;
+    }
+    let mut prev: Vec<usize> = (0..n).map(|i| (i + n - 1) % n).collect();
+    let mut next: Vec<usize> = (0..n).map(|i| (i + 1) % n).collect();
+    let mut remaining = n;
+    let mut cur = 0;
+    // Number of vertices checked since the last clipped ear, used to detect that no ear can be found.
+    let mut failed = 0;
+    while remaining > 3 {
+        let ear = is_ear(points, &ring, &prev, &next, cur);
+        if !ear && failed < remaining {
+            failed += 1;
+            cur = next[cur];
+            continue;
+        }
+        // If no ear can be found (degenerate or self-intersecting input), we clip the vertex anyway to guarantee
+        // progress, dropping it if it is collinear with its neighbors.
+        let (a, b, c) = (ring[prev[cur]], ring[cur], ring[next[cur]]);
+        if ear || (points[b] - points[a]).cross(points[c] - points[b]) > 0.0 {
+            result.push([a, b, c]);
+        }
+        next[prev[cur]] = next[cur];
+        prev[next[cur]] = prev[cur];
+        cur = next[cur];
+        remaining -= 1;
+        failed = 0;
+    }
+    let (a, b, c) = (ring[prev[cur]], ring[cur], ring[next[cur]]);
+    if (points[b] - points[a]).cross(points[c] - points[b]) > 0.0 {
+        result.push([a, b, c]);
+    }
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn square(x0: f32, y0: f32, size: f32) -> Vec<Vector2> {
+        vec![
+            Vector2::new(x0, y0),
+            Vector2::new(x0 + size, y0),
+            Vector2::new(x0 + size, y0 + size),
+            Vector2::new(x0, y0 + size),
+        ]
+    }
+
+    fn triangles_area(points: &[Vector2], tris: &[[usize; 3]]) -> f32 {
+        let mut area = 0.0;
+        for t in tris {
+            let tri_area = signed_area(&[points[t[0]], points[t[1]], points[t[2]]]);
+            assert!(tri_area > 0.0, "triangle {:?} is not counter-clockwise", t);
+            area += tri_area;
+        }
+        area
+    }
+
+    #[test]
+    fn test_signed_area() {
+        let mut sq = square(0.0, 0.0, 2.0);
+        assert_eq!(signed_area(&sq), 4.0);
+        sq.reverse();
+        assert_eq!(signed_area(&sq), -4.0);
+        assert_eq!(signed_area(&sq[..2]), 0.0);
+    }
+
+    #[test]
+    fn test_point_in_polygon() {
+        let sq = square(0.0, 0.0, 2.0);
+        assert!(point_in_polygon(Vector2::new(1.0, 1.0), &sq));
+        assert!(!point_in_polygon(Vector2::new(3.0, 1.0), &sq));
+        assert!(!point_in_polygon(Vector2::new(1.0, -1.0), &sq));
+        assert!(!point_in_polygon(Vector2::new(1.0, 1.0), &[]));
+    }
+
+    #[test]
+    fn test_convex_hull_2d() {
+        let mut points = square(0.0, 0.0, 2.0);
+        points.push(Vector2::new(1.0, 1.0));
+        points.push(Vector2::new(1.0, 0.0));
+        points.push(Vector2::new(0.0, 0.0));
+        let hull = convex_hull_2d(&points);
+        assert_eq!(
+            hull,
+            vec![Vector2::new(0.0, 0.0), Vector2::new(2.0, 0.0), Vector2::new(2.0, 2.0), Vector2::new(0.0, 2.0)]
+        );
+
+        let line = [Vector2::new(0.0, 0.0), Vector2::new(1.0, 1.0), Vector2::new(2.0, 2.0)];
+        assert_eq!(convex_hull_2d(&line), vec![Vector2::new(0.0, 0.0), Vector2::new(2.0, 2.0)]);
+        assert_eq!(convex_hull_2d(&line[..1]), vec![Vector2::new(0.0, 0.0)]);
+        assert!(convex_hull_2d(&[]).is_empty());
+    }
+
+    #[test]
+    fn test_nesting() {
+        // Outer square with a hole, an island inside the hole and a separate square.
+        let polys = vec![square(0.0, 0.0, 10.0), square(1.0, 1.0, 8.0), square(2.0, 2.0, 1.0), square(20.0, 0.0, 1.0)];
+        assert_eq!(nesting_depths(&polys), vec![0, 1, 2, 0]);
+        let nested = nest_polygons(&polys);
+        assert_eq!(
+            nested,
+            vec![
+                NestedPolygon { outer: 0, holes: vec![1] },
+                NestedPolygon { outer: 2, holes: vec![] },
+                NestedPolygon { outer: 3, holes: vec![] },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_triangulate_convex() {
+        let points = square(0.0, 0.0, 2.0);
+        let tris = triangulate(&points, &[0, 1, 2, 3], &[]);
+        assert_eq!(tris.len(), 2);
+        assert_eq!(triangles_area(&points, &tris), 4.0);
+
+        // Clockwise input produces the same counter-clockwise triangles.
+        let tris = triangulate(&points, &[3, 2, 1, 0], &[]);
+        assert_eq!(tris.len(), 2);
+        assert_eq!(triangles_area(&points, &tris), 4.0);
+    }
+
+    #[test]
+    fn test_triangulate_concave() {
+        // An L-shape.
+        let points = vec![
+            Vector2::new(0.0, 0.0),
+            Vector2::new(2.0, 0.0),
+            Vector2::new(2.0, 1.0),
+            Vector2::new(1.0, 1.0),
+            Vector2::new(1.0, 2.0),
+            Vector2::new(0.0, 2.0),
+        ];
+        let tris = triangulate(&points, &[0, 1, 2, 3, 4, 5], &[]);
+        assert_eq!(tris.len(), 4);
+        assert_eq!(triangles_area(&points, &tris), 3.0);
+    }
+
+    #[test]
+    fn test_triangulate_holes() {
+        let mut points = square(0.0, 0.0, 10.0);
+        points.extend(square(1.0, 1.0, 2.0));
+        points.extend(square(6.0, 6.0, 3.0));
+        points.extend(square(6.0, 1.0, 1.0));
+        let holes = vec![vec![4, 5, 6, 7], vec![8, 9, 10, 11], vec![15, 14, 13, 12]];
+        let tris = triangulate(&points, &[0, 1, 2, 3], &holes);
+        // n + 2 * h - 2 triangles for n vertices and h holes.
+        assert_eq!(tris.len(), 16 + 2 * 3 - 2);
+        assert!((triangles_area(&points, &tris) - (100.0 - 4.0 - 9.0 - 1.0)).abs() < 1e-4);
+    }
+
+    #[test]
+    fn test_triangulate_circle_with_hole() {
+        let mut points = vec![];
+        let count = 64;
+        for i in 0..count {
+            let angle = 2.0 * std::f32::consts::PI * i as f32 / count as f32;
+            points.push(Vector2::new(angle.cos() * 10.0, angle.sin() * 10.0));
+        }
+        for i in 0..count {
+            let angle = 2.0 * std::f32::consts::PI * i as f32 / count as f32;
+            points.push(Vector2::new(angle.cos() * 5.0 + 1.0, angle.sin() * 5.0));
+        }
+        let outer: Vec<usize> = (0..count).collect();
+        let hole: Vec<usize> = (count..2 * count).collect();
+        let tris = triangulate(&points, &outer, &[hole]);
+        assert_eq!(tris.len(), 2 * count);
+        let expected = signed_area(&points[..count]) - signed_area(&points[count..]);
+        assert!((triangles_area(&points, &tris) - expected).abs() < 1e-2);
+    }
+
+    #[test]
+    fn test_triangulate_degenerate() {
+        let points = vec![Vector2::new(0.0, 0.0), Vector2::new(1.0, 0.0), Vector2::new(2.0, 0.0)];
+        assert!(triangulate(&points, &[0, 1, 2], &[]).is_empty());
+        assert!(triangulate(&points, &[0, 1], &[]).is_empty());
+    }
+}
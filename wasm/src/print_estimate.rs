@@ -0,0 +1,415 @@
+use wasm_bindgen::prelude::*;
+
+use crate::error::{Error, checked_item};
+use crate::mesh_stats::compute_mesh_stats;
+use crate::slicer::{LayerHeights, Slicer};
+use crate::split_geometry::{enclosing_parts, split_disjoint_geometry};
+
+// Thinner layers are not printable, and the slicer would produce millions of them.
+const MIN_LAYER_HEIGHT: f32 = 0.001;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct PrintSettings {
+    /// Layer height in mm, at least MIN_LAYER_HEIGHT.
+    pub layer_height: f32,
+    /// Infill density in percents, 0 to 100.
+    pub infill_percent: f32,
+    pub wall_count: u32,
+    /// The extrusion width is assumed to be equal to the nozzle diameter.
+    pub nozzle_diameter: f32,
+    pub top_bottom_layers: u32,
+    pub filament_diameter: f32,
+    /// Filament density in g/cm^3.
+    pub filament_density: f32,
+    /// Print speeds in mm/s.
+    pub wall_speed: f32,
+    pub infill_speed: f32,
+    pub top_bottom_speed: f32,
+}
+
+impl PrintSettings {
+    /// Creates settings with 1.75mm PLA filament, 4 top/bottom layers and typical print speeds.
+    pub fn new(layer_height: f32, infill_percent: f32, wall_count: u32, nozzle_diameter: f32) -> Self {
+        Self {
+            layer_height,
+            infill_percent,
+            wall_count,
+            nozzle_diameter,
+            top_bottom_layers: 4,
+            filament_diameter: 1.75,
+            filament_density: 1.24,
+            wall_speed: 40.0,
+            infill_speed: 80.0,
+            top_bottom_speed: 40.0,
+        }
+    }
+
+    fn is_valid(&self) -> bool {
+        let positive = |v: f32| v.is_finite() && v > 0.0;
+        positive(self.layer_height)
+            && self.layer_height >= MIN_LAYER_HEIGHT
+            && positive(self.nozzle_diameter)
+            && positive(self.filament_diameter)
+            && positive(self.wall_speed)
+            && positive(self.infill_speed)
+            && positive(self.top_bottom_speed)
+            && (0.0..=100.0).contains(&self.infill_percent)
+            && self.filament_density.is_finite()
+            && self.filament_density >= 0.0
+    }
+}
+
+/// Material and time needed for one kind of extrusion.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct Extrusion {
+    /// Extruded volume in mm^3.
+    pub volume: f32,
+    /// Filament length in mm.
+    pub filament_length: f32,
+    /// Filament mass in grams.
+    pub filament_mass: f32,
+    /// Print time in seconds, not including travel moves and acceleration.
+    pub time: f32,
+}
+
+impl Extrusion {
+    fn new(volume: f64, speed: f32, settings: &PrintSettings) -> Self {
+        let filament_radius = settings.filament_diameter as f64 / 2.0;
+        let filament_length = volume / (std::f64::consts::PI * filament_radius * filament_radius);
+        // The extruded line has cross-section of extrusion width times layer height.
+        let path_length = volume / (settings.nozzle_diameter as f64 * settings.layer_height as f64);
+        Self {
+            volume: volume as f32,
+            filament_length: filament_length as f32,
+            filament_mass: (volume / 1000.0 * settings.filament_density as f64) as f32,
+            time: (path_length / speed as f64) as f32,
+        }
+    }
+
+    fn add(self, other: Self) -> Self {
+        Self {
+            volume: self.volume + other.volume,
+            filament_length: self.filament_length + other.filament_length,
+            filament_mass: self.filament_mass + other.filament_mass,
+            time: self.time + other.time,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct PartEstimate {
+    pub part_idx: usize,
+    pub volume: f32,
+    pub surface_area: f32,
+    pub layer_count: usize,
+    pub walls: Extrusion,
+    pub infill: Extrusion,
+    pub top_bottom: Extrusion,
+}
+
+impl PartEstimate {
+    pub fn total(&self) -> Extrusion {
+        self.walls.add(self.infill).add(self.top_bottom)
+    }
+}
+
+/// Estimates the filament usage and print time of a single part. The part is sliced with uniform layers, then for each
+/// layer:
+///  * walls cover the perimeter times wall_count times extrusion width, but not more than the layer area;
+///  * top/bottom skin covers the part of the layer which is not covered by the layers up to top_bottom_layers above or
+///    below it (the area difference), excluding the walls.
+///
+/// The rest of the part volume is filled with infill_percent density. The estimate is deterministic: it depends only on
+/// the geometry and the settings. Fails if the layer height is too small for the part, see Slicer::layer_heights.
+pub fn estimate_part(part_idx: usize, pos: &[f32], settings: &PrintSettings) -> Result<PartEstimate, Error> {
+    let stats = compute_mesh_stats(pos);
+    let layers = Slicer::new(pos).slice(LayerHeights::Uniform { layer_height: settings.layer_height })?;
+    let h = settings.layer_height as f64;
+    let width = settings.nozzle_diameter as f64;
+
+    let mut areas = vec![];
+    let mut wall_areas = vec![];
+    for layer in &layers {
+        let mut area = 0.0f64;
+        let mut perimeter = 0.0f64;
+        for c in layer.contours.iter().filter(|c| c.contour.closed) {
+            let a = c.contour.area.abs() as f64;
+            area += if c.is_hole { -a } else { a };
+            perimeter += c.contour.perimeter as f64;
+        }
+        let area = area.max(0.0);
+        areas.push(area);
+        wall_areas.push((perimeter * settings.wall_count as f64 * width).min(area));
+    }
+
+    let n = settings.top_bottom_layers as usize;
+    let area_at = |i: isize| if i < 0 || i as usize >= areas.len() { 0.0 } else { areas[i as usize] };
+    let mut wall_volume = 0.0f64;
+    let mut skin_volume = 0.0f64;
+    for i in 0..areas.len() {
+        wall_volume += wall_areas[i] * h;
+        let mut exposed = 0.0f64;
+        for k in 1..=n as isize {
+            let i = i as isize;
+            exposed = exposed
+                .max(areas[i as usize] - area_at(i - k))
+                .max(areas[i as usize] - area_at(i + k));
+        }
+        skin_volume += exposed.min(areas[i] - wall_areas[i]) * h;
+    }
+    let volume = stats.volume.abs() as f64;
+    let infill_volume = (volume - wall_volume - skin_volume).max(0.0) * settings.infill_percent as f64 / 100.0;
+
+    Ok(PartEstimate {
+        part_idx,
+        volume: stats.volume.abs(),
+        surface_area: stats.surface_area,
+        layer_count: layers.len(),
+        walls: Extrusion::new(wall_volume, settings.wall_speed, settings),
+        infill: Extrusion::new(infill_volume, settings.infill_speed, settings),
+        top_bottom: Extrusion::new(skin_volume, settings.top_bottom_speed, settings),
+    })
+}
+
+/// Splits the geometry into parts and estimates each of them, see estimate_part. `pos` has the same layout as in
+/// split_disjoint_geometry. Cavities of hollow parts are estimated together with the part enclosing them (see
+/// enclosing_parts), so there are no estimates for them.
+pub fn estimate_print(pos: &[f32], settings: &PrintSettings) -> Result<Vec<PartEstimate>, Error> {
+    if !settings.is_valid() {
+        return Err(Error::InvalidInput("invalid print settings".to_string()));
+    }
+    let parts = split_disjoint_geometry(pos)?;
+    let owners = enclosing_parts(&parts);
+    (0..parts.len())
+        .filter(|&part_idx| owners[part_idx] == part_idx)
+        .map(|part_idx| {
+            let body: Vec<f32> = (0..parts.len())
+                .filter(|&p| owners[p] == part_idx)
+                .flat_map(|p| parts[p].iter().copied())
+                .collect();
+            estimate_part(part_idx, &body, settings)
+        })
+        .collect()
+}
+
+/// Kinds of extrusions in the print estimate.
+#[wasm_bindgen]
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ExtrusionKind {
+    Walls = 0,
+    Infill = 1,
+    TopBottom = 2,
+}
+
+/// Result of estimate_print_time for JS.
+#[wasm_bindgen]
+pub struct PrintEstimate {
+    parts: Vec<PartEstimate>,
+}
+
+impl PrintEstimate {
+    fn part(&self, part: usize) -> Result<&PartEstimate, Error> {
+        checked_item(&self.parts, part, "part")
+    }
+
+    fn extrusion(&self, part: usize, kind: ExtrusionKind) -> Result<Extrusion, Error> {
+        let part = self.part(part)?;
+        Ok(match kind {
+            ExtrusionKind::Walls => part.walls,
+            ExtrusionKind::Infill => part.infill,
+            ExtrusionKind::TopBottom => part.top_bottom,
+        })
+    }
+
+    fn total(&self) -> Extrusion {
+        self.parts
+            .iter()
+            .fold(Extrusion::default(), |acc, p| acc.add(p.total()))
+    }
+}
+
+#[wasm_bindgen]
+impl PrintEstimate {
+    #[wasm_bindgen(getter)]
+    pub fn part_count(&self) -> usize {
+        self.parts.len()
+    }
+
+    pub fn layer_count(&self, part: usize) -> Result<usize, Error> {
+        Ok(self.part(part)?.layer_count)
+    }
+
+    pub fn filament_length(&self, part: usize, kind: ExtrusionKind) -> Result<f32, Error> {
+        Ok(self.extrusion(part, kind)?.filament_length)
+    }
+
+    pub fn filament_mass(&self, part: usize, kind: ExtrusionKind) -> Result<f32, Error> {
+        Ok(self.extrusion(part, kind)?.filament_mass)
+    }
+
+    pub fn print_time(&self, part: usize, kind: ExtrusionKind) -> Result<f32, Error> {
+        Ok(self.extrusion(part, kind)?.time)
+    }
+
+    #[wasm_bindgen(getter)]
+    pub fn total_filament_length(&self) -> f32 {
+        self.total().filament_length
+    }
+
+    #[wasm_bindgen(getter)]
+    pub fn total_filament_mass(&self) -> f32 {
+        self.total().filament_mass
+    }
+
+    #[wasm_bindgen(getter)]
+    pub fn total_print_time(&self) -> f32 {
+        self.total().time
+    }
+}
+
+/// Estimates the print with the default filament and speeds, see PrintSettings::new and estimate_print.
+#[wasm_bindgen]
+pub fn estimate_print_time(
+    pos: &[f32],
+    layer_height: f32,
+    infill_percent: f32,
+    wall_count: u32,
+    nozzle_diameter: f32,
+) -> Result<PrintEstimate, Error> {
+    let settings = PrintSettings::new(layer_height, infill_percent, wall_count, nozzle_diameter);
+    Ok(PrintEstimate { parts: estimate_print(pos, &settings)? })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::plane_section::tests::create_box_geometry;
+    use crate::vector3::Vector3;
+
+    fn assert_close(actual: f32, expected: f32) {
+        assert!((actual - expected).abs() <= 1e-3 * expected.abs().max(1.0), "{} != {}", actual, expected);
+    }
+
+    #[test]
+    fn test_invalid_settings() {
+        let cube = create_box_geometry(Vector3::ZERO, Vector3::new(1.0, 1.0, 1.0));
+        assert!(estimate_print(&cube, &PrintSettings::new(0.0, 20.0, 2, 0.4)).is_err());
+        let err = estimate_print(&cube, &PrintSettings::new(1e-20, 20.0, 2, 0.4)).unwrap_err();
+        assert_eq!(err.code(), "INVALID_INPUT");
+        assert!(estimate_print_time(&cube, 1e-20, 20.0, 2, 0.4).is_err());
+        // The slicer limits the layer count of tall parts.
+        let tower = create_box_geometry(Vector3::ZERO, Vector3::new(1.0, 1.0, 10000.0));
+        assert!(estimate_print(&tower, &PrintSettings::new(0.001, 20.0, 2, 0.4)).is_err());
+        assert!(estimate_print(&cube, &PrintSettings::new(0.2, 120.0, 2, 0.4)).is_err());
+        assert!(estimate_print(&cube, &PrintSettings::new(0.2, 20.0, 2, f32::NAN)).is_err());
+        assert!(estimate_print(&cube[..10], &PrintSettings::new(0.2, 20.0, 2, 0.4)).is_err());
+        assert!(
+            estimate_print(&[], &PrintSettings::new(0.2, 20.0, 2, 0.4))
+                .unwrap()
+                .is_empty()
+        );
+    }
+
+    #[test]
+    fn test_box_fixture() {
+        // 20x20x10 box, 50 layers with 80mm perimeter and 400mm^2 area.
+        let cube = create_box_geometry(Vector3::ZERO, Vector3::new(20.0, 20.0, 10.0));
+        let estimates = estimate_print(&cube, &PrintSettings::new(0.2, 20.0, 2, 0.4)).unwrap();
+        assert_eq!(estimates.len(), 1);
+        let e = &estimates[0];
+        assert_eq!(e.layer_count, 50);
+        assert_close(e.volume, 4000.0);
+        assert_close(e.surface_area, 1600.0);
+        // Walls: 80 * 2 * 0.4 = 64mm^2 per layer.
+        assert_close(e.walls.volume, 640.0);
+        // Skin: 4 bottom and 4 top layers of (400 - 64)mm^2.
+        assert_close(e.top_bottom.volume, 537.6);
+        // Infill: 20% of the rest.
+        assert_close(e.infill.volume, 564.48);
+
+        let total = e.total();
+        assert_close(total.volume, 1742.08);
+        // 1742.08 / (pi * 0.875^2)
+        assert_close(total.filament_length, 724.27);
+        assert_close(total.filament_mass, 2.1602);
+        // Path lengths are volume / (0.4 * 0.2), speeds are 40, 80 and 40mm/s.
+        assert_close(e.walls.time, 200.0);
+        assert_close(e.infill.time, 88.2);
+        assert_close(e.top_bottom.time, 168.0);
+    }
+
+    #[test]
+    fn test_stepped_part() {
+        // A 20x20x2 base with a 10x10x2 tower: the top of the base around the tower is a skin too.
+        let base = create_box_geometry(Vector3::ZERO, Vector3::new(20.0, 20.0, 2.0));
+        let tower = create_box_geometry(Vector3::new(5.0, 5.0, 2.0), Vector3::new(15.0, 15.0, 4.0));
+        let settings = PrintSettings { top_bottom_layers: 2, ..PrintSettings::new(0.5, 0.0, 1, 0.5) };
+        let base_estimate = estimate_part(0, &base, &settings).unwrap();
+        let mut stepped = base.clone();
+        stepped.extend_from_slice(&tower);
+        let stepped_estimate = estimate_part(0, &stepped, &settings).unwrap();
+        assert_eq!(stepped_estimate.layer_count, 8);
+        // Zero infill: everything is either walls or skin.
+        assert_eq!(stepped_estimate.infill.volume, 0.0);
+        // The two top layers of the base have an exposed ring of 300mm^2, the bottom layers of the tower are not
+        // exposed.
+        let base_layer = 400.0 - 80.0 * 0.5;
+        let tower_layer = 100.0 - 40.0 * 0.5;
+        assert_close(base_estimate.top_bottom.volume, 4.0 * base_layer * 0.5);
+        assert_close(stepped_estimate.top_bottom.volume, (2.0 * base_layer + 2.0 * 300.0 + 2.0 * tower_layer) * 0.5);
+    }
+
+    #[test]
+    fn test_hollow_part() {
+        // A 10mm cube with a 6mm cavity: the cavity shell is a separate part for split_disjoint_geometry, but its
+        // volume is subtracted from the cube.
+        let mut cube = create_box_geometry(Vector3::ZERO, Vector3::new(10.0, 10.0, 10.0));
+        let cavity = create_box_geometry(Vector3::new(2.0, 2.0, 2.0), Vector3::new(8.0, 8.0, 8.0));
+        cube.extend(
+            cavity
+                .chunks_exact(9)
+                .flat_map(|t| [&t[0..3], &t[6..9], &t[3..6]].concat()),
+        );
+        let estimates = estimate_print(&cube, &PrintSettings::new(0.2, 20.0, 2, 0.4)).unwrap();
+        assert_eq!(estimates.len(), 1);
+        assert_eq!(estimates[0].part_idx, 0);
+        assert_close(estimates[0].volume, 784.0);
+        assert_close(estimates[0].surface_area, 816.0);
+        let solid = estimate_part(0, &cube[..12 * 9], &PrintSettings::new(0.2, 20.0, 2, 0.4)).unwrap();
+        // The cavity adds walls around it.
+        assert!(estimates[0].walls.volume > solid.walls.volume);
+    }
+
+    #[test]
+    fn test_infill_scales() {
+        let cube = create_box_geometry(Vector3::ZERO, Vector3::new(30.0, 30.0, 30.0));
+        let sparse = estimate_print(&cube, &PrintSettings::new(0.2, 10.0, 2, 0.4)).unwrap();
+        let dense = estimate_print(&cube, &PrintSettings::new(0.2, 40.0, 2, 0.4)).unwrap();
+        assert_eq!(sparse[0].walls, dense[0].walls);
+        assert_eq!(sparse[0].top_bottom, dense[0].top_bottom);
+        assert_close(dense[0].infill.volume, 4.0 * sparse[0].infill.volume);
+        // Determinism.
+        assert_eq!(sparse, estimate_print(&cube, &PrintSettings::new(0.2, 10.0, 2, 0.4)).unwrap());
+    }
+
+    #[test]
+    fn test_wasm_wrapper() {
+        let mut pos = create_box_geometry(Vector3::ZERO, Vector3::new(20.0, 20.0, 10.0));
+        pos.extend(create_box_geometry(Vector3::new(30.0, 0.0, 0.0), Vector3::new(50.0, 20.0, 10.0)));
+        let estimate = estimate_print_time(&pos, 0.2, 20.0, 2, 0.4).unwrap();
+        assert_eq!(estimate.part_count(), 2);
+        assert_eq!(estimate.layer_count(1).unwrap(), 50);
+        assert_close(estimate.print_time(0, ExtrusionKind::Walls).unwrap(), 200.0);
+        assert_close(estimate.total_print_time(), 2.0 * (200.0 + 88.2 + 168.0));
+        assert_close(estimate.total_filament_length(), 2.0 * 724.27);
+        assert_close(
+            estimate.total_filament_mass(),
+            2.0 * (estimate.filament_mass(0, ExtrusionKind::Walls).unwrap()
+                + estimate.filament_mass(0, ExtrusionKind::Infill).unwrap()
+                + estimate.filament_mass(0, ExtrusionKind::TopBottom).unwrap()),
+        );
+        assert_eq!(estimate.layer_count(2).unwrap_err().code(), "INVALID_INPUT");
+        assert!(estimate.print_time(2, ExtrusionKind::Infill).is_err());
+        assert!(estimate_print_time(&pos, -1.0, 20.0, 2, 0.4).is_err());
+    }
+}
@@ -0,0 +1,194 @@
+use std::cell::Cell;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+
+use wasm_bindgen::prelude::*;
+
+use crate::error::Error;
+
+// Each stage reports at most this many times, so that the callback does not slow down the operation.
+const UPDATES_PER_STAGE: usize = 256;
+
+/// Shared state of a long operation: the progress which JS can poll and the cancellation flag. An operation which
+/// sees the flag stops with the CANCELLED error and discards its partial results.
+#[wasm_bindgen]
+#[derive(Debug, Default)]
+pub struct ProgressToken {
+    cancelled: AtomicBool,
+    // Bits of the f64 fraction of the work done.
+    progress: AtomicU64,
+}
+
+#[wasm_bindgen]
+impl ProgressToken {
+    #[wasm_bindgen(constructor)]
+    pub fn new() -> ProgressToken {
+        ProgressToken::default()
+    }
+
+    /// Requests the cancellation, the operation stops at its next progress update. May be called from the progress
+    /// callback.
+    pub fn cancel(&self) {
+        self.cancelled.store(true, Ordering::Relaxed);
+    }
+
+    #[wasm_bindgen(getter)]
+    pub fn is_cancelled(&self) -> bool {
+        self.cancelled.load(Ordering::Relaxed)
+    }
+
+    /// Fraction of the work done by the last operation, from 0 to 1.
+    #[wasm_bindgen(getter)]
+    pub fn progress(&self) -> f64 {
+        f64::from_bits(self.progress.load(Ordering::Relaxed))
+    }
+
+    /// Clears the cancellation flag and the progress so that the token can be used for another operation.
+    pub fn reset(&self) {
+        self.cancelled.store(false, Ordering::Relaxed);
+        self.progress.store(0, Ordering::Relaxed);
+    }
+}
+
+/// Progress reporting of an operation, or of one of its stages. It is passed down to the functions doing the work,
+/// which call update periodically.
+#[derive(Clone)]
+pub struct Progress<'a> {
+    token: Option<&'a ProgressToken>,
+    callback: Option<&'a dyn Fn(f64)>,
+    // The fraction of the whole operation covered by this stage.
+    start: f64,
+    end: f64,
+    // Updates with less work done are skipped.
+    next_update: Cell<usize>,
+}
+
+impl<'a> Progress<'a> {
+    /// Progress which is not reported anywhere and can not be cancelled.
+    pub fn none() -> Self {
+        Self::new(None, None)
+    }
+
+    /// Reports the progress to the token and to the callback, which receives the fraction of the work done.
+    pub fn new(token: Option<&'a ProgressToken>, callback: Option<&'a dyn Fn(f64)>) -> Self {
+        Self { token, callback, start: 0.0, end: 1.0, next_update: Cell::new(0) }
+    }
+
+    /// Returns the progress of a stage covering the fractions from `start` to `end` of this one.
+    pub fn stage(&self, start: f64, end: f64) -> Self {
+        let scale = self.end - self.start;
+        Self { start: self.start + start * scale, end: self.start + end * scale, next_update: Cell::new(0), ..*self }
+    }
+
+    /// Reports that `done` out of `total` units of work are done. Returns Error::Cancelled if the cancellation was
+    /// requested. Cheap to call for every unit: only a few hundred calls per stage do the reporting.
+    pub fn update(&self, done: usize, total: usize) -> Result<(), Error> {
+        if self.token.is_none() && self.callback.is_none() || done < self.next_update.get() && done < total {
+            return Ok(());
+        }
+        let step = (total / UPDATES_PER_STAGE).max(1);
+        self.next_update.set((done / step + 1) * step);
+        let fraction = if total == 0 { 1.0 } else { done.min(total) as f64 / total as f64 };
+        let fraction = self.start + (self.end - self.start) * fraction;
+        if let Some(callback) = self.callback {
+            callback(fraction);
+        }
+        if let Some(token) = self.token {
+            token.progress.store(fraction.to_bits(), Ordering::Relaxed);
+            if token.is_cancelled() {
+                return Err(Error::Cancelled);
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Calls `f` with the progress reporting to the token and to the JS callback, if they are given.
+pub fn with_js_progress<T>(
+    token: Option<&ProgressToken>,
+    callback: Option<js_sys::Function>,
+    f: impl FnOnce(Progress) -> Result<T, Error>,
+) -> Result<T, Error> {
+    let report = |fraction: f64| {
+        if let Some(callback) = &callback {
+            // Exceptions thrown by the callback are ignored, it can use the token to stop the operation.
+            let _ = callback.call1(&JsValue::NULL, &JsValue::from_f64(fraction));
+        }
+    };
+    let report_fn: &dyn Fn(f64) = &report;
+    f(Progress::new(token, callback.is_some().then_some(report_fn)))
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use std::cell::RefCell;
+
+    use super::*;
+
+    /// Collects the reported fractions, cancels the token when the progress reaches `cancel_at`.
+    pub(crate) fn run_with_progress<T>(
+        cancel_at: f64,
+        f: impl FnOnce(Progress) -> Result<T, Error>,
+    ) -> (Result<T, Error>, Vec<f64>) {
+        let token = ProgressToken::new();
+        let fractions = RefCell::new(vec![]);
+        let callback = |fraction: f64| {
+            fractions.borrow_mut().push(fraction);
+            if fraction >= cancel_at {
+                token.cancel();
+            }
+        };
+        let result = f(Progress::new(Some(&token), Some(&callback)));
+        (result, fractions.into_inner())
+    }
+
+    /// Checks that the fractions grow from 0 to 1.
+    pub(crate) fn assert_complete(fractions: &[f64]) {
+        assert!(fractions.windows(2).all(|w| w[0] <= w[1]), "{:?}", fractions);
+        assert_eq!(fractions.first(), Some(&0.0));
+        assert_eq!(fractions.last(), Some(&1.0));
+    }
+
+    #[test]
+    fn test_stages() {
+        let (result, fractions) = run_with_progress(2.0, |progress| {
+            let first = progress.stage(0.0, 0.5);
+            for i in 0..=4 {
+                first.update(i, 4)?;
+            }
+            let last = progress.stage(0.5, 1.0).stage(0.5, 1.0);
+            for i in 0..=1000 {
+                last.update(i, 1000)?;
+            }
+            Ok(())
+        });
+        assert_eq!(result, Ok(()));
+        assert_complete(&fractions);
+        assert_eq!(fractions[..5], [0.0, 0.125, 0.25, 0.375, 0.5]);
+        assert_eq!(fractions[5], 0.75);
+        // 1000 units are reported every 3 units.
+        assert_eq!(fractions.len(), 5 + 1000 / 3 + 2);
+    }
+
+    #[test]
+    fn test_cancel() {
+        let (result, fractions) = run_with_progress(0.5, |progress| {
+            for i in 0..=100 {
+                progress.update(i, 100)?;
+            }
+            Ok(())
+        });
+        assert_eq!(result, Err(Error::Cancelled));
+        assert_eq!(fractions.last(), Some(&0.5));
+        assert_eq!(Error::Cancelled.code(), "CANCELLED");
+
+        let token = ProgressToken::new();
+        token.cancel();
+        assert_eq!(Progress::new(Some(&token), None).update(0, 10), Err(Error::Cancelled));
+        assert_eq!(token.progress(), 0.0);
+        token.reset();
+        assert!(!token.is_cancelled());
+        assert_eq!(Progress::new(Some(&token), None).update(10, 10), Ok(()));
+        assert_eq!(token.progress(), 1.0);
+        assert_eq!(Progress::none().update(0, 0), Ok(()));
+    }
+}
@@ -0,0 +1,221 @@
+use wasm_bindgen::prelude::*;
+
+use crate::error::{Error, checked_item};
+use crate::plane_section::{Contour, Plane, section_triangles};
+use crate::polygon::{nest_polygons, triangulate};
+use crate::split_geometry::{enclosing_parts, split_disjoint_geometry};
+use crate::vector3::Vector3;
+
+/// Cap triangles of a single part, part_idx refers to the order of parts returned by split_disjoint_geometry.
+#[derive(Clone, Debug, PartialEq)]
+pub struct PartCap {
+    pub part_idx: usize,
+    /// Interleaved x, y, z coordinates, 9 floats per triangle, same layout as the input of split_disjoint_geometry.
+    pub triangles: Vec<f32>,
+}
+
+/// Triangulates closed section contours into a cap. Contours are nested using the even-odd rule, so the orientation of
+/// contours does not matter and self-overlapping parts do not produce double caps. Open contours are ignored.
+///
+/// The cap triangles face against the plane normal: when the plane is used for clipping away everything below it (as
+/// three.js clipping planes do), the cap closes the remaining part of the solid.
+pub fn cap_contours(contours: &[Contour], plane: &Plane) -> Vec<f32> {
+    let basis = plane.basis();
+    let closed: Vec<&Contour> = contours
+        .iter()
+        .filter(|c| c.closed && c.points.len() >= 3)
+        .collect();
+
+    let mut points_3d: Vec<Vector3> = vec![];
+    let mut points_2d = vec![];
+    let mut rings = vec![];
+    let mut polys = vec![];
+    for contour in &closed {
+        let start = points_3d.len();
+        points_3d.extend_from_slice(&contour.points);
+        let poly: Vec<_> = contour
+            .points
+            .iter()
+            .map(|&p| plane.project(p, basis))
+            .collect();
+        points_2d.extend_from_slice(&poly);
+        rings.push((start..points_3d.len()).collect::<Vec<usize>>());
+        polys.push(poly);
+    }
+
+    let mut result = vec![];
+    for nested in nest_polygons(&polys) {
+        let holes: Vec<Vec<usize>> = nested.holes.iter().map(|&h| rings[h].clone()).collect();
+        for [a, b, c] in triangulate(&points_2d, &rings[nested.outer], &holes) {
+            // Triangles are counter-clockwise relative to the plane normal, flip them.
+            for idx in [a, c, b] {
+                let p = points_3d[idx];
+                result.extend_from_slice(&[p.x, p.y, p.z]);
+            }
+        }
+    }
+    result
+}
+
+/// Splits the mesh into parts and computes section caps for each part intersecting the plane. Cavities are capped
+/// together with the part enclosing them (see enclosing_parts), so that their contours become holes in its cap.
+pub fn compute_section_caps(pos: &[f32], plane: &Plane) -> Result<Vec<PartCap>, Error> {
+    let parts = split_disjoint_geometry(pos)?;
+    let owners = enclosing_parts(&parts);
+    let mut contours: Vec<Vec<Contour>> = vec![vec![]; parts.len()];
+    for (part_idx, part) in parts.iter().enumerate() {
+        contours[owners[part_idx]].extend(section_triangles(part, plane));
+    }
+    Ok(contours
+        .iter()
+        .enumerate()
+        .map(|(part_idx, contours)| PartCap { part_idx, triangles: cap_contours(contours, plane) })
+        .filter(|cap| !cap.triangles.is_empty())
+        .collect())
+}
+
+/// Result of section_caps for JS: the list of caps, each one should be rendered with the color of its part.
+#[wasm_bindgen]
+pub struct SectionCaps {
+    caps: Vec<PartCap>,
+}
+
+#[wasm_bindgen]
+impl SectionCaps {
+    #[wasm_bindgen(getter)]
+    pub fn cap_count(&self) -> usize {
+        self.caps.len()
+    }
+
+    pub fn cap_part(&self, idx: usize) -> Result<usize, Error> {
+        Ok(checked_item(&self.caps, idx, "cap")?.part_idx)
+    }
+
+    /// Returns cap triangles as interleaved x, y, z coordinates, 9 floats per triangle.
+    pub fn cap_triangles(&self, idx: usize) -> Result<Box<[f32]>, Error> {
+        Ok(checked_item(&self.caps, idx, "cap")?
+            .triangles
+            .clone()
+            .into_boxed_slice())
+    }
+}
+
+/// Computes section caps for the plane passing through (origin_x, origin_y, origin_z) with the normal (normal_x,
+/// normal_y, normal_z). See compute_section_caps.
+#[wasm_bindgen]
+pub fn section_caps(
+    pos: &[f32],
+    origin_x: f32,
+    origin_y: f32,
+    origin_z: f32,
+    normal_x: f32,
+    normal_y: f32,
+    normal_z: f32,
+) -> Result<SectionCaps, Error> {
+    let origin = Vector3::new(origin_x, origin_y, origin_z);
+    let normal = Vector3::new(normal_x, normal_y, normal_z);
+    let plane = Plane::new(origin, normal).ok_or_else(|| Error::InvalidInput("plane normal is zero".to_string()))?;
+    Ok(SectionCaps { caps: compute_section_caps(pos, &plane)? })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::plane_section::compute_plane_section;
+    use crate::plane_section::tests::{create_box_geometry, z_plane};
+
+    // Returns the total area of triangles projected on the plane, positive for triangles facing against the normal.
+    fn cap_area(triangles: &[f32], plane: &Plane) -> f32 {
+        let mut area = 0.0;
+        for tri in triangles.chunks_exact(9) {
+            let a = Vector3::new(tri[0], tri[1], tri[2]);
+            let b = Vector3::new(tri[3], tri[4], tri[5]);
+            let c = Vector3::new(tri[6], tri[7], tri[8]);
+            let tri_area = -0.5 * (b - a).cross(c - a).dot(plane.normal);
+            // Collinear contour points may produce slivers with the area within the rounding error.
+            assert!(tri_area > -1e-6, "cap triangle faces the wrong way");
+            area += tri_area;
+        }
+        area
+    }
+
+    fn invert(pos: &[f32]) -> Vec<f32> {
+        let mut result = Vec::with_capacity(pos.len());
+        for tri in pos.chunks_exact(9) {
+            result.extend_from_slice(&tri[0..3]);
+            result.extend_from_slice(&tri[6..9]);
+            result.extend_from_slice(&tri[3..6]);
+        }
+        result
+    }
+
+    #[test]
+    fn test_cube_cap() {
+        let cube = create_box_geometry(Vector3::new(0.0, 0.0, 0.0), Vector3::new(2.0, 3.0, 1.0));
+        let plane = z_plane(0.5);
+        let caps = compute_section_caps(&cube, &plane).unwrap();
+        assert_eq!(caps.len(), 1);
+        assert!((cap_area(&caps[0].triangles, &plane) - 6.0).abs() < 1e-5);
+        for z in caps[0].triangles.chunks_exact(3).map(|p| p[2]) {
+            assert_eq!(z, 0.5);
+        }
+    }
+
+    #[test]
+    fn test_oblique_cap() {
+        let cube = create_box_geometry(Vector3::new(-1.0, -1.0, -1.0), Vector3::new(1.0, 1.0, 1.0));
+        let plane = Plane::new(Vector3::new(0.1, 0.0, 0.0), Vector3::new(1.0, 2.0, 3.0)).unwrap();
+        let section = compute_plane_section(&cube, &plane).unwrap();
+        let caps = compute_section_caps(&cube, &plane).unwrap();
+        assert_eq!(caps.len(), 1);
+        let expected = section[0].contours[0].area;
+        assert!((cap_area(&caps[0].triangles, &plane) - expected).abs() < 1e-4);
+    }
+
+    #[test]
+    fn test_cap_with_holes() {
+        // A box with a cavity containing another box: the cap is a square with a square hole plus the inner square.
+        let outer = create_box_geometry(Vector3::new(0.0, 0.0, 0.0), Vector3::new(10.0, 10.0, 10.0));
+        let cavity = invert(&create_box_geometry(Vector3::new(1.0, 1.0, 1.0), Vector3::new(9.0, 9.0, 9.0)));
+        let island = create_box_geometry(Vector3::new(3.0, 3.0, 3.0), Vector3::new(5.0, 5.0, 5.0));
+        let mut merged = outer.clone();
+        merged.extend_from_slice(&cavity);
+        merged.extend_from_slice(&island);
+
+        // The cavity is capped together with the outer box, the island is a separate part.
+        let plane = z_plane(4.0);
+        let caps = compute_section_caps(&merged, &plane).unwrap();
+        assert_eq!(caps.len(), 2);
+        assert_eq!(caps[0].part_idx, 0);
+        assert!((cap_area(&caps[0].triangles, &plane) - (100.0 - 64.0)).abs() < 1e-4);
+        assert!((cap_area(&caps[1].triangles, &plane) - 4.0).abs() < 1e-4);
+        let contours = section_triangles(&merged, &plane);
+        assert_eq!(contours.len(), 3);
+        let cap = cap_contours(&contours, &plane);
+        assert!((cap_area(&cap, &plane) - (100.0 - 64.0 + 4.0)).abs() < 1e-4);
+    }
+
+    #[test]
+    fn test_cap_ignores_open_contours() {
+        let cube = create_box_geometry(Vector3::new(0.0, 0.0, 0.0), Vector3::new(1.0, 1.0, 1.0));
+        let mut open = cube[..36].to_vec();
+        open.extend_from_slice(&cube[54..]);
+        let plane = z_plane(0.5);
+        assert!(compute_section_caps(&open, &plane).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_wasm_wrapper() {
+        let cube1 = create_box_geometry(Vector3::new(0.0, 0.0, 0.0), Vector3::new(1.0, 1.0, 1.0));
+        let cube2 = create_box_geometry(Vector3::new(5.0, 0.0, 0.0), Vector3::new(6.0, 1.0, 1.0));
+        let mut merged = cube1.clone();
+        merged.extend_from_slice(&cube2);
+        let caps = section_caps(&merged, 0.0, 0.0, 0.5, 0.0, 0.0, 1.0).unwrap();
+        assert_eq!(caps.cap_count(), 2);
+        assert_eq!(caps.cap_part(0).unwrap(), 0);
+        assert_eq!(caps.cap_part(1).unwrap(), 1);
+        assert_eq!(caps.cap_triangles(1).unwrap().len() % 9, 0);
+        assert_eq!(caps.cap_part(2).unwrap_err().code(), "INVALID_INPUT");
+        assert!(section_caps(&merged, 0.0, 0.0, 0.5, 0.0, 0.0, 0.0).is_err());
+    }
+}
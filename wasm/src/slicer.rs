@@ -0,0 +1,677 @@
+use wasm_bindgen::prelude::*;
+
+use crate::error::{Error, check_triangle_soup, checked_item};
+use crate::indexed_mesh::{IndexedMesh, MeshEdges};
+use crate::plane_section::{Contour, Segment, chain_segments, intersect_edge, triangle_crossing};
+use crate::polygon::nesting_depths;
+use crate::progress::{Progress, ProgressToken, with_js_progress};
+use crate::vector2::Vector2;
+use crate::vector3::Vector3;
+
+/// How to choose slicing heights.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum LayerHeights {
+    Uniform {
+        layer_height: f32,
+    },
+    /// Chooses the layer height so that the staircase error (cusp height, layer_height * |normal.z|) stays below
+    /// max_cusp on the sloped surfaces crossing the layer.
+    Adaptive {
+        min_height: f32,
+        max_height: f32,
+        max_cusp: f32,
+    },
+}
+
+// Slicing a model needs at most this many layers, e.g. 0.01 mm layers of a 10 m tall model.
+const MAX_LAYER_COUNT: f32 = (1 << 20) as f32;
+
+impl LayerHeights {
+    pub fn is_valid(&self) -> bool {
+        let positive = |v: f32| v.is_finite() && v > 0.0;
+        match *self {
+            LayerHeights::Uniform { layer_height } => positive(layer_height),
+            LayerHeights::Adaptive { min_height, max_height, max_cusp } => {
+                positive(min_height) && positive(max_height) && max_height >= min_height && positive(max_cusp)
+            }
+        }
+    }
+
+    /// The thinnest layer these heights may produce.
+    fn min_height(&self) -> f32 {
+        match *self {
+            LayerHeights::Uniform { layer_height } => layer_height,
+            LayerHeights::Adaptive { min_height, .. } => min_height,
+        }
+    }
+}
+
+/// A contour of a layer. Holes are determined using the even-odd rule among closed contours of the same layer.
+#[derive(Clone, Debug, PartialEq)]
+pub struct LayerContour {
+    pub contour: Contour,
+    pub is_hole: bool,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Layer {
+    /// The height of the slicing plane, which is in the middle of the layer.
+    pub z: f32,
+    pub thickness: f32,
+    pub contours: Vec<LayerContour>,
+}
+
+/// Precomputed data for slicing the same mesh at many heights. Building it is the most expensive part, after that each
+/// layer only visits the triangles spanning its height.
+pub struct Slicer {
+    mesh: IndexedMesh,
+    edges: MeshEdges,
+    // Triangle indices sorted by the minimum z.
+    sorted_tris: Vec<u32>,
+    tri_z_ranges: Vec<(f32, f32)>,
+    min_z: f32,
+    max_z: f32,
+}
+
+impl Slicer {
+    /// `pos` has the same layout as in split_disjoint_geometry.
+    pub fn new(pos: &[f32]) -> Self {
+        Self::with_progress(pos, Progress::none()).expect("building without a token can not fail")
+    }
+
+    /// Same as new, reports the progress and stops if the operation is cancelled.
+    pub fn with_progress(pos: &[f32], progress: Progress) -> Result<Self, Error> {
+        let mesh = IndexedMesh::from_triangle_soup_with_progress(pos, progress.stage(0.0, 0.9))?;
+        let edges = mesh.build_edges();
+        let tri_z_ranges: Vec<(f32, f32)> = (0..mesh.triangles.len())
+            .map(|t| {
+                let [a, b, c] = mesh.triangle(t);
+                (a.z.min(b.z).min(c.z), a.z.max(b.z).max(c.z))
+            })
+            .collect();
+        let mut sorted_tris: Vec<u32> = (0..mesh.triangles.len() as u32).collect();
+        sorted_tris.sort_by(|&a, &b| {
+            tri_z_ranges[a as usize]
+                .0
+                .total_cmp(&tri_z_ranges[b as usize].0)
+        });
+        let min_z = tri_z_ranges
+            .iter()
+            .map(|r| r.0)
+            .fold(f32::INFINITY, f32::min);
+        let max_z = tri_z_ranges
+            .iter()
+            .map(|r| r.1)
+            .fold(f32::NEG_INFINITY, f32::max);
+        progress.update(1, 1)?;
+        Ok(Self { mesh, edges, sorted_tris, tri_z_ranges, min_z, max_z })
+    }
+
+    /// Returns (z, thickness) of each layer, z is the middle of the layer. Fails if the heights are invalid, or if the
+    /// layers would be too thin for the model: more than MAX_LAYER_COUNT of them, or below the f32 resolution at its z.
+    pub fn layer_heights(&self, heights: LayerHeights) -> Result<Vec<(f32, f32)>, Error> {
+        check_layer_heights(heights)?;
+        if self.mesh.triangles.is_empty() {
+            return Ok(vec![]);
+        }
+        let min_height = heights.min_height();
+        let resolution = self.min_z.abs().max(self.max_z.abs()) * f32::EPSILON;
+        if min_height <= resolution || (self.max_z - self.min_z) / min_height > MAX_LAYER_COUNT {
+            return Err(Error::InvalidInput(format!(
+                "layer height {} is too small for the model spanning z from {} to {}",
+                min_height, self.min_z, self.max_z
+            )));
+        }
+        let mut result = vec![];
+        match heights {
+            LayerHeights::Uniform { layer_height } => {
+                let count = ((self.max_z - self.min_z) / layer_height).ceil().max(1.0) as usize;
+                for i in 0..count {
+                    result.push((self.min_z + (i as f32 + 0.5) * layer_height, layer_height));
+                }
+            }
+            LayerHeights::Adaptive { min_height, max_height, max_cusp } => {
+                // Sweep over triangles sorted by min z, keeping the ones which may overlap the next layer.
+                let mut active: Vec<u32> = vec![];
+                let mut next_sorted = 0;
+                let mut bottom = self.min_z;
+                while bottom < self.max_z {
+                    let top = bottom + max_height;
+                    while next_sorted < self.sorted_tris.len()
+                        && self.tri_z_ranges[self.sorted_tris[next_sorted] as usize].0 < top
+                    {
+                        active.push(self.sorted_tris[next_sorted]);
+                        next_sorted += 1;
+                    }
+                    active.retain(|&t| self.tri_z_ranges[t as usize].1 > bottom);
+
+                    let mut max_nz: f32 = 0.0;
+                    for &t in &active {
+                        let [a, b, c] = self.mesh.triangle(t as usize);
+                        let nz = (b - a).cross(c - a).normalize().z.abs();
+                        // Horizontal faces do not produce the staircase effect.
+                        if nz < 0.999 {
+                            max_nz = max_nz.max(nz);
+                        }
+                    }
+                    let height = if max_nz > 0.0 { max_cusp / max_nz } else { max_height };
+                    let height = height.clamp(min_height, max_height);
+                    result.push((bottom + 0.5 * height, height));
+                    let next = bottom + height;
+                    // Can not happen above the resolution, but a stuck sweep would never end.
+                    if next <= bottom {
+                        return Err(Error::InvalidInput(format!(
+                            "layer height {} is too small at z {}",
+                            height, bottom
+                        )));
+                    }
+                    bottom = next;
+                }
+            }
+        }
+        Ok(result)
+    }
+
+    /// Slices the mesh at the given heights. The result contains one layer per height in the same order, thickness is
+    /// set to zero.
+    pub fn slice_at(&self, zs: &[f32]) -> Vec<Layer> {
+        self.slice_layers(&at_heights(zs), Progress::none())
+            .expect("slicing without a token can not fail")
+    }
+
+    /// Slices the mesh with the given layer heights, fails if they are invalid, see layer_heights.
+    pub fn slice(&self, heights: LayerHeights) -> Result<Vec<Layer>, Error> {
+        self.slice_layers(&self.layer_heights(heights)?, Progress::none())
+    }
+
+    /// Slices the mesh at the given (z, thickness) layers, reports the progress and stops if the operation is
+    /// cancelled. The layers are returned in the same order.
+    pub fn slice_layers(&self, layers: &[(f32, f32)], progress: Progress) -> Result<Vec<Layer>, Error> {
+        let mut order: Vec<usize> = (0..layers.len()).collect();
+        order.sort_by(|&a, &b| layers[a].0.total_cmp(&layers[b].0));
+
+        let mut result: Vec<Option<Layer>> = vec![None; layers.len()];
+        let mut active: Vec<u32> = vec![];
+        let mut next_sorted = 0;
+        // Maps edge index -> point index for the current layer, valid only if edge_stamp matches the layer.
+        let mut edge_point = vec![0u32; self.edges.vertices.len()];
+        let mut edge_stamp = vec![usize::MAX; self.edges.vertices.len()];
+        let mut points = vec![];
+        let mut segments = vec![];
+
+        for (done, layer_idx) in order.into_iter().enumerate() {
+            progress.update(done, layers.len())?;
+            let (z, thickness) = layers[layer_idx];
+            // A triangle crosses the plane if min_z < z <= max_z, see triangle_crossing.
+            while next_sorted < self.sorted_tris.len()
+                && self.tri_z_ranges[self.sorted_tris[next_sorted] as usize].0 < z
+            {
+                active.push(self.sorted_tris[next_sorted]);
+                next_sorted += 1;
+            }
+            active.retain(|&t| self.tri_z_ranges[t as usize].1 >= z);
+
+            points.clear();
+            segments.clear();
+            for &t in &active {
+                let tri = self.mesh.triangles[t as usize];
+                let d = tri.map(|v| self.mesh.positions[v as usize].z - z);
+                let Some((down, up)) = triangle_crossing(d) else {
+                    continue;
+                };
+                let mut get_point = |i: usize| {
+                    let edge = self.edges.triangle_edges[t as usize][i] as usize;
+                    if edge_stamp[edge] != layer_idx {
+                        let [a, b] = self.edges.vertices[edge].map(|v| self.mesh.positions[v as usize]);
+                        edge_stamp[edge] = layer_idx;
+                        edge_point[edge] = points.len() as u32;
+                        points.push(intersect_edge(a, a.z - z, b, b.z - z));
+                    }
+                    edge_point[edge]
+                };
+                let from = get_point(down);
+                let to = get_point(up);
+                segments.push(Segment { from, to });
+            }
+
+            let contours = chain_segments(&points, &segments, Vector3::new(0.0, 0.0, 1.0));
+            result[layer_idx] = Some(Layer { z, thickness, contours: classify_holes(contours) });
+        }
+
+        progress.update(layers.len(), layers.len())?;
+        Ok(result.into_iter().map(|layer| layer.unwrap()).collect())
+    }
+}
+
+fn at_heights(zs: &[f32]) -> Vec<(f32, f32)> {
+    zs.iter().map(|&z| (z, 0.0)).collect()
+}
+
+fn classify_holes(contours: Vec<Contour>) -> Vec<LayerContour> {
+    let closed: Vec<usize> = (0..contours.len())
+        .filter(|&i| contours[i].closed)
+        .collect();
+    let polys: Vec<Vec<Vector2>> = closed
+        .iter()
+        .map(|&i| {
+            contours[i]
+                .points
+                .iter()
+                .map(|p| Vector2::new(p.x, p.y))
+                .collect()
+        })
+        .collect();
+    let mut is_hole = vec![false; contours.len()];
+    for (&i, depth) in closed.iter().zip(nesting_depths(&polys)) {
+        is_hole[i] = depth % 2 == 1;
+    }
+    contours
+        .into_iter()
+        .zip(is_hole)
+        .map(|(contour, is_hole)| LayerContour { contour, is_hole })
+        .collect()
+}
+
+/// Result of slicing for JS.
+#[wasm_bindgen]
+pub struct SlicedLayers {
+    layers: Vec<Layer>,
+}
+
+impl SlicedLayers {
+    fn layer(&self, layer: usize) -> Result<&Layer, Error> {
+        checked_item(&self.layers, layer, "layer")
+    }
+
+    fn contour(&self, layer: usize, contour: usize) -> Result<&LayerContour, Error> {
+        checked_item(&self.layer(layer)?.contours, contour, "contour")
+    }
+}
+
+#[wasm_bindgen]
+impl SlicedLayers {
+    #[wasm_bindgen(getter)]
+    pub fn layer_count(&self) -> usize {
+        self.layers.len()
+    }
+
+    pub fn layer_z(&self, layer: usize) -> Result<f32, Error> {
+        Ok(self.layer(layer)?.z)
+    }
+
+    pub fn layer_thickness(&self, layer: usize) -> Result<f32, Error> {
+        Ok(self.layer(layer)?.thickness)
+    }
+
+    pub fn contour_count(&self, layer: usize) -> Result<usize, Error> {
+        Ok(self.layer(layer)?.contours.len())
+    }
+
+    /// Returns interleaved x, y, z coordinates of contour points.
+    pub fn contour_points(&self, layer: usize, contour: usize) -> Result<Box<[f32]>, Error> {
+        Ok(self
+            .contour(layer, contour)?
+            .contour
+            .points
+            .iter()
+            .flat_map(|p| [p.x, p.y, p.z])
+            .collect())
+    }
+
+    pub fn contour_is_hole(&self, layer: usize, contour: usize) -> Result<bool, Error> {
+        Ok(self.contour(layer, contour)?.is_hole)
+    }
+
+    pub fn contour_closed(&self, layer: usize, contour: usize) -> Result<bool, Error> {
+        Ok(self.contour(layer, contour)?.contour.closed)
+    }
+}
+
+// Building the slicer is reported as the first half of the work, slicing the layers as the second one.
+fn slice_with(
+    pos: &[f32],
+    token: &ProgressToken,
+    on_progress: Option<js_sys::Function>,
+    layers: impl FnOnce(&Slicer) -> Result<Vec<(f32, f32)>, Error>,
+) -> Result<SlicedLayers, Error> {
+    check_triangle_soup(pos)?;
+    with_js_progress(Some(token), on_progress, |progress| {
+        let slicer = Slicer::with_progress(pos, progress.stage(0.0, 0.5))?;
+        let layers = slicer.slice_layers(&layers(&slicer)?, progress.stage(0.5, 1.0))?;
+        Ok(SlicedLayers { layers })
+    })
+}
+
+fn check_layer_heights(heights: LayerHeights) -> Result<(), Error> {
+    if !heights.is_valid() {
+        return Err(Error::InvalidInput(format!("invalid layer heights {:?}", heights)));
+    }
+    Ok(())
+}
+
+/// Slices the mesh at the given heights.
+#[wasm_bindgen]
+pub fn slice_at_heights(pos: &[f32], zs: &[f32]) -> Result<SlicedLayers, Error> {
+    check_triangle_soup(pos)?;
+    Ok(SlicedLayers { layers: Slicer::new(pos).slice_at(zs) })
+}
+
+/// Same as slice_at_heights, reports the progress to the token and to the optional callback (which receives the
+/// fraction of the work done). The token can cancel the slicing.
+#[wasm_bindgen]
+pub fn slice_at_heights_with_progress(
+    pos: &[f32],
+    zs: &[f32],
+    token: &ProgressToken,
+    on_progress: Option<js_sys::Function>,
+) -> Result<SlicedLayers, Error> {
+    slice_with(pos, token, on_progress, |_| Ok(at_heights(zs)))
+}
+
+/// Slices the mesh with uniform layer height.
+#[wasm_bindgen]
+pub fn slice_uniform(pos: &[f32], layer_height: f32) -> Result<SlicedLayers, Error> {
+    check_triangle_soup(pos)?;
+    let heights = LayerHeights::Uniform { layer_height };
+    check_layer_heights(heights)?;
+    Ok(SlicedLayers { layers: Slicer::new(pos).slice(heights)? })
+}
+
+/// Same as slice_uniform, see slice_at_heights_with_progress for the progress reporting.
+#[wasm_bindgen]
+pub fn slice_uniform_with_progress(
+    pos: &[f32],
+    layer_height: f32,
+    token: &ProgressToken,
+    on_progress: Option<js_sys::Function>,
+) -> Result<SlicedLayers, Error> {
+    let heights = LayerHeights::Uniform { layer_height };
+    check_layer_heights(heights)?;
+    slice_with(pos, token, on_progress, |slicer| slicer.layer_heights(heights))
+}
+
+/// Slices the mesh with adaptive layer height, see LayerHeights::Adaptive.
+#[wasm_bindgen]
+pub fn slice_adaptive(pos: &[f32], min_height: f32, max_height: f32, max_cusp: f32) -> Result<SlicedLayers, Error> {
+    check_triangle_soup(pos)?;
+    let heights = LayerHeights::Adaptive { min_height, max_height, max_cusp };
+    check_layer_heights(heights)?;
+    Ok(SlicedLayers { layers: Slicer::new(pos).slice(heights)? })
+}
+
+/// Same as slice_adaptive, see slice_at_heights_with_progress for the progress reporting.
+#[wasm_bindgen]
+pub fn slice_adaptive_with_progress(
+    pos: &[f32],
+    min_height: f32,
+    max_height: f32,
+    max_cusp: f32,
+    token: &ProgressToken,
+    on_progress: Option<js_sys::Function>,
+) -> Result<SlicedLayers, Error> {
+    let heights = LayerHeights::Adaptive { min_height, max_height, max_cusp };
+    check_layer_heights(heights)?;
+    slice_with(pos, token, on_progress, |slicer| slicer.layer_heights(heights))
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+    use crate::plane_section::tests::create_box_geometry;
+    use crate::plane_section::{Plane, section_triangles};
+    use crate::progress::tests::{assert_complete, run_with_progress};
+
+    // Creates a UV sphere with outward-facing normals and 2 * segments * (rings - 1) triangles.
+    pub(crate) fn create_sphere_geometry(radius: f32, segments: usize, rings: usize) -> Vec<f32> {
+        let point = |ring: usize, seg: usize| {
+            let theta = std::f32::consts::PI * ring as f32 / rings as f32;
+            let phi = 2.0 * std::f32::consts::PI * (seg % segments) as f32 / segments as f32;
+            if ring == 0 {
+                return [0.0, 0.0, radius];
+            } else if ring == rings {
+                return [0.0, 0.0, -radius];
+            }
+            [radius * theta.sin() * phi.cos(), radius * theta.sin() * phi.sin(), radius * theta.cos()]
+        };
+        let mut result = vec![];
+        for ring in 0..rings {
+            for seg in 0..segments {
+                let (a, b, c, d) =
+                    (point(ring, seg), point(ring + 1, seg), point(ring + 1, seg + 1), point(ring, seg + 1));
+                if ring != 0 {
+                    result.extend(a.iter().chain(&b).chain(&d));
+                }
+                if ring != rings - 1 {
+                    result.extend(b.iter().chain(&c).chain(&d));
+                }
+            }
+        }
+        result
+    }
+
+    #[test]
+    fn test_empty() {
+        let slicer = Slicer::new(&[]);
+        assert!(
+            slicer
+                .slice(LayerHeights::Uniform { layer_height: 0.2 })
+                .unwrap()
+                .is_empty()
+        );
+        assert!(slicer.slice_at(&[0.0])[0].contours.is_empty());
+    }
+
+    #[test]
+    fn test_uniform_layers() {
+        let cube = create_box_geometry(Vector3::new(0.0, 0.0, 0.0), Vector3::new(2.0, 3.0, 1.0));
+        let slicer = Slicer::new(&cube);
+        let layers = slicer
+            .slice(LayerHeights::Uniform { layer_height: 0.25 })
+            .unwrap();
+        assert_eq!(layers.len(), 4);
+        for (i, layer) in layers.iter().enumerate() {
+            assert_eq!(layer.z, 0.125 + 0.25 * i as f32);
+            assert_eq!(layer.thickness, 0.25);
+            assert_eq!(layer.contours.len(), 1);
+            assert!(!layer.contours[0].is_hole);
+            assert!(layer.contours[0].contour.closed);
+            assert!((layer.contours[0].contour.area - 6.0).abs() < 1e-5);
+        }
+        assert!(
+            slicer
+                .slice(LayerHeights::Uniform { layer_height: 0.0 })
+                .is_err()
+        );
+    }
+
+    #[test]
+    fn test_too_thin_layers() {
+        // A tiny layer height would need about usize::MAX layers.
+        let cube = create_box_geometry(Vector3::new(0.0, 0.0, 0.0), Vector3::new(1.0, 1.0, 1.0));
+        let slicer = Slicer::new(&cube);
+        let err = slicer
+            .layer_heights(LayerHeights::Uniform { layer_height: 1e-30 })
+            .unwrap_err();
+        assert_eq!(err.code(), "INVALID_INPUT");
+        assert!(slice_uniform(&cube, 1e-30).is_err());
+        assert_eq!(
+            slicer
+                .layer_heights(LayerHeights::Uniform { layer_height: 1e-5 })
+                .unwrap()
+                .len(),
+            100000
+        );
+        let tall = create_box_geometry(Vector3::new(0.0, 0.0, 0.0), Vector3::new(1.0, 1.0, 100.0));
+        assert!(
+            Slicer::new(&tall)
+                .layer_heights(LayerHeights::Uniform { layer_height: 1e-5 })
+                .is_err()
+        );
+
+        // Far from the origin a thin layer does not advance the sweep, which would never end.
+        let far = create_box_geometry(Vector3::new(0.0, 0.0, 1e6), Vector3::new(1.0, 1.0, 1e6 + 1.0));
+        let adaptive = LayerHeights::Adaptive { min_height: 0.01, max_height: 0.3, max_cusp: 0.02 };
+        assert!(Slicer::new(&far).layer_heights(adaptive).is_err());
+        assert!(slice_adaptive(&far, 0.01, 0.3, 0.02).is_err());
+        let adaptive = LayerHeights::Adaptive { min_height: 0.125, max_height: 0.25, max_cusp: 0.02 };
+        assert_eq!(Slicer::new(&far).layer_heights(adaptive).unwrap().len(), 4);
+    }
+
+    #[test]
+    fn test_matches_plane_section() {
+        let sphere = create_sphere_geometry(10.0, 32, 16);
+        let slicer = Slicer::new(&sphere);
+        let zs = [5.0, -9.5, 0.0, 3.3];
+        let layers = slicer.slice_at(&zs);
+        for (layer, &z) in layers.iter().zip(&zs) {
+            assert_eq!(layer.z, z);
+            let plane = Plane::new(Vector3::new(0.0, 0.0, z), Vector3::new(0.0, 0.0, 1.0)).unwrap();
+            let expected = section_triangles(&sphere, &plane);
+            assert_eq!(layer.contours.len(), expected.len());
+            assert_eq!(layer.contours.len(), 1);
+            assert_eq!(layer.contours[0].contour.points.len(), expected[0].points.len());
+            assert!((layer.contours[0].contour.area - expected[0].area).abs() < 1e-3);
+            assert!(layer.contours[0].contour.area > 0.0);
+        }
+    }
+
+    #[test]
+    fn test_holes() {
+        // A square tube along Z: every layer has one outer and one hole contour.
+        let outer = create_box_geometry(Vector3::new(0.0, 0.0, 0.0), Vector3::new(4.0, 4.0, 2.0));
+        let inner = create_box_geometry(Vector3::new(1.0, 1.0, 0.0), Vector3::new(3.0, 3.0, 2.0));
+        let mut merged = outer.clone();
+        for tri in inner.chunks_exact(9) {
+            merged.extend_from_slice(&tri[0..3]);
+            merged.extend_from_slice(&tri[6..9]);
+            merged.extend_from_slice(&tri[3..6]);
+        }
+        let layers = Slicer::new(&merged)
+            .slice(LayerHeights::Uniform { layer_height: 0.5 })
+            .unwrap();
+        assert_eq!(layers.len(), 4);
+        for layer in &layers {
+            assert_eq!(layer.contours.len(), 2);
+            let (holes, outers): (Vec<_>, Vec<_>) = layer.contours.iter().partition(|c| c.is_hole);
+            assert_eq!(holes.len(), 1);
+            assert_eq!(outers.len(), 1);
+            assert!((holes[0].contour.area + 4.0).abs() < 1e-5);
+            assert!((outers[0].contour.area - 16.0).abs() < 1e-5);
+        }
+    }
+
+    #[test]
+    fn test_adaptive_layers() {
+        let cube = create_box_geometry(Vector3::new(0.0, 0.0, 0.0), Vector3::new(1.0, 1.0, 1.0));
+        let adaptive = LayerHeights::Adaptive { min_height: 0.05, max_height: 0.3, max_cusp: 0.02 };
+        // Vertical walls have no staircase error, so the cube is sliced with the maximum layer height.
+        let heights = Slicer::new(&cube).layer_heights(adaptive).unwrap();
+        assert_eq!(heights.len(), 4);
+        assert!(heights.iter().all(|&(_, h)| h == 0.3));
+
+        // The sphere needs thinner layers near the poles.
+        let sphere = create_sphere_geometry(10.0, 32, 32);
+        let heights = Slicer::new(&sphere).layer_heights(adaptive).unwrap();
+        let first = heights[0].1;
+        let middle = heights[heights.len() / 2].1;
+        assert!(first < middle, "{} vs {}", first, middle);
+        assert!(heights.iter().all(|&(_, h)| (0.05..=0.3).contains(&h)));
+        let total: f32 = heights.iter().map(|&(_, h)| h).sum();
+        assert!(total >= 20.0);
+
+        let invalid = LayerHeights::Adaptive { min_height: 0.0, max_height: 0.3, max_cusp: 0.02 };
+        assert!(Slicer::new(&sphere).layer_heights(invalid).is_err());
+        for max_cusp in [0.0, -0.02, f32::NAN, f32::INFINITY] {
+            let invalid = LayerHeights::Adaptive { min_height: 0.05, max_height: 0.3, max_cusp };
+            assert!(!invalid.is_valid());
+        }
+    }
+
+    #[test]
+    fn test_wasm_wrapper() {
+        let cube = create_box_geometry(Vector3::new(0.0, 0.0, 0.0), Vector3::new(1.0, 1.0, 1.0));
+        let layers = slice_uniform(&cube, 0.5).unwrap();
+        assert_eq!(layers.layer_count(), 2);
+        assert_eq!(layers.layer_z(1).unwrap(), 0.75);
+        assert_eq!(layers.layer_thickness(1).unwrap(), 0.5);
+        assert_eq!(layers.contour_count(0).unwrap(), 1);
+        assert!(!layers.contour_is_hole(0, 0).unwrap());
+        assert!(layers.contour_closed(0, 0).unwrap());
+        assert_eq!(layers.contour_points(0, 0).unwrap().len() % 3, 0);
+        assert_eq!(layers.layer_z(2).unwrap_err().code(), "INVALID_INPUT");
+        assert!(layers.contour_closed(0, 1).is_err());
+        assert!(layers.contour_count(2).is_err());
+        assert_eq!(slice_adaptive(&cube, 0.1, 0.5, 0.01).unwrap().layer_count(), 2);
+        assert_eq!(
+            slice_at_heights(&cube, &[0.1, 2.0])
+                .unwrap()
+                .contour_count(1)
+                .unwrap(),
+            0
+        );
+        assert!(slice_uniform(&cube, 0.0).is_err());
+        assert!(slice_adaptive(&cube, 0.5, 0.1, 0.01).is_err());
+        assert!(slice_at_heights(&cube[..10], &[0.5]).is_err());
+    }
+
+    #[test]
+    fn test_progress() {
+        let sphere = create_sphere_geometry(10.0, 32, 16);
+        let slicer = Slicer::new(&sphere);
+        let layers = slicer
+            .layer_heights(LayerHeights::Uniform { layer_height: 0.1 })
+            .unwrap();
+        let (result, fractions) = run_with_progress(2.0, |progress| {
+            let slicer = Slicer::with_progress(&sphere, progress.stage(0.0, 0.5))?;
+            slicer.slice_layers(&layers, progress.stage(0.5, 1.0))
+        });
+        assert_eq!(result, slicer.slice(LayerHeights::Uniform { layer_height: 0.1 }));
+        assert_complete(&fractions);
+        let (result, fractions) = run_with_progress(0.7, |progress| slicer.slice_layers(&layers, progress));
+        assert_eq!(result, Err(Error::Cancelled));
+        assert!(fractions.last().unwrap() < &0.8);
+
+        let token = ProgressToken::new();
+        token.cancel();
+        let result = slice_uniform_with_progress(&sphere, 0.5, &token, None);
+        assert_eq!(result.err(), Some(Error::Cancelled));
+        token.reset();
+        let layers = slice_uniform_with_progress(&sphere, 0.5, &token, None).unwrap();
+        assert_eq!(layers.layer_count(), 40);
+        assert_eq!(token.progress(), 1.0);
+        assert!(slice_uniform_with_progress(&sphere, 0.0, &token, None).is_err());
+    }
+
+    // Run with `cargo test --release bench_slice_large_model -- --ignored`. Slicing a layer only visits the triangles
+    // spanning it, so all layers together must take about as long as building the slicer, not layers times longer.
+    #[test]
+    #[ignore]
+    fn bench_slice_large_model() {
+        let sphere = create_sphere_geometry(100.0, 1000, 1000);
+
+        let start = std::time::Instant::now();
+        let slicer = Slicer::new(&sphere);
+        let build_time = start.elapsed();
+
+        let start = std::time::Instant::now();
+        let layers = slicer
+            .slice(LayerHeights::Uniform { layer_height: 0.2 })
+            .unwrap();
+        let slice_time = start.elapsed();
+
+        let start = std::time::Instant::now();
+        let adaptive = LayerHeights::Adaptive { min_height: 0.05, max_height: 0.3, max_cusp: 0.02 };
+        let adaptive_layers = slicer.slice(adaptive).unwrap();
+        let adaptive_time = start.elapsed();
+
+        assert_eq!(layers.len(), 1000);
+        assert!(adaptive_layers.len() > 666);
+        assert!(slice_time < 4 * build_time, "{:?} slicing vs {:?} building", slice_time, build_time);
+        assert!(adaptive_time < 4 * build_time, "{:?} slicing vs {:?} building", adaptive_time, build_time);
+        assert!(
+            layers
+                .iter()
+                .all(|l| l.contours.len() == 1 && l.contours[0].contour.closed)
+        );
+    }
+}
@@ -1,7 +1,12 @@
-use std::collections::HashMap;
 use std::f32::consts::PI;
+use std::sync::atomic::{AtomicU32, Ordering};
 
+use crate::bvh::intersect_triangle;
+use crate::error::{Error, check_triangle_soup};
+use crate::mesh_stats::{MeshStats, compute_mesh_stats};
 use crate::not_atan::not_atan2;
+use crate::parallel::{self, Prehashed, PrehashedMap};
+use crate::progress::Progress;
 use crate::vector3::{Edge, Vector3};
 
 #[derive(Clone, Copy, Debug)]
@@ -57,18 +62,134 @@ fn find_next_triangle_with_shared_edge(v1: Vector3, v2: Vector3, v3: Vector3, tr
     best_tri_idx
 }
 
+// Maps each edge to the triangles having it, in the order of triangles. The map is split into shards by the edge hash, so
+// that the shards can be built in parallel.
+struct EdgeMap {
+    shards: Vec<PrehashedMap<Edge, Vec<TriangleInfo>>>,
+}
+
+impl EdgeMap {
+    fn new(pos: &[f32]) -> Self {
+        let tri_count = pos.len() / 9;
+        let tris = parallel::map_chunks(&pos[..tri_count * 9], 9, |tri| {
+            let v1 = Vector3::new(tri[0], tri[1], tri[2]);
+            let v2 = Vector3::new(tri[3], tri[4], tri[5]);
+            let v3 = Vector3::new(tri[6], tri[7], tri[8]);
+            let tri_normal = (v2 - v1).cross(v3 - v1);
+            let edges = [Edge::new(v1, v2), Edge::new(v2, v3), Edge::new(v3, v1)].map(Prehashed::new);
+            (tri_normal, edges)
+        });
+        // Each shard scans all triangles, but it only inserts its own edges, which is the expensive part.
+        let shard_count = parallel::thread_count();
+        let shards = parallel::map_tasks(shard_count, |shard| {
+            let mut map = PrehashedMap::with_capacity_and_hasher(3 * tri_count / shard_count, Default::default());
+            for (tri_idx, &(tri_normal, edges)) in tris.iter().enumerate() {
+                for edge in edges {
+                    if Self::shard_index(edge.hash, shard_count) == shard {
+                        map.entry(edge)
+                            .or_insert_with(Vec::new)
+                            .push(TriangleInfo { tri_idx, tri_normal });
+                    }
+                }
+            }
+            map
+        });
+        Self { shards }
+    }
+
+    fn shard_index(hash: u64, shard_count: usize) -> usize {
+        // The maps use the low bits of the hash to select the bucket, use the high ones for the shard.
+        (hash >> 32) as usize % shard_count
+    }
+
+    fn get(&self, edge: Edge) -> Option<&Vec<TriangleInfo>> {
+        let edge = Prehashed::new(edge);
+        self.shards[Self::shard_index(edge.hash, self.shards.len())].get(&edge)
+    }
+}
+
 /// Splits a triangle mesh into multiple meshes, where each mesh represents a disjoint body. Assumes T-junctions are
 /// accidental and the normals of each body are outward-facing. `pos` must contain interleaved array of x, y, z
 /// coordinates of vertices, 3 vertices (9 floats) per triangle. Returns a vector of parts, each part is a flat vector
 /// of 9 * triangle_count floats.
-#[allow(dead_code)]
-pub fn split_disjoint_geometry(pos: &[f32]) -> Vec<Vec<f32>> {
-    if !pos.len().is_multiple_of(9) {
-        return vec![];
-    }
+pub fn split_disjoint_geometry(pos: &[f32]) -> Result<Vec<Vec<f32>>, Error> {
+    let parts = split_disjoint_triangles(pos)?;
+    Ok(parts
+        .iter()
+        .map(|part| {
+            // Copy triangle vertices into a new flat array
+            let mut part_pos = vec![0.0f32; part.len() * 9];
+            for (i, &tri_idx) in part.iter().enumerate() {
+                let src_start = tri_idx * 9;
+                let dst_start = i * 9;
+                part_pos[dst_start..dst_start + 9].copy_from_slice(&pos[src_start..src_start + 9]);
+            }
+            part_pos
+        })
+        .collect())
+}
+
+// Direction of the rays testing if a point is inside a part. It is not aligned with the axes, so that the rays do not
+// run along the edges of axis-aligned geometry.
+const INSIDE_TEST_DIR: Vector3 = Vector3 { x: 0.5123, y: 0.3217, z: 0.7961 };
+
+// Returns true if the point is inside the closed triangle soup, by the parity of the ray crossings.
+fn is_inside(point: Vector3, pos: &[f32]) -> bool {
+    let crossings = pos
+        .chunks_exact(9)
+        .filter(|t| {
+            let tri = [Vector3::new(t[0], t[1], t[2]), Vector3::new(t[3], t[4], t[5]), Vector3::new(t[6], t[7], t[8])];
+            intersect_triangle(point, INSIDE_TEST_DIR, tri).is_some_and(|t| t > 0.0)
+        })
+        .count();
+    crossings % 2 == 1
+}
+
+/// Groups the parts returned by split_disjoint_geometry into bodies. split_disjoint_geometry returns the inner surface
+/// of a hollow body as a separate part facing inward (with negative volume). Returns for each part the index of the
+/// part it belongs to: a cavity belongs to the smallest part enclosing it, the other parts to themselves.
+pub fn enclosing_parts(parts: &[Vec<f32>]) -> Vec<usize> {
+    let stats: Vec<MeshStats> = parts.iter().map(|p| compute_mesh_stats(p)).collect();
+    let contains_bbox = |outer: &MeshStats, inner: &MeshStats| {
+        let (lo, hi) = (outer.bbox_min, outer.bbox_max);
+        let (inner_lo, inner_hi) = (inner.bbox_min, inner.bbox_max);
+        lo.x <= inner_lo.x
+            && lo.y <= inner_lo.y
+            && lo.z <= inner_lo.z
+            && inner_hi.x <= hi.x
+            && inner_hi.y <= hi.y
+            && inner_hi.z <= hi.z
+    };
+    (0..parts.len())
+        .map(|part| {
+            if stats[part].volume >= 0.0 {
+                return part;
+            }
+            let point = Vector3::new(parts[part][0], parts[part][1], parts[part][2]);
+            (0..parts.len())
+                .filter(|&outer| {
+                    stats[outer].volume > 0.0
+                        && contains_bbox(&stats[outer], &stats[part])
+                        && is_inside(point, &parts[outer])
+                })
+                .min_by(|&a, &b| stats[a].volume.total_cmp(&stats[b].volume))
+                .unwrap_or(part)
+        })
+        .collect()
+}
+
+/// Same as split_disjoint_geometry, but returns the indices of the triangles of each part in the visiting order.
+pub fn split_disjoint_triangles(pos: &[f32]) -> Result<Vec<Vec<usize>>, Error> {
+    split_disjoint_triangles_with_progress(pos, Progress::none())
+}
+
+/// Same as split_disjoint_triangles, reports the progress and stops if the operation is cancelled.
+pub fn split_disjoint_triangles_with_progress(pos: &[f32], progress: Progress) -> Result<Vec<Vec<usize>>, Error> {
+    check_triangle_soup(pos)?;
     let tri_count = pos.len() / 9;
     if tri_count == 0 {
-        return vec![];
+        progress.update(0, 0)?;
+        return Ok(vec![]);
     }
 
     // We find triangle neighbors by shared edges: if the triangle 2 has the same edge as triangle 1, but oriented the
@@ -80,23 +201,10 @@ pub fn split_disjoint_geometry(pos: &[f32]) -> Vec<Vec<f32>> {
     // the nearest candidate triangle based on angle between triangle normals.
 
     // Maps edge -> list of triangles with that edge.
-    let mut edge_map: HashMap<Edge, Vec<TriangleInfo>> = HashMap::with_capacity(3 * tri_count);
-
-    for tri_idx in 0..tri_count {
-        let off = tri_idx * 9;
-        let v1 = Vector3::new(pos[off], pos[off + 1], pos[off + 2]);
-        let v2 = Vector3::new(pos[off + 3], pos[off + 4], pos[off + 5]);
-        let v3 = Vector3::new(pos[off + 6], pos[off + 7], pos[off + 8]);
-        let tri_normal = (v2 - v1).cross(v3 - v1);
-
-        let tri_info = TriangleInfo { tri_idx, tri_normal };
-        #[rustfmt::skip]
-        edge_map.entry(Edge::new(v1, v2)).or_default().push(tri_info);
-        #[rustfmt::skip]
-        edge_map.entry(Edge::new(v2, v3)).or_default().push(tri_info);
-        #[rustfmt::skip]
-        edge_map.entry(Edge::new(v3, v1)).or_default().push(tri_info);
-    }
+    let edge_map_progress = progress.stage(0.0, 0.5);
+    edge_map_progress.update(0, tri_count)?;
+    let edge_map = EdgeMap::new(pos);
+    edge_map_progress.update(tri_count, tri_count)?;
 
     // Flag for each triangle if it has been visited.
     let mut visited = vec![false; tri_count];
@@ -107,10 +215,10 @@ pub fn split_disjoint_geometry(pos: &[f32]) -> Vec<Vec<f32>> {
     let mut part = vec![];
     let mut result = vec![];
 
-    // Helper function to visit an edge and add neighboring triangle to the stack. 
+    // Helper function to visit an edge and add neighboring triangle to the stack.
     let visit_edge = |stack: &mut Vec<usize>, visited: &mut [bool], v1: Vector3, v2: Vector3, v3: Vector3| {
         // We need the neighbor to have a reverse edge
-        if let Some(tris) = edge_map.get(&Edge::new(v2, v1)) {
+        if let Some(tris) = edge_map.get(Edge::new(v2, v1)) {
             let next_tri = find_next_triangle_with_shared_edge(v1, v2, v3, tris);
             if !visited[next_tri] {
                 stack.push(next_tri);
@@ -119,6 +227,8 @@ pub fn split_disjoint_geometry(pos: &[f32]) -> Vec<Vec<f32>> {
         }
     };
 
+    let search_progress = progress.stage(0.5, 1.0);
+    let mut visited_count = 0;
     for start_tri_idx in 0..tri_count {
         if visited[start_tri_idx] {
             continue;
@@ -130,6 +240,8 @@ pub fn split_disjoint_geometry(pos: &[f32]) -> Vec<Vec<f32>> {
         visited[start_tri_idx] = true;
 
         while let Some(next_tri_idx) = stack.pop() {
+            search_progress.update(visited_count, tri_count)?;
+            visited_count += 1;
             part.push(next_tri_idx);
 
             let off = next_tri_idx * 9;
@@ -143,28 +255,154 @@ pub fn split_disjoint_geometry(pos: &[f32]) -> Vec<Vec<f32>> {
             visit_edge(&mut stack, &mut visited, v3, v1, v2);
         }
 
-        // Copy triangle vertices into a new flat array
-        let mut part_pos = vec![0.0f32; part.len() * 9];
-        for (i, &tri_idx) in part.iter().enumerate() {
-            let src_start = tri_idx * 9;
-            let dst_start = i * 9;
-            part_pos[dst_start..dst_start + 9].copy_from_slice(&pos[src_start..src_start + 9]);
+        result.push(std::mem::take(&mut part));
+    }
+    search_progress.update(tri_count, tri_count)?;
+
+    Ok(result)
+}
+
+// Lock-free union-find. The root of each set is its smallest element, so the sets and their roots do not depend on the
+// order of the unions.
+struct UnionFind {
+    parents: Vec<AtomicU32>,
+}
+
+impl UnionFind {
+    fn new(len: usize) -> Self {
+        Self { parents: (0..len as u32).map(AtomicU32::new).collect() }
+    }
+
+    fn find(&self, mut x: u32) -> u32 {
+        loop {
+            let parent = self.parents[x as usize].load(Ordering::Relaxed);
+            if parent == x {
+                return x;
+            }
+            // Path halving. Parents only decrease, so if another thread changed the parent meanwhile, the grandparent
+            // is still an ancestor and losing the race only leaves a longer path.
+            let grandparent = self.parents[parent as usize].load(Ordering::Relaxed);
+            let _ =
+                self.parents[x as usize].compare_exchange(parent, grandparent, Ordering::Relaxed, Ordering::Relaxed);
+            x = grandparent;
+        }
+    }
+
+    fn union(&self, a: u32, b: u32) {
+        let (mut a, mut b) = (a, b);
+        loop {
+            a = self.find(a);
+            b = self.find(b);
+            if a == b {
+                return;
+            }
+            let (child, root) = if a > b { (a, b) } else { (b, a) };
+            // Fails if another thread has linked the child meanwhile, then retry from the new roots.
+            if self.parents[child as usize]
+                .compare_exchange(child, root, Ordering::Relaxed, Ordering::Relaxed)
+                .is_ok()
+            {
+                return;
+            }
+        }
+    }
+}
+
+/// Same partition as split_disjoint_triangles, but computed by a union-find over the triangle neighbors instead of the
+/// DFS, so that it runs in parallel with the threads feature. The neighbors are selected by the same rule
+/// (find_next_triangle_with_shared_edge). The parts are ordered by their first triangle as in split_disjoint_triangles,
+/// the triangles of each part are in ascending order.
+pub fn split_disjoint_triangles_union_find(pos: &[f32], progress: Progress) -> Result<Vec<Vec<usize>>, Error> {
+    check_triangle_soup(pos)?;
+    let tri_count = pos.len() / 9;
+
+    let edge_map_progress = progress.stage(0.0, 0.4);
+    edge_map_progress.update(0, tri_count)?;
+    let edge_map = EdgeMap::new(pos);
+    edge_map_progress.update(tri_count, tri_count)?;
+
+    let union_progress = progress.stage(0.4, 0.8);
+    union_progress.update(0, tri_count)?;
+    // The neighbor selected through each edge of each triangle, NO_NEIGHBOR if the edge is open.
+    const NO_NEIGHBOR: u32 = u32::MAX;
+    let neighbors = parallel::map_range(tri_count, |tri_idx| {
+        let off = tri_idx * 9;
+        let v1 = Vector3::new(pos[off], pos[off + 1], pos[off + 2]);
+        let v2 = Vector3::new(pos[off + 3], pos[off + 4], pos[off + 5]);
+        let v3 = Vector3::new(pos[off + 6], pos[off + 7], pos[off + 8]);
+        [(v1, v2, v3), (v2, v3, v1), (v3, v1, v2)].map(|(v1, v2, v3)| match edge_map.get(Edge::new(v2, v1)) {
+            Some(tris) => find_next_triangle_with_shared_edge(v1, v2, v3, tris) as u32,
+            None => NO_NEIGHBOR,
+        })
+    });
+    // Triangles selecting each other are always in the same part. For bodies with outward-facing normals all links are
+    // mutual, so the union-find does all the work.
+    let sets = UnionFind::new(tri_count);
+    parallel::for_each_range(tri_count, |tri_idx| {
+        for next_tri in neighbors[tri_idx] {
+            if next_tri != NO_NEIGHBOR && neighbors[next_tri as usize].contains(&(tri_idx as u32)) {
+                sets.union(tri_idx as u32, next_tri);
+            }
         }
-        result.push(part_pos);
-        part.clear();
+    });
+    let roots = parallel::map_range(tri_count, |tri_idx| sets.find(tri_idx as u32) as usize);
+    union_progress.update(tri_count, tri_count)?;
+
+    // A link which is not mutual only joins the parts when the DFS reaches its source first, which depends on the
+    // visiting order. Repeat the DFS over the sets (the DFS always visits a set as a whole) following these links.
+    let components_progress = progress.stage(0.8, 1.0);
+    let mut one_way_links: Vec<(usize, usize)> = (0..tri_count)
+        .flat_map(|tri_idx| neighbors[tri_idx].map(|next_tri| (tri_idx, next_tri)))
+        .filter(|&(tri_idx, next_tri)| next_tri != NO_NEIGHBOR && roots[tri_idx] != roots[next_tri as usize])
+        .map(|(tri_idx, next_tri)| (roots[tri_idx], roots[next_tri as usize]))
+        .collect();
+    one_way_links.sort_unstable();
+    let mut part_indices = vec![usize::MAX; tri_count];
+    let mut part_count = 0;
+    let mut stack = vec![];
+    for root in 0..tri_count {
+        components_progress.update(root, 2 * tri_count)?;
+        if roots[root] != root || part_indices[root] != usize::MAX {
+            continue;
+        }
+        part_indices[root] = part_count;
+        stack.push(root);
+        while let Some(set) = stack.pop() {
+            let first = one_way_links.partition_point(|&(from, _)| from < set);
+            for &(_, next_set) in one_way_links[first..]
+                .iter()
+                .take_while(|&&(from, _)| from == set)
+            {
+                if part_indices[next_set] == usize::MAX {
+                    part_indices[next_set] = part_count;
+                    stack.push(next_set);
+                }
+            }
+        }
+        part_count += 1;
     }
 
-    result
+    let mut result = vec![vec![]; part_count];
+    for (tri_idx, &root) in roots.iter().enumerate() {
+        components_progress.update(tri_count + tri_idx, 2 * tri_count)?;
+        result[part_indices[root]].push(tri_idx);
+    }
+    components_progress.update(2 * tri_count, 2 * tri_count)?;
+    Ok(result)
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::parallel::tests::with_threads;
+    use crate::plane_section::tests::create_box_geometry;
+    use crate::progress::tests::{assert_complete, run_with_progress};
+    use crate::slicer::tests::create_sphere_geometry;
     use std::collections::HashSet;
 
     // Helper function similar to TypeScript's getTris
     fn get_tris_set(pos: &[f32]) -> HashSet<String> {
-        if pos.len() % 9 != 0 {
+        if !pos.len().is_multiple_of(9) {
             panic!("Position array length must be multiple of 9");
         }
         let mut result = HashSet::new();
@@ -190,17 +428,31 @@ mod tests {
         result
     }
 
+    // Splits the geometry and checks that the union-find gives the same partition as the DFS.
+    fn split_checked(pos: &[f32]) -> Vec<Vec<f32>> {
+        let mut dfs_parts = split_disjoint_triangles(pos).unwrap();
+        for part in &mut dfs_parts {
+            part.sort();
+        }
+        assert_eq!(split_disjoint_triangles_union_find(pos, Progress::none()).unwrap(), dfs_parts);
+        split_disjoint_geometry(pos).unwrap()
+    }
+
     #[test]
     fn test_empty() {
-        let result = split_disjoint_geometry(&[]);
+        let result = split_checked(&[]);
         assert_eq!(result.len(), 0);
+        assert_eq!(
+            split_disjoint_geometry(&[0.0; 10]),
+            Err(Error::InvalidInput("triangle soup length 10 is not a multiple of 9".to_string()))
+        );
     }
 
     #[test]
     fn test_single_triangle() {
         // A single triangle
         let pos = [0.0, 0.0, 0.0, 1.0, 0.0, 0.0, 0.0, 1.0, 0.0];
-        let result = split_disjoint_geometry(&pos);
+        let result = split_checked(&pos);
         assert_eq!(result.len(), 1);
         assert_eq!(result[0], pos);
     }
@@ -213,7 +465,7 @@ mod tests {
             0.0, 0.0, 0.0, 1.0, 0.0, 0.0, 0.0, 1.0, 0.0, // triangle 2
             10.0, 0.0, 0.0, 11.0, 0.0, 0.0, 10.0, 1.0, 0.0,
         ];
-        let result = split_disjoint_geometry(&pos);
+        let result = split_checked(&pos);
         assert_eq!(result.len(), 2);
         // Each part should have 9 floats
         assert_eq!(result[0].len(), 9);
@@ -243,7 +495,7 @@ mod tests {
         let mut merged = cube1.clone();
         merged.extend_from_slice(&cube2);
 
-        let parts = split_disjoint_geometry(&merged);
+        let parts = split_checked(&merged);
         // println!("Two separate cubes: found {} parts", parts.len());
         assert_eq!(parts.len(), 2, "Two separate cubes should be 2 parts");
 
@@ -295,7 +547,7 @@ mod tests {
         let cube_pos = create_cube_geometry();
         let cube_tris = get_tris_set(&cube_pos);
 
-        let parts = split_disjoint_geometry(&cube_pos);
+        let parts = split_checked(&cube_pos);
         assert_eq!(parts.len(), 1, "Single cube should be one part");
 
         let part_tris = get_tris_set(&parts[0]);
@@ -326,7 +578,7 @@ mod tests {
         let cube1_tris = get_tris_set(&cube1);
         let cube2_tris = get_tris_set(&cube2_translated);
 
-        let parts = split_disjoint_geometry(&merged);
+        let parts = split_checked(&merged);
         assert_eq!(parts.len(), 2, "Expected 2 parts for contacting cubes, got {}", parts.len());
 
         // Check that each part matches one of the original cubes
@@ -369,7 +621,7 @@ mod tests {
         let angle0_tris = get_tris_set(&angle0);
         let angle1_tris = get_tris_set(&angle1);
 
-        let parts = split_disjoint_geometry(&merged);
+        let parts = split_checked(&merged);
         assert_eq!(parts.len(), 2);
 
         let part0_tris = get_tris_set(&parts[0]);
@@ -428,7 +680,7 @@ mod tests {
         let num_geo_tris = pos.len() / 9;
 
         // Check that we don't hang and have some basic sanity checks
-        let parts = split_disjoint_geometry(&pos);
+        let parts = split_checked(&pos);
         assert!(parts.len() > 1);
         assert!(parts.len() < num_geo_tris);
 
@@ -440,4 +692,63 @@ mod tests {
         }
         assert_eq!(&reassembled_tris, &original_tris);
     }
+
+    #[test]
+    fn test_progress() {
+        let mut pos = create_sphere_geometry(1.0, 32, 16);
+        pos.extend(create_box_geometry(Vector3::new(2.0, 0.0, 0.0), Vector3::new(3.0, 1.0, 1.0)));
+        let expected = split_disjoint_triangles(&pos).unwrap();
+        let (result, fractions) =
+            run_with_progress(2.0, |progress| split_disjoint_triangles_with_progress(&pos, progress));
+        assert_eq!(result, Ok(expected));
+        assert_complete(&fractions);
+
+        let (result, fractions) =
+            run_with_progress(0.6, |progress| split_disjoint_triangles_with_progress(&pos, progress));
+        assert_eq!(result, Err(Error::Cancelled));
+        assert!(fractions.last().unwrap() < &0.7);
+
+        let (result, fractions) =
+            run_with_progress(2.0, |progress| split_disjoint_triangles_union_find(&pos, progress));
+        assert_eq!(result.unwrap().len(), 2);
+        assert_complete(&fractions);
+        let (result, _) = run_with_progress(0.9, |progress| split_disjoint_triangles_union_find(&pos, progress));
+        assert_eq!(result, Err(Error::Cancelled));
+    }
+
+    #[test]
+    fn test_threads() {
+        let mut pos = create_sphere_geometry(1.0, 128, 64);
+        for i in 0..20 {
+            let min = Vector3::new(2.0 + 2.0 * i as f32, 0.0, 0.0);
+            pos.extend(create_box_geometry(min, min + Vector3::new(1.0, 1.0, 1.0)));
+        }
+        let expected = with_threads(1, || split_disjoint_triangles(&pos).unwrap());
+        assert_eq!(expected.len(), 21);
+        assert_eq!(with_threads(4, || split_disjoint_triangles(&pos).unwrap()), expected);
+        let union_find = || split_disjoint_triangles_union_find(&pos, Progress::none()).unwrap();
+        let expected = with_threads(1, union_find);
+        assert_eq!(expected.len(), 21);
+        assert_eq!(with_threads(4, union_find), expected);
+    }
+
+    #[test]
+    fn test_enclosing_parts() {
+        let mut outer = create_box_geometry(Vector3::new(0.0, 0.0, 0.0), Vector3::new(10.0, 10.0, 10.0));
+        // The cavity faces inward.
+        let cavity: Vec<f32> = create_box_geometry(Vector3::new(2.0, 2.0, 2.0), Vector3::new(8.0, 8.0, 8.0))
+            .chunks_exact(9)
+            .flat_map(|t| [&t[0..3], &t[6..9], &t[3..6]].concat())
+            .collect();
+        let island = create_box_geometry(Vector3::new(4.0, 4.0, 4.0), Vector3::new(5.0, 5.0, 5.0));
+        let separate = create_box_geometry(Vector3::new(20.0, 0.0, 0.0), Vector3::new(21.0, 1.0, 1.0));
+        outer.extend(cavity.iter().chain(&island).chain(&separate));
+        let parts = split_disjoint_geometry(&outer).unwrap();
+        assert_eq!(parts.len(), 4);
+        let volumes: Vec<f32> = parts.iter().map(|p| compute_mesh_stats(p).volume).collect();
+        assert_eq!(volumes, [1000.0, -216.0, 1.0, 1.0]);
+        assert_eq!(enclosing_parts(&parts), [0, 0, 2, 3]);
+        // A cavity without the enclosing part stays alone.
+        assert_eq!(enclosing_parts(&parts[1..]), [0, 1, 2]);
+    }
 }
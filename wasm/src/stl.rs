@@ -0,0 +1,314 @@
+use std::collections::HashMap;
+
+use wasm_bindgen::prelude::*;
+
+use crate::error::Error;
+use crate::model::{DEFAULT_PART_NAME, ImportedModel, Model, ModelPart};
+
+pub const BINARY_HEADER_SIZE: usize = 84;
+pub const BINARY_FACET_SIZE: usize = 50;
+
+/// Returns true if the data looks like a binary STL: the facet count in the header matches the data size. ASCII STLs
+/// can not satisfy this by chance in practice, while binary STLs often start with "solid" in the header.
+pub fn is_binary_stl(data: &[u8]) -> bool {
+    data.len() >= BINARY_HEADER_SIZE
+        && BINARY_HEADER_SIZE as u64 + binary_facet_count(data) as u64 * BINARY_FACET_SIZE as u64 == data.len() as u64
+}
+
+/// Returns true if the data looks like an ASCII STL.
+pub fn is_ascii_stl(data: &[u8]) -> bool {
+    let start = data
+        .iter()
+        .position(|c| !c.is_ascii_whitespace())
+        .unwrap_or(data.len());
+    let data = &data[start..];
+    let prefix = &data[..data.len().min(1024)];
+    data.starts_with(b"solid")
+        && (prefix.windows(5).any(|w| w == b"facet") || prefix.windows(8).any(|w| w == b"endsolid"))
+}
+
+fn binary_facet_count(data: &[u8]) -> u32 {
+    u32::from_le_bytes(data[80..84].try_into().unwrap())
+}
+
+fn expand_5bit(c: u16) -> u8 {
+    let c = (c & 0x1f) as u8;
+    (c << 3) | (c >> 2)
+}
+
+// Decodes the facet color. Materialise Magics files have "COLOR=" in the header, red in the lowest bits and bit 15
+// cleared for facets with their own color. VisCAM and SolidView files have blue in the lowest bits and bit 15 set.
+fn decode_color(attribute: u16, materialise: bool, default: Option<[u8; 4]>) -> Option<[u8; 4]> {
+    let (low, mid, high) = (expand_5bit(attribute), expand_5bit(attribute >> 5), expand_5bit(attribute >> 10));
+    match (materialise, attribute & 0x8000 != 0) {
+        (true, false) => Some([low, mid, high, 255]),
+        (true, true) => default,
+        (false, true) => Some([high, mid, low, 255]),
+        (false, false) => None,
+    }
+}
+
+fn header_text(header: &[u8]) -> Option<String> {
+    let end = header.iter().position(|&c| c == 0).unwrap_or(header.len());
+    let text = std::str::from_utf8(&header[..end]).ok()?.trim();
+    let printable = !text.is_empty() && text.chars().all(|c| !c.is_control());
+    printable.then(|| text.to_string())
+}
+
+fn parse_binary_stl(data: &[u8]) -> Result<Model, Error> {
+    if data.len() < BINARY_HEADER_SIZE {
+        return Err(Error::TruncatedFile("binary STL is too short".to_string()));
+    }
+    let mut model = Model::default();
+    let header = &data[..80];
+    if let Some(text) = header_text(header) {
+        model.metadata.push(("header".to_string(), text));
+    }
+    let materialise_color = header.windows(10).find_map(|w| {
+        let color = w.strip_prefix(b"COLOR=")?;
+        Some([color[0], color[1], color[2], color[3]])
+    });
+
+    let declared = binary_facet_count(data) as usize;
+    let available = (data.len() - BINARY_HEADER_SIZE) / BINARY_FACET_SIZE;
+    if declared > available {
+        return Err(Error::TruncatedFile(format!(
+            "binary STL is truncated: {} facets declared, {} present",
+            declared, available
+        )));
+    }
+    let facets_end = BINARY_HEADER_SIZE + declared * BINARY_FACET_SIZE;
+    if data.len() > facets_end {
+        model.warn(0, format!("{} bytes of trailing data after the facets", data.len() - facets_end));
+    }
+
+    // Facets are grouped into parts by color.
+    let mut part_ids: HashMap<Option<[u8; 4]>, usize> = HashMap::new();
+    for facet in data[BINARY_HEADER_SIZE..]
+        .chunks_exact(BINARY_FACET_SIZE)
+        .take(declared)
+    {
+        let attribute = u16::from_le_bytes([facet[48], facet[49]]);
+        let color = decode_color(attribute, materialise_color.is_some(), materialise_color);
+        let part_id = *part_ids.entry(color).or_insert_with(|| {
+            let name = match color {
+                Some([r, g, b, _]) => format!("{} #{:02X}{:02X}{:02X}", DEFAULT_PART_NAME, r, g, b),
+                None => DEFAULT_PART_NAME.to_string(),
+            };
+            model
+                .parts
+                .push(ModelPart { name, color, ..Default::default() });
+            model.parts.len() - 1
+        });
+        // Skip the normal, it is recomputed from the vertex order.
+        let positions = &mut model.parts[part_id].positions;
+        for v in facet[12..48].chunks_exact(4) {
+            positions.push(f32::from_le_bytes(v.try_into().unwrap()));
+        }
+    }
+    if let [part] = model.parts.as_mut_slice() {
+        part.name = DEFAULT_PART_NAME.to_string();
+    }
+    Ok(model)
+}
+
+fn parse_ascii_stl(text: &str) -> Model {
+    let mut model = Model::default();
+    let mut part: Option<ModelPart> = None;
+    let mut facet = vec![];
+    for (line_idx, line) in text.lines().enumerate() {
+        let line_number = line_idx + 1;
+        let mut tokens = line.split_whitespace();
+        match tokens.next() {
+            Some("solid") => {
+                if let Some(part) = part.take() {
+                    model.warn(line_number, "missing endsolid".to_string());
+                    model.parts.push(part);
+                }
+                let name = line.trim_start()["solid".len()..].trim();
+                let name = if name.is_empty() { DEFAULT_PART_NAME } else { name };
+                part = Some(ModelPart { name: name.to_string(), ..Default::default() });
+            }
+            Some("facet") => facet.clear(),
+            Some("vertex") => {
+                let coords: Vec<f32> = tokens.filter_map(|t| t.parse().ok()).collect();
+                if coords.len() != 3 || coords.iter().any(|c| !c.is_finite()) {
+                    model.warn(line_number, "invalid vertex".to_string());
+                    // The facet is dropped at endloop.
+                    facet.push(f32::NAN);
+                    continue;
+                }
+                facet.extend_from_slice(&coords);
+            }
+            Some("endloop") => {
+                let Some(part) = &mut part else {
+                    model.warn(line_number, "facet outside of solid".to_string());
+                    continue;
+                };
+                if facet.len() == 9 {
+                    part.positions.extend_from_slice(&facet);
+                } else {
+                    model.warn(line_number, "facet does not have 3 valid vertices".to_string());
+                }
+                facet.clear();
+            }
+            Some("endsolid") => {
+                if let Some(part) = part.take() {
+                    model.parts.push(part);
+                }
+            }
+            Some("outer" | "endfacet") | None => {}
+            Some(keyword) => model.warn(line_number, format!("unknown keyword '{}'", keyword)),
+        }
+    }
+    if let Some(part) = part {
+        model.warn(0, "missing endsolid".to_string());
+        model.parts.push(part);
+    }
+    for part in &model.parts {
+        if part.name != DEFAULT_PART_NAME {
+            model
+                .metadata
+                .push(("solid".to_string(), part.name.clone()));
+        }
+    }
+    model
+}
+
+/// Parses a binary or ASCII STL. Binary facets are grouped into parts by their color (see decode_color), each solid of
+/// an ASCII STL becomes a part named after the solid. Facet normals are ignored.
+pub fn parse_stl(data: &[u8]) -> Result<Model, Error> {
+    if is_binary_stl(data) {
+        return parse_binary_stl(data);
+    }
+    if is_ascii_stl(data) {
+        let text = String::from_utf8_lossy(data);
+        return Ok(parse_ascii_stl(&text));
+    }
+    // Some writers add padding after the facets, which breaks the size check.
+    parse_binary_stl(data)
+}
+
+/// See parse_stl.
+#[wasm_bindgen]
+pub fn load_stl(data: &[u8]) -> Result<ImportedModel, Error> {
+    parse_stl(data).map(ImportedModel::new)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::stl_writer::{FacetColors, write_ascii_stl, write_binary_stl};
+
+    const TRIS: [f32; 18] = [
+        0.0, 0.0, 0.0, 1.0, 0.0, 0.0, 0.0, 1.0, 0.0, //
+        0.0, 0.0, 1.0, 1.0, 0.0, 1.0, 0.0, 1.0, 1.0,
+    ];
+
+    #[test]
+    fn test_binary() {
+        let data = write_binary_stl(&TRIS, FacetColors::None);
+        assert!(is_binary_stl(&data));
+        assert!(!is_ascii_stl(&data));
+        let model = parse_stl(&data).unwrap();
+        assert_eq!(model.parts.len(), 1);
+        assert_eq!(model.parts[0].name, DEFAULT_PART_NAME);
+        assert_eq!(model.parts[0].positions, TRIS);
+        assert_eq!(model.parts[0].color, None);
+        assert_eq!(model.metadata, vec![("header".to_string(), "Binary STL written by stl-web-viewer".to_string())]);
+    }
+
+    #[test]
+    fn test_binary_colors() {
+        let data = write_binary_stl(&TRIS, FacetColors::PerFacet(&[255, 0, 0, 0, 0, 255]));
+        let model = parse_stl(&data).unwrap();
+        assert_eq!(model.parts.len(), 2);
+        assert_eq!(model.parts[0].name, "Mesh #FF0000");
+        assert_eq!(model.parts[0].color, Some([255, 0, 0, 255]));
+        assert_eq!(model.parts[0].positions, TRIS[..9]);
+        assert_eq!(model.parts[1].color, Some([0, 0, 255, 255]));
+
+        // A single color gives a single part with the default name.
+        let data = write_binary_stl(&TRIS, FacetColors::Uniform([0, 255, 0]));
+        let model = parse_stl(&data).unwrap();
+        assert_eq!(model.parts.len(), 1);
+        assert_eq!(model.parts[0].name, DEFAULT_PART_NAME);
+        assert_eq!(model.parts[0].color, Some([0, 255, 0, 255]));
+    }
+
+    #[test]
+    fn test_materialise_colors() {
+        assert_eq!(decode_color(0x001f, true, None), Some([255, 0, 0, 255]));
+        assert_eq!(decode_color(0x801f, true, Some([1, 2, 3, 4])), Some([1, 2, 3, 4]));
+        assert_eq!(decode_color(0x801f, false, None), Some([0, 0, 255, 255]));
+        assert_eq!(decode_color(0x001f, false, None), None);
+
+        let mut data = write_binary_stl(&TRIS, FacetColors::None);
+        data[..80].fill(b' ');
+        data[..10].copy_from_slice(b"COLOR=\x10\x20\x30\xff");
+        // The first facet uses the default color, the second has its own.
+        data[84 + 48..84 + 50].copy_from_slice(&0x8000u16.to_le_bytes());
+        data[134 + 48..134 + 50].copy_from_slice(&(0x1fu16 << 5).to_le_bytes());
+        let model = parse_stl(&data).unwrap();
+        assert_eq!(model.parts[0].color, Some([0x10, 0x20, 0x30, 0xff]));
+        assert_eq!(model.parts[1].color, Some([0, 255, 0, 255]));
+    }
+
+    #[test]
+    fn test_binary_errors() {
+        let mut data = write_binary_stl(&TRIS, FacetColors::None);
+        data.extend_from_slice(&[0; 7]);
+        let model = parse_stl(&data).unwrap();
+        assert_eq!(model.parts[0].positions, TRIS);
+        assert_eq!(model.warnings.len(), 1);
+        assert!(parse_stl(&data[..100]).is_err());
+        assert!(parse_stl(b"").is_err());
+    }
+
+    #[test]
+    fn test_ascii() {
+        let mut text = write_ascii_stl(&TRIS[..9], "first");
+        text.push_str(&write_ascii_stl(&TRIS[9..], ""));
+        assert!(is_ascii_stl(text.as_bytes()));
+        assert!(!is_binary_stl(text.as_bytes()));
+        let model = parse_stl(text.as_bytes()).unwrap();
+        assert!(model.warnings.is_empty());
+        assert_eq!(model.parts.len(), 2);
+        assert_eq!(model.parts[0].name, "first");
+        assert_eq!(model.parts[0].positions, TRIS[..9]);
+        assert_eq!(model.parts[1].name, DEFAULT_PART_NAME);
+        assert_eq!(model.parts[1].positions, TRIS[9..]);
+        assert_eq!(model.metadata, vec![("solid".to_string(), "first".to_string())]);
+    }
+
+    #[test]
+    fn test_ascii_errors() {
+        let text = "solid x
+facet normal 0 0 1
+outer loop
+vertex 0 0 0
+vertex 1 0 x
+vertex 0 1 0
+endloop
+endfacet
+facet normal 0 0 1
+outer loop
+vertex 0 0 0
+vertex 1 0 0
+vertex 0 1 0
+endloop
+endfacet
+bogus
+";
+        let model = parse_stl(text.as_bytes()).unwrap();
+        assert_eq!(model.parts[0].positions.len(), 9);
+        let lines: Vec<usize> = model.warnings.iter().map(|w| w.line).collect();
+        assert_eq!(lines, vec![5, 7, 16, 0]);
+    }
+
+    #[test]
+    fn test_wasm_wrapper() {
+        let model = load_stl(&write_binary_stl(&TRIS, FacetColors::None)).unwrap();
+        assert_eq!(model.positions().len(), 18);
+    }
+}
@@ -0,0 +1,423 @@
+use wasm_bindgen::prelude::*;
+
+use crate::error::{Error, checked_item};
+use crate::model::ModelWarning;
+use crate::stl::{BINARY_FACET_SIZE, BINARY_HEADER_SIZE, is_ascii_stl};
+
+// Bytes collected before choosing between binary and ASCII STL, see is_ascii_stl.
+const DETECT_LEN: usize = 1024;
+// Protects from accumulating a binary file misdetected as ASCII in a single line.
+const MAX_LINE_LEN: usize = 1 << 16;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Encoding {
+    Unknown,
+    /// The facet count is known after the header is read.
+    Binary(Option<usize>),
+    Ascii,
+}
+
+/// Incremental STL parser for files which are read in chunks (e.g. from a stream), so that the whole file never has to
+/// be in memory. Chunks are passed to push() in order, the parsed triangles are accumulated until take_positions() is
+/// called, and finish() checks that the file is complete. Binary and ASCII STLs are supported; unlike parse_stl, all
+/// triangles go into a single soup and facet colors are ignored.
+#[wasm_bindgen]
+pub struct StlStreamParser {
+    total_size: u64,
+    bytes_received: u64,
+    encoding: Encoding,
+    // The beginning of the file until the encoding is known, then an incomplete facet or line.
+    pending: Vec<u8>,
+    facets_read: usize,
+    trailing_bytes: usize,
+    line_number: usize,
+    facet: Vec<f32>,
+    positions: Vec<f32>,
+    triangle_count: usize,
+    warnings: Vec<ModelWarning>,
+    finished: bool,
+}
+
+impl StlStreamParser {
+    fn detect_encoding(&mut self) {
+        let size_matches = self.pending.len() >= BINARY_HEADER_SIZE && {
+            let count = u32::from_le_bytes(self.pending[80..84].try_into().unwrap()) as u64;
+            BINARY_HEADER_SIZE as u64 + count * BINARY_FACET_SIZE as u64 == self.total_size
+        };
+        self.encoding =
+            if !size_matches && is_ascii_stl(&self.pending) { Encoding::Ascii } else { Encoding::Binary(None) };
+    }
+
+    fn process(&mut self, data: &[u8]) -> Result<(), Error> {
+        match self.encoding {
+            Encoding::Unknown => unreachable!(),
+            Encoding::Binary(_) => self.process_binary(data),
+            Encoding::Ascii => self.process_ascii(data),
+        }
+    }
+
+    fn process_binary(&mut self, mut data: &[u8]) -> Result<(), Error> {
+        // Completes the header or the facet from the previous chunks.
+        let mut fill_pending = |pending: &mut Vec<u8>, len: usize| {
+            let n = (len - pending.len()).min(data.len());
+            pending.extend_from_slice(&data[..n]);
+            data = &data[n..];
+            pending.len() == len
+        };
+        if self.encoding == Encoding::Binary(None) {
+            if !fill_pending(&mut self.pending, BINARY_HEADER_SIZE) {
+                return Ok(());
+            }
+            let declared = u32::from_le_bytes(self.pending[80..84].try_into().unwrap()) as usize;
+            self.encoding = Encoding::Binary(Some(declared));
+            self.pending.clear();
+        }
+        if !self.pending.is_empty() {
+            if !fill_pending(&mut self.pending, BINARY_FACET_SIZE) {
+                return Ok(());
+            }
+            let facet = std::mem::take(&mut self.pending);
+            self.binary_facet(&facet);
+        }
+        // The declared count is not trusted for the allocation, only the facets present in the chunk are reserved.
+        let Encoding::Binary(Some(declared)) = self.encoding else { unreachable!() };
+        let expected = (data.len() / BINARY_FACET_SIZE).min(declared - self.facets_read);
+        self.positions
+            .try_reserve(expected * 9)
+            .map_err(|_| Error::OutOfMemory(format!("not enough memory for {} facets", expected)))?;
+        let mut facets = data.chunks_exact(BINARY_FACET_SIZE);
+        for facet in &mut facets {
+            self.binary_facet(facet);
+        }
+        self.pending.extend_from_slice(facets.remainder());
+        Ok(())
+    }
+
+    fn binary_facet(&mut self, facet: &[u8]) {
+        let Encoding::Binary(Some(declared)) = self.encoding else { unreachable!() };
+        if self.facets_read == declared {
+            self.trailing_bytes += facet.len();
+            return;
+        }
+        self.facets_read += 1;
+        // Skip the normal, it is recomputed from the vertex order.
+        for v in facet[12..48].chunks_exact(4) {
+            self.positions
+                .push(f32::from_le_bytes(v.try_into().unwrap()));
+        }
+        self.triangle_count += 1;
+    }
+
+    fn process_ascii(&mut self, data: &[u8]) -> Result<(), Error> {
+        for piece in data.split_inclusive(|&c| c == b'\n') {
+            if !piece.ends_with(b"\n") {
+                self.pending.extend_from_slice(piece);
+                if self.pending.len() > MAX_LINE_LEN {
+                    return Err(Error::InvalidInput(format!("line {} is too long", self.line_number + 1)));
+                }
+                continue;
+            }
+            if self.pending.is_empty() {
+                self.ascii_line(piece);
+            } else {
+                let mut line = std::mem::take(&mut self.pending);
+                line.extend_from_slice(piece);
+                self.ascii_line(&line);
+            }
+        }
+        Ok(())
+    }
+
+    // Follows parse_ascii_stl, except that the solids are not tracked.
+    fn ascii_line(&mut self, line: &[u8]) {
+        self.line_number += 1;
+        let line = String::from_utf8_lossy(line);
+        let mut tokens = line.split_whitespace();
+        match tokens.next() {
+            Some("facet") => self.facet.clear(),
+            Some("vertex") => {
+                let coords: Vec<f32> = tokens.filter_map(|t| t.parse().ok()).collect();
+                if coords.len() != 3 || coords.iter().any(|c| !c.is_finite()) {
+                    self.warn("invalid vertex".to_string());
+                    // The facet is dropped at endloop.
+                    self.facet.push(f32::NAN);
+                    return;
+                }
+                self.facet.extend_from_slice(&coords);
+            }
+            Some("endloop") => {
+                if self.facet.len() == 9 {
+                    self.positions.extend_from_slice(&self.facet);
+                    self.triangle_count += 1;
+                } else {
+                    self.warn("facet does not have 3 valid vertices".to_string());
+                }
+                self.facet.clear();
+            }
+            _ => {}
+        }
+    }
+
+    fn warn(&mut self, message: String) {
+        self.warnings
+            .push(ModelWarning { line: self.line_number, message });
+    }
+}
+
+#[wasm_bindgen]
+impl StlStreamParser {
+    /// Creates a parser for a file of `total_size` bytes, 0 if the size is unknown. The size is used for the progress
+    /// and to tell binary STLs with "solid" in the header from ASCII ones.
+    #[wasm_bindgen(constructor)]
+    pub fn new(total_size: f64) -> StlStreamParser {
+        StlStreamParser {
+            total_size: total_size.max(0.0) as u64,
+            bytes_received: 0,
+            encoding: Encoding::Unknown,
+            pending: vec![],
+            facets_read: 0,
+            trailing_bytes: 0,
+            line_number: 0,
+            facet: vec![],
+            positions: vec![],
+            triangle_count: 0,
+            warnings: vec![],
+            finished: false,
+        }
+    }
+
+    /// Parses the next chunk of the file.
+    pub fn push(&mut self, chunk: &[u8]) -> Result<(), Error> {
+        if self.finished {
+            return Err(Error::InvalidInput("the parser is already finished".to_string()));
+        }
+        self.bytes_received += chunk.len() as u64;
+        if self.encoding != Encoding::Unknown {
+            return self.process(chunk);
+        }
+        self.pending.extend_from_slice(chunk);
+        let complete = self.total_size > 0 && self.bytes_received >= self.total_size;
+        if self.pending.len() < DETECT_LEN && !complete {
+            return Ok(());
+        }
+        self.detect_encoding();
+        let pending = std::mem::take(&mut self.pending);
+        self.process(&pending)
+    }
+
+    /// Parses the remaining data and checks that the file is complete. Trailing data after the binary facets is
+    /// reported as a warning.
+    pub fn finish(&mut self) -> Result<(), Error> {
+        if self.finished {
+            return Ok(());
+        }
+        self.finished = true;
+        if self.encoding == Encoding::Unknown {
+            self.detect_encoding();
+            let pending = std::mem::take(&mut self.pending);
+            self.process(&pending)?;
+        }
+        match self.encoding {
+            Encoding::Binary(None) => Err(Error::TruncatedFile("binary STL is too short".to_string())),
+            Encoding::Binary(Some(declared)) => {
+                if self.facets_read < declared {
+                    return Err(Error::TruncatedFile(format!(
+                        "binary STL is truncated: {} facets declared, {} present",
+                        declared, self.facets_read
+                    )));
+                }
+                let trailing = self.trailing_bytes + self.pending.len();
+                if trailing > 0 {
+                    self.warn(format!("{} bytes of trailing data after the facets", trailing));
+                }
+                Ok(())
+            }
+            _ => {
+                if !self.pending.is_empty() {
+                    let line = std::mem::take(&mut self.pending);
+                    self.ascii_line(&line);
+                }
+                Ok(())
+            }
+        }
+    }
+
+    /// True for binary STL, false for ASCII, undefined until enough data is received.
+    #[wasm_bindgen(getter)]
+    pub fn is_binary(&self) -> Option<bool> {
+        match self.encoding {
+            Encoding::Unknown => None,
+            Encoding::Binary(_) => Some(true),
+            Encoding::Ascii => Some(false),
+        }
+    }
+
+    #[wasm_bindgen(getter)]
+    pub fn bytes_received(&self) -> f64 {
+        self.bytes_received as f64
+    }
+
+    /// Fraction of the file parsed so far, from 0 to 1. Without the total size, only binary STLs report the progress
+    /// (from the facet count in the header).
+    #[wasm_bindgen(getter)]
+    pub fn progress(&self) -> f64 {
+        if self.finished {
+            return 1.0;
+        }
+        if self.total_size > 0 {
+            return (self.bytes_received as f64 / self.total_size as f64).min(1.0);
+        }
+        match self.encoding {
+            Encoding::Binary(Some(declared)) if declared > 0 => self.facets_read as f64 / declared as f64,
+            _ => 0.0,
+        }
+    }
+
+    /// Number of triangles parsed so far, including the ones already taken.
+    #[wasm_bindgen(getter)]
+    pub fn triangle_count(&self) -> usize {
+        self.triangle_count
+    }
+
+    /// Returns the triangles parsed since the previous call as a triangle soup, 9 floats per triangle.
+    pub fn take_positions(&mut self) -> Box<[f32]> {
+        std::mem::take(&mut self.positions).into_boxed_slice()
+    }
+
+    #[wasm_bindgen(getter)]
+    pub fn warning_count(&self) -> usize {
+        self.warnings.len()
+    }
+
+    pub fn warning_line(&self, idx: usize) -> Result<usize, Error> {
+        Ok(checked_item(&self.warnings, idx, "warning")?.line)
+    }
+
+    pub fn warning_message(&self, idx: usize) -> Result<String, Error> {
+        Ok(checked_item(&self.warnings, idx, "warning")?
+            .message
+            .clone())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::stl::parse_stl;
+    use crate::stl_writer::{FacetColors, write_ascii_stl, write_binary_stl};
+
+    fn triangles(count: usize) -> Vec<f32> {
+        (0..count * 9).map(|i| i as f32 * 0.5).collect()
+    }
+
+    // Streams the data in chunks of the given size, taking the positions after each chunk.
+    fn stream(data: &[u8], total_size: usize, chunk_size: usize) -> (StlStreamParser, Result<Vec<f32>, Error>) {
+        let mut parser = StlStreamParser::new(total_size as f64);
+        let mut positions = vec![];
+        for chunk in data.chunks(chunk_size) {
+            if let Err(e) = parser.push(chunk) {
+                return (parser, Err(e));
+            }
+            positions.extend_from_slice(&parser.take_positions());
+        }
+        let result = parser.finish();
+        positions.extend_from_slice(&parser.take_positions());
+        (parser, result.map(|_| positions))
+    }
+
+    #[test]
+    fn test_binary() {
+        let tris = triangles(100);
+        let mut data = write_binary_stl(&tris, FacetColors::None);
+        // Binary STLs may start with "solid".
+        data[..6].copy_from_slice(b"solid ");
+        for total_size in [0, data.len()] {
+            for chunk_size in [1, 7, 50, 1000, data.len()] {
+                let (parser, positions) = stream(&data, total_size, chunk_size);
+                assert_eq!(positions.unwrap(), tris, "{} {}", total_size, chunk_size);
+                assert_eq!(parser.is_binary(), Some(true));
+                assert_eq!(parser.triangle_count(), 100);
+                assert_eq!(parser.progress(), 1.0);
+                assert_eq!(parser.warning_count(), 0);
+            }
+        }
+    }
+
+    #[test]
+    fn test_ascii() {
+        let tris = triangles(50);
+        let mut text = write_ascii_stl(&tris, "a");
+        text.push_str("solid b\nfacet normal 0 0 1\nouter loop\nvertex 1 2\nvertex 0 0 0\nvertex 0 0 0\nendloop\n");
+        // The last line has no line break.
+        text.push_str("endfacet\nendsolid b");
+        let expected = parse_stl(text.as_bytes()).unwrap();
+        for total_size in [0, text.len()] {
+            for chunk_size in [1, 13, 4096, text.len()] {
+                let (parser, positions) = stream(text.as_bytes(), total_size, chunk_size);
+                assert_eq!(positions.unwrap(), tris, "{} {}", total_size, chunk_size);
+                assert_eq!(parser.is_binary(), Some(false));
+                assert_eq!(parser.warning_count(), 2);
+                assert_eq!(parser.warning_line(0).unwrap(), expected.warnings[0].line);
+                assert_eq!(parser.warning_message(1).unwrap(), expected.warnings[1].message);
+                assert!(parser.warning_line(2).is_err());
+            }
+        }
+    }
+
+    #[test]
+    fn test_progress() {
+        let data = write_binary_stl(&triangles(30), FacetColors::None);
+        let mut parser = StlStreamParser::new(0.0);
+        assert_eq!(parser.is_binary(), None);
+        parser
+            .push(&data[..BINARY_HEADER_SIZE + 5 * BINARY_FACET_SIZE])
+            .unwrap();
+        // The encoding is not known yet without the total size.
+        assert_eq!(parser.progress(), 0.0);
+        parser
+            .push(&data[BINARY_HEADER_SIZE + 5 * BINARY_FACET_SIZE..])
+            .unwrap();
+        assert_eq!(parser.progress(), 1.0);
+        assert_eq!(parser.triangle_count(), 30);
+
+        let mut parser = StlStreamParser::new(data.len() as f64 * 2.0);
+        parser.push(&data).unwrap();
+        assert_eq!(parser.progress(), 0.5);
+        assert_eq!(parser.bytes_received(), data.len() as f64);
+    }
+
+    #[test]
+    fn test_errors() {
+        let data = write_binary_stl(&triangles(10), FacetColors::None);
+        let (parser, result) = stream(&data[..data.len() - 1], 0, 100);
+        assert_eq!(
+            result.unwrap_err(),
+            Error::TruncatedFile("binary STL is truncated: 10 facets declared, 9 present".to_string())
+        );
+        assert_eq!(parser.triangle_count(), 9);
+        assert!(stream(&data[..50], 0, 100).1.is_err());
+
+        // A bogus declared count without the file size must not be allocated upfront.
+        let mut bogus = write_binary_stl(&triangles(2), FacetColors::None);
+        bogus[80..84].copy_from_slice(&u32::MAX.to_le_bytes());
+        let mut parser = StlStreamParser::new(0.0);
+        parser.push(&bogus).unwrap();
+        assert!(parser.positions.capacity() < 100);
+        assert_eq!(parser.finish().unwrap_err().code(), "TRUNCATED_FILE");
+        assert_eq!(parser.take_positions().to_vec(), triangles(2));
+
+        let mut padded = data.clone();
+        padded.extend_from_slice(&[0; 60]);
+        let (parser, result) = stream(&padded, padded.len(), 64);
+        assert_eq!(result.unwrap().len(), 90);
+        assert_eq!(parser.warning_message(0).unwrap(), "60 bytes of trailing data after the facets");
+
+        let mut long_line = b"solid a\nfacet endsolid\n".to_vec();
+        long_line.resize(MAX_LINE_LEN * 2, b'x');
+        assert!(stream(&long_line, 0, 4096).1.is_err());
+
+        let mut parser = StlStreamParser::new(0.0);
+        parser.finish().unwrap_err();
+        assert!(parser.push(b"solid").is_err());
+    }
+}
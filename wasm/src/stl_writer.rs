@@ -0,0 +1,219 @@
+use std::fmt::Write;
+
+use wasm_bindgen::prelude::*;
+
+use crate::error::{Error, check_triangle_soup};
+use crate::vector3::Vector3;
+
+const BINARY_HEADER: &[u8] = b"Binary STL written by stl-web-viewer";
+
+/// Per-facet colors for binary STL.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum FacetColors<'a> {
+    None,
+    /// The same RGB color for all facets.
+    Uniform([u8; 3]),
+    /// RGB color for each facet, 3 bytes per triangle.
+    PerFacet(&'a [u8]),
+}
+
+fn triangle(tri: &[f32]) -> [Vector3; 3] {
+    [Vector3::new(tri[0], tri[1], tri[2]), Vector3::new(tri[3], tri[4], tri[5]), Vector3::new(tri[6], tri[7], tri[8])]
+}
+
+/// Returns the unit normal of the triangle computed from the vertex order, or zero for degenerate triangles.
+pub fn facet_normal(tri: &[f32]) -> Vector3 {
+    let [a, b, c] = triangle(tri);
+    (b - a).cross(c - a).normalize()
+}
+
+// Encodes the color as in VisCAM and SolidView: 5 bits per channel, blue in the lowest bits, bit 15 marks the color
+// as valid.
+fn encode_color(rgb: [u8; 3]) -> u16 {
+    let [r, g, b] = rgb.map(|c| (c >> 3) as u16);
+    0x8000 | (r << 10) | (g << 5) | b
+}
+
+/// Writes a binary STL. `pos` has the same layout as in split_disjoint_geometry, facet normals are recomputed from
+/// the vertex order. Colors are stored in the attribute bytes, see encode_color; facets without a color (if there are
+/// fewer colors than facets) get zero attributes.
+pub fn write_binary_stl(pos: &[f32], colors: FacetColors) -> Vec<u8> {
+    let tri_count = pos.len() / 9;
+    let mut result = Vec::with_capacity(84 + tri_count * 50);
+    let mut header = [0u8; 80];
+    header[..BINARY_HEADER.len()].copy_from_slice(BINARY_HEADER);
+    result.extend_from_slice(&header);
+    result.extend_from_slice(&(tri_count as u32).to_le_bytes());
+    for (i, tri) in pos.chunks_exact(9).enumerate() {
+        let n = facet_normal(tri);
+        for v in [n.x, n.y, n.z].iter().chain(tri) {
+            result.extend_from_slice(&v.to_le_bytes());
+        }
+        let attribute = match colors {
+            FacetColors::None => 0,
+            FacetColors::Uniform(rgb) => encode_color(rgb),
+            FacetColors::PerFacet(colors) => colors
+                .get(i * 3..i * 3 + 3)
+                .map_or(0, |c| encode_color([c[0], c[1], c[2]])),
+        };
+        result.extend_from_slice(&attribute.to_le_bytes());
+    }
+    result
+}
+
+/// Writes an ASCII STL with the given solid name. Coordinates are written with the shortest representation which
+/// parses back to the same f32 value.
+pub fn write_ascii_stl(pos: &[f32], name: &str) -> String {
+    // Names with line breaks would break the format.
+    let name: String = name
+        .chars()
+        .map(|c| if c.is_control() { ' ' } else { c })
+        .collect();
+    let mut result = String::with_capacity(pos.len() / 9 * 250);
+    writeln!(result, "solid {}", name).unwrap();
+    for tri in pos.chunks_exact(9) {
+        let n = facet_normal(tri);
+        writeln!(result, "  facet normal {} {} {}", n.x, n.y, n.z).unwrap();
+        result.push_str("    outer loop\n");
+        for v in tri.chunks_exact(3) {
+            writeln!(result, "      vertex {} {} {}", v[0], v[1], v[2]).unwrap();
+        }
+        result.push_str("    endloop\n  endfacet\n");
+    }
+    writeln!(result, "endsolid {}", name).unwrap();
+    result
+}
+
+/// Writes a binary STL, see write_binary_stl. `colors` may be empty (no colors), contain a single RGB color for all
+/// facets or an RGB color per facet.
+#[wasm_bindgen]
+pub fn export_binary_stl(pos: &[f32], colors: &[u8]) -> Result<Box<[u8]>, Error> {
+    check_triangle_soup(pos)?;
+    let colors = match colors {
+        [] => FacetColors::None,
+        &[r, g, b] => FacetColors::Uniform([r, g, b]),
+        colors if colors.len() == pos.len() / 3 => FacetColors::PerFacet(colors),
+        colors => {
+            return Err(Error::InvalidInput(format!(
+                "expected 3 color bytes per facet for {} facets, got {} bytes",
+                pos.len() / 9,
+                colors.len()
+            )));
+        }
+    };
+    Ok(write_binary_stl(pos, colors).into_boxed_slice())
+}
+
+/// Writes an ASCII STL, see write_ascii_stl.
+#[wasm_bindgen]
+pub fn export_ascii_stl(pos: &[f32], name: &str) -> Result<Box<[u8]>, Error> {
+    check_triangle_soup(pos)?;
+    Ok(write_ascii_stl(pos, name).into_bytes().into_boxed_slice())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::plane_section::tests::create_box_geometry;
+
+    fn read_f32(data: &[u8], offset: usize) -> f32 {
+        f32::from_le_bytes(data[offset..offset + 4].try_into().unwrap())
+    }
+
+    #[test]
+    fn test_binary() {
+        let cube = create_box_geometry(Vector3::new(0.0, 0.0, 0.0), Vector3::new(1.0, 2.0, 3.0));
+        let data = write_binary_stl(&cube, FacetColors::None);
+        assert_eq!(data.len(), 84 + 12 * 50);
+        assert!(!data.starts_with(b"solid"));
+        assert_eq!(u32::from_le_bytes(data[80..84].try_into().unwrap()), 12);
+        for t in 0..12 {
+            let offset = 84 + t * 50;
+            let normal =
+                Vector3::new(read_f32(&data, offset), read_f32(&data, offset + 4), read_f32(&data, offset + 8));
+            assert_eq!(normal, facet_normal(&cube[t * 9..t * 9 + 9]));
+            assert!((normal.length() - 1.0).abs() < 1e-6);
+            for i in 0..9 {
+                assert_eq!(read_f32(&data, offset + 12 + i * 4), cube[t * 9 + i]);
+            }
+            assert_eq!(data[offset + 48..offset + 50], [0, 0]);
+        }
+        // Normals point outwards.
+        let first = &data[84..96];
+        assert_eq!([read_f32(first, 0), read_f32(first, 4), read_f32(first, 8)], [0.0, 0.0, -1.0]);
+    }
+
+    #[test]
+    fn test_colors() {
+        assert_eq!(encode_color([255, 255, 255]), 0xffff);
+        assert_eq!(encode_color([0, 0, 0]), 0x8000);
+        assert_eq!(encode_color([255, 0, 0]), 0x8000 | 0x7c00);
+        assert_eq!(encode_color([0, 0, 8]), 0x8001);
+
+        let tris = [0.0, 0.0, 0.0, 1.0, 0.0, 0.0, 0.0, 1.0, 0.0].repeat(2);
+        let attribute = |data: &[u8], t: usize| u16::from_le_bytes([data[84 + t * 50 + 48], data[84 + t * 50 + 49]]);
+        let uniform = write_binary_stl(&tris, FacetColors::Uniform([0, 255, 0]));
+        assert_eq!(attribute(&uniform, 0), 0x83e0);
+        assert_eq!(attribute(&uniform, 1), 0x83e0);
+        let per_facet = write_binary_stl(&tris, FacetColors::PerFacet(&[0, 0, 255]));
+        assert_eq!(attribute(&per_facet, 0), 0x801f);
+        assert_eq!(attribute(&per_facet, 1), 0);
+    }
+
+    #[test]
+    fn test_degenerate() {
+        let tri = [1.0, 1.0, 1.0, 2.0, 2.0, 2.0, 3.0, 3.0, 3.0];
+        assert_eq!(facet_normal(&tri), Vector3::ZERO);
+        let data = write_binary_stl(&tri, FacetColors::None);
+        assert_eq!(data[84..96], [0; 12]);
+        assert_eq!(write_binary_stl(&[], FacetColors::None).len(), 84);
+    }
+
+    #[test]
+    fn test_ascii() {
+        let tri = [0.0, 0.0, 0.0, 1.5, 0.0, 0.0, 0.0, 0.1, 0.0];
+        let text = write_ascii_stl(&tri, "part\n1");
+        assert_eq!(
+            text,
+            "solid part 1
+  facet normal 0 0 1
+    outer loop
+      vertex 0 0 0
+      vertex 1.5 0 0
+      vertex 0 0.1 0
+    endloop
+  endfacet
+endsolid part 1
+"
+        );
+        // Values are written with the full precision.
+        let precise = [0.1f32 + 1e-7, 0.0, 0.0, 1.0, 0.0, 0.0, 0.0, 1.0, 0.0];
+        let text = write_ascii_stl(&precise, "");
+        let written: f32 = text
+            .lines()
+            .nth(3)
+            .unwrap()
+            .split_whitespace()
+            .nth(1)
+            .unwrap()
+            .parse()
+            .unwrap();
+        assert_eq!(written, precise[0]);
+    }
+
+    #[test]
+    fn test_wasm_wrapper() {
+        let tris = [0.0, 0.0, 0.0, 1.0, 0.0, 0.0, 0.0, 1.0, 0.0].repeat(2);
+        let data = export_binary_stl(&tris, &[]).unwrap();
+        assert_eq!(data.len(), 184);
+        assert_eq!(export_binary_stl(&tris, &[255, 255, 255]).unwrap()[84 + 48..84 + 50], [0xff, 0xff]);
+        assert_eq!(export_binary_stl(&tris, &[0, 0, 0, 255, 255, 255]).unwrap()[184 - 2..], [0xff, 0xff]);
+        assert!(
+            export_ascii_stl(&tris, "x")
+                .unwrap()
+                .starts_with(b"solid x\n")
+        );
+        assert!(export_binary_stl(&tris, &[0, 0, 0, 255]).is_err());
+        assert!(export_ascii_stl(&tris[..10], "x").is_err());
+    }
+}
@@ -0,0 +1,588 @@
+use std::collections::HashMap;
+
+use wasm_bindgen::prelude::*;
+
+use crate::error::Error;
+use crate::model::{ImportedModel, Model, ModelPart};
+use crate::vector3::Vector3;
+use crate::xml::{XmlEvent, XmlReader, local_name};
+use crate::zip::ZipArchive;
+
+pub const DEFAULT_MODEL_PATH: &str = "3D/3dmodel.model";
+pub const MODEL_RELATIONSHIP_TYPE: &str = "http://schemas.microsoft.com/3dmanufacturing/2013/01/3dmodel";
+// Limits the recursion for deeply nested components, cycles are rejected by expanded_sizes.
+const MAX_COMPONENT_DEPTH: usize = 32;
+// Limit of the triangles plus object instances in the build with the components flattened. A few KB of shared
+// components can describe exponentially many copies.
+const MAX_EXPANDED_SIZE: u64 = 1 << 24;
+
+/// Affine transform in 3MF notation: "m00 m01 m02 m10 m11 m12 m20 m21 m22 m30 m31 m32", points are row vectors
+/// multiplied from the left, so the last three values are the translation.
+pub type Transform = [f32; 12];
+
+pub const IDENTITY: Transform = [1.0, 0.0, 0.0, 0.0, 1.0, 0.0, 0.0, 0.0, 1.0, 0.0, 0.0, 0.0];
+
+pub fn apply_transform(t: &Transform, p: Vector3) -> Vector3 {
+    Vector3::new(
+        p.x * t[0] + p.y * t[3] + p.z * t[6] + t[9],
+        p.x * t[1] + p.y * t[4] + p.z * t[7] + t[10],
+        p.x * t[2] + p.y * t[5] + p.z * t[8] + t[11],
+    )
+}
+
+/// Returns the transform which applies `first` and then `then`.
+pub fn compose(first: &Transform, then: &Transform) -> Transform {
+    let mut result = [0.0; 12];
+    for row in 0..4 {
+        for col in 0..3 {
+            let mut sum = if row == 3 { then[9 + col] } else { 0.0 };
+            for k in 0..3 {
+                sum += first[row * 3 + k] * then[k * 3 + col];
+            }
+            result[row * 3 + col] = sum;
+        }
+    }
+    result
+}
+
+fn parse_transform(s: &str) -> Result<Transform, String> {
+    let values: Vec<f32> = s
+        .split_whitespace()
+        .map(|v| v.parse::<f32>().ok().filter(|v| v.is_finite()))
+        .collect::<Option<_>>()
+        .ok_or_else(|| format!("invalid transform '{}'", s))?;
+    values
+        .try_into()
+        .map_err(|_| format!("invalid transform '{}'", s))
+}
+
+/// Parses "#RRGGBB" or "#RRGGBBAA" into RGBA.
+fn parse_color(s: &str) -> Option<[u8; 4]> {
+    let hex = s.trim().strip_prefix('#')?;
+    if (hex.len() != 6 && hex.len() != 8) || !hex.is_ascii() {
+        return None;
+    }
+    let channel = |i: usize| u8::from_str_radix(&hex[i * 2..i * 2 + 2], 16).ok();
+    let alpha = if hex.len() == 8 { channel(3)? } else { 255 };
+    Some([channel(0)?, channel(1)?, channel(2)?, alpha])
+}
+
+fn unit_scale(unit: &str) -> Option<f32> {
+    Some(match unit {
+        "micron" => 0.001,
+        "millimeter" => 1.0,
+        "centimeter" => 10.0,
+        "inch" => 25.4,
+        "foot" => 304.8,
+        "meter" => 1000.0,
+        _ => return None,
+    })
+}
+
+#[derive(Clone, Debug, Default)]
+struct Object {
+    name: Option<String>,
+    vertices: Vec<Vector3>,
+    triangles: Vec<[u32; 3]>,
+    components: Vec<(u32, Transform)>,
+    // Material group id and index within it, either of the object or of its first triangle.
+    property: Option<(u32, usize)>,
+}
+
+// A material or a color in a property group.
+#[derive(Clone, Debug, Default)]
+struct Material {
+    name: Option<String>,
+    color: Option<[u8; 4]>,
+}
+
+#[derive(Default)]
+struct ModelFile {
+    scale: f32,
+    objects: HashMap<u32, Object>,
+    property_groups: HashMap<u32, Vec<Material>>,
+    build: Vec<(u32, Transform)>,
+}
+
+fn parse_id(value: Option<std::borrow::Cow<str>>, what: &str) -> Result<u32, String> {
+    let value = value.ok_or_else(|| format!("{} without id", what))?;
+    value
+        .trim()
+        .parse()
+        .map_err(|_| format!("invalid {} id '{}'", what, value))
+}
+
+fn parse_model_xml(text: &str, model: &mut Model) -> Result<ModelFile, String> {
+    let mut reader = XmlReader::new(text);
+    let mut file = ModelFile { scale: 1.0, ..Default::default() };
+    let mut object: Option<(u32, Object)> = None;
+    let mut group: Option<u32> = None;
+    let mut in_build = false;
+
+    while let Some(event) = reader.next_event()? {
+        match event {
+            XmlEvent::Start { name, attributes, .. } => {
+                let attr_f32 = |name: &str| -> Result<f32, String> {
+                    attributes
+                        .get(name)
+                        .and_then(|v| v.trim().parse::<f32>().ok())
+                        .filter(|v| v.is_finite())
+                        .ok_or_else(|| format!("invalid or missing attribute {}", name))
+                };
+                let attr_u32 = |name: &str| {
+                    attributes
+                        .get(name)
+                        .and_then(|v| v.trim().parse::<u32>().ok())
+                };
+                match local_name(name) {
+                    "model" => {
+                        if let Some(unit) = attributes.get("unit") {
+                            file.scale = unit_scale(&unit).ok_or_else(|| format!("unknown unit '{}'", unit))?;
+                        }
+                    }
+                    "basematerials" | "colorgroup" => {
+                        let id = parse_id(attributes.get("id"), local_name(name))?;
+                        file.property_groups.insert(id, vec![]);
+                        group = Some(id);
+                    }
+                    "base" | "color" => {
+                        if let Some(materials) = group.and_then(|id| file.property_groups.get_mut(&id)) {
+                            let color = attributes
+                                .get(if local_name(name) == "base" { "displaycolor" } else { "color" })
+                                .and_then(|c| parse_color(&c));
+                            let name = attributes.get("name").map(|n| n.into_owned());
+                            materials.push(Material { name, color });
+                        }
+                    }
+                    "object" => {
+                        let id = parse_id(attributes.get("id"), "object")?;
+                        let property = attr_u32("pid").map(|pid| (pid, attr_u32("pindex").unwrap_or(0) as usize));
+                        let name = attributes.get("name").map(|n| n.into_owned());
+                        object = Some((id, Object { name, property, ..Default::default() }));
+                    }
+                    "vertex" => {
+                        if let Some((_, object)) = object.as_mut() {
+                            object
+                                .vertices
+                                .push(Vector3::new(attr_f32("x")?, attr_f32("y")?, attr_f32("z")?));
+                        }
+                    }
+                    "triangle" => {
+                        if let Some((id, object)) = object.as_mut() {
+                            let v = [attr_u32("v1"), attr_u32("v2"), attr_u32("v3")];
+                            let vertex_count = object.vertices.len() as u32;
+                            match v {
+                                [Some(a), Some(b), Some(c)]
+                                    if a < vertex_count && b < vertex_count && c < vertex_count =>
+                                {
+                                    object.triangles.push([a, b, c]);
+                                }
+                                _ => {
+                                    model.warn(0, format!("invalid triangle in object {}", id));
+                                    continue;
+                                }
+                            }
+                            if object.property.is_none() {
+                                object.property =
+                                    attr_u32("pid").map(|pid| (pid, attr_u32("p1").unwrap_or(0) as usize));
+                            }
+                        }
+                    }
+                    "component" => {
+                        if let Some((_, object)) = object.as_mut() {
+                            if attributes
+                                .iter()
+                                .any(|(name, _)| local_name(name) == "path")
+                            {
+                                model.warn(0, "components from other model files are not supported".to_string());
+                                continue;
+                            }
+                            let transform = match attributes.get("transform") {
+                                Some(t) => parse_transform(&t)?,
+                                None => IDENTITY,
+                            };
+                            object
+                                .components
+                                .push((parse_id(attributes.get("objectid"), "component")?, transform));
+                        }
+                    }
+                    "build" => in_build = true,
+                    "item" if in_build => {
+                        let transform = match attributes.get("transform") {
+                            Some(t) => parse_transform(&t)?,
+                            None => IDENTITY,
+                        };
+                        file.build
+                            .push((parse_id(attributes.get("objectid"), "item")?, transform));
+                    }
+                    _ => {}
+                }
+            }
+            XmlEvent::End { name } => match local_name(name) {
+                "object" => {
+                    if let Some((id, object)) = object.take() {
+                        file.objects.insert(id, object);
+                    }
+                }
+                "basematerials" | "colorgroup" => group = None,
+                "build" => in_build = false,
+                _ => {}
+            },
+            XmlEvent::Text(_) => {}
+        }
+    }
+    Ok(file)
+}
+
+// Returns the size of each object with its components flattened: the number of its triangles and object instances,
+// unknown components do not count. Returns an error if an object contains itself through its components or if the size
+// does not fit in u64.
+fn expanded_sizes(file: &ModelFile) -> Result<HashMap<u32, u64>, Error> {
+    // Objects on the current DFS path map to None, the fully expanded ones to their size.
+    let mut sizes: HashMap<u32, Option<u64>> = HashMap::new();
+    let too_large = |id: u32| Error::InvalidInput(format!("object {} has too many components", id));
+    for &root in file.objects.keys() {
+        if sizes.contains_key(&root) {
+            continue;
+        }
+        sizes.insert(root, None);
+        // Object id, the index of its next component and the size so far.
+        let mut stack = vec![(root, 0, 1 + file.objects[&root].triangles.len() as u64)];
+        while let Some(&mut (id, ref mut next, size)) = stack.last_mut() {
+            let components = &file.objects[&id].components;
+            let Some(&(child, _)) = components.get(*next) else {
+                sizes.insert(id, Some(size));
+                stack.pop();
+                if let Some((parent, _, parent_size)) = stack.last_mut() {
+                    *parent_size = parent_size
+                        .checked_add(size)
+                        .ok_or_else(|| too_large(*parent))?;
+                }
+                continue;
+            };
+            *next += 1;
+            match sizes.get(&child) {
+                Some(None) => {
+                    return Err(Error::InvalidInput(format!(
+                        "object {} contains itself through its components",
+                        child
+                    )));
+                }
+                Some(&Some(child_size)) => {
+                    let (_, _, size) = stack.last_mut().unwrap();
+                    *size = size.checked_add(child_size).ok_or_else(|| too_large(id))?;
+                }
+                None => {
+                    if let Some(object) = file.objects.get(&child) {
+                        sizes.insert(child, None);
+                        stack.push((child, 0, 1 + object.triangles.len() as u64));
+                    }
+                }
+            }
+        }
+    }
+    Ok(sizes
+        .into_iter()
+        .map(|(id, size)| (id, size.unwrap()))
+        .collect())
+}
+
+// Returns an error if the build items expand to more than MAX_EXPANDED_SIZE triangles and object instances, so that a
+// small file can not make the import hang or run out of memory.
+fn check_expanded_size(file: &ModelFile) -> Result<(), Error> {
+    let sizes = expanded_sizes(file)?;
+    let total = file
+        .build
+        .iter()
+        .try_fold(0u64, |total, (id, _)| total.checked_add(sizes.get(id).copied().unwrap_or(0)));
+    match total {
+        Some(total) if total <= MAX_EXPANDED_SIZE => Ok(()),
+        _ => Err(Error::InvalidInput(format!(
+            "the build expands to more than {} triangles and components",
+            MAX_EXPANDED_SIZE
+        ))),
+    }
+}
+
+// Appends the triangles of the object and its components to `positions`.
+fn emit_object(
+    file: &ModelFile,
+    id: u32,
+    transform: &Transform,
+    depth: usize,
+    positions: &mut Vec<f32>,
+    model: &mut Model,
+) {
+    let Some(object) = file.objects.get(&id) else {
+        model.warn(0, format!("reference to unknown object {}", id));
+        return;
+    };
+    if depth > MAX_COMPONENT_DEPTH {
+        model.warn(0, format!("components of object {} are nested too deep", id));
+        return;
+    }
+    for tri in &object.triangles {
+        for &v in tri {
+            let p = apply_transform(transform, object.vertices[v as usize]);
+            positions.extend_from_slice(&[p.x, p.y, p.z]);
+        }
+    }
+    for (child, child_transform) in &object.components {
+        emit_object(file, *child, &compose(child_transform, transform), depth + 1, positions, model);
+    }
+}
+
+// Returns the material of the object: its own or the first one found among its components.
+fn object_material(file: &ModelFile, id: u32, depth: usize) -> Option<&Material> {
+    let object = file.objects.get(&id)?;
+    if let Some((pid, index)) = object.property {
+        return file.property_groups.get(&pid)?.get(index);
+    }
+    if depth > MAX_COMPONENT_DEPTH {
+        return None;
+    }
+    object
+        .components
+        .iter()
+        .find_map(|(child, _)| object_material(file, *child, depth + 1))
+}
+
+/// Parses a 3MF package. Each build item becomes a part with its components flattened and the build transforms
+/// applied, coordinates are converted to millimeters. The part name is the name of the object and the material is the
+/// base material (or the color) assigned to the object, or to its first triangle if the object has none. Per-triangle
+/// materials are not preserved. Only the root model file is read.
+pub fn parse_3mf(data: &[u8]) -> Result<Model, Error> {
+    let archive = ZipArchive::new(data)?;
+    let mut model_path = DEFAULT_MODEL_PATH.to_string();
+    if let Some(rels) = archive.find("_rels/.rels") {
+        let rels = archive.read(rels)?;
+        let rels = String::from_utf8_lossy(&rels);
+        let mut reader = XmlReader::new(&rels);
+        while let Some(event) = reader.next_event()? {
+            if let XmlEvent::Start { name, attributes, .. } = event
+                && local_name(name) == "Relationship"
+                && attributes.get("Type").as_deref() == Some(MODEL_RELATIONSHIP_TYPE)
+                && let Some(target) = attributes.get("Target")
+            {
+                model_path = target.into_owned();
+                break;
+            }
+        }
+    }
+    let entry = archive
+        .find(&model_path)
+        .ok_or_else(|| format!("3MF package has no model {}", model_path))?;
+    let contents = archive.read(entry)?;
+    let text = std::str::from_utf8(&contents).map_err(|_| "3MF model is not valid UTF-8".to_string())?;
+
+    let mut model = Model::default();
+    let file = parse_model_xml(text, &mut model)?;
+    check_expanded_size(&file)?;
+    let scale: Transform = [file.scale, 0.0, 0.0, 0.0, file.scale, 0.0, 0.0, 0.0, file.scale, 0.0, 0.0, 0.0];
+    for &(id, transform) in &file.build {
+        let mut positions = vec![];
+        emit_object(&file, id, &compose(&transform, &scale), 0, &mut positions, &mut model);
+        let object = file.objects.get(&id);
+        let material = object_material(&file, id, 0);
+        model.parts.push(ModelPart {
+            name: object
+                .and_then(|o| o.name.clone())
+                .unwrap_or_else(|| format!("Object {}", id)),
+            material: material.and_then(|m| m.name.clone()),
+            color: material.and_then(|m| m.color),
+            positions,
+        });
+    }
+    Ok(model)
+}
+
+/// See parse_3mf.
+#[wasm_bindgen]
+pub fn load_3mf(data: &[u8]) -> Result<ImportedModel, Error> {
+    parse_3mf(data).map(ImportedModel::new)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::zip::tests::create_zip;
+
+    const MODEL: &str = r##"<?xml version="1.0" encoding="UTF-8"?>
+<model unit="centimeter" xml:lang="en-US" xmlns="http://schemas.microsoft.com/3dmanufacturing/core/2015/02"
+       xmlns:m="http://schemas.microsoft.com/3dmanufacturing/material/2015/02">
+  <resources>
+    <basematerials id="1">
+      <base name="Red PLA" displaycolor="#FF0000" />
+      <base name="Clear" displaycolor="#FFFFFF80" />
+    </basematerials>
+    <m:colorgroup id="2">
+      <m:color color="#00FF00" />
+    </m:colorgroup>
+    <object id="3" type="model" name="Tetra" pid="1" pindex="1">
+      <mesh>
+        <vertices>
+          <vertex x="0" y="0" z="0" />
+          <vertex x="1" y="0" z="0" />
+          <vertex x="0" y="1" z="0" />
+          <vertex x="0" y="0" z="1" />
+        </vertices>
+        <triangles>
+          <triangle v1="0" v2="2" v3="1" />
+          <triangle v1="0" v2="1" v3="3" />
+          <triangle v1="0" v2="3" v3="2" />
+          <triangle v1="1" v2="2" v3="3" />
+          <triangle v1="1" v2="2" v3="7" />
+        </triangles>
+      </mesh>
+    </object>
+    <object id="4" type="model">
+      <mesh>
+        <vertices>
+          <vertex x="0" y="0" z="0" />
+          <vertex x="1" y="0" z="0" />
+          <vertex x="0" y="1" z="0" />
+        </vertices>
+        <triangles>
+          <triangle v1="0" v2="1" v3="2" pid="2" p1="0" />
+        </triangles>
+      </mesh>
+    </object>
+    <object id="5" type="model" name="Assembly">
+      <components>
+        <component objectid="3" />
+        <component objectid="4" transform="1 0 0 0 1 0 0 0 1 5 0 0" />
+      </components>
+    </object>
+  </resources>
+  <build>
+    <item objectid="5" transform="0 1 0 -1 0 0 0 0 1 0 0 2" />
+    <item objectid="4" />
+    <item objectid="9" />
+  </build>
+</model>
+"##;
+
+    const RELS: &str = r#"<?xml version="1.0" encoding="UTF-8"?>
+<Relationships xmlns="http://schemas.openxmlformats.org/package/2006/relationships">
+  <Relationship Target="/3D/Model.model" Id="rel0" Type="http://schemas.microsoft.com/3dmanufacturing/2013/01/3dmodel" />
+</Relationships>
+"#;
+
+    fn triangles(positions: &[f32]) -> Vec<[Vector3; 3]> {
+        positions
+            .chunks_exact(9)
+            .map(|t| [0, 3, 6].map(|i| Vector3::new(t[i], t[i + 1], t[i + 2])))
+            .collect()
+    }
+
+    #[test]
+    fn test_transforms() {
+        let t: Transform = [0.0, 1.0, 0.0, -1.0, 0.0, 0.0, 0.0, 0.0, 1.0, 10.0, 0.0, 0.0];
+        assert_eq!(apply_transform(&t, Vector3::new(1.0, 2.0, 3.0)), Vector3::new(8.0, 1.0, 3.0));
+        let twice = compose(&t, &t);
+        assert_eq!(apply_transform(&twice, Vector3::new(1.0, 2.0, 3.0)), Vector3::new(9.0, 8.0, 3.0));
+        assert_eq!(compose(&IDENTITY, &t), t);
+        assert_eq!(parse_color("#FF000080"), Some([255, 0, 0, 128]));
+        assert_eq!(parse_color("#00ff00"), Some([0, 255, 0, 255]));
+        assert_eq!(parse_color("red"), None);
+    }
+
+    #[test]
+    fn test_parse() {
+        let data = create_zip(&[("_rels/.rels", RELS.as_bytes(), false), ("3D/Model.model", MODEL.as_bytes(), true)]);
+        let model = parse_3mf(&data).unwrap();
+        let warnings: Vec<&str> = model.warnings.iter().map(|w| w.message.as_str()).collect();
+        assert_eq!(warnings, vec!["invalid triangle in object 3", "reference to unknown object 9"]);
+
+        assert_eq!(model.parts.len(), 3);
+        let assembly = &model.parts[0];
+        assert_eq!(assembly.name, "Assembly");
+        // The material of the first component.
+        assert_eq!(assembly.material.as_deref(), Some("Clear"));
+        assert_eq!(assembly.color, Some([255, 255, 255, 128]));
+        let tris = triangles(&assembly.positions);
+        assert_eq!(tris.len(), 5);
+        // The item transform rotates by 90 degrees around Z and moves up by 2cm, then centimeters are converted to
+        // millimeters.
+        assert_eq!(tris[1][1], Vector3::new(0.0, 10.0, 20.0));
+        assert_eq!(tris[1][2], Vector3::new(0.0, 0.0, 30.0));
+        // The second component is moved by 5cm along X before rotation.
+        assert_eq!(tris[4][1], Vector3::new(0.0, 60.0, 20.0));
+
+        let triangle = &model.parts[1];
+        assert_eq!(triangle.name, "Object 4");
+        assert_eq!(triangle.material, None);
+        assert_eq!(triangle.color, Some([0, 255, 0, 255]));
+        assert_eq!(
+            triangles(&triangle.positions),
+            vec![[Vector3::ZERO, Vector3::new(10.0, 0.0, 0.0), Vector3::new(0.0, 10.0, 0.0)]]
+        );
+        assert!(model.parts[2].positions.is_empty());
+    }
+
+    #[test]
+    fn test_default_path_and_errors() {
+        let minimal = "<model><resources><object id=\"1\"><mesh><vertices><vertex x=\"0\" y=\"0\" z=\"0\"/>\
+                       <vertex x=\"1\" y=\"0\" z=\"0\"/><vertex x=\"0\" y=\"1\" z=\"0\"/></vertices><triangles>\
+                       <triangle v1=\"0\" v2=\"1\" v3=\"2\"/></triangles></mesh></object></resources>\
+                       <build><item objectid=\"1\"/></build></model>";
+        let data = create_zip(&[("3D/3dmodel.model", minimal.as_bytes(), true)]);
+        let model = parse_3mf(&data).unwrap();
+        assert_eq!(model.parts.len(), 1);
+        assert_eq!(model.parts[0].positions.len(), 9);
+
+        assert!(parse_3mf(b"not a zip").is_err());
+        assert!(parse_3mf(&create_zip(&[("other.txt", b"", false)])).is_err());
+        let bad_unit = minimal.replace("<model>", "<model unit=\"parsec\">");
+        assert!(parse_3mf(&create_zip(&[("3D/3dmodel.model", bad_unit.as_bytes(), false)])).is_err());
+        let bad_vertex = minimal.replace("x=\"1\"", "x=\"one\"");
+        assert!(parse_3mf(&create_zip(&[("3D/3dmodel.model", bad_vertex.as_bytes(), false)])).is_err());
+        let cyclic = "<model><resources><object id=\"1\"><components><component objectid=\"1\"/></components>\
+                      </object></resources><build><item objectid=\"1\"/></build></model>";
+        let error = parse_3mf(&create_zip(&[("3D/3dmodel.model", cyclic.as_bytes(), false)]))
+            .err()
+            .unwrap();
+        assert_eq!(error.code(), "INVALID_INPUT");
+        let fan_out = "<model><resources><object id=\"1\"><components><component objectid=\"1\"/>\
+                       <component objectid=\"1\"/></components></object></resources>\
+                       <build><item objectid=\"1\"/></build></model>";
+        assert!(parse_3mf(&create_zip(&[("3D/3dmodel.model", fan_out.as_bytes(), false)])).is_err());
+        let indirect = "<model><resources><object id=\"1\"><components><component objectid=\"2\"/></components>\
+                        </object><object id=\"2\"><components><component objectid=\"3\"/>\
+                        <component objectid=\"1\"/></components></object></resources>\
+                        <build><item objectid=\"2\"/></build></model>";
+        assert!(parse_3mf(&create_zip(&[("3D/3dmodel.model", indirect.as_bytes(), false)])).is_err());
+
+        // Object i has two components referencing object i + 1, the leaf triangle would be copied 2^31 times.
+        let mut chain = "<model><resources>".to_string();
+        for id in 1..32 {
+            chain += &format!(
+                "<object id=\"{}\"><components><component objectid=\"{}\"/><component objectid=\"{}\"/></components>\
+                 </object>",
+                id,
+                id + 1,
+                id + 1
+            );
+        }
+        chain += &minimal[minimal.find("<object").unwrap()..].replace("<object id=\"1\"", "<object id=\"32\"");
+        let error = parse_3mf(&create_zip(&[("3D/3dmodel.model", chain.as_bytes(), false)]))
+            .err()
+            .unwrap();
+        assert_eq!(error.code(), "INVALID_INPUT");
+        // A short chain expands fine.
+        let short = chain.replace("objectid=\"1\"", "objectid=\"28\"");
+        let model = parse_3mf(&create_zip(&[("3D/3dmodel.model", short.as_bytes(), false)])).unwrap();
+        assert_eq!(model.parts[0].positions.len(), 9 * 16);
+    }
+
+    #[test]
+    fn test_wasm_wrapper() {
+        let data = create_zip(&[("_rels/.rels", RELS.as_bytes(), false), ("3D/Model.model", MODEL.as_bytes(), true)]);
+        let model = load_3mf(&data).unwrap();
+        assert_eq!(model.part_count(), 3);
+        assert_eq!(model.part_color(1).unwrap().unwrap().to_vec(), vec![0, 255, 0, 255]);
+        assert_eq!(model.warning_count(), 2);
+
+        assert!(load_3mf(b"garbage").is_err());
+    }
+}
@@ -0,0 +1,214 @@
+use std::fmt::Write;
+
+use wasm_bindgen::prelude::*;
+
+use crate::error::{Error, split_export_parts};
+use crate::indexed_mesh::IndexedMesh;
+use crate::three_mf::{DEFAULT_MODEL_PATH, MODEL_RELATIONSHIP_TYPE};
+use crate::xml::escape;
+use crate::zip::ZipWriter;
+
+const CONTENT_TYPES: &str = r#"<?xml version="1.0" encoding="UTF-8"?>
+<Types xmlns="http://schemas.openxmlformats.org/package/2006/content-types">
+  <Default Extension="rels" ContentType="application/vnd.openxmlformats-package.relationships+xml"/>
+  <Default Extension="model" ContentType="application/vnd.ms-package.3dmanufacturing-3dmodel+xml"/>
+</Types>
+"#;
+const MATERIALS_ID: u32 = 1;
+
+/// A part of the exported package.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct ThreeMfPart<'a> {
+    pub name: &'a str,
+    /// Interleaved x, y, z coordinates in millimeters, 9 floats per triangle.
+    pub positions: &'a [f32],
+    /// sRGB color and alpha.
+    pub color: Option<[u8; 4]>,
+}
+
+// Writes the mesh of an object. Triangles which become degenerate after welding (3MF requires three distinct
+// vertices) and triangles with non-finite coordinates are dropped.
+fn write_mesh(xml: &mut String, positions: &[f32]) -> bool {
+    let mesh = IndexedMesh::from_triangle_soup(positions);
+    let triangles: Vec<[u32; 3]> = mesh
+        .triangles
+        .iter()
+        .filter(|&&[a, b, c]| a != b && b != c && a != c)
+        .filter(|tri| {
+            tri.iter().all(|&v| {
+                let p = mesh.positions[v as usize];
+                p.x.is_finite() && p.y.is_finite() && p.z.is_finite()
+            })
+        })
+        .copied()
+        .collect();
+    if triangles.is_empty() {
+        return false;
+    }
+    // Vertices used only by the dropped triangles are still written, the spec allows unused vertices.
+    xml.push_str("      <mesh>\n        <vertices>\n");
+    for p in &mesh.positions {
+        let coord = |v: f32| if v.is_finite() { v } else { 0.0 };
+        writeln!(xml, "          <vertex x=\"{}\" y=\"{}\" z=\"{}\"/>", coord(p.x), coord(p.y), coord(p.z)).unwrap();
+    }
+    xml.push_str("        </vertices>\n        <triangles>\n");
+    for [a, b, c] in triangles {
+        writeln!(xml, "          <triangle v1=\"{}\" v2=\"{}\" v3=\"{}\"/>", a, b, c).unwrap();
+    }
+    xml.push_str("        </triangles>\n      </mesh>\n");
+    true
+}
+
+/// Writes a 3MF package with one mesh object per part. Vertices are welded (see IndexedMesh), coordinates are in
+/// millimeters and are kept in place, so the build items have no transforms. Parts with colors get a base material
+/// named after the part; parts without triangles are skipped.
+pub fn write_3mf(parts: &[ThreeMfPart]) -> Vec<u8> {
+    let mut xml = String::from(
+        "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n<model unit=\"millimeter\" xml:lang=\"en-US\" \
+         xmlns=\"http://schemas.microsoft.com/3dmanufacturing/core/2015/02\">\n  \
+         <metadata name=\"Application\">stl-web-viewer</metadata>\n  <resources>\n",
+    );
+    let mut objects = String::new();
+    let mut materials = String::new();
+    let mut material_count = 0;
+    let mut build = vec![];
+    for (i, part) in parts.iter().enumerate() {
+        let id = MATERIALS_ID + 1 + i as u32;
+        let mut mesh = String::new();
+        if !write_mesh(&mut mesh, part.positions) {
+            continue;
+        }
+        let name = escape(part.name);
+        write!(objects, "    <object id=\"{}\" type=\"model\" name=\"{}\"", id, name).unwrap();
+        if let Some([r, g, b, a]) = part.color {
+            write!(objects, " pid=\"{}\" pindex=\"{}\"", MATERIALS_ID, material_count).unwrap();
+            writeln!(
+                materials,
+                "      <base name=\"{}\" displaycolor=\"#{:02X}{:02X}{:02X}{:02X}\"/>",
+                name, r, g, b, a
+            )
+            .unwrap();
+            material_count += 1;
+        }
+        objects.push_str(">\n");
+        objects.push_str(&mesh);
+        objects.push_str("    </object>\n");
+        build.push(id);
+    }
+    if material_count > 0 {
+        writeln!(xml, "    <basematerials id=\"{}\">", MATERIALS_ID).unwrap();
+        xml.push_str(&materials);
+        xml.push_str("    </basematerials>\n");
+    }
+    xml.push_str(&objects);
+    xml.push_str("  </resources>\n  <build>\n");
+    for id in build {
+        writeln!(xml, "    <item objectid=\"{}\"/>", id).unwrap();
+    }
+    xml.push_str("  </build>\n</model>\n");
+
+    let rels = format!(
+        "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n\
+         <Relationships xmlns=\"http://schemas.openxmlformats.org/package/2006/relationships\">\n  \
+         <Relationship Target=\"/{}\" Id=\"rel0\" Type=\"{}\"/>\n</Relationships>\n",
+        DEFAULT_MODEL_PATH, MODEL_RELATIONSHIP_TYPE
+    );
+    let mut writer = ZipWriter::new();
+    writer.add_file("[Content_Types].xml", CONTENT_TYPES.as_bytes(), true);
+    writer.add_file("_rels/.rels", rels.as_bytes(), true);
+    writer.add_file(DEFAULT_MODEL_PATH, xml.as_bytes(), true);
+    writer.finish()
+}
+
+/// Writes a 3MF package, see write_3mf. `pos` contains the triangles of all parts one after another, `part_tri_counts`
+/// contains the number of triangles in each part. `colors` contains an RGB color for each part, parts without a color
+/// get no material. Parts without a name get "part N".
+#[wasm_bindgen]
+pub fn export_3mf(pos: &[f32], part_tri_counts: &[u32], colors: &[u8], names: Vec<String>) -> Result<Box<[u8]>, Error> {
+    let parts = split_export_parts(pos, part_tri_counts, colors, names)?;
+    let parts: Vec<ThreeMfPart> = parts
+        .iter()
+        .map(|p| ThreeMfPart { name: &p.name, positions: p.positions, color: p.color.map(|[r, g, b]| [r, g, b, 255]) })
+        .collect();
+    Ok(write_3mf(&parts).into_boxed_slice())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::plane_section::tests::create_box_geometry;
+    use crate::three_mf::parse_3mf;
+    use crate::vector3::Vector3;
+    use crate::zip::ZipArchive;
+
+    fn model_xml(data: &[u8]) -> String {
+        let archive = ZipArchive::new(data).unwrap();
+        String::from_utf8(
+            archive
+                .read(archive.find(DEFAULT_MODEL_PATH).unwrap())
+                .unwrap(),
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let cube = create_box_geometry(Vector3::new(0.0, 0.0, 0.0), Vector3::new(1.0, 2.0, 3.0));
+        let other = create_box_geometry(Vector3::new(10.1, 0.0, 0.0), Vector3::new(12.0, 1.0, 1.0));
+        let parts = [
+            ThreeMfPart { name: "Cube <1> & co", positions: &cube, color: Some([255, 0, 0, 255]) },
+            ThreeMfPart { name: "Empty", positions: &[], color: Some([0, 0, 0, 255]) },
+            ThreeMfPart { name: "Plain", positions: &other, color: None },
+        ];
+        let data = write_3mf(&parts);
+        let model = parse_3mf(&data).unwrap();
+        assert!(model.warnings.is_empty());
+        assert_eq!(model.parts.len(), 2);
+        assert_eq!(model.parts[0].name, "Cube <1> & co");
+        assert_eq!(model.parts[0].color, Some([255, 0, 0, 255]));
+        assert_eq!(model.parts[0].material.as_deref(), Some("Cube <1> & co"));
+        assert_eq!(model.parts[0].positions, cube);
+        assert_eq!(model.parts[1].name, "Plain");
+        assert_eq!(model.parts[1].color, None);
+        assert_eq!(model.parts[1].positions, other);
+
+        // The vertices are shared between the triangles.
+        let xml = model_xml(&data);
+        assert_eq!(xml.matches("<vertex ").count(), 16);
+        assert_eq!(xml.matches("<triangle ").count(), 24);
+        assert_eq!(xml.matches("<item ").count(), 2);
+        assert!(!xml.contains("Empty"));
+    }
+
+    #[test]
+    fn test_degenerate_triangles() {
+        let pos = [0.0, 0.0, 0.0, 1.0, 0.0, 0.0, 0.0, 1.0, 0.0, 1.0, 0.0, 0.0, 1.0, 0.0, 0.0, 0.0, 1.0, 0.0];
+        let data = write_3mf(&[ThreeMfPart { name: "tri", positions: &pos, color: None }]);
+        let model = parse_3mf(&data).unwrap();
+        assert_eq!(model.parts[0].positions, pos[..9]);
+        // A part with only degenerate triangles is skipped.
+        let data = write_3mf(&[ThreeMfPart { name: "line", positions: &pos[9..], color: None }]);
+        assert!(parse_3mf(&data).unwrap().parts.is_empty());
+    }
+
+    #[test]
+    fn test_package() {
+        let data = write_3mf(&[]);
+        let archive = ZipArchive::new(&data).unwrap();
+        let names: Vec<&str> = archive.entries.iter().map(|e| e.name.as_str()).collect();
+        assert_eq!(names, vec!["[Content_Types].xml", "_rels/.rels", DEFAULT_MODEL_PATH]);
+        assert!(model_xml(&data).contains("<build>\n  </build>"));
+    }
+
+    #[test]
+    fn test_wasm_wrapper() {
+        let cube = create_box_geometry(Vector3::new(0.0, 0.0, 0.0), Vector3::new(1.0, 1.0, 1.0));
+        let pos = cube.repeat(2);
+        let data = export_3mf(&pos, &[12, 12], &[0, 128, 255], vec![]).unwrap();
+        let model = parse_3mf(&data).unwrap();
+        assert_eq!(model.parts[0].name, "part 1");
+        assert_eq!(model.parts[0].color, Some([0, 128, 255, 255]));
+        assert_eq!(model.parts[1].name, "part 2");
+        assert_eq!(model.parts[1].color, None);
+    }
+}
@@ -0,0 +1,117 @@
+use wasm_bindgen::prelude::*;
+
+use crate::error::Error;
+
+/// Allocates a zero-filled vector, reporting allocation failure instead of aborting.
+pub fn try_zeroed_vec<T: Copy + Default>(len: usize) -> Result<Vec<T>, Error> {
+    let mut data = Vec::new();
+    data.try_reserve_exact(len)
+        .map_err(|_| Error::OutOfMemory(format!("failed to allocate {} elements of {} bytes", len, size_of::<T>())))?;
+    data.resize(len, T::default());
+    Ok(data)
+}
+
+// Buffers owned by Rust which JS can read and write in place. Returning one from an exported function hands the
+// buffer to JS without a copy, passing it back by value returns the ownership to Rust (the JS object becomes unusable).
+macro_rules! typed_vec {
+    ($name:ident, $elem:ty, $array:ident) => {
+        #[wasm_bindgen]
+        pub struct $name {
+            data: Vec<$elem>,
+        }
+
+        #[wasm_bindgen]
+        impl $name {
+            /// Creates a zero-filled buffer, fails with OUT_OF_MEMORY if it can not be allocated.
+            #[wasm_bindgen(constructor)]
+            pub fn new(len: usize) -> Result<$name, Error> {
+                Ok($name { data: try_zeroed_vec(len)? })
+            }
+
+            /// Copies the JS array into a new buffer.
+            pub fn from_slice(data: &[$elem]) -> Result<$name, Error> {
+                let mut result = $name::new(data.len())?;
+                result.data.copy_from_slice(data);
+                Ok(result)
+            }
+
+            #[wasm_bindgen(getter)]
+            pub fn data_ptr(&mut self) -> *mut $elem {
+                self.data.as_mut_ptr()
+            }
+
+            #[wasm_bindgen(getter)]
+            pub fn len(&self) -> usize {
+                self.data.len()
+            }
+
+            /// Returns a view of the buffer, it becomes invalid when the wasm memory grows or the buffer is freed.
+            // This method is a bit slower than creating the array in JS code using data_ptr + len.
+            #[wasm_bindgen(getter)]
+            pub fn array(&self) -> js_sys::$array {
+                unsafe { js_sys::$array::view(&self.data) }
+            }
+        }
+
+        impl $name {
+            pub fn as_slice(&self) -> &[$elem] {
+                &self.data
+            }
+
+            pub fn as_mut_slice(&mut self) -> &mut [$elem] {
+                &mut self.data
+            }
+
+            pub fn into_vec(self) -> Vec<$elem> {
+                self.data
+            }
+        }
+
+        impl From<Vec<$elem>> for $name {
+            fn from(data: Vec<$elem>) -> Self {
+                $name { data }
+            }
+        }
+    };
+}
+
+typed_vec!(Float32Vec, f32, Float32Array);
+typed_vec!(Uint32Vec, u32, Uint32Array);
+typed_vec!(Uint16Vec, u16, Uint16Array);
+typed_vec!(Uint8Vec, u8, Uint8Array);
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_new() {
+        let mut vec = Uint32Vec::new(3).unwrap();
+        assert_eq!(vec.len(), 3);
+        assert_eq!(vec.as_slice(), [0, 0, 0]);
+        vec.as_mut_slice()[1] = 7;
+        let ptr = vec.data_ptr();
+        assert_eq!(unsafe { *ptr.add(1) }, 7);
+        assert_eq!(vec.into_vec(), vec![0, 7, 0]);
+        assert_eq!(Float32Vec::new(0).unwrap().len(), 0);
+    }
+
+    #[test]
+    fn test_ownership() {
+        // The buffer is moved in and out without reallocation.
+        let data = vec![1u16, 2, 3];
+        let ptr = data.as_ptr();
+        let mut vec = Uint16Vec::from(data);
+        assert_eq!(vec.data_ptr() as *const u16, ptr);
+        let data = vec.into_vec();
+        assert_eq!(data.as_ptr(), ptr);
+        assert_eq!(Uint8Vec::from_slice(&[4, 5]).unwrap().as_slice(), [4, 5]);
+    }
+
+    #[test]
+    fn test_out_of_memory() {
+        let error = Uint32Vec::new(usize::MAX).err().unwrap();
+        assert_eq!(error.code(), "OUT_OF_MEMORY");
+        assert!(try_zeroed_vec::<f32>(usize::MAX / 2).is_err());
+    }
+}
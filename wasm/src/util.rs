@@ -23,6 +23,7 @@ pub fn get_settings() -> String {
     let optimized = !cfg!(debug_assertions);
 
     let atomics = cfg!(target_feature = "atomics");
+    let threads = crate::parallel::thread_count();
     let bulk_memory = cfg!(target_feature = "bulk-memory");
     let multivalue = cfg!(target_feature = "multivalue");
     let nontrapping_fptoint = cfg!(target_feature = "nontrapping-fptoint");
@@ -36,10 +37,11 @@ pub fn get_settings() -> String {
     let memory_bytes: usize = 0;
 
     format!(
-        "optimized: {}, atomics: {}, bulk-memory: {}, multivalue: {}, nontrapping-fptoint: {}, sign-ext: {}, \
-         simd128: {}, relaxed-simd: {}, memory: {}",
+        "optimized: {}, atomics: {}, threads: {}, bulk-memory: {}, multivalue: {}, nontrapping-fptoint: {}, \
+         sign-ext: {}, simd128: {}, relaxed-simd: {}, memory: {}",
         optimized,
         atomics,
+        threads,
         bulk_memory,
         multivalue,
         nontrapping_fptoint,
@@ -0,0 +1,83 @@
+use std::ops::{Add, Mul, Sub};
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Vector2 {
+    pub x: f32,
+    pub y: f32,
+}
+
+#[allow(dead_code)]
+impl Vector2 {
+    pub const ZERO: Self = Self { x: 0.0, y: 0.0 };
+
+    pub fn new(x: f32, y: f32) -> Self {
+        Self { x, y }
+    }
+
+    /// The z component of the 3D cross product, positive if other is counter-clockwise from self.
+    pub fn cross(self, other: Self) -> f32 {
+        self.x * other.y - self.y * other.x
+    }
+
+    pub fn dot(self, other: Self) -> f32 {
+        self.x * other.x + self.y * other.y
+    }
+
+    pub fn length(self) -> f32 {
+        self.dot(self).sqrt()
+    }
+}
+
+impl Add for Vector2 {
+    type Output = Self;
+
+    fn add(self, other: Self) -> Self {
+        Self { x: self.x + other.x, y: self.y + other.y }
+    }
+}
+
+impl Sub for Vector2 {
+    type Output = Self;
+
+    fn sub(self, other: Self) -> Self {
+        Self { x: self.x - other.x, y: self.y - other.y }
+    }
+}
+
+impl Mul<f32> for Vector2 {
+    type Output = Self;
+
+    fn mul(self, scale: f32) -> Self {
+        Self { x: self.x * scale, y: self.y * scale }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_vector2_add_sub_mul() {
+        let v1 = Vector2::new(1.0, 2.0);
+        let v2 = Vector2::new(4.0, -5.0);
+        assert_eq!(v1 + v2, Vector2::new(5.0, -3.0));
+        assert_eq!(v1 - v2, Vector2::new(-3.0, 7.0));
+        assert_eq!(v1 * 2.0, Vector2::new(2.0, 4.0));
+    }
+
+    #[test]
+    fn test_vector2_cross_dot() {
+        let i = Vector2::new(1.0, 0.0);
+        let j = Vector2::new(0.0, 1.0);
+        assert_eq!(i.cross(j), 1.0);
+        assert_eq!(j.cross(i), -1.0);
+        assert_eq!(i.dot(j), 0.0);
+        assert_eq!(Vector2::new(1.0, 2.0).dot(Vector2::new(3.0, 4.0)), 11.0);
+    }
+
+    #[test]
+    fn test_vector2_length() {
+        assert_eq!(Vector2::ZERO.length(), 0.0);
+        assert_eq!(Vector2::new(3.0, -4.0).length(), 5.0);
+    }
+}
@@ -1,5 +1,5 @@
 use std::hash::{Hash, Hasher};
-use std::ops::{Add, Sub};
+use std::ops::{Add, Mul, Sub};
 
 #[derive(Clone, Copy, Debug, PartialEq)]
 pub struct Vector3 {
@@ -31,6 +31,12 @@ impl Vector3 {
     pub fn length(self) -> f32 {
         self.dot(self).sqrt()
     }
+
+    /// Returns the vector scaled to unit length or ZERO if the vector has zero length.
+    pub fn normalize(self) -> Self {
+        let len = self.length();
+        if len == 0.0 { Self::ZERO } else { self * (1.0 / len) }
+    }
 }
 
 impl Add for Vector3 {
@@ -49,6 +55,14 @@ impl Sub for Vector3 {
     }
 }
 
+impl Mul<f32> for Vector3 {
+    type Output = Self;
+
+    fn mul(self, scale: f32) -> Self {
+        Self { x: self.x * scale, y: self.y * scale, z: self.z * scale }
+    }
+}
+
 impl Eq for Vector3 {}
 
 impl Hash for Vector3 {
@@ -144,4 +158,16 @@ mod tests {
         assert_eq!(Vector3::new(1.0, 2.0, 2.0).length(), 3.0);
         assert_eq!(Vector3::new(-3.0, -4.0, 0.0).length(), 5.0);
     }
+
+    #[test]
+    fn test_vector3_mul() {
+        assert_eq!(Vector3::new(1.0, -2.0, 3.0) * 2.0, Vector3::new(2.0, -4.0, 6.0));
+        assert_eq!(Vector3::new(1.0, 2.0, 3.0) * 0.0, Vector3::ZERO);
+    }
+
+    #[test]
+    fn test_vector3_normalize() {
+        assert_eq!(Vector3::new(3.0, 0.0, 4.0).normalize(), Vector3::new(0.6, 0.0, 0.8));
+        assert_eq!(Vector3::ZERO.normalize(), Vector3::ZERO);
+    }
 }
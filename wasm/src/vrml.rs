@@ -0,0 +1,731 @@
+use std::collections::HashMap;
+
+use wasm_bindgen::prelude::*;
+
+use crate::error::Error;
+use crate::model::{ImportedModel, Model, ModelPart, triangulate_face};
+use crate::three_mf::{IDENTITY, Transform, apply_transform, compose};
+use crate::vector3::Vector3;
+use crate::xml::{XmlEvent, XmlReader, local_name};
+
+// Protects from stack overflows on deeply nested or malicious files.
+const MAX_DEPTH: usize = 256;
+
+/// A node of the VRML97 or X3D scene graph. Both encodings are converted into this form: scalar fields keep their raw
+/// tokens, nodes from all SFNode and MFNode fields are collected into `children`.
+#[derive(Clone, Debug, Default, PartialEq)]
+struct SceneNode {
+    kind: String,
+    def: Option<String>,
+    fields: Vec<(String, Vec<String>)>,
+    children: Vec<SceneNode>,
+}
+
+impl SceneNode {
+    fn field(&self, name: &str) -> Option<&[String]> {
+        self.fields
+            .iter()
+            .find(|(n, _)| n == name)
+            .map(|(_, v)| v.as_slice())
+    }
+
+    // Returns None if the field is missing or has invalid values.
+    fn floats(&self, name: &str) -> Option<Vec<f32>> {
+        self.field(name)?
+            .iter()
+            .map(|v| v.parse::<f32>().ok().filter(|v| v.is_finite()))
+            .collect()
+    }
+
+    fn bool(&self, name: &str) -> Option<bool> {
+        match self.field(name)?.first()?.as_str() {
+            "TRUE" | "true" => Some(true),
+            "FALSE" | "false" => Some(false),
+            _ => None,
+        }
+    }
+
+    fn child(&self, kinds: &[&str]) -> Option<&SceneNode> {
+        self.children
+            .iter()
+            .find(|c| kinds.contains(&c.kind.as_str()))
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+enum Token<'a> {
+    Word(&'a str),
+    Str(String),
+    Open(char),
+    Close(char),
+}
+
+// Splits the classic VRML encoding into tokens with their line numbers. Commas are whitespace.
+fn tokenize(text: &str) -> Result<Vec<(Token<'_>, usize)>, String> {
+    let mut tokens = vec![];
+    let mut line = 1;
+    let mut chars = text.char_indices().peekable();
+    while let Some((i, c)) = chars.next() {
+        match c {
+            '\n' => line += 1,
+            c if c.is_whitespace() || c == ',' => {}
+            '#' => while chars.next_if(|&(_, c)| c != '\n').is_some() {},
+            '{' | '[' => tokens.push((Token::Open(c), line)),
+            '}' | ']' => tokens.push((Token::Close(c), line)),
+            '"' => {
+                let start_line = line;
+                let mut s = String::new();
+                loop {
+                    match chars.next() {
+                        Some((_, '"')) => break,
+                        Some((_, '\\')) => {
+                            if let Some((_, escaped)) = chars.next() {
+                                s.push(escaped);
+                            }
+                        }
+                        Some((_, c)) => {
+                            if c == '\n' {
+                                line += 1;
+                            }
+                            s.push(c);
+                        }
+                        None => return Err(format!("unterminated string at line {}", start_line)),
+                    }
+                }
+                tokens.push((Token::Str(s), start_line));
+            }
+            _ => {
+                let mut end = i + c.len_utf8();
+                while let Some(&(j, c)) = chars.peek() {
+                    if c.is_whitespace() || ",{}[]\"#".contains(c) {
+                        break;
+                    }
+                    end = j + c.len_utf8();
+                    chars.next();
+                }
+                tokens.push((Token::Word(&text[i..end]), line));
+            }
+        }
+    }
+    Ok(tokens)
+}
+
+fn is_scalar(token: &Token) -> bool {
+    match token {
+        Token::Str(_) => true,
+        Token::Word(w) => {
+            matches!(*w, "TRUE" | "FALSE") || w.starts_with(|c: char| c.is_ascii_digit() || "+-.".contains(c))
+        }
+        _ => false,
+    }
+}
+
+struct VrmlParser<'a> {
+    tokens: Vec<(Token<'a>, usize)>,
+    pos: usize,
+    defs: HashMap<String, SceneNode>,
+    model: &'a mut Model,
+}
+
+impl<'a> VrmlParser<'a> {
+    fn peek(&self, offset: usize) -> Option<&Token<'a>> {
+        self.tokens.get(self.pos + offset).map(|(t, _)| t)
+    }
+
+    fn line(&self) -> usize {
+        self.tokens
+            .get(self.pos)
+            .or(self.tokens.last())
+            .map_or(0, |(_, line)| *line)
+    }
+
+    fn next(&mut self) -> Result<Token<'a>, String> {
+        let token = self
+            .tokens
+            .get(self.pos)
+            .map(|(t, _)| t.clone())
+            .ok_or("unexpected end of file")?;
+        self.pos += 1;
+        Ok(token)
+    }
+
+    fn word(&mut self) -> Result<&'a str, String> {
+        match self.next()? {
+            Token::Word(w) => Ok(w),
+            _ => Err(format!("expected a name at line {}", self.line())),
+        }
+    }
+
+    fn expect(&mut self, expected: Token<'static>) -> Result<(), String> {
+        if self.next()? != expected {
+            return Err(format!("expected {:?} at line {}", expected, self.line()));
+        }
+        Ok(())
+    }
+
+    // Skips a bracketed block starting at the current token.
+    fn skip_block(&mut self) -> Result<(), String> {
+        let mut depth = 0;
+        loop {
+            match self.next()? {
+                Token::Open(_) => depth += 1,
+                Token::Close(_) => depth -= 1,
+                _ => {}
+            }
+            if depth <= 0 {
+                return Ok(());
+            }
+        }
+    }
+
+    fn starts_node(&self) -> bool {
+        match self.peek(0) {
+            Some(Token::Word("DEF" | "USE" | "NULL")) => true,
+            Some(Token::Word(_)) => self.peek(1) == Some(&Token::Open('{')),
+            _ => false,
+        }
+    }
+
+    // Parses a node, DEF or USE statement. Returns None for NULL and unknown USE names.
+    fn parse_node(&mut self, depth: usize) -> Result<Option<SceneNode>, String> {
+        if depth > MAX_DEPTH {
+            return Err(format!("nodes are nested too deep at line {}", self.line()));
+        }
+        let mut def = None;
+        match self.word()? {
+            "NULL" => return Ok(None),
+            "USE" => {
+                let name = self.word()?;
+                let node = self.defs.get(name).cloned();
+                if node.is_none() {
+                    self.model
+                        .warn(self.line(), format!("USE of undefined node {}", name));
+                }
+                return Ok(node);
+            }
+            "DEF" => def = Some(self.word()?.to_string()),
+            _ => self.pos -= 1,
+        }
+        let kind = self.word()?.to_string();
+        self.expect(Token::Open('{'))?;
+        let mut node = SceneNode { kind, def, ..Default::default() };
+        loop {
+            match self.next()? {
+                Token::Close('}') => break,
+                Token::Word("ROUTE") => self.pos += 3,
+                Token::Word(name) => self.parse_field(&mut node, name, depth)?,
+                _ => return Err(format!("expected a field name at line {}", self.line())),
+            }
+        }
+        if let Some(def) = &node.def {
+            self.defs.insert(def.clone(), node.clone());
+        }
+        Ok(Some(node))
+    }
+
+    fn parse_field(&mut self, node: &mut SceneNode, name: &str, depth: usize) -> Result<(), String> {
+        let mut values = vec![];
+        if self.peek(0) == Some(&Token::Open('[')) {
+            self.pos += 1;
+            loop {
+                if self.peek(0) == Some(&Token::Close(']')) {
+                    self.pos += 1;
+                    break;
+                }
+                // Routes may appear among the children.
+                if self.peek(0) == Some(&Token::Word("ROUTE")) {
+                    self.pos += 4;
+                    continue;
+                }
+                if self.starts_node() {
+                    node.children.extend(self.parse_node(depth + 1)?);
+                } else {
+                    match self.next()? {
+                        Token::Word(w) => values.push(w.to_string()),
+                        Token::Str(s) => values.push(s),
+                        _ => return Err(format!("unexpected bracket at line {}", self.line())),
+                    }
+                }
+            }
+        } else if self.starts_node() {
+            node.children.extend(self.parse_node(depth + 1)?);
+        } else {
+            while let Some(token) = self.peek(0).filter(|t| is_scalar(t)) {
+                values.push(match token {
+                    Token::Word(w) => w.to_string(),
+                    Token::Str(s) => s.clone(),
+                    _ => unreachable!(),
+                });
+                self.pos += 1;
+            }
+        }
+        if !values.is_empty() {
+            node.fields.push((name.to_string(), values));
+        }
+        Ok(())
+    }
+
+    fn parse_scene(&mut self) -> Result<SceneNode, String> {
+        let mut root = SceneNode::default();
+        while let Some(token) = self.peek(0) {
+            match token {
+                Token::Word("PROTO") => {
+                    self.pos += 2;
+                    // Interface declaration and body.
+                    self.skip_block()?;
+                    self.skip_block()?;
+                }
+                Token::Word("EXTERNPROTO") => {
+                    self.pos += 2;
+                    self.skip_block()?;
+                    if self.peek(0) == Some(&Token::Open('[')) {
+                        self.skip_block()?;
+                    } else {
+                        self.pos += 1;
+                    }
+                }
+                Token::Word("ROUTE") => self.pos += 4,
+                Token::Word("PROFILE" | "COMPONENT") => self.pos += 2,
+                Token::Word("META") => self.pos += 3,
+                Token::Word("UNIT") => self.pos += 4,
+                _ => root.children.extend(self.parse_node(0)?),
+            }
+        }
+        Ok(root)
+    }
+}
+
+// Builds the scene graph from the X3D XML encoding. Attributes become fields, child elements become children.
+fn parse_x3d_tree(text: &str, model: &mut Model) -> Result<SceneNode, String> {
+    let mut reader = XmlReader::new(text);
+    let mut stack = vec![SceneNode::default()];
+    let mut defs: HashMap<String, SceneNode> = HashMap::new();
+    while let Some(event) = reader.next_event()? {
+        let node = match event {
+            XmlEvent::Start { name, attributes, self_closing } => {
+                if stack.len() > MAX_DEPTH {
+                    return Err("nodes are nested too deep".to_string());
+                }
+                let mut node = SceneNode { kind: local_name(name).to_string(), ..Default::default() };
+                for (attr, value) in attributes.iter() {
+                    match attr {
+                        "DEF" => node.def = Some(value.into_owned()),
+                        "USE" => match defs.get(value.as_ref()) {
+                            Some(used) => node = used.clone(),
+                            None => model.warn(0, format!("USE of undefined node {}", value)),
+                        },
+                        "containerField" => {}
+                        _ => {
+                            let values = value
+                                .split(|c: char| c.is_whitespace() || c == ',')
+                                .filter(|v| !v.is_empty())
+                                .map(|v| v.to_string())
+                                .collect();
+                            node.fields.push((attr.to_string(), values));
+                        }
+                    }
+                }
+                if !self_closing {
+                    stack.push(node);
+                    continue;
+                }
+                node
+            }
+            XmlEvent::End { .. } => stack.pop().unwrap(),
+            XmlEvent::Text(_) => continue,
+        };
+        if let Some(def) = &node.def {
+            defs.insert(def.clone(), node.clone());
+        }
+        stack.last_mut().unwrap().children.push(node);
+    }
+    Ok(stack.pop().unwrap())
+}
+
+fn scale_transform(s: [f32; 3]) -> Transform {
+    [s[0], 0.0, 0.0, 0.0, s[1], 0.0, 0.0, 0.0, s[2], 0.0, 0.0, 0.0]
+}
+
+fn translate_transform(t: [f32; 3]) -> Transform {
+    [1.0, 0.0, 0.0, 0.0, 1.0, 0.0, 0.0, 0.0, 1.0, t[0], t[1], t[2]]
+}
+
+// Rotation around the axis by the angle in radians, see three_mf::Transform for the layout.
+fn rotation_transform(axis: [f32; 3], angle: f32) -> Transform {
+    let axis = Vector3::new(axis[0], axis[1], axis[2]).normalize();
+    if axis == Vector3::ZERO {
+        return IDENTITY;
+    }
+    let (x, y, z) = (axis.x, axis.y, axis.z);
+    let (s, c) = angle.sin_cos();
+    let t = 1.0 - c;
+    // Rows are the images of the basis vectors.
+    [
+        t * x * x + c,
+        t * x * y + s * z,
+        t * x * z - s * y,
+        t * x * y - s * z,
+        t * y * y + c,
+        t * y * z + s * x,
+        t * x * z + s * y,
+        t * y * z - s * x,
+        t * z * z + c,
+        0.0,
+        0.0,
+        0.0,
+    ]
+}
+
+fn determinant(t: &Transform) -> f32 {
+    t[0] * (t[4] * t[8] - t[5] * t[7]) - t[1] * (t[3] * t[8] - t[5] * t[6]) + t[2] * (t[3] * t[7] - t[4] * t[6])
+}
+
+// Returns the local transform of a Transform node: translation * center * rotation * scale * -center.
+// scaleOrientation is ignored.
+fn node_transform(node: &SceneNode) -> Transform {
+    let vec3 = |name: &str, default: [f32; 3]| {
+        node.floats(name)
+            .and_then(|v| v.get(..3).map(|v| [v[0], v[1], v[2]]))
+            .unwrap_or(default)
+    };
+    let center = vec3("center", [0.0; 3]);
+    let rotation = node
+        .floats("rotation")
+        .filter(|r| r.len() >= 4)
+        .map_or(IDENTITY, |r| rotation_transform([r[0], r[1], r[2]], r[3]));
+    let mut result = translate_transform(center.map(|c| -c));
+    result = compose(&result, &scale_transform(vec3("scale", [1.0; 3])));
+    result = compose(&result, &rotation);
+    result = compose(&result, &translate_transform(center));
+    compose(&result, &translate_transform(vec3("translation", [0.0; 3])))
+}
+
+struct SceneWalker<'a> {
+    model: &'a mut Model,
+    shape_count: usize,
+}
+
+impl SceneWalker<'_> {
+    fn walk(&mut self, node: &SceneNode, transform: &Transform, name: Option<&str>) {
+        let name = node.def.as_deref().or(name);
+        match node.kind.as_str() {
+            "Transform" => {
+                let transform = compose(&node_transform(node), transform);
+                for child in &node.children {
+                    self.walk(child, &transform, name);
+                }
+            }
+            "Shape" => self.emit_shape(node, transform, name),
+            // Only the selected choice of a switch and the most detailed level of LOD are shown.
+            "Switch" => {
+                let choice = node
+                    .field("whichChoice")
+                    .and_then(|v| v.first()?.parse::<usize>().ok());
+                if let Some(child) = choice.and_then(|c| node.children.get(c)) {
+                    self.walk(child, transform, name);
+                }
+            }
+            "LOD" => {
+                if let Some(child) = node.children.first() {
+                    self.walk(child, transform, name);
+                }
+            }
+            "Inline" => self
+                .model
+                .warn(0, "Inline nodes are not supported".to_string()),
+            _ => {
+                for child in &node.children {
+                    self.walk(child, transform, name);
+                }
+            }
+        }
+    }
+
+    fn emit_shape(&mut self, shape: &SceneNode, transform: &Transform, name: Option<&str>) {
+        self.shape_count += 1;
+        let Some(geometry) = shape.child(&["IndexedFaceSet"]) else {
+            if let Some(other) = shape.children.iter().find(|c| c.kind != "Appearance") {
+                self.model
+                    .warn(0, format!("{} geometry is not supported", other.kind));
+            }
+            return;
+        };
+        let points = geometry
+            .child(&["Coordinate", "CoordinateDouble"])
+            .and_then(|c| c.floats("point"))
+            .unwrap_or_default();
+        let vertices: Vec<Vector3> = points
+            .chunks_exact(3)
+            .map(|p| Vector3::new(p[0], p[1], p[2]))
+            .collect();
+        let indices: Vec<i64> = geometry
+            .field("coordIndex")
+            .unwrap_or_default()
+            .iter()
+            .filter_map(|v| v.parse().ok())
+            .collect();
+        // Mirroring transforms flip the orientation of the faces.
+        let flip = (geometry.bool("ccw") == Some(false)) != (determinant(transform) < 0.0);
+
+        let mut positions = vec![];
+        let mut invalid = 0;
+        for face in indices.split(|&i| i < 0).filter(|f| !f.is_empty()) {
+            if face.iter().any(|&i| i as usize >= vertices.len()) {
+                invalid += 1;
+                continue;
+            }
+            let face: Vec<u32> = face.iter().map(|&i| i as u32).collect();
+            for mut tri in triangulate_face(&vertices, &face) {
+                if flip {
+                    tri.swap(1, 2);
+                }
+                for v in tri {
+                    let p = apply_transform(transform, vertices[v as usize]);
+                    positions.extend_from_slice(&[p.x, p.y, p.z]);
+                }
+            }
+        }
+        let name = shape
+            .def
+            .clone()
+            .or(name.map(|n| n.to_string()))
+            .unwrap_or_else(|| format!("Shape {}", self.shape_count));
+        if invalid > 0 {
+            self.model
+                .warn(0, format!("{} faces of {} have invalid vertex indices", invalid, name));
+        }
+
+        let material = shape
+            .child(&["Appearance"])
+            .and_then(|a| a.child(&["Material"]));
+        let color = material.and_then(|m| {
+            let diffuse = m.floats("diffuseColor").filter(|c| c.len() >= 3)?;
+            let alpha = 1.0
+                - m.floats("transparency")
+                    .and_then(|t| t.first().copied())
+                    .unwrap_or(0.0);
+            let channel = |c: f32| (c.clamp(0.0, 1.0) * 255.0).round() as u8;
+            Some([channel(diffuse[0]), channel(diffuse[1]), channel(diffuse[2]), channel(alpha)])
+        });
+        self.model
+            .parts
+            .push(ModelPart { name, material: material.and_then(|m| m.def.clone()), color, positions });
+    }
+}
+
+fn scene_to_model(root: &SceneNode, mut model: Model) -> Model {
+    let mut walker = SceneWalker { model: &mut model, shape_count: 0 };
+    walker.walk(root, &IDENTITY, None);
+    model
+}
+
+/// Parses a VRML97 file or an X3D file in the classic VRML encoding. Each Shape with an IndexedFaceSet becomes a part
+/// named after the DEF name of the shape or of its closest named ancestor; the part color is the diffuse color of its
+/// material. Transforms are applied, coordinates are kept in the file units. Other geometry nodes, PROTO instances,
+/// Inline and textures are not supported.
+pub fn parse_vrml(text: &str) -> Result<Model, Error> {
+    let header = text
+        .trim_start_matches('\u{feff}')
+        .lines()
+        .next()
+        .unwrap_or_default();
+    if header.starts_with("#VRML V1.0") {
+        return Err(Error::UnsupportedFormat("VRML 1.0 is not supported".to_string()));
+    }
+    if !header.starts_with("#VRML") && !header.starts_with("#X3D") {
+        return Err(Error::InvalidInput("missing VRML header".to_string()));
+    }
+    let mut model = Model::default();
+    let tokens = tokenize(text)?;
+    let root = VrmlParser { tokens, pos: 0, defs: HashMap::new(), model: &mut model }.parse_scene()?;
+    Ok(scene_to_model(&root, model))
+}
+
+/// Parses an X3D file in the XML encoding, see parse_vrml.
+pub fn parse_x3d(text: &str) -> Result<Model, Error> {
+    let mut model = Model::default();
+    let root = parse_x3d_tree(text, &mut model)?;
+    if root.child(&["X3D"]).is_none() {
+        return Err(Error::InvalidInput("missing <X3D> root element".to_string()));
+    }
+    Ok(scene_to_model(&root, model))
+}
+
+/// See parse_vrml.
+#[wasm_bindgen]
+pub fn load_vrml(text: &str) -> Result<ImportedModel, Error> {
+    parse_vrml(text).map(ImportedModel::new)
+}
+
+/// See parse_x3d.
+#[wasm_bindgen]
+pub fn load_x3d(text: &str) -> Result<ImportedModel, Error> {
+    parse_x3d(text).map(ImportedModel::new)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const VRML: &str = r#"#VRML V2.0 utf8
+# A quad and a mirrored triangle.
+PROTO Unused [ field SFFloat x 1 ] { Group { } }
+DEF Plate Transform {
+  translation 10 0 0
+  children [
+    Shape {
+      appearance Appearance { material DEF Red Material { diffuseColor 1 0 0 transparency 0.5 } }
+      geometry IndexedFaceSet {
+        coord DEF Points Coordinate { point [ 0 0 0, 1 0 0, 1 1 0, 0 1 0 ] }
+        coordIndex [ 0 1 2 3 -1 ]
+      }
+    }
+  ]
+}
+DEF Mirror Transform {
+  scale -1 1 1
+  children Shape {
+    geometry IndexedFaceSet { coord USE Points coordIndex [ 0, 1, 3, -1, 0, 1, 7 ] }
+  }
+}
+Shape { geometry Sphere { radius 1 } }
+ROUTE A.b TO C.d
+"#;
+
+    const X3D: &str = r#"<?xml version="1.0" encoding="UTF-8"?>
+<!DOCTYPE X3D PUBLIC "ISO//Web3D//DTD X3D 3.3//EN" "http://www.web3d.org/specifications/x3d-3.3.dtd">
+<X3D profile="Interchange" version="3.3">
+  <head><meta name="title" content="test"/></head>
+  <Scene>
+    <Transform rotation="0 0 1 1.5707964">
+      <Shape DEF="Tri">
+        <Appearance><Material diffuseColor="0 0 1"/></Appearance>
+        <IndexedFaceSet coordIndex="0 1 2 -1" ccw="false">
+          <Coordinate DEF="P" point="0 0 0, 1 0 0, 0 1 0"/>
+        </IndexedFaceSet>
+      </Shape>
+    </Transform>
+    <Switch whichChoice="-1">
+      <Shape><IndexedFaceSet coordIndex="0 1 2"><Coordinate USE="P"/></IndexedFaceSet></Shape>
+    </Switch>
+    <Shape><IndexedFaceSet coordIndex="0 1 2"><Coordinate USE="P"/></IndexedFaceSet></Shape>
+  </Scene>
+</X3D>
+"#;
+
+    fn assert_close(actual: &[f32], expected: &[f32]) {
+        assert_eq!(actual.len(), expected.len());
+        for (a, e) in actual.iter().zip(expected) {
+            assert!((a - e).abs() < 1e-6, "{:?} != {:?}", actual, expected);
+        }
+    }
+
+    #[test]
+    fn test_vrml() {
+        let model = parse_vrml(VRML).unwrap();
+        assert_eq!(model.parts.len(), 2);
+        let plate = &model.parts[0];
+        assert_eq!(plate.name, "Plate");
+        assert_eq!(plate.material.as_deref(), Some("Red"));
+        assert_eq!(plate.color, Some([255, 0, 0, 128]));
+        assert_eq!(plate.positions.len(), 18);
+        assert!(
+            plate
+                .positions
+                .chunks(3)
+                .all(|p| p[0] >= 10.0 && p[2] == 0.0)
+        );
+
+        // The mirrored triangle keeps its outward orientation.
+        let mirror = &model.parts[1];
+        assert_eq!(mirror.name, "Mirror");
+        assert_close(&mirror.positions, &[0.0, 0.0, 0.0, 0.0, 1.0, 0.0, -1.0, 0.0, 0.0]);
+        let messages: Vec<&str> = model.warnings.iter().map(|w| w.message.as_str()).collect();
+        assert_eq!(messages, vec!["1 faces of Mirror have invalid vertex indices", "Sphere geometry is not supported"]);
+    }
+
+    #[test]
+    fn test_x3d() {
+        let model = parse_x3d(X3D).unwrap();
+        assert_eq!(model.parts.len(), 2);
+        let tri = &model.parts[0];
+        assert_eq!(tri.name, "Tri");
+        assert_eq!(tri.color, Some([0, 0, 255, 255]));
+        // Rotated by 90 degrees around Z, ccw="false" flips the triangle.
+        assert_close(&tri.positions, &[0.0, 0.0, 0.0, -1.0, 0.0, 0.0, 0.0, 1.0, 0.0]);
+        assert_eq!(model.parts[1].name, "Shape 2");
+        assert_eq!(model.parts[1].positions, [0.0, 0.0, 0.0, 1.0, 0.0, 0.0, 0.0, 1.0, 0.0]);
+    }
+
+    #[test]
+    fn test_classic_x3d() {
+        let text = "#X3D V3.3 utf8\nPROFILE Interchange\nMETA \"title\" \"t\"\n\
+                    Shape { geometry IndexedFaceSet { coord Coordinate { point [0 0 0 1 0 0 0 1 0] } coordIndex [0 1 2] } }";
+        let model = parse_vrml(text).unwrap();
+        assert_eq!(model.parts[0].name, "Shape 1");
+        assert_eq!(model.parts[0].positions.len(), 9);
+    }
+
+    #[test]
+    fn test_transform() {
+        let node = SceneNode {
+            kind: "Transform".to_string(),
+            fields: vec![
+                ("translation".to_string(), vec!["1".into(), "2".into(), "3".into()]),
+                ("rotation".to_string(), vec!["0".into(), "0".into(), "1".into(), "3.1415927".into()]),
+                ("center".to_string(), vec!["1".into(), "0".into(), "0".into()]),
+                ("scale".to_string(), vec!["2".into(), "2".into(), "2".into()]),
+            ],
+            ..Default::default()
+        };
+        let t = node_transform(&node);
+        // (0, 0, 0) - center = (-1, 0, 0), scaled to (-2, 0, 0), rotated to (2, 0, 0), + center + translation.
+        let p = apply_transform(&t, Vector3::ZERO);
+        assert_close(&[p.x, p.y, p.z], &[4.0, 2.0, 3.0]);
+        assert!(determinant(&t) > 0.0);
+        assert!(determinant(&scale_transform([-1.0, 1.0, 1.0])) < 0.0);
+    }
+
+    #[test]
+    fn test_tokenize() {
+        let tokens: Vec<Token> = tokenize("a{b [1,2] \"s \\\"q\\\"\"}# c\nd")
+            .unwrap()
+            .into_iter()
+            .map(|t| t.0)
+            .collect();
+        assert_eq!(
+            tokens,
+            vec![
+                Token::Word("a"),
+                Token::Open('{'),
+                Token::Word("b"),
+                Token::Open('['),
+                Token::Word("1"),
+                Token::Word("2"),
+                Token::Close(']'),
+                Token::Str("s \"q\"".to_string()),
+                Token::Close('}'),
+                Token::Word("d"),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_errors() {
+        assert!(parse_vrml("#VRML V1.0 ascii\nSeparator {}").is_err());
+        assert!(parse_vrml("Shape {}").is_err());
+        assert!(parse_vrml("#VRML V2.0 utf8\nShape { geometry ").is_err());
+        assert!(parse_vrml("#VRML V2.0 utf8\n\"open").is_err());
+        let nested = format!("#VRML V2.0 utf8\n{}", "Group { children ".repeat(1000));
+        assert!(parse_vrml(&nested).is_err());
+        assert!(parse_x3d("<amf/>").is_err());
+        assert!(parse_x3d("<X3D>").is_err());
+    }
+
+    #[test]
+    fn test_wasm_wrappers() {
+        assert_eq!(load_vrml(VRML).unwrap().part_count(), 2);
+        assert_eq!(load_x3d(X3D).unwrap().part_count(), 2);
+        assert!(load_x3d("").is_err());
+    }
+}
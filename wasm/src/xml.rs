@@ -0,0 +1,297 @@
+use std::borrow::Cow;
+
+/// An event of the XML pull parser. Names are returned as written, including namespace prefixes.
+#[derive(Clone, Debug, PartialEq)]
+pub enum XmlEvent<'a> {
+    Start { name: &'a str, attributes: Attributes<'a>, self_closing: bool },
+    End { name: &'a str },
+    Text(Cow<'a, str>),
+}
+
+/// Raw attributes of a start tag, parsed lazily.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Attributes<'a> {
+    raw: &'a str,
+}
+
+impl<'a> Attributes<'a> {
+    /// Returns the value of the attribute with the given name (including the prefix), with entities decoded.
+    pub fn get(&self, name: &str) -> Option<Cow<'a, str>> {
+        self.iter().find(|(n, _)| *n == name).map(|(_, v)| v)
+    }
+
+    /// Iterates over (name, value) pairs. Malformed attributes are skipped.
+    pub fn iter(&self) -> impl Iterator<Item = (&'a str, Cow<'a, str>)> + use<'a> {
+        let mut rest = self.raw;
+        std::iter::from_fn(move || {
+            loop {
+                rest = rest.trim_start();
+                let eq = rest.find('=')?;
+                let name = rest[..eq].trim();
+                let after = rest[eq + 1..].trim_start();
+                let quote = after.chars().next()?;
+                if quote != '"' && quote != '\'' {
+                    rest = after;
+                    continue;
+                }
+                let end = after[1..].find(quote)?;
+                let value = &after[1..1 + end];
+                rest = &after[end + 2..];
+                return Some((name, decode_entities(value)));
+            }
+        })
+    }
+}
+
+/// Strips the namespace prefix from the name.
+pub fn local_name(name: &str) -> &str {
+    name.rsplit(':').next().unwrap()
+}
+
+/// Decodes the predefined and numeric character entities. Unknown entities are kept as is.
+pub fn decode_entities(s: &str) -> Cow<'_, str> {
+    if !s.contains('&') {
+        return Cow::Borrowed(s);
+    }
+    let mut result = String::with_capacity(s.len());
+    let mut rest = s;
+    while let Some(amp) = rest.find('&') {
+        result.push_str(&rest[..amp]);
+        rest = &rest[amp..];
+        let Some(semicolon) = rest.find(';') else {
+            break;
+        };
+        let entity = &rest[1..semicolon];
+        let decoded = match entity {
+            "lt" => Some('<'),
+            "gt" => Some('>'),
+            "amp" => Some('&'),
+            "quot" => Some('"'),
+            "apos" => Some('\''),
+            _ => {
+                let code = if let Some(hex) = entity
+                    .strip_prefix("#x")
+                    .or_else(|| entity.strip_prefix("#X"))
+                {
+                    u32::from_str_radix(hex, 16).ok()
+                } else if let Some(dec) = entity.strip_prefix('#') {
+                    dec.parse().ok()
+                } else {
+                    None
+                };
+                code.and_then(char::from_u32)
+            }
+        };
+        match decoded {
+            Some(c) => {
+                result.push(c);
+                rest = &rest[semicolon + 1..];
+            }
+            None => {
+                result.push('&');
+                rest = &rest[1..];
+            }
+        }
+    }
+    result.push_str(rest);
+    Cow::Owned(result)
+}
+
+/// Escapes the text for use in element content or attribute values. Control characters which XML 1.0 does not allow
+/// are dropped.
+pub fn escape(s: &str) -> Cow<'_, str> {
+    if !s.contains(|c: char| "&<>\"'".contains(c) || c.is_control()) {
+        return Cow::Borrowed(s);
+    }
+    let mut result = String::with_capacity(s.len() + 16);
+    for c in s.chars() {
+        match c {
+            '&' => result.push_str("&amp;"),
+            '<' => result.push_str("&lt;"),
+            '>' => result.push_str("&gt;"),
+            '"' => result.push_str("&quot;"),
+            '\'' => result.push_str("&apos;"),
+            // Escaped so that attribute value normalization keeps them.
+            '\t' | '\n' | '\r' => result.push_str(&format!("&#{};", c as u32)),
+            c if (c as u32) < 0x20 => {}
+            c => result.push(c),
+        }
+    }
+    Cow::Owned(result)
+}
+
+/// A minimal non-validating XML pull parser. It handles elements, attributes, text, CDATA and entities, and skips
+/// comments, processing instructions and DOCTYPE declarations. The nesting of elements is checked.
+pub struct XmlReader<'a> {
+    text: &'a str,
+    pos: usize,
+    stack: Vec<&'a str>,
+}
+
+impl<'a> XmlReader<'a> {
+    pub fn new(text: &'a str) -> Self {
+        // Skip the byte order mark.
+        Self { text: text.strip_prefix('\u{feff}').unwrap_or(text), pos: 0, stack: vec![] }
+    }
+
+    /// Returns the next event or None at the end of the document.
+    pub fn next_event(&mut self) -> Result<Option<XmlEvent<'a>>, String> {
+        loop {
+            let rest = &self.text[self.pos..];
+            if rest.is_empty() {
+                if let Some(name) = self.stack.last() {
+                    return Err(format!("unclosed element <{}>", name));
+                }
+                return Ok(None);
+            }
+            if !rest.starts_with('<') {
+                let end = rest.find('<').unwrap_or(rest.len());
+                self.pos += end;
+                let text = &rest[..end];
+                if text.trim().is_empty() {
+                    continue;
+                }
+                return Ok(Some(XmlEvent::Text(decode_entities(text))));
+            }
+
+            let skip_until = |marker: &str| {
+                rest.find(marker)
+                    .map(|i| i + marker.len())
+                    .ok_or_else(|| format!("unterminated markup at offset {}", self.pos))
+            };
+            if rest.starts_with("<!--") {
+                self.pos += skip_until("-->")?;
+            } else if let Some(cdata) = rest.strip_prefix("<![CDATA[") {
+                let end = cdata.find("]]>").ok_or("unterminated CDATA")?;
+                self.pos += 9 + end + 3;
+                return Ok(Some(XmlEvent::Text(Cow::Borrowed(&cdata[..end]))));
+            } else if rest.starts_with("<?") {
+                self.pos += skip_until("?>")?;
+            } else if rest.starts_with("<!") {
+                // DOCTYPE, internal subsets with nested brackets are not supported.
+                self.pos += skip_until(">")?;
+            } else if let Some(end_tag) = rest.strip_prefix("</") {
+                let end = end_tag.find('>').ok_or("unterminated end tag")?;
+                let name = end_tag[..end].trim();
+                match self.stack.pop() {
+                    Some(open) if open == name => {}
+                    Some(open) => return Err(format!("mismatched end tag </{}> for <{}>", name, open)),
+                    None => return Err(format!("unexpected end tag </{}>", name)),
+                }
+                self.pos += 2 + end + 1;
+                return Ok(Some(XmlEvent::End { name }));
+            } else {
+                let end = find_tag_end(rest).ok_or("unterminated start tag")?;
+                let (content, self_closing) = match rest[1..end].strip_suffix('/') {
+                    Some(content) => (content, true),
+                    None => (&rest[1..end], false),
+                };
+                let name_end = content
+                    .find(|c: char| c.is_ascii_whitespace())
+                    .unwrap_or(content.len());
+                let name = &content[..name_end];
+                if name.is_empty() {
+                    return Err(format!("invalid start tag at offset {}", self.pos));
+                }
+                if !self_closing {
+                    self.stack.push(name);
+                }
+                self.pos += end + 1;
+                let attributes = Attributes { raw: &content[name_end..] };
+                return Ok(Some(XmlEvent::Start { name, attributes, self_closing }));
+            }
+        }
+    }
+}
+
+// Finds the closing '>' of a tag, ignoring the ones inside quoted attribute values.
+fn find_tag_end(s: &str) -> Option<usize> {
+    let mut quote = None;
+    for (i, c) in s.char_indices() {
+        match (c, quote) {
+            ('"' | '\'', None) => quote = Some(c),
+            (c, Some(q)) if c == q => quote = None,
+            ('>', None) => return Some(i),
+            _ => {}
+        }
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn collect(text: &str) -> Result<Vec<String>, String> {
+        let mut reader = XmlReader::new(text);
+        let mut events = vec![];
+        while let Some(event) = reader.next_event()? {
+            events.push(match event {
+                XmlEvent::Start { name, attributes, self_closing } => {
+                    let attrs: Vec<String> = attributes
+                        .iter()
+                        .map(|(n, v)| format!("{}={}", n, v))
+                        .collect();
+                    format!("<{} {}{}>", name, attrs.join(","), if self_closing { "/" } else { "" })
+                }
+                XmlEvent::End { name } => format!("</{}>", name),
+                XmlEvent::Text(text) => text.trim().to_string(),
+            });
+        }
+        Ok(events)
+    }
+
+    #[test]
+    fn test_events() {
+        let xml = "\u{feff}<?xml version=\"1.0\"?>\n<!DOCTYPE model>\n<!-- comment <a> -->\n\
+                   <m:model xmlns:m=\"urn:x\" unit = 'inch'>\n  <item a=\"1 &gt; 0\" b='x&amp;y' c=\"a>b\"/>\n\
+                   <t>text &#65;&#x42; &unknown;<![CDATA[<raw>]]></t>\n</m:model>";
+        assert_eq!(
+            collect(xml).unwrap(),
+            vec![
+                "<m:model xmlns:m=urn:x,unit=inch>",
+                "<item a=1 > 0,b=x&y,c=a>b/>",
+                "<t >",
+                "text AB &unknown;",
+                "<raw>",
+                "</t>",
+                "</m:model>",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_attributes() {
+        let mut reader = XmlReader::new("<v x=\"1.5\" y='2' z=\"-3\"/>");
+        let Some(XmlEvent::Start { attributes, .. }) = reader.next_event().unwrap() else {
+            panic!("expected start tag");
+        };
+        assert_eq!(attributes.get("y").as_deref(), Some("2"));
+        assert_eq!(attributes.get("w"), None);
+        assert_eq!(local_name("p:path"), "path");
+        assert_eq!(local_name("path"), "path");
+    }
+
+    #[test]
+    fn test_escape() {
+        assert!(matches!(escape("plain"), Cow::Borrowed("plain")));
+        let escaped = escape("a<b & 'c'\n\u{1}\"d\">");
+        assert_eq!(escaped, "a&lt;b &amp; &apos;c&apos;&#10;&quot;d&quot;&gt;");
+        let text = format!("<a v=\"{}\"/>", escaped);
+        let mut reader = XmlReader::new(&text);
+        let Some(XmlEvent::Start { attributes, .. }) = reader.next_event().unwrap() else {
+            panic!("expected start tag");
+        };
+        assert_eq!(attributes.get("v").as_deref(), Some("a<b & 'c'\n\"d\">"));
+    }
+
+    #[test]
+    fn test_errors() {
+        assert!(collect("<a><b></a>").is_err());
+        assert!(collect("<a>").is_err());
+        assert!(collect("</a>").is_err());
+        assert!(collect("<a x=\"1>").is_err());
+        assert!(collect("<!-- unterminated").is_err());
+        assert!(collect("<>").is_err());
+    }
+}
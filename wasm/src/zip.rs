@@ -0,0 +1,415 @@
+use miniz_oxide::deflate::compress_to_vec;
+use miniz_oxide::inflate::{TINFLStatus, decompress_to_vec_with_limit};
+
+use crate::error::Error;
+
+const LOCAL_HEADER_SIGNATURE: u32 = 0x04034b50;
+const CENTRAL_HEADER_SIGNATURE: u32 = 0x02014b50;
+const END_OF_CENTRAL_DIR_SIGNATURE: u32 = 0x06054b50;
+const METHOD_STORED: u16 = 0;
+const METHOD_DEFLATE: u16 = 8;
+
+/// CRC-32 as used by zip and gzip.
+pub fn crc32(data: &[u8]) -> u32 {
+    static TABLE: std::sync::OnceLock<[u32; 256]> = std::sync::OnceLock::new();
+    let table = TABLE.get_or_init(|| {
+        let mut table = [0u32; 256];
+        for (i, entry) in table.iter_mut().enumerate() {
+            let mut c = i as u32;
+            for _ in 0..8 {
+                c = if c & 1 != 0 { 0xedb88320 ^ (c >> 1) } else { c >> 1 };
+            }
+            *entry = c;
+        }
+        table
+    });
+    let mut crc = 0xffffffffu32;
+    for &b in data {
+        crc = table[((crc ^ b as u32) & 0xff) as usize] ^ (crc >> 8);
+    }
+    !crc
+}
+
+// Returns `len` bytes at `offset`. Offsets come from the file and may overflow on wasm32.
+fn read_bytes(data: &[u8], offset: usize, len: usize) -> Result<&[u8], Error> {
+    offset
+        .checked_add(len)
+        .and_then(|end| data.get(offset..end))
+        .ok_or_else(|| Error::TruncatedFile("unexpected end of zip data".to_string()))
+}
+
+fn read_u16(data: &[u8], offset: usize) -> Result<u16, Error> {
+    read_bytes(data, offset, 2).map(|b| u16::from_le_bytes([b[0], b[1]]))
+}
+
+fn read_u32(data: &[u8], offset: usize) -> Result<u32, Error> {
+    read_bytes(data, offset, 4).map(|b| u32::from_le_bytes([b[0], b[1], b[2], b[3]]))
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct ZipEntry {
+    pub name: String,
+    method: u16,
+    crc: u32,
+    compressed_size: usize,
+    uncompressed_size: usize,
+    local_header_offset: usize,
+}
+
+/// Read-only view of a zip archive in memory. Only stored and deflated entries are supported, zip64 and encryption are
+/// not.
+pub struct ZipArchive<'a> {
+    data: &'a [u8],
+    pub entries: Vec<ZipEntry>,
+}
+
+impl<'a> ZipArchive<'a> {
+    pub fn new(data: &'a [u8]) -> Result<Self, Error> {
+        // The end of central directory record is 22 bytes plus a comment of up to 65535 bytes.
+        const EOCD_SIZE: usize = 22;
+        // The central directory is at the end, so a truncated archive looks like an unknown file.
+        let missing_eocd = || {
+            if read_u32(data, 0) == Ok(LOCAL_HEADER_SIGNATURE) {
+                Error::TruncatedFile("zip archive is truncated".to_string())
+            } else {
+                Error::InvalidInput("not a zip archive".to_string())
+            }
+        };
+        if data.len() < EOCD_SIZE {
+            return Err(missing_eocd());
+        }
+        let search_start = data.len().saturating_sub(EOCD_SIZE + 0xffff);
+        let eocd = (search_start..=data.len() - EOCD_SIZE)
+            .rev()
+            .find(|&i| read_u32(data, i) == Ok(END_OF_CENTRAL_DIR_SIGNATURE))
+            .ok_or_else(missing_eocd)?;
+        let entry_count = read_u16(data, eocd + 10)? as usize;
+        let dir_offset = read_u32(data, eocd + 16)? as usize;
+        if entry_count == 0xffff || dir_offset == 0xffffffff {
+            return Err(Error::UnsupportedFormat("zip64 archives are not supported".to_string()));
+        }
+
+        let mut entries = Vec::with_capacity(entry_count.min(data.len() / 46));
+        let mut offset = dir_offset;
+        for _ in 0..entry_count {
+            if read_u32(data, offset)? != CENTRAL_HEADER_SIGNATURE {
+                return Err(Error::InvalidInput("invalid zip central directory".to_string()));
+            }
+            let flags = read_u16(data, offset + 8)?;
+            let name_len = read_u16(data, offset + 28)? as usize;
+            let extra_len = read_u16(data, offset + 30)? as usize;
+            let comment_len = read_u16(data, offset + 32)? as usize;
+            let name = read_bytes(data, offset + 46, name_len)?;
+            if flags & 1 != 0 {
+                return Err(Error::UnsupportedFormat("encrypted zip entries are not supported".to_string()));
+            }
+            entries.push(ZipEntry {
+                name: String::from_utf8_lossy(name).into_owned(),
+                method: read_u16(data, offset + 10)?,
+                crc: read_u32(data, offset + 16)?,
+                compressed_size: read_u32(data, offset + 20)? as usize,
+                uncompressed_size: read_u32(data, offset + 24)? as usize,
+                local_header_offset: read_u32(data, offset + 42)? as usize,
+            });
+            offset += 46 + name_len + extra_len + comment_len;
+        }
+        Ok(Self { data, entries })
+    }
+
+    /// Finds the entry by name. Leading slashes are ignored and the comparison is case-insensitive, as 3MF and most
+    /// other formats based on zip expect.
+    pub fn find(&self, name: &str) -> Option<&ZipEntry> {
+        let name = name.trim_start_matches('/');
+        self.entries
+            .iter()
+            .find(|e| e.name.trim_start_matches('/').eq_ignore_ascii_case(name))
+    }
+
+    /// Returns the uncompressed contents of the entry, checking the CRC.
+    pub fn read(&self, entry: &ZipEntry) -> Result<Vec<u8>, Error> {
+        let offset = entry.local_header_offset;
+        if read_u32(self.data, offset)? != LOCAL_HEADER_SIGNATURE {
+            return Err(Error::InvalidInput(format!("invalid zip local header for {}", entry.name)));
+        }
+        // The sizes in the local header may be zero if a data descriptor is used, take them from the central directory.
+        let name_len = read_u16(self.data, offset + 26)? as usize;
+        let extra_len = read_u16(self.data, offset + 28)? as usize;
+        let start = offset + 30 + name_len + extra_len;
+        let compressed = read_bytes(self.data, start, entry.compressed_size)?;
+        let contents = match entry.method {
+            METHOD_STORED => compressed.to_vec(),
+            METHOD_DEFLATE => decompress_to_vec_with_limit(compressed, entry.uncompressed_size)
+                .map_err(|e| format!("failed to inflate {}: {:?}", entry.name, e.status))?,
+            method => {
+                return Err(Error::UnsupportedFormat(format!(
+                    "unsupported compression method {} for {}",
+                    method, entry.name
+                )));
+            }
+        };
+        if contents.len() != entry.uncompressed_size || crc32(&contents) != entry.crc {
+            return Err(Error::InvalidInput(format!("corrupted zip entry {}", entry.name)));
+        }
+        Ok(contents)
+    }
+}
+
+/// Writes a zip archive in memory. Entries get a fixed modification time, so the output is deterministic.
+#[derive(Default)]
+pub struct ZipWriter {
+    data: Vec<u8>,
+    central: Vec<u8>,
+    entry_count: usize,
+}
+
+impl ZipWriter {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds a file. If `compress` is set, the contents are deflated unless that does not make them smaller.
+    pub fn add_file(&mut self, name: &str, contents: &[u8], compress: bool) {
+        let deflated = if compress { Some(compress_to_vec(contents, 6)) } else { None };
+        let (method, stored) = match &deflated {
+            Some(deflated) if deflated.len() < contents.len() => (METHOD_DEFLATE, deflated.as_slice()),
+            _ => (METHOD_STORED, contents),
+        };
+        // Bit 11 marks UTF-8 names.
+        let flags: u16 = if name.is_ascii() { 0 } else { 0x800 };
+        let offset = self.data.len() as u32;
+        let mut fields = vec![];
+        fields.extend_from_slice(&20u16.to_le_bytes());
+        fields.extend_from_slice(&flags.to_le_bytes());
+        fields.extend_from_slice(&method.to_le_bytes());
+        // Time 00:00:00, date 1980-01-01 in DOS format.
+        fields.extend_from_slice(&0u16.to_le_bytes());
+        fields.extend_from_slice(&0x21u16.to_le_bytes());
+        fields.extend_from_slice(&crc32(contents).to_le_bytes());
+        fields.extend_from_slice(&(stored.len() as u32).to_le_bytes());
+        fields.extend_from_slice(&(contents.len() as u32).to_le_bytes());
+        fields.extend_from_slice(&(name.len() as u16).to_le_bytes());
+        fields.extend_from_slice(&0u16.to_le_bytes());
+
+        self.data
+            .extend_from_slice(&LOCAL_HEADER_SIGNATURE.to_le_bytes());
+        self.data.extend_from_slice(&fields);
+        self.data.extend_from_slice(name.as_bytes());
+        self.data.extend_from_slice(stored);
+
+        self.central
+            .extend_from_slice(&CENTRAL_HEADER_SIGNATURE.to_le_bytes());
+        self.central.extend_from_slice(&20u16.to_le_bytes());
+        self.central.extend_from_slice(&fields);
+        // Comment length, disk number, internal and external attributes.
+        self.central.extend_from_slice(&[0; 10]);
+        self.central.extend_from_slice(&offset.to_le_bytes());
+        self.central.extend_from_slice(name.as_bytes());
+        self.entry_count += 1;
+    }
+
+    pub fn finish(mut self) -> Vec<u8> {
+        let dir_offset = self.data.len() as u32;
+        self.data.extend_from_slice(&self.central);
+        self.data
+            .extend_from_slice(&END_OF_CENTRAL_DIR_SIGNATURE.to_le_bytes());
+        self.data.extend_from_slice(&[0; 4]);
+        self.data
+            .extend_from_slice(&(self.entry_count as u16).to_le_bytes());
+        self.data
+            .extend_from_slice(&(self.entry_count as u16).to_le_bytes());
+        self.data
+            .extend_from_slice(&(self.central.len() as u32).to_le_bytes());
+        self.data.extend_from_slice(&dir_offset.to_le_bytes());
+        self.data.extend_from_slice(&0u16.to_le_bytes());
+        self.data
+    }
+}
+
+const GZIP_MAGIC: &[u8] = b"\x1f\x8b";
+const GZIP_FLAG_HCRC: u8 = 2;
+const GZIP_FLAG_EXTRA: u8 = 4;
+const GZIP_FLAG_NAME: u8 = 8;
+const GZIP_FLAG_COMMENT: u8 = 16;
+
+/// Returns true if the data starts with the gzip header.
+pub fn is_gzip(data: &[u8]) -> bool {
+    data.starts_with(GZIP_MAGIC)
+}
+
+/// Decompresses a gzip file, checking the CRC and the size. Returns the original file name stored in the header, if
+/// any, and the contents. Only the first member of multi-member files is read.
+pub fn gunzip(data: &[u8]) -> Result<(Option<String>, Vec<u8>), Error> {
+    if !is_gzip(data) {
+        return Err(Error::InvalidInput("not a gzip file".to_string()));
+    }
+    let truncated = || Error::TruncatedFile("unexpected end of gzip data".to_string());
+    match data.get(2) {
+        Some(8) => {}
+        Some(method) => {
+            return Err(Error::UnsupportedFormat(format!("unsupported gzip compression method {}", method)));
+        }
+        None => return Err(truncated()),
+    }
+    let flags = *data.get(3).ok_or_else(truncated)?;
+    let mut offset = 10;
+    if flags & GZIP_FLAG_EXTRA != 0 {
+        offset += 2 + read_u16(data, offset).map_err(|_| truncated())? as usize;
+    }
+    let read_zero_terminated = |offset: &mut usize| -> Result<String, Error> {
+        let rest = data.get(*offset..).ok_or_else(truncated)?;
+        let len = rest.iter().position(|&c| c == 0).ok_or_else(truncated)?;
+        *offset += len + 1;
+        // The name is in Latin-1.
+        Ok(rest[..len].iter().map(|&c| c as char).collect())
+    };
+    let name = if flags & GZIP_FLAG_NAME != 0 { Some(read_zero_terminated(&mut offset)?) } else { None };
+    if flags & GZIP_FLAG_COMMENT != 0 {
+        read_zero_terminated(&mut offset)?;
+    }
+    if flags & GZIP_FLAG_HCRC != 0 {
+        offset += 2;
+    }
+    if offset + 8 > data.len() {
+        return Err(truncated());
+    }
+    // The trailer has the CRC and the size modulo 2^32 of the uncompressed data.
+    let crc = read_u32(data, data.len() - 8)?;
+    let size = read_u32(data, data.len() - 4)? as usize;
+    // The trailer is passed to the inflater too: the deflate stream ends by itself, and if the file is truncated, the
+    // inflater runs out of input instead of treating the last bytes as the trailer.
+    let contents = decompress_to_vec_with_limit(&data[offset..], size).map_err(|e| match e.status {
+        TINFLStatus::FailedCannotMakeProgress => truncated(),
+        status => Error::InvalidInput(format!("failed to inflate gzip data: {:?}", status)),
+    })?;
+    if contents.len() != size || crc32(&contents) != crc {
+        return Err(Error::InvalidInput("corrupted gzip data".to_string()));
+    }
+    Ok((name, contents))
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+
+    // Builds a zip archive with the given entries, deflating the ones with `deflate` set.
+    pub(crate) fn create_zip(files: &[(&str, &[u8], bool)]) -> Vec<u8> {
+        let mut writer = ZipWriter::new();
+        for &(name, contents, deflate) in files {
+            writer.add_file(name, contents, deflate);
+        }
+        writer.finish()
+    }
+
+    #[test]
+    fn test_crc32() {
+        assert_eq!(crc32(b""), 0);
+        assert_eq!(crc32(b"123456789"), 0xcbf43926);
+    }
+
+    #[test]
+    fn test_read() {
+        let text = "hello ".repeat(100);
+        let data = create_zip(&[("a.txt", b"stored", false), ("Dir/B.txt", text.as_bytes(), true)]);
+        let archive = ZipArchive::new(&data).unwrap();
+        assert_eq!(archive.entries.len(), 2);
+        assert_eq!(archive.read(archive.find("a.txt").unwrap()).unwrap(), b"stored");
+        let entry = archive.find("/dir/b.TXT").unwrap();
+        assert!(entry.compressed_size < text.len());
+        assert_eq!(archive.read(entry).unwrap(), text.as_bytes());
+        assert!(archive.find("c.txt").is_none());
+    }
+
+    #[test]
+    fn test_write() {
+        let mut writer = ZipWriter::new();
+        writer.add_file("tiny.txt", b"x", true);
+        writer.add_file("детали.txt", b"utf-8 name", false);
+        let data = writer.finish();
+        let archive = ZipArchive::new(&data).unwrap();
+        // Deflating a single byte makes it larger, so it is stored.
+        assert_eq!(archive.entries[0].method, METHOD_STORED);
+        assert_eq!(archive.read(&archive.entries[0]).unwrap(), b"x");
+        assert_eq!(archive.entries[1].name, "детали.txt");
+        assert_eq!(ZipWriter::new().finish().len(), 22);
+    }
+
+    #[test]
+    fn test_errors() {
+        assert!(ZipArchive::new(b"").is_err());
+        assert!(ZipArchive::new(&[0u8; 100]).is_err());
+        let mut data = create_zip(&[("a.txt", b"contents", false)]);
+        // Corrupt the contents: the CRC check fails.
+        let pos = data.windows(8).position(|w| w == b"contents").unwrap();
+        data[pos] = b'C';
+        let archive = ZipArchive::new(&data).unwrap();
+        assert!(archive.read(&archive.entries[0]).is_err());
+        // Truncated central directory.
+        assert_eq!(
+            ZipArchive::new(&data[..data.len() - 30]).err(),
+            Some(Error::TruncatedFile("zip archive is truncated".to_string()))
+        );
+    }
+
+    #[test]
+    fn test_truncated_and_invalid_offsets() {
+        let text = "truncated ".repeat(50);
+        let data = create_zip(&[("a.txt", text.as_bytes(), true), ("b.txt", b"b", false)]);
+        // No truncation panics, and the archives which still open give errors instead of partial contents.
+        for len in 0..data.len() {
+            if let Ok(archive) = ZipArchive::new(&data[..len]) {
+                for entry in &archive.entries {
+                    let _ = archive.read(entry);
+                }
+            }
+        }
+        let mut data = data;
+        let len = data.len();
+        data[len - 6..len - 2].copy_from_slice(&0xfffffff0u32.to_le_bytes());
+        assert!(ZipArchive::new(&data).is_err());
+    }
+
+    // Builds a gzip file with the stored name.
+    pub(crate) fn create_gzip(name: Option<&str>, contents: &[u8]) -> Vec<u8> {
+        let mut data = vec![0x1f, 0x8b, 8, if name.is_some() { GZIP_FLAG_NAME } else { 0 }, 0, 0, 0, 0, 0, 3];
+        if let Some(name) = name {
+            data.extend_from_slice(name.as_bytes());
+            data.push(0);
+        }
+        data.extend_from_slice(&compress_to_vec(contents, 6));
+        data.extend_from_slice(&crc32(contents).to_le_bytes());
+        data.extend_from_slice(&(contents.len() as u32).to_le_bytes());
+        data
+    }
+
+    #[test]
+    fn test_gunzip() {
+        let text = "gzip ".repeat(100);
+        let data = create_gzip(Some("a.stl"), text.as_bytes());
+        assert!(is_gzip(&data));
+        assert_eq!(gunzip(&data).unwrap(), (Some("a.stl".to_string()), text.into_bytes()));
+        assert_eq!(gunzip(&create_gzip(None, b"")).unwrap(), (None, vec![]));
+
+        // Extra field, comment and header CRC.
+        let mut data = vec![0x1f, 0x8b, 8, GZIP_FLAG_EXTRA | GZIP_FLAG_COMMENT | GZIP_FLAG_HCRC, 0, 0, 0, 0, 0, 3];
+        data.extend_from_slice(&[3, 0, 1, 2, 3]);
+        data.extend_from_slice(b"comment\0");
+        data.extend_from_slice(&[0, 0]);
+        data.extend_from_slice(&create_gzip(None, b"abc")[10..]);
+        assert_eq!(gunzip(&data).unwrap(), (None, b"abc".to_vec()));
+    }
+
+    #[test]
+    fn test_gunzip_errors() {
+        let data = create_gzip(Some("a.stl"), "gzip ".repeat(100).as_bytes());
+        for len in [2, 5, 12, 20, data.len() / 2] {
+            assert_eq!(gunzip(&data[..len]).unwrap_err().code(), "TRUNCATED_FILE", "{}", len);
+        }
+        // The truncated trailer can not be told from a corrupted one.
+        assert!(gunzip(&data[..data.len() - 1]).is_err());
+        let mut corrupted = data.clone();
+        let len = corrupted.len();
+        corrupted[len - 8] ^= 1;
+        assert!(gunzip(&corrupted).is_err());
+        assert!(gunzip(b"PK\x03\x04").is_err());
+    }
+}